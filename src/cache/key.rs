@@ -0,0 +1,246 @@
+//! Cache keys and output fingerprints
+//!
+//! A key is a SHA-256 over everything that can change what a task does: the
+//! project directory, the full command (program, argv, working directory and
+//! environment), the declared outputs and the relative path and contents of
+//! every input file. Input files inside `.git` or inside a declared output
+//! are skipped, so a task's own outputs never invalidate its key.
+
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::config::CacheConfig;
+use crate::error::TaskError;
+use crate::runner::CommandSpec;
+
+/// Bumped whenever the key layout changes, invalidating old entries
+const KEY_VERSION: &str = "makefilehub-cache-v1";
+
+/// Hash identifying one set of task inputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    hash: String,
+    outputs: Vec<PathBuf>,
+}
+
+impl CacheKey {
+    /// Compute the key for running `command` in `project_dir`
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If an input glob is invalid
+    /// * `TaskError::Io` - If an input file can't be read
+    pub fn compute(
+        project_dir: &Path,
+        command: &CommandSpec,
+        config: &CacheConfig,
+    ) -> Result<Self, TaskError> {
+        let inputs = build_globset(&config.inputs)?;
+        let outputs: Vec<PathBuf> = config.outputs.iter().map(PathBuf::from).collect();
+
+        let mut hasher = Sha256::new();
+        field(&mut hasher, KEY_VERSION);
+        field(&mut hasher, &project_dir.display().to_string());
+        field(
+            &mut hasher,
+            &serde_json::to_string(command).unwrap_or_default(),
+        );
+        for output in &config.outputs {
+            field(&mut hasher, output);
+        }
+
+        for (relative, path) in walk_files(project_dir)? {
+            if !inputs.is_match(&relative) || outputs.iter().any(|o| relative.starts_with(o)) {
+                continue;
+            }
+            field(&mut hasher, &relative.display().to_string());
+            hash_file(&mut hasher, &path)?;
+        }
+
+        Ok(Self {
+            hash: format!("{:x}", hasher.finalize()),
+            outputs,
+        })
+    }
+
+    /// Hex-encoded hash
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Fingerprint of the declared outputs in `project_dir`
+    ///
+    /// Returns `None` when an output is missing or can't be read, which
+    /// never matches a stored entry.
+    pub fn outputs_fingerprint(&self, project_dir: &Path) -> Option<String> {
+        let mut hasher = Sha256::new();
+
+        for output in &self.outputs {
+            let path = project_dir.join(output);
+            field(&mut hasher, &output.display().to_string());
+            if path.is_dir() {
+                for (relative, file) in walk_files(&path).ok()? {
+                    field(&mut hasher, &relative.display().to_string());
+                    hash_file(&mut hasher, &file).ok()?;
+                }
+            } else {
+                hash_file(&mut hasher, &path).ok()?;
+            }
+        }
+
+        Some(format!("{:x}", hasher.finalize()))
+    }
+}
+
+/// Hash a length-prefixed string so that adjacent fields can't run together
+fn field(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value.as_bytes());
+}
+
+fn hash_file(hasher: &mut Sha256, path: &Path) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let mut contents = Sha256::new();
+    io::copy(&mut file, &mut contents)?;
+    hasher.update(contents.finalize());
+    Ok(())
+}
+
+/// Regular files under `root` as (relative, absolute) paths, sorted, skipping `.git`
+fn walk_files(root: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut files = Vec::new();
+    let walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git");
+
+    for entry in walker {
+        let entry = entry.map_err(io::Error::from)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(entry.path())
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        files.push((relative, entry.path().to_path_buf()));
+    }
+
+    Ok(files)
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, TaskError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                TaskError::Config(format!("Invalid cache input glob '{}': {}", pattern, e))
+            })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| TaskError::Config(format!("Invalid cache input globs: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn cache_config(inputs: &[&str], outputs: &[&str]) -> CacheConfig {
+        CacheConfig {
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn write(temp: &TempDir, path: &str, contents: &str) {
+        let path = temp.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_key_changes_with_inputs_only() {
+        let temp = TempDir::new().unwrap();
+        write(&temp, "src/main.rs", "fn main() {}");
+        write(&temp, "README.md", "docs");
+        let config = cache_config(&["src/**"], &["out"]);
+        let command = CommandSpec::new("make").arg("build");
+
+        let key = |temp: &TempDir| CacheKey::compute(temp.path(), &command, &config).unwrap();
+        let first = key(&temp);
+        assert_eq!(first, key(&temp));
+
+        // Files outside the inputs and the outputs themselves don't matter
+        write(&temp, "README.md", "more docs");
+        write(&temp, "out/app", "binary");
+        assert_eq!(first, key(&temp));
+
+        write(&temp, "src/main.rs", "fn main() { println!() }");
+        assert_ne!(first, key(&temp));
+    }
+
+    #[test]
+    fn test_key_includes_command_and_env() {
+        let temp = TempDir::new().unwrap();
+        let config = cache_config(&["**"], &[]);
+        let key = |command: CommandSpec| {
+            CacheKey::compute(temp.path(), &command, &config)
+                .unwrap()
+                .hash
+        };
+
+        let base = key(CommandSpec::new("make").arg("build"));
+        assert_ne!(
+            base,
+            key(CommandSpec::new("make").arg("build").arg("MODE=release"))
+        );
+        assert_ne!(
+            base,
+            key(CommandSpec::new("make").arg("build").env("CI", "1"))
+        );
+    }
+
+    #[test]
+    fn test_outputs_fingerprint() {
+        let temp = TempDir::new().unwrap();
+        let config = cache_config(&[], &["dist", "app.bin"]);
+        let key = CacheKey::compute(temp.path(), &CommandSpec::new("true"), &config).unwrap();
+
+        assert!(key.outputs_fingerprint(temp.path()).is_none());
+
+        write(&temp, "dist/index.js", "js");
+        write(&temp, "app.bin", "bin");
+        let fingerprint = key.outputs_fingerprint(temp.path()).unwrap();
+        assert_eq!(
+            key.outputs_fingerprint(temp.path()),
+            Some(fingerprint.clone())
+        );
+
+        write(&temp, "dist/index.js", "changed");
+        assert_ne!(key.outputs_fingerprint(temp.path()), Some(fingerprint));
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let temp = TempDir::new().unwrap();
+        let result = CacheKey::compute(
+            temp.path(),
+            &CommandSpec::new("true"),
+            &cache_config(&["src/["], &[]),
+        );
+        assert!(matches!(result, Err(TaskError::Config(_))));
+    }
+}
@@ -0,0 +1,147 @@
+//! Content-hash task caching
+//!
+//! Tasks with a `[services.<name>.cache.<task>]` block are skipped when
+//! their inputs, command, arguments and environment match a previous
+//! successful run and that run's outputs are unchanged; the stored output is
+//! returned instead with `cached` set.
+//! - `key` - cache keys from input contents and output fingerprints
+//! - `store` - cache entries under `$XDG_CACHE_HOME/makefilehub`
+
+pub mod key;
+pub mod store;
+
+pub use key::CacheKey;
+pub use store::{CacheEntry, TaskCache};
+
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::CacheConfig;
+use crate::error::TaskError;
+use crate::runner::{RunOptions, RunResult, Runner};
+
+/// Run a task, or return the cached result of an identical earlier run
+///
+/// Without a cache config the task simply runs. Problems reading input files
+/// or writing the cache are logged and never fail the task.
+///
+/// # Errors
+/// * `TaskError::Config` - If an input glob is invalid
+/// * Any error from [`Runner::run_task`]
+pub fn run_cached(
+    cache: &TaskCache,
+    config: Option<&CacheConfig>,
+    runner: &dyn Runner,
+    dir: &Path,
+    task: &str,
+    options: &RunOptions,
+) -> Result<RunResult, TaskError> {
+    let Some(config) = config else {
+        return runner.run_task(dir, task, options);
+    };
+
+    let start = Instant::now();
+    let command = runner.build_command(dir, task, options);
+    let key = match CacheKey::compute(dir, &command, config) {
+        Ok(key) => key,
+        Err(e @ TaskError::Config(_)) => return Err(e),
+        Err(e) => {
+            tracing::warn!("Skipping task cache for {}: {}", command, e);
+            return runner.run_task(dir, task, options);
+        }
+    };
+
+    if let Some(entry) = cache.lookup(&key, dir) {
+        tracing::debug!("Cache hit for {} ({})", command, key.hash());
+        return Ok(RunResult {
+            success: true,
+            exit_code: entry.exit_code,
+            stdout: entry.stdout,
+            stderr: entry.stderr,
+            command,
+            duration_ms: start.elapsed().as_millis() as u64,
+            cached: true,
+            stdout_truncation: None,
+            stderr_truncation: None,
+        });
+    }
+
+    let result = runner.run_task(dir, task, options)?;
+    if let Err(e) = cache.store(&key, dir, &result) {
+        tracing::warn!("Failed to write task cache for {}: {}", command, e);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::MakefileRunner;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_cached_skips_unchanged_task() {
+        let project = TempDir::new().unwrap();
+        let cache_dir = TempDir::new().unwrap();
+        std::fs::create_dir(project.path().join("src")).unwrap();
+        std::fs::write(project.path().join("src/input.txt"), "one").unwrap();
+        std::fs::write(
+            project.path().join("Makefile"),
+            "build:\n\t@echo run >> runs.log\n\t@cp src/input.txt out.txt\n\t@echo built\n",
+        )
+        .unwrap();
+
+        let cache = TaskCache::new(cache_dir.path());
+        let config = CacheConfig {
+            inputs: vec!["src/**".to_string()],
+            outputs: vec!["out.txt".to_string()],
+        };
+        let runner = MakefileRunner::new();
+        let options = RunOptions {
+            capture_output: true,
+            ..RunOptions::in_dir(project.path())
+        };
+        let run = || {
+            run_cached(
+                &cache,
+                Some(&config),
+                &runner,
+                project.path(),
+                "build",
+                &options,
+            )
+            .unwrap()
+        };
+        let runs = || {
+            std::fs::read_to_string(project.path().join("runs.log"))
+                .unwrap()
+                .lines()
+                .count()
+        };
+
+        let first = run();
+        assert!(first.success);
+        assert!(!first.cached);
+
+        let second = run();
+        assert!(second.cached);
+        assert_eq!(second.stdout, "built\n");
+        assert_eq!(runs(), 1);
+
+        // Changed inputs run the task again
+        std::fs::write(project.path().join("src/input.txt"), "two").unwrap();
+        assert!(!run().cached);
+        assert_eq!(runs(), 2);
+
+        // So do deleted outputs
+        std::fs::remove_file(project.path().join("out.txt")).unwrap();
+        assert!(!run().cached);
+        assert_eq!(runs(), 3);
+
+        // Without a cache config the task always runs
+        let uncached =
+            run_cached(&cache, None, &runner, project.path(), "build", &options).unwrap();
+        assert!(!uncached.cached);
+        assert_eq!(runs(), 4);
+    }
+}
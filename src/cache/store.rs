@@ -0,0 +1,201 @@
+//! On-disk cache entries
+//!
+//! Each successful cached run is stored as `<key>.json` in the cache
+//! directory, together with its output and a fingerprint of its outputs.
+//! Entries are written to a temporary file and renamed into place, so a
+//! concurrent reader never sees a partial entry.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::TaskError;
+use crate::runner::RunResult;
+
+use super::key::CacheKey;
+
+/// A stored successful run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Command that produced the entry (shell-escaped)
+    pub command: String,
+    /// Fingerprint of the outputs right after the run
+    pub outputs: String,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Duration of the original run in milliseconds
+    pub duration_ms: u64,
+    /// Unix timestamp of the original run
+    pub created_at: u64,
+}
+
+/// Directory of cache entries
+#[derive(Debug, Clone)]
+pub struct TaskCache {
+    dir: PathBuf,
+}
+
+impl TaskCache {
+    /// Use `dir` for cache entries; it is created on first store
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache in `defaults.cache_dir`, else `$XDG_CACHE_HOME/makefilehub`
+    pub fn from_config(config: &Config) -> Self {
+        let dir = config
+            .defaults
+            .cache_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join("makefilehub")))
+            .unwrap_or_else(|| std::env::temp_dir().join("makefilehub-cache"));
+        Self::new(dir)
+    }
+
+    /// Directory holding the entries
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Entry for `key`, if one exists and the outputs in `project_dir` are
+    /// still as the cached run left them
+    pub fn lookup(&self, key: &CacheKey, project_dir: &Path) -> Option<CacheEntry> {
+        let contents = std::fs::read(self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&contents).ok()?;
+
+        (key.outputs_fingerprint(project_dir)? == entry.outputs).then_some(entry)
+    }
+
+    /// Store a successful run under `key`
+    ///
+    /// Runs whose outputs can't be fingerprinted (e.g. a declared output
+    /// wasn't created) are not stored.
+    ///
+    /// # Errors
+    /// * `TaskError::Io` - If the entry can't be written
+    pub fn store(
+        &self,
+        key: &CacheKey,
+        project_dir: &Path,
+        result: &RunResult,
+    ) -> Result<(), TaskError> {
+        if !result.success {
+            return Ok(());
+        }
+        let Some(outputs) = key.outputs_fingerprint(project_dir) else {
+            tracing::debug!("Not caching {}: outputs missing", result.command);
+            return Ok(());
+        };
+
+        let entry = CacheEntry {
+            command: result.command.to_string(),
+            outputs,
+            exit_code: result.exit_code,
+            stdout: result.stdout.clone(),
+            stderr: result.stderr.clone(),
+            duration_ms: result.duration_ms,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(key);
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec(&entry).unwrap_or_default())?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.hash()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CacheConfig;
+    use crate::runner::CommandSpec;
+    use tempfile::TempDir;
+
+    fn key(project: &Path, outputs: &[&str]) -> CacheKey {
+        let config = CacheConfig {
+            inputs: vec!["src/**".to_string()],
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+        };
+        CacheKey::compute(project, &CommandSpec::new("make").arg("build"), &config).unwrap()
+    }
+
+    #[test]
+    fn test_store_and_lookup() {
+        let project = TempDir::new().unwrap();
+        let cache_dir = TempDir::new().unwrap();
+        let cache = TaskCache::new(cache_dir.path().join("cache"));
+        std::fs::write(project.path().join("app"), "v1").unwrap();
+
+        let key = key(project.path(), &["app"]);
+        assert!(cache.lookup(&key, project.path()).is_none());
+
+        let result = RunResult::success(CommandSpec::new("make").arg("build"), "built\n", 42);
+        cache.store(&key, project.path(), &result).unwrap();
+
+        let entry = cache.lookup(&key, project.path()).unwrap();
+        assert_eq!(entry.stdout, "built\n");
+        assert_eq!(entry.duration_ms, 42);
+        assert_eq!(entry.command, "make build");
+
+        // Modified or deleted outputs invalidate the entry
+        std::fs::write(project.path().join("app"), "tampered").unwrap();
+        assert!(cache.lookup(&key, project.path()).is_none());
+        std::fs::remove_file(project.path().join("app")).unwrap();
+        assert!(cache.lookup(&key, project.path()).is_none());
+    }
+
+    #[test]
+    fn test_failed_runs_and_missing_outputs_not_stored() {
+        let project = TempDir::new().unwrap();
+        let cache_dir = TempDir::new().unwrap();
+        let cache = TaskCache::new(cache_dir.path());
+        let command = CommandSpec::new("make").arg("build");
+
+        let key = key(project.path(), &[]);
+        cache
+            .store(
+                &key,
+                project.path(),
+                &RunResult::failed(command.clone(), Some(2), "", "error", 1),
+            )
+            .unwrap();
+        assert!(cache.lookup(&key, project.path()).is_none());
+
+        let key = CacheKey::compute(
+            project.path(),
+            &command,
+            &CacheConfig {
+                inputs: vec![],
+                outputs: vec!["never-created".to_string()],
+            },
+        )
+        .unwrap();
+        cache
+            .store(&key, project.path(), &RunResult::success(command, "", 1))
+            .unwrap();
+        assert_eq!(std::fs::read_dir(cache_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_from_config_cache_dir() {
+        let mut config = Config::default();
+        config.defaults.cache_dir = Some("/tmp/custom-cache".to_string());
+        assert_eq!(
+            TaskCache::from_config(&config).dir(),
+            Path::new("/tmp/custom-cache")
+        );
+    }
+}
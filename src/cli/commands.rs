@@ -46,6 +46,15 @@ pub enum Commands {
 
     /// Rebuild a service with dependency handling
     Rebuild(RebuildArgs),
+
+    /// Re-run a task whenever project files change
+    Watch(WatchArgs),
+
+    /// Show recorded task runs and rebuild steps
+    History(HistoryArgs),
+
+    /// Approve a project's .makefilehub.toml so it is loaded
+    Trust(TrustArgs),
 }
 
 /// Arguments for the `run` subcommand
@@ -71,13 +80,31 @@ pub struct RunArgs {
     #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     pub positional: Vec<String>,
 
-    /// Timeout in seconds (0 for no timeout)
-    #[arg(short, long, default_value = "300")]
-    pub timeout: u64,
+    /// Timeout in seconds (0 for no timeout; defaults to the service or config timeout)
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
 
-    /// Don't capture output, stream directly
+    /// Show output live (stdin is passed through; Ctrl-C goes to the task)
     #[arg(long)]
     pub stream: bool,
+
+    /// Run in a pseudo-terminal, for tools that need a TTY (stdout and stderr are merged)
+    #[arg(long)]
+    pub pty: bool,
+
+    /// Run even if the task cache has an up-to-date entry
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Output format (json prints the run result with diagnostics and test summary)
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value = "plain",
+        conflicts_with = "stream"
+    )]
+    pub format: OutputFormat,
 }
 
 impl RunArgs {
@@ -111,6 +138,53 @@ pub struct ListArgs {
     pub format: OutputFormat,
 }
 
+/// Arguments for the `history` subcommand
+#[derive(Parser, Debug)]
+pub struct HistoryArgs {
+    /// Project path or service name
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Only runs of this configured service
+    #[arg(short, long)]
+    pub service: Option<String>,
+
+    /// Only runs of this task
+    #[arg(short, long)]
+    pub task: Option<String>,
+
+    /// Only failed runs
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Maximum number of runs to show (0 for all)
+    #[arg(short = 'n', long, default_value = "20")]
+    pub limit: usize,
+
+    /// Show the recorded output of each run
+    #[arg(long)]
+    pub output: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+}
+
+/// Arguments for the `trust` subcommand
+#[derive(Parser, Debug)]
+pub struct TrustArgs {
+    /// Project config file or its directory (defaults to ./.makefilehub.toml)
+    pub path: Option<String>,
+
+    /// Withdraw the approval instead
+    #[arg(long, conflicts_with = "list")]
+    pub revoke: bool,
+
+    /// List approved files
+    #[arg(long)]
+    pub list: bool,
+}
+
 /// Output format options
 #[derive(Debug, Clone, ValueEnum)]
 pub enum OutputFormat {
@@ -153,14 +227,14 @@ pub struct ConfigArgs {
 #[derive(Parser, Debug)]
 pub struct RebuildArgs {
     /// Service to rebuild
-    #[arg(required = true)]
-    pub service: String,
+    #[arg(required_unless_present = "changed")]
+    pub service: Option<String>,
 
     /// Additional services to rebuild
     #[arg(short = 's', long)]
     pub services: Vec<String>,
 
-    /// Skip dependency restart
+    /// Only build the listed services: skip dependency builds and dependent restarts
     #[arg(long)]
     pub skip_deps: bool,
 
@@ -168,9 +242,79 @@ pub struct RebuildArgs {
     #[arg(long)]
     pub skip_recreate: bool,
 
-    /// Timeout in seconds
-    #[arg(short, long, default_value = "600")]
-    pub timeout: u64,
+    /// Print the rebuild plan without running it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Maximum number of concurrent build steps (defaults to defaults.max_parallel)
+    #[arg(short = 'j', long)]
+    pub max_parallel: Option<usize>,
+
+    /// Stop starting new steps after the first failure
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Timeout in seconds for each step (0 for no timeout; defaults to the service timeout)
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+
+    /// Rebuild services affected by git changes (and services depending on them)
+    #[arg(long)]
+    pub changed: bool,
+
+    /// Git ref to compare against with --changed (defaults to uncommitted changes)
+    #[arg(long, requires = "changed")]
+    pub since: Option<String>,
+    /// Run every build even if its task cache entry is up to date
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+/// Arguments for the `watch` subcommand
+#[derive(Parser, Debug)]
+pub struct WatchArgs {
+    /// Task name to re-run on changes
+    #[arg(required = true)]
+    pub task: String,
+
+    /// Project path or name (defaults to current directory)
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Force specific runner (make, just, or script name)
+    #[arg(short, long)]
+    pub runner: Option<String>,
+
+    /// Named arguments in KEY=VALUE format
+    #[arg(short = 'a', long = "arg", value_parser = parse_key_value)]
+    pub args: Vec<(String, String)>,
+
+    /// Positional arguments passed after task
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub positional: Vec<String>,
+
+    /// Only re-run when a changed file matches this glob (repeatable)
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Ignore changed files matching this glob (repeatable)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Quiet period in milliseconds before re-running (defaults to the service setting)
+    #[arg(long)]
+    pub debounce_ms: Option<u64>,
+
+    /// Timeout in seconds for each run (0 for no timeout; defaults to the service or config timeout)
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+}
+
+impl WatchArgs {
+    /// Convert args to a HashMap
+    pub fn args_as_map(&self) -> HashMap<String, String> {
+        self.args.iter().cloned().collect()
+    }
 }
 
 #[cfg(test)]
@@ -285,15 +429,100 @@ mod tests {
             "-s",
             "web-frontend",
             "--skip-deps",
+            "--dry-run",
+            "-j",
+            "2",
         ]);
         if let Commands::Rebuild(args) = cli.command {
-            assert_eq!(args.service, "web-api");
+            assert_eq!(args.service, Some("web-api".to_string()));
             assert_eq!(args.services, vec!["web-frontend".to_string()]);
             assert!(args.skip_deps);
             assert!(!args.skip_recreate);
+            assert!(args.dry_run);
+            assert_eq!(args.timeout, None);
+            assert_eq!(args.max_parallel, Some(2));
+            assert!(!args.fail_fast);
+        } else {
+            panic!("Expected Rebuild command");
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_rebuild_changed() {
+        let cli = Cli::parse_from(["makefilehub", "rebuild", "--changed", "--since", "main"]);
+        if let Commands::Rebuild(args) = cli.command {
+            assert!(args.service.is_none());
+            assert!(args.changed);
+            assert_eq!(args.since, Some("main".to_string()));
         } else {
             panic!("Expected Rebuild command");
         }
+
+        assert!(Cli::try_parse_from(["makefilehub", "rebuild"]).is_err());
+        assert!(Cli::try_parse_from(["makefilehub", "rebuild", "api", "--since", "main"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_run_format() {
+        let cli = Cli::parse_from(["makefilehub", "run", "test", "--format", "json"]);
+        if let Commands::Run(args) = cli.command {
+            assert!(matches!(args.format, OutputFormat::Json));
+        } else {
+            panic!("Expected Run command");
+        }
+
+        let result = Cli::try_parse_from(["makefilehub", "run", "test", "-f", "json", "--stream"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_history() {
+        let cli = Cli::parse_from([
+            "makefilehub",
+            "history",
+            "-p",
+            "api",
+            "--failed",
+            "-n",
+            "5",
+            "--format",
+            "json",
+        ]);
+        if let Commands::History(args) = cli.command {
+            assert_eq!(args.project.as_deref(), Some("api"));
+            assert!(args.failed);
+            assert_eq!(args.limit, 5);
+            assert!(!args.output);
+            assert!(matches!(args.format, OutputFormat::Json));
+        } else {
+            panic!("Expected History command");
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_watch() {
+        let cli = Cli::parse_from([
+            "makefilehub",
+            "watch",
+            "build",
+            "--include",
+            "src/**",
+            "--include",
+            "Cargo.toml",
+            "--exclude",
+            "*.log",
+            "--debounce-ms",
+            "100",
+        ]);
+        if let Commands::Watch(args) = cli.command {
+            assert_eq!(args.task, "build");
+            assert_eq!(args.include, vec!["src/**", "Cargo.toml"]);
+            assert_eq!(args.exclude, vec!["*.log"]);
+            assert_eq!(args.debounce_ms, Some(100));
+            assert_eq!(args.timeout, None);
+        } else {
+            panic!("Expected Watch command");
+        }
     }
 
     #[test]
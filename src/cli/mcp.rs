@@ -3,10 +3,11 @@
 //! Starts the MCP server over stdio for Claude Code integration.
 
 use anyhow::{Context, Result};
-use rmcp::ServiceExt;
+use rmcp::transport::IntoTransport;
+use rmcp::{RoleServer, ServiceExt};
 use tokio::io::{stdin, stdout};
 
-use crate::config::load_config;
+use crate::config::{interpolate_config, load_config};
 use crate::mcp::MakefilehubServer;
 
 /// Run the MCP server over stdio.
@@ -22,17 +23,29 @@ use crate::mcp::MakefilehubServer;
 /// * `Err(e)` - Server failed to start or encountered an error
 pub async fn run_mcp_server(config_path: Option<&str>) -> Result<()> {
     // Load configuration
-    let config = load_config(config_path).context("Failed to load configuration")?;
+    let mut config = load_config(config_path).context("Failed to load configuration")?;
+    interpolate_config(&mut config);
 
     // Create server with loaded config
     let server = MakefilehubServer::with_config(config);
 
     // Create stdio transport - tuple of (reader, writer)
-    let transport = (stdin(), stdout());
+    serve(server, (stdin(), stdout())).await
+}
 
+/// Serve `server` over `transport` until the client disconnects
+async fn serve<T, E, A>(server: MakefilehubServer, transport: T) -> Result<()>
+where
+    T: IntoTransport<RoleServer, E, A>,
+    E: std::error::Error + From<std::io::Error> + Send + Sync + 'static,
+{
     // Start serving with the transport
     let service = server.serve(transport).await?;
 
+    // The client is initialized: scope the session to its roots. Tool calls
+    // arriving meanwhile wait for them, and are refused if they never come.
+    service.service().refresh_roots().await;
+
     // Wait for completion
     service.waiting().await?;
 
@@ -43,9 +56,145 @@ pub async fn run_mcp_server(config_path: Option<&str>) -> Result<()> {
 mod tests {
     // Note: actual MCP server testing requires integration tests
     // with a mock stdio transport
+    use super::*;
+    use crate::config::Config;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
     #[test]
     fn test_module_compiles() {
         // Just verify the module compiles correctly
     }
+
+    /// Write a JSON-RPC message line
+    async fn send(writer: &mut (impl AsyncWriteExt + Unpin), message: Value) {
+        let mut line = message.to_string();
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_calls_wait_for_client_roots() {
+        let allowed = tempfile::tempdir().unwrap();
+        let root = allowed.path().join("opened");
+        let other = allowed.path().join("other");
+        for dir in [&root, &other] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(dir.join("Makefile"), "build:\n").unwrap();
+        }
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.enabled = false;
+
+        let (client, transport) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(serve(MakefilehubServer::with_config(config), transport));
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(reader).lines();
+        send(
+            &mut writer,
+            json!({
+                "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "roots": { "listChanged": true } },
+                    "clientInfo": { "name": "test", "version": "1.0" }
+                }
+            }),
+        )
+        .await;
+        lines.next_line().await.unwrap().unwrap();
+        send(
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .await;
+
+        // A call sent right away, before the server has the roots
+        send(
+            &mut writer,
+            json!({
+                "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": {
+                    "name": "detect_runner",
+                    "arguments": { "project": other.display().to_string() }
+                }
+            }),
+        )
+        .await;
+
+        let request: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(request["method"], "roots/list", "{}", request);
+        let uri = format!("file://{}", root.canonicalize().unwrap().display());
+        send(
+            &mut writer,
+            json!({
+                "jsonrpc": "2.0", "id": request["id"],
+                "result": { "roots": [{ "uri": uri }] }
+            }),
+        )
+        .await;
+
+        let response = lines.next_line().await.unwrap().unwrap();
+        assert!(
+            response.contains("outside the client's roots"),
+            "{}",
+            response
+        );
+        server.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_calls_refused_without_client_roots() {
+        let allowed = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.enabled = false;
+
+        let (client, transport) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(serve(MakefilehubServer::with_config(config), transport));
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(reader).lines();
+        send(
+            &mut writer,
+            json!({
+                "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "roots": { "listChanged": true } },
+                    "clientInfo": { "name": "test", "version": "1.0" }
+                }
+            }),
+        )
+        .await;
+        lines.next_line().await.unwrap().unwrap();
+        send(
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .await;
+        send(
+            &mut writer,
+            json!({
+                "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": {
+                    "name": "detect_runner",
+                    "arguments": { "project": allowed.path().display().to_string() }
+                }
+            }),
+        )
+        .await;
+
+        // The client never answers roots/list
+        let request: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(request["method"], "roots/list", "{}", request);
+
+        let response: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 2, "{}", response);
+        let message = response["error"]["message"].as_str().unwrap_or_default();
+        assert!(message.contains("roots are not loaded"), "{}", response);
+        server.abort();
+    }
 }
@@ -7,6 +7,9 @@
 //! - `detect` - Detect build system
 //! - `config` - Show configuration
 //! - `rebuild` - Rebuild a service with dependencies
+//! - `watch` - Re-run a task on file changes
+//! - `history` - Show recorded runs
+//! - `trust` - Approve a project-level config file
 
 pub mod commands;
 pub mod mcp;
@@ -0,0 +1,157 @@
+//! Dotenv files for services
+//!
+//! A service's `env_file` is read each time one of its tasks runs, in the
+//! usual `.env` format:
+//! - `KEY=value` lines, optionally prefixed with `export`
+//! - blank lines and `#` comments are skipped; so is ` #...` after an
+//!   unquoted value
+//! - `'single'` quoted values are taken literally
+//! - `"double"` quoted values understand `\n`, `\t`, `\"` and `\\`
+//!
+//! Unquoted and double-quoted values are interpolated like the config
+//! itself (see [`interpolate`](super::interpolate)), with the variables
+//! defined earlier in the file taking precedence over the environment.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::interpolate::interpolate_with;
+
+/// Valid variable names
+static KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("Invalid regex"));
+
+/// Read and parse the env file at `path`
+///
+/// # Errors
+/// Fails if the file can't be read or has a malformed line.
+pub fn load_env_file(path: &Path) -> Result<HashMap<String, String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read env file {}", path.display()))?;
+    parse_env(&text).with_context(|| format!("Invalid env file {}", path.display()))
+}
+
+/// Parse env file contents
+///
+/// # Errors
+/// Fails on lines that aren't `KEY=value`, invalid names and unterminated
+/// quotes.
+pub fn parse_env(text: &str) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, raw)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=value", number + 1);
+        };
+        let key = key.trim();
+        if !KEY_RE.is_match(key) {
+            bail!("line {}: invalid variable name '{}'", number + 1, key);
+        }
+        let value = parse_value(raw.trim(), &vars)
+            .with_context(|| format!("line {}: value of {}", number + 1, key))?;
+        vars.insert(key.to_string(), value);
+    }
+    Ok(vars)
+}
+
+/// Unquote and interpolate a value
+fn parse_value(raw: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let interpolate = |text: &str| {
+        interpolate_with(text, |var| {
+            vars.get(var).cloned().or_else(|| std::env::var(var).ok())
+        })
+    };
+
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let Some(end) = rest.find('\'') else {
+            bail!("unterminated single quote");
+        };
+        return Ok(rest[..end].to_string());
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(interpolate(&value)),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        bail!("unterminated double quote");
+    }
+
+    let value = match raw.find(" #") {
+        Some(comment) => raw[..comment].trim_end(),
+        None => raw,
+    };
+    Ok(interpolate(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env() {
+        std::env::set_var("ENV_FILE_TEST_HOME", "/home/me");
+        let vars = parse_env(
+            r#"
+            # database
+            export DB_HOST=localhost
+            DB_URL=postgres://$DB_HOST:5432/app   # local
+            DATA=${ENV_FILE_TEST_HOME}/data
+            LITERAL='$DB_HOST #not a comment'
+            QUOTED="line one\nline \"two\""
+            EMPTY=
+            "#,
+        )
+        .unwrap();
+        std::env::remove_var("ENV_FILE_TEST_HOME");
+
+        assert_eq!(vars["DB_HOST"], "localhost");
+        assert_eq!(vars["DB_URL"], "postgres://localhost:5432/app");
+        assert_eq!(vars["DATA"], "/home/me/data");
+        assert_eq!(vars["LITERAL"], "$DB_HOST #not a comment");
+        assert_eq!(vars["QUOTED"], "line one\nline \"two\"");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn test_parse_env_errors() {
+        let err = parse_env("OK=1\nnot a variable\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{:#}", err);
+        assert!(parse_env("1BAD=x").is_err());
+        assert!(parse_env("KEY='open").is_err());
+        assert!(parse_env("KEY=\"open").is_err());
+    }
+
+    #[test]
+    fn test_load_env_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "TOKEN=abc\n").unwrap();
+        assert_eq!(load_env_file(&path).unwrap()["TOKEN"], "abc");
+
+        let err = load_env_file(&dir.path().join("missing")).unwrap_err();
+        assert!(err.to_string().contains("Failed to read env file"));
+    }
+}
@@ -7,7 +7,9 @@
 //!
 //! Shell command interpolation (`$(command)`) was removed for security reasons.
 //! Config files from untrusted sources (e.g., cloned repositories) could execute
-//! arbitrary code. Only environment variable interpolation is supported.
+//! arbitrary code. Only environment variable interpolation is supported, and
+//! a project's own `.makefilehub.toml` is only loaded once trusted (see
+//! [`trust`](super::trust)).
 
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -49,27 +51,32 @@ pub fn interpolate_string(s: &str) -> String {
 
 /// Interpolate environment variables: $VAR or ${VAR}
 fn interpolate_env_vars(s: &str) -> String {
-    // Match ${VAR} first (explicit boundaries)
-    let result = BRACKETED_RE
-        .replace_all(s, |caps: &regex::Captures| {
-            let var = &caps[1];
-            std::env::var(var).unwrap_or_else(|_| {
-                tracing::debug!("Environment variable '{}' not set", var);
-                String::new()
-            })
+    interpolate_with(s, |var| std::env::var(var).ok())
+}
+
+/// Interpolate `$VAR` and `${VAR}` with the values `lookup` gives, leaving
+/// unknown variables empty
+///
+/// ```
+/// use makefilehub::config::interpolate::interpolate_with;
+///
+/// let result = interpolate_with("${HOST}:$PORT", |var| (var == "HOST").then(|| "db".into()));
+/// assert_eq!(result, "db:");
+/// ```
+pub fn interpolate_with(s: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let value = |caps: &regex::Captures| {
+        let var = &caps[1];
+        lookup(var).unwrap_or_else(|| {
+            tracing::debug!("Environment variable '{}' not set", var);
+            String::new()
         })
-        .to_string();
+    };
+
+    // Match ${VAR} first (explicit boundaries)
+    let result = BRACKETED_RE.replace_all(s, value).to_string();
 
     // Then match $VAR (simple form)
-    SIMPLE_RE
-        .replace_all(&result, |caps: &regex::Captures| {
-            let var = &caps[1];
-            std::env::var(var).unwrap_or_else(|_| {
-                tracing::debug!("Environment variable '{}' not set", var);
-                String::new()
-            })
-        })
-        .to_string()
+    SIMPLE_RE.replace_all(&result, value).to_string()
 }
 
 /// Interpolate all string values in a Config
@@ -82,6 +89,22 @@ pub fn interpolate_config(config: &mut super::model::Config) {
         *pattern = interpolate_string(pattern);
     }
 
+    if let Some(ref mut dir) = config.defaults.cache_dir {
+        *dir = interpolate_string(dir);
+    }
+    if let Some(ref mut path) = config.history.path {
+        *path = interpolate_string(path);
+    }
+    if let Some(ref mut dir) = config.output.spill_dir {
+        *dir = interpolate_string(dir);
+    }
+    if let Some(ref mut path) = config.security.audit.path {
+        *path = interpolate_string(path);
+    }
+    for path in &mut config.security.sandbox.writable {
+        *path = interpolate_string(path);
+    }
+
     // Interpolate service configs
     for service in config.services.values_mut() {
         if let Some(ref mut dir) = service.project_dir {
@@ -90,9 +113,25 @@ pub fn interpolate_config(config: &mut super::model::Config) {
         if let Some(ref mut script) = service.script {
             *script = interpolate_string(script);
         }
+        if let Some(ref mut file) = service.env_file {
+            *file = interpolate_string(file);
+        }
         for value in service.env.values_mut() {
             *value = interpolate_string(value);
         }
+        for path in service
+            .watch_paths
+            .iter_mut()
+            .chain(service.test_reports.iter_mut())
+            .chain(
+                service
+                    .sandbox
+                    .iter_mut()
+                    .flat_map(|s| s.writable.iter_mut()),
+            )
+        {
+            *path = interpolate_string(path);
+        }
     }
 }
 
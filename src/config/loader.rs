@@ -5,6 +5,10 @@
 //! 2. `~/.config/makefilehub/config.toml`
 //! 3. `~/.makefilehub.toml`
 //! 4. `./.makefilehub.toml` (highest priority)
+//!
+//! The project file in the current directory only applies once approved
+//! with `makefilehub trust`, and never with its security keys (see
+//! [`trust`](super::trust)).
 
 use std::path::PathBuf;
 
@@ -15,6 +19,7 @@ use figment::{
 };
 
 use super::model::Config;
+use super::trust::{project_layer, TrustStore};
 
 /// Application name used for XDG directories
 const APP_NAME: &str = "makefilehub";
@@ -37,11 +42,16 @@ pub fn config_paths() -> Vec<PathBuf> {
     }
 
     // 4. Current directory / project root (highest priority)
-    paths.push(PathBuf::from(format!(".{}.toml", APP_NAME)));
+    paths.push(project_config_path());
 
     paths
 }
 
+/// Project-level config file in the current directory
+pub fn project_config_path() -> PathBuf {
+    PathBuf::from(format!(".{}.toml", APP_NAME))
+}
+
 /// Load configuration with XDG layering
 ///
 /// Configurations are merged in priority order, with later files
@@ -54,16 +64,38 @@ pub fn config_paths() -> Vec<PathBuf> {
 /// # Returns
 /// * `Result<Config>` - The merged configuration
 pub fn load_config(override_path: Option<&str>) -> Result<Config> {
+    let store = TrustStore::open_default()
+        .map_err(|e| tracing::warn!("Trust store unavailable: {:#}", e))
+        .ok();
+    load_config_with(override_path, store.as_ref())
+}
+
+/// Load configuration, applying the project-level file only if `store`
+/// trusts it
+pub fn load_config_with(override_path: Option<&str>, store: Option<&TrustStore>) -> Result<Config> {
     let mut figment = Figment::new();
 
     // Start with defaults
     figment = figment.merge(Serialized::defaults(Config::default()));
 
     // Layer configs from lowest to highest priority
+    let project = project_config_path();
+    let mut loaded = Vec::new();
     for path in config_paths() {
-        if path.exists() {
+        if !path.exists() {
+            continue;
+        }
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if path != project {
             tracing::debug!("Loading config from: {}", path.display());
             figment = figment.merge(Toml::file(&path));
+            loaded.push(canonical);
+        } else if !loaded.contains(&canonical) {
+            // Started from home, the project file is `~/.makefilehub.toml`,
+            // which is the user's own and already loaded
+            if let Some(layer) = project_layer(&path, store) {
+                figment = figment.merge(Toml::string(&layer));
+            }
         }
     }
 
@@ -86,6 +118,22 @@ pub fn load_config(override_path: Option<&str>) -> Result<Config> {
     figment.extract().context("Failed to load configuration")
 }
 
+/// The project config file in the current directory, canonicalized, if it
+/// exists but is ignored because `store` doesn't trust it
+pub fn untrusted_project_config(store: Option<&TrustStore>) -> Option<PathBuf> {
+    let canonical = project_config_path().canonicalize().ok()?;
+    let home = dirs::home_dir()
+        .and_then(|home| home.join(format!(".{}.toml", APP_NAME)).canonicalize().ok());
+    if home.as_ref() == Some(&canonical) {
+        return None;
+    }
+    let contents = std::fs::read(&canonical).ok()?;
+    match store {
+        Some(store) if store.is_trusted(&canonical, &contents) => None,
+        _ => Some(canonical),
+    }
+}
+
 /// Find all existing config files (for debugging/introspection)
 pub fn find_config_files() -> Vec<PathBuf> {
     config_paths().into_iter().filter(|p| p.exists()).collect()
@@ -1,12 +1,17 @@
 //! Configuration module for makefilehub
 //!
 //! Provides XDG-compliant layered configuration loading with
-//! environment variable and shell command interpolation.
+//! environment variable and shell command interpolation. Project-level
+//! config files only apply once trusted (see [`trust`]); services can load
+//! variables from dotenv files (see [`env_file`]).
 
+pub mod env_file;
 pub mod interpolate;
 pub mod loader;
 pub mod model;
+pub mod trust;
 
 pub use interpolate::interpolate_config;
-pub use loader::{config_paths, find_config_files, load_config};
+pub use loader::{config_paths, find_config_files, load_config, load_config_with};
 pub use model::*;
+pub use trust::TrustStore;
@@ -4,7 +4,11 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::env_file::load_env_file;
+use crate::error::TaskError;
 
 /// Root configuration structure
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -28,6 +32,238 @@ pub struct Config {
     /// Security settings
     #[serde(default)]
     pub security: SecurityConfig,
+
+    /// Run history settings
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    /// Error-suggestion rules, tried before the built-in ones
+    #[serde(default)]
+    pub suggestions: Vec<SuggestionRule>,
+
+    /// Task output capture settings
+    #[serde(default)]
+    pub output: OutputConfig,
+
+    /// Pseudo-terminal execution settings
+    #[serde(default)]
+    pub pty: PtyConfig,
+}
+
+/// Task output capture configuration
+///
+/// Output larger than `head_bytes + tail_bytes` keeps only its beginning and
+/// end; the full log is written to a spill file that `read_output` pages through.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OutputConfig {
+    /// Bytes kept from the start of stdout and of stderr
+    #[serde(default = "default_output_head_bytes")]
+    pub head_bytes: usize,
+
+    /// Bytes kept from the end of stdout and of stderr
+    #[serde(default = "default_output_tail_bytes")]
+    pub tail_bytes: usize,
+
+    /// Write the full output of truncated streams to spill files
+    #[serde(default = "default_output_spill")]
+    pub spill: bool,
+
+    /// Spill file directory (defaults to `$XDG_CACHE_HOME/makefilehub/output`)
+    pub spill_dir: Option<String>,
+
+    /// Number of most recent spill files kept
+    #[serde(default = "default_output_max_spill_files")]
+    pub max_spill_files: usize,
+
+    /// Remove ANSI escape sequences from captured output
+    #[serde(default = "default_output_strip_ansi")]
+    pub strip_ansi: bool,
+
+    /// Whether tasks are told to use colors
+    #[serde(default)]
+    pub color: ColorMode,
+}
+
+/// Pseudo-terminal execution, for tools that behave differently or hang
+/// without a TTY
+///
+/// Tasks run this way see a terminal of `cols` x `rows` on stdin, stdout and
+/// stderr; their output is captured as a single merged stream.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PtyConfig {
+    /// Run every task in a pseudo-terminal
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Tasks run in a pseudo-terminal even when `enabled` is off
+    #[serde(default)]
+    pub tasks: Vec<String>,
+
+    /// Terminal width in columns
+    #[serde(default = "default_pty_cols")]
+    pub cols: u16,
+
+    /// Terminal height in rows
+    #[serde(default = "default_pty_rows")]
+    pub rows: u16,
+}
+
+fn default_pty_cols() -> u16 {
+    120
+}
+
+fn default_pty_rows() -> u16 {
+    40
+}
+
+impl Default for PtyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tasks: vec![],
+            cols: default_pty_cols(),
+            rows: default_pty_rows(),
+        }
+    }
+}
+
+impl PtyConfig {
+    /// Whether `task` runs in a pseudo-terminal
+    pub fn applies_to(&self, task: &str) -> bool {
+        self.enabled || self.tasks.iter().any(|t| t == task)
+    }
+}
+
+/// Color output requested from tasks via `NO_COLOR` / `CLICOLOR_FORCE`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Colors only when the CLI prints to a terminal
+    #[default]
+    Auto,
+    /// Always ask for colors
+    Always,
+    /// Never ask for colors
+    Never,
+}
+
+fn default_output_head_bytes() -> usize {
+    16 * 1024
+}
+
+fn default_output_tail_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_output_spill() -> bool {
+    true
+}
+
+fn default_output_max_spill_files() -> usize {
+    100
+}
+
+fn default_output_strip_ansi() -> bool {
+    true
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            head_bytes: default_output_head_bytes(),
+            tail_bytes: default_output_tail_bytes(),
+            spill: default_output_spill(),
+            spill_dir: None,
+            max_spill_files: default_output_max_spill_files(),
+            strip_ansi: default_output_strip_ansi(),
+            color: ColorMode::default(),
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Directory spill files are written to, or `None` if spilling is disabled
+    pub fn spill_dir(&self) -> Option<PathBuf> {
+        self.spill.then(|| {
+            self.spill_dir
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(default_spill_dir)
+        })
+    }
+}
+
+/// The user's cache directory, else a directory in `$TMPDIR` named after
+/// the user's id so that users don't share one
+fn default_spill_dir() -> PathBuf {
+    if let Some(cache) = dirs::cache_dir() {
+        return cache.join("makefilehub").join("output");
+    }
+    #[cfg(unix)]
+    // SAFETY: geteuid has no preconditions
+    let name = format!("makefilehub-output-{}", unsafe { libc::geteuid() });
+    #[cfg(not(unix))]
+    let name = "makefilehub-output".to_string();
+    std::env::temp_dir().join(name)
+}
+
+/// Rule suggesting a fix when a failed command's stderr matches `match`
+///
+/// `suggestion` may refer to capture groups of the pattern as `$1` or
+/// `${name}` (`$$` for a literal `$`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SuggestionRule {
+    /// Regex matched against stderr
+    #[serde(rename = "match")]
+    pub pattern: String,
+
+    /// Only apply to this runner (`make`, `just`, `docker`, or a script name)
+    pub runner: Option<String>,
+
+    /// Suggestion text
+    pub suggestion: String,
+}
+
+/// Run history configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryConfig {
+    /// Record task runs and rebuild steps
+    #[serde(default = "default_history_enabled")]
+    pub enabled: bool,
+
+    /// History file (defaults to `$XDG_DATA_HOME/makefilehub/history.jsonl`)
+    pub path: Option<String>,
+
+    /// Number of most recent runs kept
+    #[serde(default = "default_history_max_entries")]
+    pub max_entries: usize,
+
+    /// Bytes kept from the end of each run's stdout and stderr
+    #[serde(default = "default_history_max_output")]
+    pub max_output_bytes: usize,
+}
+
+fn default_history_enabled() -> bool {
+    true
+}
+
+fn default_history_max_entries() -> usize {
+    1000
+}
+
+fn default_history_max_output() -> usize {
+    8 * 1024
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_history_enabled(),
+            path: None,
+            max_entries: default_history_max_entries(),
+            max_output_bytes: default_history_max_output(),
+        }
+    }
 }
 
 /// Security configuration
@@ -43,6 +279,342 @@ pub struct SecurityConfig {
     /// Only enable if you trust all callers and understand the risks
     #[serde(default)]
     pub allow_any_path: bool,
+
+    /// Resource limits for tasks run by the MCP server
+    #[serde(default)]
+    pub limits: ResourceLimits,
+
+    /// Namespace sandbox for tasks run by the MCP server
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+
+    /// Only allow the MCP tools that inspect projects (`list_tasks`,
+    /// `detect_runner`); every other tool is refused
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Which tasks MCP callers may run
+    #[serde(default)]
+    pub policy: TaskPolicy,
+
+    /// Validation of the arguments MCP callers pass to tasks
+    #[serde(default)]
+    pub args: ArgsConfig,
+
+    /// Audit log of MCP tool calls
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Masking of secrets in task output
+    #[serde(default)]
+    pub redact: RedactConfig,
+
+    /// How the roots an MCP client reports limit the paths it may use
+    #[serde(default)]
+    pub roots: RootsMode,
+
+    /// Environment that tasks run by the MCP server start from
+    #[serde(default)]
+    pub env_mode: EnvMode,
+
+    /// Globs on variable names passed through from the server's environment
+    /// in `clean` and `allowlist` mode
+    #[serde(default)]
+    pub env_passthrough: Vec<String>,
+
+    /// Canonical directories the MCP client has open (its roots), if it
+    /// reported any; set by the server, never read from config files
+    #[serde(skip)]
+    pub client_roots: Option<Vec<PathBuf>>,
+}
+
+/// How the roots an MCP client reports limit the paths it may use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RootsMode {
+    /// Paths must be inside one of the roots and also allowed by
+    /// `allowed_paths` (or the working directory)
+    #[default]
+    Intersect,
+    /// The roots take the place of `allowed_paths` and the working directory
+    Replace,
+    /// Roots are not requested and don't limit anything
+    Ignore,
+}
+
+/// Environment that a task starts from, before its service's `env_file`
+/// and `env` are applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvMode {
+    /// The server's whole environment
+    #[default]
+    Inherit,
+    /// A few basic variables (`PATH`, `HOME`, `LANG`, ...) plus
+    /// `env_passthrough`
+    Clean,
+    /// Only the variables matching `env_passthrough`
+    Allowlist,
+}
+
+/// Masking of secrets in task output
+///
+/// Applies to the CLI as well as the MCP server: output is masked before it
+/// is returned, printed, recorded in the run history or spilled to a file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RedactConfig {
+    /// Mask secrets in task output
+    #[serde(default = "default_redact_enabled")]
+    pub enabled: bool,
+
+    /// Globs on environment variable names whose values are secret
+    /// (matched case-insensitively)
+    #[serde(default = "default_redact_env")]
+    pub env: Vec<String>,
+
+    /// Regexes for secrets besides the built-in key formats; a `secret`
+    /// group limits the mask to that part of the match
+    #[serde(default)]
+    pub patterns: Vec<String>,
+
+    /// Mask the built-in key formats (AWS, GitHub, Slack, private keys, ...)
+    #[serde(default = "default_redact_builtin")]
+    pub builtin: bool,
+}
+
+fn default_redact_enabled() -> bool {
+    true
+}
+
+fn default_redact_env() -> Vec<String> {
+    vec![
+        "*_TOKEN".to_string(),
+        "*_SECRET".to_string(),
+        "*PASSWORD*".to_string(),
+    ]
+}
+
+fn default_redact_builtin() -> bool {
+    true
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_redact_enabled(),
+            env: default_redact_env(),
+            patterns: Vec::new(),
+            builtin: default_redact_builtin(),
+        }
+    }
+}
+
+/// Audit log of MCP tool calls
+///
+/// One JSON line per call, appended to `path`; when the file would grow
+/// past `max_bytes` it is rotated to `path.1`, `path.1` to `path.2` and so
+/// on, keeping `max_files` rotated files.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuditConfig {
+    /// Record MCP tool calls
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Log file (default: `$XDG_STATE_HOME/makefilehub/audit.jsonl`)
+    pub path: Option<String>,
+
+    /// Rotate the log once it reaches this size
+    #[serde(default = "default_audit_max_bytes")]
+    pub max_bytes: u64,
+
+    /// Rotated files to keep
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+fn default_audit_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> usize {
+    5
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_bytes: default_audit_max_bytes(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+/// Validation of the named and positional arguments MCP callers pass to tasks
+///
+/// Named argument keys must be valid for the runner (`KEY` for make and
+/// just, `key` of a `--key` option for scripts). Make and just variables
+/// that change how the tool itself runs (`MAKEFLAGS`, `SHELL`, `PATH`, ...)
+/// are refused, as are values with control characters.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ArgsConfig {
+    /// Variable names to refuse besides the built-in ones
+    #[serde(default)]
+    pub deny_vars: Vec<String>,
+
+    /// Built-in refused variable names to accept anyway
+    #[serde(default)]
+    pub allow_vars: Vec<String>,
+
+    /// Accept positional arguments starting with `-`, which scripts and
+    /// just would read as options
+    #[serde(default)]
+    pub allow_flags: bool,
+
+    /// Escape `$` in make variable values so make does not expand them
+    /// (`$(shell ...)` would otherwise run)
+    #[serde(default = "default_escape_make_values")]
+    pub escape_make_values: bool,
+}
+
+fn default_escape_make_values() -> bool {
+    true
+}
+
+impl Default for ArgsConfig {
+    fn default() -> Self {
+        Self {
+            deny_vars: Vec::new(),
+            allow_vars: Vec::new(),
+            allow_flags: false,
+            escape_make_values: default_escape_make_values(),
+        }
+    }
+}
+
+/// Which tasks MCP callers may run
+///
+/// Patterns are globs on task names (`deploy*`, `db-*`), matched against the
+/// requested name and the name it maps to through a service's task overrides.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TaskPolicy {
+    /// Only tasks matching one of these may run; empty means every task
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Tasks that never run
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Tasks that only run when the call passes `confirm: true`
+    #[serde(default)]
+    pub confirm: Vec<String>,
+}
+
+impl TaskPolicy {
+    /// Whether the policy lets every task run unconfirmed
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && self.confirm.is_empty()
+    }
+
+    /// Combine with a service's policy: its `deny` and `confirm` patterns
+    /// are added to these, and its `allow` list replaces this one if set
+    pub fn with(&self, service: &TaskPolicy) -> Self {
+        Self {
+            allow: if service.allow.is_empty() {
+                self.allow.clone()
+            } else {
+                service.allow.clone()
+            },
+            deny: self.deny.iter().chain(&service.deny).cloned().collect(),
+            confirm: self
+                .confirm
+                .iter()
+                .chain(&service.confirm)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Resource limits applied to a task's process before it starts
+///
+/// Unset limits are inherited. Limits can only be lowered: values above the
+/// inherited hard limit are capped to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ResourceLimits {
+    /// CPU time in seconds (`RLIMIT_CPU`)
+    pub cpu_secs: Option<u64>,
+
+    /// Virtual address space in MiB (`RLIMIT_AS`)
+    pub address_space_mb: Option<u64>,
+
+    /// Open file descriptors (`RLIMIT_NOFILE`)
+    pub open_files: Option<u64>,
+
+    /// Processes of the user, counted across the whole system (`RLIMIT_NPROC`)
+    pub max_processes: Option<u64>,
+
+    /// Scheduling niceness (0-19; negative values need privileges)
+    pub nice: Option<i32>,
+
+    /// I/O scheduling class (Linux)
+    pub ionice: Option<IoClass>,
+
+    /// Priority within the best-effort class (0 = highest, 7 = lowest)
+    pub ionice_level: Option<u8>,
+}
+
+impl ResourceLimits {
+    /// Whether any limit is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// I/O scheduling class of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoClass {
+    /// Only gets disk time when no one else needs it
+    Idle,
+    /// Normal class, with a priority from `ionice_level`
+    BestEffort,
+}
+
+/// Linux namespace sandbox for tasks
+///
+/// The task runs in new user and mount namespaces where the whole
+/// filesystem is read-only except the project directory and `writable`,
+/// and, unless `network` is set, in a network namespace with only loopback.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SandboxConfig {
+    /// Run tasks in the sandbox
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Keep network access
+    #[serde(default)]
+    pub network: bool,
+
+    /// Paths writable besides the project directory (missing ones are skipped)
+    #[serde(default = "default_sandbox_writable")]
+    pub writable: Vec<String>,
+}
+
+fn default_sandbox_writable() -> Vec<String> {
+    vec!["/tmp".to_string()]
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            network: false,
+            writable: default_sandbox_writable(),
+        }
+    }
 }
 
 fn default_allowed_paths() -> Vec<String> {
@@ -54,6 +626,17 @@ impl Default for SecurityConfig {
         Self {
             allowed_paths: default_allowed_paths(),
             allow_any_path: false,
+            limits: ResourceLimits::default(),
+            sandbox: SandboxConfig::default(),
+            read_only: false,
+            policy: TaskPolicy::default(),
+            args: ArgsConfig::default(),
+            audit: AuditConfig::default(),
+            redact: RedactConfig::default(),
+            roots: RootsMode::default(),
+            env_mode: EnvMode::default(),
+            env_passthrough: Vec::new(),
+            client_roots: None,
         }
     }
 }
@@ -76,6 +659,24 @@ pub struct Defaults {
     /// Default timeout in seconds
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+
+    /// Maximum number of rebuild steps run concurrently (minimum 1)
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: usize,
+
+    /// Maximum number of watch jobs running at once (minimum 1)
+    #[serde(default = "default_max_watches")]
+    pub max_watches: usize,
+
+    /// Directory for task cache entries (defaults to `$XDG_CACHE_HOME/makefilehub`)
+    pub cache_dir: Option<String>,
+}
+
+impl Defaults {
+    /// Default timeout as a `Duration` (`None` when set to 0, meaning no timeout)
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        (self.timeout > 0).then(|| Duration::from_secs(self.timeout))
+    }
 }
 
 fn default_runner_priority() -> Vec<String> {
@@ -90,6 +691,14 @@ fn default_timeout() -> u64 {
     300
 }
 
+fn default_max_parallel() -> usize {
+    4
+}
+
+fn default_max_watches() -> usize {
+    8
+}
+
 impl Default for Defaults {
     fn default() -> Self {
         Self {
@@ -97,6 +706,9 @@ impl Default for Defaults {
             default_script: default_script(),
             task_aliases: HashMap::new(),
             timeout: default_timeout(),
+            max_parallel: default_max_parallel(),
+            max_watches: default_max_watches(),
+            cache_dir: None,
         }
     }
 }
@@ -234,10 +846,14 @@ pub struct ServiceConfig {
     /// Script to use (for script runner)
     pub script: Option<String>,
 
-    /// Services that depend on this one (will be restarted after build)
+    /// Services this one depends on (rebuilt first, in dependency order)
     #[serde(default)]
     pub depends_on: Vec<String>,
 
+    /// Services that depend on this one (restarted via `up` after it is rebuilt)
+    #[serde(default)]
+    pub dependents: Vec<String>,
+
     /// Containers to force-recreate after build
     #[serde(default)]
     pub force_recreate: Vec<String>,
@@ -250,8 +866,191 @@ pub struct ServiceConfig {
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Names of `env` entries whose values are masked in task output
+    #[serde(default)]
+    pub secret_env: Vec<String>,
+
+    /// Dotenv file (relative to `project_dir` or absolute) loaded into the
+    /// environment before `env`
+    pub env_file: Option<String>,
+
+    /// Environment the service's tasks start from, replacing
+    /// `[security].env_mode`
+    pub env_mode: Option<EnvMode>,
+
+    /// Variables passed through in `clean` and `allowlist` mode, replacing
+    /// `[security].env_passthrough`
+    pub env_passthrough: Option<Vec<String>>,
+
     /// Timeout override in seconds
     pub timeout: Option<u64>,
+
+    /// Health check run after the service is brought up
+    pub health: Option<HealthConfig>,
+
+    /// File watching settings for `watch`
+    #[serde(default)]
+    pub watch: WatchConfig,
+
+    /// Extra paths (relative to `project_dir` or absolute) whose changes
+    /// affect this service, for `rebuild --changed`
+    #[serde(default)]
+    pub watch_paths: Vec<String>,
+
+    /// Content-hash caching, keyed by task name
+    #[serde(default)]
+    pub cache: HashMap<String, CacheConfig>,
+
+    /// Globs (relative to `project_dir`) of JUnit XML reports written by test runs
+    #[serde(default)]
+    pub test_reports: Vec<String>,
+
+    /// Error-suggestion rules, tried before the global ones
+    #[serde(default)]
+    pub suggestions: Vec<SuggestionRule>,
+
+    /// Pseudo-terminal settings, replacing the global `[pty]`
+    pub pty: Option<PtyConfig>,
+
+    /// Resource limits, replacing `[security.limits]`
+    pub limits: Option<ResourceLimits>,
+
+    /// Sandbox settings, replacing `[security.sandbox]`
+    pub sandbox: Option<SandboxConfig>,
+
+    /// Task policy, combined with `[security.policy]`
+    #[serde(default)]
+    pub policy: TaskPolicy,
+
+    /// Argument validation, replacing `[security.args]`
+    pub args: Option<ArgsConfig>,
+}
+
+/// Content-hash caching for a task
+///
+/// The task is skipped when its input files, command, arguments and
+/// environment are unchanged since its last successful run and its outputs
+/// are still as that run left them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CacheConfig {
+    /// Globs (relative to the project) of files whose contents key the cache
+    pub inputs: Vec<String>,
+
+    /// Files or directories (relative to the project) the task produces
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+/// File watching settings for a service
+///
+/// Changes to files ignored by the project's `.gitignore` never trigger a run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WatchConfig {
+    /// Only changes matching one of these globs (relative to the project) trigger a run;
+    /// empty means every file
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Changes matching these globs never trigger a run
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Quiet period in milliseconds after the last change before re-running
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_debounce_ms() -> u64 {
+    300
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            include: vec![],
+            exclude: vec![],
+            debounce_ms: default_debounce_ms(),
+        }
+    }
+}
+
+/// Health check for a service, run after its `up` task succeeds
+///
+/// ```toml
+/// [services.web-api.health]
+/// type = "http"        # http | tcp | command | compose
+/// port = 8080
+/// path = "/health"
+/// retries = 10
+/// interval = 2
+/// timeout = 5
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HealthConfig {
+    /// What to check
+    #[serde(flatten)]
+    pub check: HealthCheck,
+
+    /// Number of attempts before the service is reported unhealthy
+    #[serde(default = "default_health_retries")]
+    pub retries: u32,
+
+    /// Seconds to wait between attempts
+    #[serde(default = "default_health_interval")]
+    pub interval: u64,
+
+    /// Timeout in seconds for a single attempt
+    #[serde(default = "default_health_timeout")]
+    pub timeout: u64,
+}
+
+impl HealthConfig {
+    /// Delay between attempts as a `Duration`
+    pub fn interval_duration(&self) -> Duration {
+        Duration::from_secs(self.interval)
+    }
+
+    /// Per-attempt timeout as a `Duration` (at least one second)
+    pub fn timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.timeout.max(1))
+    }
+}
+
+/// Kind of health check
+///
+/// HTTP and TCP checks always target `127.0.0.1` so that a service config
+/// can't be used to probe other hosts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HealthCheck {
+    /// HTTP GET against a local port; healthy on a 2xx or 3xx response
+    Http {
+        port: u16,
+        #[serde(default = "default_health_path")]
+        path: String,
+    },
+    /// TCP connect to a local port
+    Tcp { port: u16 },
+    /// Command (argv, no shell) run in the project directory; healthy on exit 0
+    Command { command: Vec<String> },
+    /// `docker compose ps` status of a compose service in the project directory
+    Compose { service: String },
+}
+
+fn default_health_retries() -> u32 {
+    10
+}
+
+fn default_health_interval() -> u64 {
+    2
+}
+
+fn default_health_timeout() -> u64 {
+    5
+}
+
+fn default_health_path() -> String {
+    "/".to_string()
 }
 
 /// Fully resolved service configuration (after applying defaults)
@@ -262,10 +1061,109 @@ pub struct ResolvedService {
     pub runner: Option<String>,
     pub script: Option<String>,
     pub depends_on: Vec<String>,
+    pub dependents: Vec<String>,
     pub force_recreate: Vec<String>,
     pub tasks: HashMap<String, String>,
     pub env: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secret_env: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    /// The service's `env_mode`, or `[security].env_mode`
+    pub env_mode: EnvMode,
+    /// The service's `env_passthrough`, or `[security].env_passthrough`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_passthrough: Vec<String>,
     pub timeout: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthConfig>,
+    pub watch: WatchConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub watch_paths: Vec<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub cache: HashMap<String, CacheConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub test_reports: Vec<String>,
+    /// Service rules followed by the global `[[suggestions]]`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<SuggestionRule>,
+    /// The service's `pty` settings, or the global `[pty]`
+    pub pty: PtyConfig,
+    /// The service's `limits`, or `[security.limits]`
+    #[serde(skip_serializing_if = "ResourceLimits::is_empty")]
+    pub limits: ResourceLimits,
+    /// The service's `sandbox` settings, or `[security.sandbox]`
+    pub sandbox: SandboxConfig,
+    #[serde(skip_serializing_if = "TaskPolicy::is_empty")]
+    pub policy: TaskPolicy,
+    pub args: ArgsConfig,
+}
+
+impl ResolvedService {
+    /// Map a generic task name (e.g. "build") through the service's task overrides
+    pub fn task_name<'a>(&'a self, task: &'a str) -> &'a str {
+        self.tasks.get(task).map(|s| s.as_str()).unwrap_or(task)
+    }
+
+    /// Runner to force for this service, in the form used for runner selection
+    ///
+    /// Returns `"make"`, `"just"` or `"script:<path>"`, or `None` to auto-detect.
+    pub fn runner_override(&self) -> Option<String> {
+        match (self.runner.as_deref(), self.script.as_deref()) {
+            (Some("script") | None, Some(script)) => Some(format!("script:{}", script)),
+            (Some(runner), _) => Some(runner.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Variables set on the service's tasks: its `env_file`, if any, with
+    /// `env` on top
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If the env file can't be read or parsed
+    pub fn environment(&self) -> Result<HashMap<String, String>, TaskError> {
+        let mut env = match &self.env_file {
+            Some(file) => {
+                let path = Path::new(&self.project_dir).join(file);
+                load_env_file(&path)
+                    .map_err(|e| TaskError::Config(format!("Service '{}': {:#}", self.name, e)))?
+            }
+            None => HashMap::new(),
+        };
+        env.extend(self.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(env)
+    }
+
+    /// Values of the variables listed in `secret_env`, looked up in `env`
+    /// (see [`environment`](Self::environment))
+    pub fn secret_values(&self, env: &HashMap<String, String>) -> Vec<String> {
+        self.secret_env
+            .iter()
+            .filter_map(|key| env.get(key).cloned())
+            .collect()
+    }
+
+    /// Timeout as a `Duration` (`None` when set to 0, meaning no timeout)
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        (self.timeout > 0).then(|| Duration::from_secs(self.timeout))
+    }
+
+    /// Cache settings for a task, by generic name (e.g. "build") or by the
+    /// name it maps to through the task overrides
+    pub fn cache_config(&self, task: &str) -> Option<&CacheConfig> {
+        self.cache.get(task).or_else(|| {
+            self.cache
+                .iter()
+                .find(|(name, _)| self.task_name(name) == task)
+                .map(|(_, cache)| cache)
+        })
+    }
+
+    /// Whether a task runs in a pseudo-terminal, by generic name (e.g.
+    /// "up") or by the name it maps to through the task overrides
+    pub fn uses_pty(&self, task: &str) -> bool {
+        self.pty.applies_to(task) || self.pty.tasks.iter().any(|t| self.task_name(t) == task)
+    }
 }
 
 impl Config {
@@ -273,6 +1171,11 @@ impl Config {
     ///
     /// Returns Ok(canonicalized_path) if the path is allowed, Err otherwise.
     /// This prevents path traversal attacks and restricts access to configured directories.
+    ///
+    /// The path as written is checked before anything on disk is looked at,
+    /// so a rejected path doesn't reveal whether it exists. The canonical
+    /// path is checked again, so a symlink can't lead outside; callers should
+    /// use the returned path from then on.
     pub fn validate_path(&self, path: &Path) -> Result<std::path::PathBuf, String> {
         // If allow_any_path is enabled, skip validation (DANGEROUS)
         if self.security.allow_any_path {
@@ -282,8 +1185,11 @@ impl Config {
                 .map_err(|e| format!("Failed to canonicalize path: {}", e));
         }
 
+        let requested = absolute_path(path)?;
+        self.check_allowed(&requested)?;
+
         // Canonicalize the requested path
-        let canonical = path.canonicalize().map_err(|e| {
+        let canonical = requested.canonicalize().map_err(|e| {
             format!(
                 "Path '{}' does not exist or is not accessible: {}",
                 path.display(),
@@ -291,34 +1197,80 @@ impl Config {
             )
         })?;
 
-        // Get expanded allowed paths
-        let allowed = self.get_expanded_allowed_paths();
+        // Symlinks on the way must not lead outside either
+        self.check_allowed(&canonical).map_err(|e| {
+            format!(
+                "Path '{}' leads outside through a symlink: {}",
+                requested.display(),
+                e
+            )
+        })?;
+        Ok(canonical)
+    }
 
-        // Check if canonical path starts with any allowed path
-        for allowed_path in &allowed {
-            if let Ok(allowed_canonical) = std::path::Path::new(allowed_path).canonicalize() {
-                if canonical.starts_with(&allowed_canonical) {
-                    return Ok(canonical);
-                }
+    /// Check that `path`, as written, is within allowed directories
+    ///
+    /// `.` and `..` are resolved without looking at the filesystem, so this
+    /// can decide on a path before its existence is checked;
+    /// [`validate_path`](Self::validate_path) still has to follow symlinks.
+    pub fn check_path_allowed(&self, path: &Path) -> Result<(), String> {
+        if self.security.allow_any_path {
+            return Ok(());
+        }
+        self.check_allowed(&absolute_path(path)?)
+    }
+
+    /// Check an absolute, normalized path against the client's roots, the
+    /// allowed paths and the current directory
+    fn check_allowed(&self, path: &Path) -> Result<(), String> {
+        // A client's roots scope its session to the workspaces it opened
+        if let Some(roots) = self.client_roots() {
+            if !roots.iter().any(|root| path.starts_with(root)) {
+                return Err(format!(
+                    "Path '{}' is outside the client's roots: {:?}",
+                    path.display(),
+                    roots
+                ));
+            }
+            if self.security.roots == RootsMode::Replace {
+                return Ok(());
             }
         }
 
+        // Allowed directories match as configured or canonicalized
+        let inside = |dir: &Path| {
+            path.starts_with(dir) || dir.canonicalize().is_ok_and(|dir| path.starts_with(dir))
+        };
+
+        // Get expanded allowed paths
+        let allowed = self.get_expanded_allowed_paths();
+        if allowed
+            .iter()
+            .any(|allowed_path| inside(Path::new(allowed_path)))
+        {
+            return Ok(());
+        }
+
         // Also allow current working directory
-        if let Ok(cwd) = std::env::current_dir() {
-            if let Ok(cwd_canonical) = cwd.canonicalize() {
-                if canonical.starts_with(&cwd_canonical) {
-                    return Ok(canonical);
-                }
-            }
+        if std::env::current_dir().is_ok_and(|cwd| inside(&cwd)) {
+            return Ok(());
         }
 
         Err(format!(
             "Path '{}' is outside allowed directories. Allowed: {:?}",
-            canonical.display(),
+            path.display(),
             allowed
         ))
     }
 
+    /// The MCP client's roots, if it reported any and they apply
+    pub fn client_roots(&self) -> Option<&[PathBuf]> {
+        match self.security.roots {
+            RootsMode::Ignore => None,
+            RootsMode::Intersect | RootsMode::Replace => self.security.client_roots.as_deref(),
+        }
+    }
+
     /// Get expanded allowed paths (with $HOME expanded)
     fn get_expanded_allowed_paths(&self) -> Vec<String> {
         self.security
@@ -339,6 +1291,9 @@ impl Config {
     }
 
     /// Get resolved configuration for a service
+    ///
+    /// A service with `runner = "script"` and no `script` falls back to
+    /// `defaults.default_script`.
     pub fn get_service(&self, name: &str) -> ResolvedService {
         let service = self.services.get(name);
 
@@ -346,20 +1301,62 @@ impl Config {
             .and_then(|s| s.project_dir.clone())
             .unwrap_or_else(|| self.resolve_project_dir(name));
 
+        let runner = service.and_then(|s| s.runner.clone());
+        let script = service.and_then(|s| s.script.clone()).or_else(|| {
+            (runner.as_deref() == Some("script")).then(|| self.defaults.default_script.clone())
+        });
+
         ResolvedService {
             name: name.to_string(),
             project_dir,
-            runner: service.and_then(|s| s.runner.clone()),
-            script: service.and_then(|s| s.script.clone()),
+            runner,
+            script,
             depends_on: service.map(|s| s.depends_on.clone()).unwrap_or_default(),
+            dependents: service.map(|s| s.dependents.clone()).unwrap_or_default(),
             force_recreate: service
                 .map(|s| s.force_recreate.clone())
                 .unwrap_or_default(),
             tasks: service.map(|s| s.tasks.clone()).unwrap_or_default(),
             env: service.map(|s| s.env.clone()).unwrap_or_default(),
+            secret_env: service.map(|s| s.secret_env.clone()).unwrap_or_default(),
+            env_file: service.and_then(|s| s.env_file.clone()),
+            env_mode: service
+                .and_then(|s| s.env_mode)
+                .unwrap_or(self.security.env_mode),
+            env_passthrough: service
+                .and_then(|s| s.env_passthrough.clone())
+                .unwrap_or_else(|| self.security.env_passthrough.clone()),
             timeout: service
                 .and_then(|s| s.timeout)
                 .unwrap_or(self.defaults.timeout),
+            health: service.and_then(|s| s.health.clone()),
+            watch: service.map(|s| s.watch.clone()).unwrap_or_default(),
+            watch_paths: service.map(|s| s.watch_paths.clone()).unwrap_or_default(),
+            cache: service.map(|s| s.cache.clone()).unwrap_or_default(),
+            test_reports: service.map(|s| s.test_reports.clone()).unwrap_or_default(),
+            suggestions: service
+                .map(|s| s.suggestions.iter())
+                .into_iter()
+                .flatten()
+                .chain(&self.suggestions)
+                .cloned()
+                .collect(),
+            pty: service
+                .and_then(|s| s.pty.clone())
+                .unwrap_or_else(|| self.pty.clone()),
+            limits: service
+                .and_then(|s| s.limits)
+                .unwrap_or(self.security.limits),
+            sandbox: service
+                .and_then(|s| s.sandbox.clone())
+                .unwrap_or_else(|| self.security.sandbox.clone()),
+            policy: match service {
+                Some(s) => self.security.policy.with(&s.policy),
+                None => self.security.policy.clone(),
+            },
+            args: service
+                .and_then(|s| s.args.clone())
+                .unwrap_or_else(|| self.security.args.clone()),
         }
     }
 
@@ -403,6 +1400,29 @@ impl Config {
     }
 }
 
+/// `path` made absolute against the current directory, with `.` and `..`
+/// resolved lexically
+fn absolute_path(path: &Path) -> Result<PathBuf, String> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("Failed to get current directory: {}", e))?
+            .join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    Ok(normalized)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -417,6 +1437,7 @@ mod tests {
         );
         assert_eq!(config.defaults.default_script, "./run.sh");
         assert_eq!(config.defaults.timeout, 300);
+        assert_eq!(config.defaults.max_parallel, 4);
     }
 
     #[test]
@@ -504,6 +1525,7 @@ mod tests {
             project_dir = "/projects/web-api"
             runner = "script"
             depends_on = ["frontend"]
+            dependents = ["gateway"]
             force_recreate = ["nginx"]
             timeout = 120
         "#;
@@ -515,10 +1537,112 @@ mod tests {
         assert_eq!(resolved.project_dir, "/projects/web-api");
         assert_eq!(resolved.runner, Some("script".to_string()));
         assert_eq!(resolved.depends_on, vec!["frontend"]);
+        assert_eq!(resolved.dependents, vec!["gateway"]);
         assert_eq!(resolved.force_recreate, vec!["nginx"]);
         assert_eq!(resolved.timeout, 120);
     }
 
+    #[test]
+    fn test_service_health_config() {
+        let toml = r#"
+            [services.api.health]
+            type = "http"
+            port = 8080
+            path = "/health"
+            retries = 3
+
+            [services.db.health]
+            type = "tcp"
+            port = 5432
+
+            [services.worker.health]
+            type = "command"
+            command = ["./healthcheck", "--quick"]
+
+            [services.web.health]
+            type = "compose"
+            service = "web"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+
+        let api = config.get_service("api").health.unwrap();
+        assert_eq!(
+            api.check,
+            HealthCheck::Http {
+                port: 8080,
+                path: "/health".to_string()
+            }
+        );
+        assert_eq!(api.retries, 3);
+        assert_eq!(api.interval, 2);
+        assert_eq!(api.timeout, 5);
+
+        let db = config.get_service("db").health.unwrap();
+        assert_eq!(db.check, HealthCheck::Tcp { port: 5432 });
+
+        let worker = config.get_service("worker").health.unwrap();
+        assert_eq!(
+            worker.check,
+            HealthCheck::Command {
+                command: vec!["./healthcheck".to_string(), "--quick".to_string()]
+            }
+        );
+
+        let web = config.get_service("web").health.unwrap();
+        assert_eq!(
+            web.check,
+            HealthCheck::Compose {
+                service: "web".to_string()
+            }
+        );
+
+        assert!(config.get_service("other").health.is_none());
+    }
+
+    #[test]
+    fn test_service_watch_config() {
+        let toml = r#"
+            [services.api.watch]
+            include = ["src/**/*.rs"]
+            exclude = ["src/generated/**"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let watch = config.get_service("api").watch;
+        assert_eq!(watch.include, vec!["src/**/*.rs"]);
+        assert_eq!(watch.exclude, vec!["src/generated/**"]);
+        assert_eq!(watch.debounce_ms, 300);
+
+        assert_eq!(config.get_service("other").watch, WatchConfig::default());
+    }
+
+    #[test]
+    fn test_service_cache_config() {
+        let toml = r#"
+            [services.api.tasks]
+            build = "compile"
+
+            [services.api.cache.build]
+            inputs = ["src/**/*.rs", "Cargo.toml"]
+            outputs = ["target/release/api"]
+
+            [services.api.cache.lint]
+            inputs = ["src/**"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let service = config.get_service("api");
+
+        let build = service.cache_config("build").unwrap();
+        assert_eq!(build.inputs, vec!["src/**/*.rs", "Cargo.toml"]);
+        assert_eq!(build.outputs, vec!["target/release/api"]);
+        // Mapped task names find the same settings
+        assert_eq!(service.cache_config("compile"), Some(build));
+        assert!(service.cache_config("lint").unwrap().outputs.is_empty());
+        assert!(service.cache_config("test").is_none());
+    }
+
     #[test]
     fn test_get_service_without_config() {
         let config = Config::default();
@@ -530,6 +1654,61 @@ mod tests {
         assert_eq!(resolved.timeout, 300); // Default timeout
     }
 
+    #[test]
+    fn test_get_service_script_runner_defaults_script() {
+        let toml = r#"
+            [defaults]
+            default_script = "./build.sh"
+
+            [services.scripted]
+            project_dir = "/projects/scripted"
+            runner = "script"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let resolved = config.get_service("scripted");
+
+        assert_eq!(resolved.script, Some("./build.sh".to_string()));
+        assert_eq!(
+            resolved.runner_override(),
+            Some("script:./build.sh".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolved_service_overrides() {
+        let toml = r#"
+            [services.api]
+            project_dir = "/projects/api"
+            runner = "just"
+            timeout = 0
+
+            [services.api.tasks]
+            build = "compile"
+
+            [services.tool]
+            project_dir = "/projects/tool"
+            script = "./task.sh"
+            timeout = 45
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+
+        let api = config.get_service("api");
+        assert_eq!(api.task_name("build"), "compile");
+        assert_eq!(api.task_name("test"), "test");
+        assert_eq!(api.runner_override(), Some("just".to_string()));
+        assert_eq!(api.timeout_duration(), None);
+
+        let tool = config.get_service("tool");
+        assert_eq!(tool.runner_override(), Some("script:./task.sh".to_string()));
+        assert_eq!(tool.timeout_duration(), Some(Duration::from_secs(45)));
+
+        let unknown = config.get_service("unknown");
+        assert_eq!(unknown.runner_override(), None);
+        assert_eq!(unknown.timeout_duration(), Some(Duration::from_secs(300)));
+    }
+
     #[test]
     fn test_list_services() {
         let toml = r#"
@@ -646,6 +1825,234 @@ mod tests {
         let _: Config = toml::from_str(&toml_str).unwrap();
     }
 
+    #[test]
+    fn test_suggestion_rules() {
+        let config: Config = toml::from_str(
+            r#"
+[[suggestions]]
+match = "ECONNREFUSED"
+suggestion = "Start the database"
+
+[services.api]
+[[services.api.suggestions]]
+match = "migration (\\d+)"
+runner = "make"
+suggestion = "Run migration $1"
+"#,
+        )
+        .unwrap();
+
+        let patterns: Vec<_> = config
+            .get_service("api")
+            .suggestions
+            .iter()
+            .map(|rule| rule.pattern.clone())
+            .collect();
+        assert_eq!(patterns, vec![r"migration (\d+)", "ECONNREFUSED"]);
+        assert_eq!(
+            config.get_service("api").suggestions[0].runner.as_deref(),
+            Some("make")
+        );
+        assert_eq!(config.get_service("other").suggestions.len(), 1);
+    }
+
+    #[test]
+    fn test_security_limits_and_sandbox() {
+        let config: Config = toml::from_str(
+            r#"
+[security.limits]
+cpu_secs = 600
+nice = 10
+ionice = "best-effort"
+ionice_level = 7
+
+[security.sandbox]
+enabled = true
+
+[services.api]
+limits = { address_space_mb = 2048 }
+sandbox = { enabled = true, network = true, writable = ["/var/cache/api"] }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.security.limits.cpu_secs, Some(600));
+        assert_eq!(config.security.limits.ionice, Some(IoClass::BestEffort));
+        assert_eq!(config.security.sandbox.writable, vec!["/tmp"]);
+        assert!(!config.security.sandbox.network);
+
+        let other = config.get_service("other");
+        assert_eq!(other.limits, config.security.limits);
+        assert!(other.sandbox.enabled);
+
+        let api = config.get_service("api");
+        assert_eq!(api.limits.address_space_mb, Some(2048));
+        assert_eq!(api.limits.cpu_secs, None);
+        assert!(api.sandbox.network);
+        assert_eq!(api.sandbox.writable, vec!["/var/cache/api"]);
+    }
+
+    #[test]
+    fn test_task_policy() {
+        let config: Config = toml::from_str(
+            r#"
+[security]
+read_only = true
+
+[security.policy]
+allow = ["build", "test*"]
+deny = ["clean-db"]
+confirm = ["deploy*"]
+
+[services.api]
+policy = { allow = ["*"], deny = ["reset"] }
+"#,
+        )
+        .unwrap();
+
+        assert!(config.security.read_only);
+        assert_eq!(config.get_service("other").policy, config.security.policy);
+
+        let api = config.get_service("api").policy;
+        assert_eq!(api.allow, vec!["*"]);
+        assert_eq!(api.deny, vec!["clean-db", "reset"]);
+        assert_eq!(api.confirm, vec!["deploy*"]);
+        assert!(TaskPolicy::default().is_empty());
+    }
+
+    #[test]
+    fn test_args_config() {
+        let config: Config = toml::from_str(
+            r#"
+[security.args]
+deny_vars = ["DEPLOY_ENV"]
+
+[services.legacy]
+args = { allow_vars = ["CC"], allow_flags = true, escape_make_values = false }
+"#,
+        )
+        .unwrap();
+
+        let other = config.get_service("other").args;
+        assert_eq!(other.deny_vars, vec!["DEPLOY_ENV"]);
+        assert!(!other.allow_flags);
+        assert!(other.escape_make_values);
+
+        let legacy = config.get_service("legacy").args;
+        assert!(legacy.deny_vars.is_empty());
+        assert_eq!(legacy.allow_vars, vec!["CC"]);
+        assert!(legacy.allow_flags);
+        assert!(!legacy.escape_make_values);
+    }
+
+    #[test]
+    fn test_validate_path_client_roots() {
+        let allowed = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        let root = allowed.path().join("workspace");
+        let other = allowed.path().join("other");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::create_dir(&other).unwrap();
+
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        assert!(config.validate_path(&other).is_ok());
+
+        // Intersect: inside a root and the allowed paths
+        config.security.client_roots = Some(vec![
+            root.canonicalize().unwrap(),
+            outside.path().canonicalize().unwrap(),
+        ]);
+        assert!(config.validate_path(&root).is_ok());
+        let err = config.validate_path(&other).unwrap_err();
+        assert!(err.contains("outside the client's roots"), "{}", err);
+        assert!(config.validate_path(outside.path()).is_err());
+        assert!(config
+            .validate_path(&std::env::current_dir().unwrap())
+            .is_err());
+
+        // Replace: the roots alone
+        config.security.roots = RootsMode::Replace;
+        assert!(config.validate_path(outside.path()).is_ok());
+        assert!(config.validate_path(&other).is_err());
+
+        // Ignore: config alone
+        config.security.roots = RootsMode::Ignore;
+        assert!(config.validate_path(&other).is_ok());
+        assert!(config.validate_path(outside.path()).is_err());
+    }
+
+    #[test]
+    fn test_service_environment() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".env"), "API_TOKEN=from-file\nREGION=eu\n").unwrap();
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+            [security]
+            env_mode = "clean"
+            env_passthrough = ["AWS_*"]
+
+            [services.api]
+            project_dir = "{}"
+            env_file = ".env"
+            env_mode = "allowlist"
+            secret_env = ["API_TOKEN"]
+
+            [services.api.env]
+            REGION = "us"
+
+            [services.web]
+            "#,
+            dir.path().display()
+        ))
+        .unwrap();
+
+        let api = config.get_service("api");
+        assert_eq!(api.env_mode, EnvMode::Allowlist);
+        assert_eq!(api.env_passthrough, vec!["AWS_*"]);
+        let env = api.environment().unwrap();
+        assert_eq!(env["API_TOKEN"], "from-file");
+        assert_eq!(env["REGION"], "us");
+        assert_eq!(api.secret_values(&env), vec!["from-file"]);
+
+        let web = config.get_service("web");
+        assert_eq!(web.env_mode, EnvMode::Clean);
+        assert!(web.environment().unwrap().is_empty());
+
+        config.services.get_mut("api").unwrap().env_file = Some("missing.env".into());
+        let err = config.get_service("api").environment().unwrap_err();
+        assert!(
+            err.to_string().contains("Failed to read env file"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_pty_config() {
+        let config: Config = toml::from_str(
+            r#"
+[pty]
+cols = 100
+
+[services.web]
+tasks = { up = "compose-up" }
+pty = { tasks = ["up"], rows = 50 }
+"#,
+        )
+        .unwrap();
+
+        assert!(!config.pty.applies_to("up"));
+        assert_eq!((config.pty.cols, config.pty.rows), (100, 40));
+
+        let web = config.get_service("web");
+        assert!(web.uses_pty("up"));
+        assert!(web.uses_pty("compose-up"));
+        assert!(!web.uses_pty("build"));
+        assert_eq!((web.pty.cols, web.pty.rows), (120, 50));
+        assert_eq!(config.get_service("other").pty, config.pty);
+    }
+
     #[test]
     fn test_resolved_service_serialization() {
         let resolved = ResolvedService {
@@ -654,10 +2061,26 @@ mod tests {
             runner: Some("make".to_string()),
             script: None,
             depends_on: vec!["dep".to_string()],
+            dependents: vec![],
             force_recreate: vec!["container".to_string()],
             tasks: HashMap::new(),
             env: HashMap::new(),
+            secret_env: vec![],
+            env_file: None,
+            env_mode: EnvMode::Inherit,
+            env_passthrough: vec![],
             timeout: 300,
+            health: None,
+            watch: WatchConfig::default(),
+            watch_paths: vec![],
+            cache: HashMap::new(),
+            test_reports: vec![],
+            suggestions: vec![],
+            pty: PtyConfig::default(),
+            limits: ResourceLimits::default(),
+            sandbox: SandboxConfig::default(),
+            policy: TaskPolicy::default(),
+            args: ArgsConfig::default(),
         };
 
         let json = serde_json::to_string(&resolved).unwrap();
@@ -0,0 +1,287 @@
+//! Trust store for project-level config files
+//!
+//! `./.makefilehub.toml` comes with whatever directory we were started in,
+//! which may be a freshly cloned repository. Like direnv, such a file is
+//! ignored until it is approved with `makefilehub trust`, which records the
+//! SHA-256 of its contents; any later edit has to be approved again.
+//!
+//! Even a trusted project file can't loosen the guardrails: `[security]` and
+//! the per-service `limits`, `sandbox`, `policy`, `args`, `env_mode` and
+//! `env_passthrough` settings are dropped from it before it is merged.
+//!
+//! The store is `$XDG_DATA_HOME/makefilehub/trusted.json`, outside any
+//! configuration a project could override.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Per-service keys that project files can't set
+const SERVICE_SECURITY_KEYS: &[&str] = &[
+    "limits",
+    "sandbox",
+    "policy",
+    "args",
+    "env_mode",
+    "env_passthrough",
+];
+
+/// Approved project config files and the hashes they were approved with
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    path: PathBuf,
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl TrustStore {
+    /// The store in `$XDG_DATA_HOME/makefilehub/trusted.json`
+    ///
+    /// # Errors
+    /// Fails if there is no data directory or the store can't be read.
+    pub fn open_default() -> Result<Self> {
+        let dir = dirs::data_dir().context("No data directory for the trust store")?;
+        Self::open(dir.join("makefilehub").join("trusted.json"))
+    }
+
+    /// The store in `path`, empty if the file doesn't exist yet
+    ///
+    /// # Errors
+    /// Fails if the file exists but can't be read or parsed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let files = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid trust store {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, files })
+    }
+
+    /// Store file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Approved files and their hashes
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.files
+            .iter()
+            .map(|(path, hash)| (path.as_path(), hash.as_str()))
+    }
+
+    /// Whether `file` (canonical) was approved with exactly `contents`
+    pub fn is_trusted(&self, file: &Path, contents: &[u8]) -> bool {
+        self.files
+            .get(file)
+            .is_some_and(|hash| *hash == content_hash(contents))
+    }
+
+    /// Approve `file` as it is now, returning its canonical path and hash
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or the store can't be written.
+    pub fn trust(&mut self, file: &Path) -> Result<(PathBuf, String)> {
+        let canonical = file
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", file.display()))?;
+        let contents = fs::read(&canonical)
+            .with_context(|| format!("Failed to read {}", canonical.display()))?;
+        let hash = content_hash(&contents);
+        self.files.insert(canonical.clone(), hash.clone());
+        self.save()?;
+        Ok((canonical, hash))
+    }
+
+    /// Withdraw approval of `file`, returning whether it was approved
+    ///
+    /// # Errors
+    /// Fails if the store can't be written.
+    pub fn revoke(&mut self, file: &Path) -> Result<bool> {
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        if self.files.remove(&canonical).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Write the store through a temporary file, readable only by us
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&temp)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        file.write_all(&serde_json::to_vec_pretty(&self.files)?)
+            .and_then(|_| file.sync_all())
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        fs::rename(&temp, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Hex SHA-256 of a config file's contents
+pub fn content_hash(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The project config file at `path` as TOML to merge, or `None` if it
+/// doesn't exist, isn't trusted or can't be parsed
+///
+/// The file is read once, so the contents checked against the store are
+/// the contents that get merged.
+pub fn project_layer(path: &Path, store: Option<&TrustStore>) -> Option<String> {
+    let canonical = path.canonicalize().ok()?;
+    let contents = fs::read(&canonical).ok()?;
+
+    let Some(store) = store.filter(|store| store.is_trusted(&canonical, &contents)) else {
+        tracing::warn!(
+            "Ignoring untrusted project config {}; run `makefilehub trust` to load it",
+            canonical.display()
+        );
+        return None;
+    };
+
+    let mut table: toml::Table = match std::str::from_utf8(&contents)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str(text).map_err(|e| e.to_string()))
+    {
+        Ok(table) => table,
+        Err(e) => {
+            tracing::warn!("Ignoring invalid {}: {}", canonical.display(), e);
+            return None;
+        }
+    };
+    let dropped = strip_security_keys(&mut table);
+    if !dropped.is_empty() {
+        tracing::warn!(
+            "Ignoring security settings in project config {}: {}",
+            canonical.display(),
+            dropped.join(", ")
+        );
+    }
+    tracing::debug!(
+        "Loading trusted project config {} (trust store {})",
+        canonical.display(),
+        store.path().display()
+    );
+    toml::to_string(&table).ok()
+}
+
+/// Remove the keys project files can't set, returning their dotted names
+pub fn strip_security_keys(table: &mut toml::Table) -> Vec<String> {
+    let mut dropped = Vec::new();
+    if table.remove("security").is_some() {
+        dropped.push("security".to_string());
+    }
+    if let Some(toml::Value::Table(services)) = table.get_mut("services") {
+        for (name, service) in services.iter_mut() {
+            let Some(service) = service.as_table_mut() else {
+                continue;
+            };
+            for key in SERVICE_SECURITY_KEYS {
+                if service.remove(*key).is_some() {
+                    dropped.push(format!("services.{}.{}", name, key));
+                }
+            }
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PROJECT: &str = r#"
+        [defaults]
+        timeout = 42
+
+        [security]
+        allow_any_path = true
+
+        [services.api]
+        runner = "make"
+
+        [services.api.sandbox]
+        enabled = false
+    "#;
+
+    #[test]
+    fn test_trust_and_revoke() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join(".makefilehub.toml");
+        fs::write(&file, PROJECT).unwrap();
+        let store_path = dir.path().join("store").join("trusted.json");
+
+        let mut store = TrustStore::open(&store_path).unwrap();
+        let canonical = file.canonicalize().unwrap();
+        assert!(!store.is_trusted(&canonical, PROJECT.as_bytes()));
+
+        let (path, hash) = store.trust(&file).unwrap();
+        assert_eq!(path, canonical);
+        assert_eq!(hash, content_hash(PROJECT.as_bytes()));
+
+        // Persisted, and tied to the contents
+        let reopened = TrustStore::open(&store_path).unwrap();
+        assert!(reopened.is_trusted(&canonical, PROJECT.as_bytes()));
+        assert!(!reopened.is_trusted(&canonical, b"[defaults]\ntimeout = 1\n"));
+
+        assert!(store.revoke(&file).unwrap());
+        assert!(!store.revoke(&file).unwrap());
+        let reopened = TrustStore::open(&store_path).unwrap();
+        assert_eq!(reopened.files().count(), 0);
+    }
+
+    #[test]
+    fn test_project_layer_requires_trust() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join(".makefilehub.toml");
+        fs::write(&file, PROJECT).unwrap();
+        let mut store = TrustStore::open(dir.path().join("trusted.json")).unwrap();
+
+        assert!(project_layer(&file, None).is_none());
+        assert!(project_layer(&file, Some(&store)).is_none());
+
+        store.trust(&file).unwrap();
+        let layer = project_layer(&file, Some(&store)).unwrap();
+        let table: toml::Table = toml::from_str(&layer).unwrap();
+        assert_eq!(table["defaults"]["timeout"].as_integer(), Some(42));
+        assert!(table.get("security").is_none());
+        assert!(table["services"]["api"].get("sandbox").is_none());
+        assert_eq!(table["services"]["api"]["runner"].as_str(), Some("make"));
+
+        // Editing the file withdraws the approval
+        fs::write(&file, format!("{}\n# edited\n", PROJECT)).unwrap();
+        assert!(project_layer(&file, Some(&store)).is_none());
+    }
+
+    #[test]
+    fn test_strip_security_keys() {
+        let mut table: toml::Table = toml::from_str(PROJECT).unwrap();
+        assert_eq!(
+            strip_security_keys(&mut table),
+            vec!["security", "services.api.sandbox"]
+        );
+        assert!(strip_security_keys(&mut table).is_empty());
+    }
+}
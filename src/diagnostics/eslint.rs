@@ -0,0 +1,78 @@
+//! ESLint diagnostics
+//!
+//! The default `stylish` formatter prints the file on its own line, followed
+//! by one indented `line:col  severity  message  rule` line per problem.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{Diagnostic, Severity};
+
+static PROBLEM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s+(\d+):(\d+)\s+(error|warning)\s+(.+?)(?:\s{2,}(\S+))?\s*$")
+        .expect("Invalid regex")
+});
+
+pub fn parse(lines: &[&str], index: usize) -> Option<(Vec<Diagnostic>, usize)> {
+    let file = lines[index].trim_end();
+    if file.is_empty() || file.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // The file header must be followed directly by a problem line
+    PROBLEM_RE.captures(lines.get(index + 1)?)?;
+
+    let mut diagnostics = Vec::new();
+    for line in &lines[index + 1..] {
+        let Some(caps) = PROBLEM_RE.captures(line) else {
+            break;
+        };
+        diagnostics.push(
+            Diagnostic::new(
+                file,
+                Severity::from_label(&caps[3]).unwrap_or(Severity::Error),
+                &caps[4],
+            )
+            .at(caps[1].parse().ok(), caps[2].parse().ok())
+            .code(caps.get(5).map(|rule| rule.as_str().to_string())),
+        );
+    }
+
+    let consumed = diagnostics.len() + 1;
+    Some((diagnostics, consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::extract;
+
+    #[test]
+    fn test_stylish() {
+        let output = "
+/work/web/src/index.js
+   3:7   error    'unused' is assigned a value but never used  no-unused-vars
+  10:1   warning  Unexpected console statement                 no-console
+
+/work/web/src/other.js
+  1:1  error  Parsing error: Unexpected token
+
+✖ 3 problems (2 errors, 1 warning)
+";
+        let diagnostics = extract(output, "");
+        assert_eq!(diagnostics.len(), 3);
+
+        assert_eq!(diagnostics[0].file, "/work/web/src/index.js");
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(7));
+        assert_eq!(
+            diagnostics[0].message,
+            "'unused' is assigned a value but never used"
+        );
+        assert_eq!(diagnostics[0].code.as_deref(), Some("no-unused-vars"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+
+        assert_eq!(diagnostics[2].file, "/work/web/src/other.js");
+        assert_eq!(diagnostics[2].message, "Parsing error: Unexpected token");
+        assert_eq!(diagnostics[2].code, None);
+    }
+}
@@ -0,0 +1,88 @@
+//! GNU-style diagnostics
+//!
+//! `file:line[:col]: [severity:] message`, as printed by gcc and clang
+//! (`main.c:3:10: error: ...`), go build and go vet (`./main.go:5:2: ...`)
+//! and pytest tracebacks (`tests/test_x.py:12: AssertionError`). Lines
+//! without a severity are errors. gcc's trailing `[-Wflag]` becomes the code.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{Diagnostic, Severity};
+
+static LOCATION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^([^\s:*\[\]()]+\.[A-Za-z0-9+_]+):(\d+):(?:(\d+):)? (?:(fatal error|error|warning|note): )?(.+?)(?: \[(-W[^\]]+)\])?$",
+    )
+    .expect("Invalid regex")
+});
+
+pub fn parse(lines: &[&str], index: usize) -> Option<(Vec<Diagnostic>, usize)> {
+    let caps = LOCATION_RE.captures(lines[index].trim_end())?;
+
+    let severity = caps
+        .get(4)
+        .and_then(|label| Severity::from_label(label.as_str()))
+        .unwrap_or(Severity::Error);
+    let diagnostic = Diagnostic::new(&caps[1], severity, caps[5].trim())
+        .at(
+            caps[2].parse().ok(),
+            caps.get(3).and_then(|col| col.as_str().parse().ok()),
+        )
+        .code(caps.get(6).map(|flag| flag.as_str().to_string()));
+    Some((vec![diagnostic], 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::extract;
+
+    #[test]
+    fn test_gcc_and_clang() {
+        let output = "\
+main.c: In function 'main':
+main.c:3:10: warning: unused variable 'y' [-Wunused-variable]
+    3 |     int y;
+      |         ^
+src/util.cpp:14:5: error: 'foo' was not declared in this scope
+include/a.h:2:1: note: previous definition is here
+main.c:9: fatal error: missing.h: No such file or directory
+make: *** [Makefile:4: build] Error 1
+";
+        let diagnostics = extract("", output);
+        assert_eq!(diagnostics.len(), 4);
+
+        assert_eq!(diagnostics[0].file, "main.c");
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(10));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "unused variable 'y'");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("-Wunused-variable"));
+
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[2].severity, Severity::Note);
+        assert_eq!(diagnostics[3].column, None);
+        assert_eq!(
+            diagnostics[3].message,
+            "missing.h: No such file or directory"
+        );
+    }
+
+    #[test]
+    fn test_go_build() {
+        let output = "\
+# example.com/app
+./main.go:5:2: undefined: foo
+pkg/server.go:21:14: cannot use x (variable of type int) as string value in argument
+";
+        let diagnostics = extract("", output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "./main.go");
+        assert_eq!(diagnostics[0].line, Some(5));
+        assert_eq!(diagnostics[0].column, Some(2));
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "undefined: foo");
+        assert_eq!(diagnostics[1].file, "pkg/server.go");
+    }
+}
@@ -0,0 +1,181 @@
+//! Compiler and tool diagnostics
+//!
+//! Extracts `{file, line, column, severity, message, code}` records from
+//! task output, so callers can jump to failing locations without reading
+//! the whole log:
+//! - `rust` - rustc/cargo human output, `--message-format=json` and test panics
+//! - `tsc` - TypeScript compiler (plain and `--pretty`)
+//! - `eslint` - ESLint stylish output
+//! - `pytest` - pytest short test summary
+//! - `gcc` - GNU-style `file:line:col: severity: message` (gcc, clang, go, pytest tracebacks)
+//!
+//! Parsers are tried in that order on every line; the first one that
+//! recognizes a line consumes it (and any continuation lines).
+
+pub mod eslint;
+pub mod gcc;
+pub mod pytest;
+pub mod rust;
+pub mod tsc;
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of diagnostics extracted from one run
+pub const MAX_DIAGNOSTICS: usize = 200;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Notes, help and info messages
+    Note,
+}
+
+impl Severity {
+    /// Parse a severity label such as `error`, `fatal error` or `warning`
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "error" | "fatal error" | "fatal" | "failed" | "failure" => Some(Self::Error),
+            "warning" | "warn" => Some(Self::Warning),
+            "note" | "help" | "info" | "failure-note" => Some(Self::Note),
+            _ => None,
+        }
+    }
+}
+
+/// A located message from a compiler, linter or test runner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// File as printed by the tool (usually relative to the project directory)
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    /// Error code or rule, e.g. `E0425`, `TS2322`, `no-unused-vars`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(file: impl Into<String>, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            line: None,
+            column: None,
+            severity,
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    /// Set the line and column (either may be missing)
+    pub fn at(mut self, line: Option<u32>, column: Option<u32>) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+    pub fn code(mut self, code: Option<String>) -> Self {
+        self.code = code.filter(|code| !code.is_empty());
+        self
+    }
+}
+
+/// Recognizes diagnostics starting at `lines[index]`
+///
+/// Returns the diagnostics found and the number of lines consumed (at
+/// least one), or `None` if the line isn't in this parser's format.
+type Parser = fn(&[&str], usize) -> Option<(Vec<Diagnostic>, usize)>;
+
+const PARSERS: &[Parser] = &[
+    rust::parse,
+    tsc::parse,
+    eslint::parse,
+    pytest::parse,
+    gcc::parse,
+];
+
+/// Extract diagnostics from a run's stdout and stderr
+///
+/// Duplicates (same location and message) are dropped and at most
+/// [`MAX_DIAGNOSTICS`] are returned, in output order.
+pub fn extract(stdout: &str, stderr: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for output in [stdout, stderr] {
+        let lines: Vec<&str> = output.lines().collect();
+        let mut index = 0;
+        while index < lines.len() && diagnostics.len() < MAX_DIAGNOSTICS {
+            let parsed = PARSERS.iter().find_map(|parse| parse(&lines, index));
+            let Some((found, consumed)) = parsed else {
+                index += 1;
+                continue;
+            };
+
+            for diagnostic in found {
+                if diagnostics.len() < MAX_DIAGNOSTICS && !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+            index += consumed.max(1);
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_mixed_output() {
+        let stdout = "cargo build\n";
+        let stderr = "\
+error[E0425]: cannot find value `x` in this scope
+ --> src/main.rs:2:5
+  |
+2 |     x
+  |     ^ not found in this scope
+
+main.c:3:10: warning: unused variable 'y' [-Wunused-variable]
+src/app.ts(4,7): error TS2322: Type 'string' is not assignable to type 'number'.
+make: *** [Makefile:4: build] Error 1
+";
+        let diagnostics = extract(stdout, stderr);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].file, "src/main.rs");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E0425"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[2].code.as_deref(), Some("TS2322"));
+    }
+
+    #[test]
+    fn test_extract_dedups_and_caps() {
+        let line = "main.go:5:2: undefined: foo\n";
+        assert_eq!(extract(line, line).len(), 1);
+
+        let many: String = (0..MAX_DIAGNOSTICS + 10)
+            .map(|i| format!("main.go:{}:1: undefined: foo\n", i + 1))
+            .collect();
+        assert_eq!(extract("", &many).len(), MAX_DIAGNOSTICS);
+    }
+
+    #[test]
+    fn test_extract_no_diagnostics() {
+        assert!(extract("all good\n", "make: Nothing to be done for 'build'.\n").is_empty());
+    }
+
+    #[test]
+    fn test_severity_from_label() {
+        assert_eq!(Severity::from_label("fatal error"), Some(Severity::Error));
+        assert_eq!(Severity::from_label("Warning"), Some(Severity::Warning));
+        assert_eq!(Severity::from_label("help"), Some(Severity::Note));
+        assert_eq!(Severity::from_label("debug"), None);
+    }
+}
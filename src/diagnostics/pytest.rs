@@ -0,0 +1,66 @@
+//! pytest diagnostics
+//!
+//! Parses the short test summary (`FAILED path::test - message` and
+//! `ERROR path - message`). Traceback locations (`path.py:12: AssertionError`)
+//! are picked up by the GNU-style parser.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{Diagnostic, Severity};
+
+static SUMMARY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(FAILED|ERROR) ([^\s:]+\.py)(?:::(\S+))?(?: - (.+))?$").expect("Invalid regex")
+});
+
+pub fn parse(lines: &[&str], index: usize) -> Option<(Vec<Diagnostic>, usize)> {
+    let caps = SUMMARY_RE.captures(lines[index])?;
+
+    let message = match (caps.get(3), caps.get(4)) {
+        (Some(test), Some(message)) => format!("{} failed: {}", test.as_str(), message.as_str()),
+        (Some(test), None) => format!("{} failed", test.as_str()),
+        (None, Some(message)) => message.as_str().to_string(),
+        (None, None) => "error collecting tests".to_string(),
+    };
+    let diagnostic = Diagnostic::new(&caps[2], Severity::Error, message)
+        .code(caps.get(3).map(|test| test.as_str().to_string()));
+    Some((vec![diagnostic], 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::extract;
+
+    #[test]
+    fn test_short_summary() {
+        let output = "\
+    def test_add():
+>       assert add(1, 1) == 3
+E       assert 2 == 3
+
+tests/test_math.py:5: AssertionError
+=========================== short test summary info ============================
+FAILED tests/test_math.py::test_add - assert 2 == 3
+ERROR tests/test_broken.py - ModuleNotFoundError: No module named 'nope'
+========================= 1 failed, 1 error in 0.05s ==========================
+";
+        let diagnostics = extract(output, "");
+        assert_eq!(diagnostics.len(), 3);
+
+        // Traceback location from the GNU-style parser
+        assert_eq!(diagnostics[0].file, "tests/test_math.py");
+        assert_eq!(diagnostics[0].line, Some(5));
+        assert_eq!(diagnostics[0].message, "AssertionError");
+
+        assert_eq!(diagnostics[1].file, "tests/test_math.py");
+        assert_eq!(diagnostics[1].line, None);
+        assert_eq!(diagnostics[1].message, "test_add failed: assert 2 == 3");
+        assert_eq!(diagnostics[1].code.as_deref(), Some("test_add"));
+
+        assert_eq!(diagnostics[2].file, "tests/test_broken.py");
+        assert_eq!(
+            diagnostics[2].message,
+            "ModuleNotFoundError: No module named 'nope'"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! rustc and cargo diagnostics
+//!
+//! Handles the human-readable format (`error[E0425]: message` followed by
+//! a ` --> file:line:col` line), JSON messages from
+//! `cargo --message-format=json` or `rustc --error-format=json`, and
+//! `thread '...' panicked at file:line:col:` from failing tests.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use super::{Diagnostic, Severity};
+
+static HEADER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(error|warning)(?:\[([A-Za-z0-9_:-]+)\])?: (.+)$").expect("Invalid regex")
+});
+
+static LOCATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*--> (.+?):(\d+):(\d+)\s*$").expect("Invalid regex"));
+
+static PANIC_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^thread '([^']*)' panicked at (.+?):(\d+):(\d+):?(?: (.+))?$")
+        .expect("Invalid regex")
+});
+
+/// Panic format before Rust 1.73: `panicked at 'message', file:line:col`
+static OLD_PANIC_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^thread '([^']*)' panicked at '(.*)', (.+?):(\d+):(\d+)$").expect("Invalid regex")
+});
+
+/// Lines searched for the ` --> ` location after a header
+const LOCATION_LOOKAHEAD: usize = 3;
+
+pub fn parse(lines: &[&str], index: usize) -> Option<(Vec<Diagnostic>, usize)> {
+    let line = lines[index];
+
+    if line.trim_start().starts_with('{') {
+        return parse_json(line).map(|diagnostic| (diagnostic.into_iter().collect(), 1));
+    }
+
+    if let Some(caps) = OLD_PANIC_RE.captures(line) {
+        let diagnostic = Diagnostic::new(
+            &caps[3],
+            Severity::Error,
+            format!("test {} panicked: {}", &caps[1], &caps[2]),
+        )
+        .at(caps[4].parse().ok(), caps[5].parse().ok());
+        return Some((vec![diagnostic], 1));
+    }
+
+    if let Some(caps) = PANIC_RE.captures(line) {
+        // Since Rust 1.73 the panic message is on the following line
+        let (message, consumed) = match caps.get(5) {
+            Some(message) => (message.as_str().to_string(), 1),
+            None => match lines.get(index + 1) {
+                Some(next) if !next.trim().is_empty() => (next.trim().to_string(), 2),
+                _ => ("panicked".to_string(), 1),
+            },
+        };
+        let diagnostic = Diagnostic::new(
+            &caps[2],
+            Severity::Error,
+            format!("test {} panicked: {}", &caps[1], message),
+        )
+        .at(caps[3].parse().ok(), caps[4].parse().ok());
+        return Some((vec![diagnostic], consumed));
+    }
+
+    let caps = HEADER_RE.captures(line)?;
+    let end = lines.len().min(index + 1 + LOCATION_LOOKAHEAD);
+    for (offset, candidate) in lines[index + 1..end].iter().enumerate() {
+        if let Some(location) = LOCATION_RE.captures(candidate) {
+            let diagnostic = Diagnostic::new(
+                &location[1],
+                Severity::from_label(&caps[1]).unwrap_or(Severity::Error),
+                &caps[3],
+            )
+            .at(location[2].parse().ok(), location[3].parse().ok())
+            .code(caps.get(2).map(|code| code.as_str().to_string()));
+            return Some((vec![diagnostic], offset + 2));
+        }
+    }
+
+    // Summaries such as "error: could not compile `app`" have no location
+    None
+}
+
+/// Parse a cargo `compiler-message` or a raw rustc JSON diagnostic
+fn parse_json(line: &str) -> Option<Option<Diagnostic>> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let message = match value.get("reason").and_then(Value::as_str) {
+        Some("compiler-message") => value.get("message")?,
+        Some(_) => return Some(None),
+        None if value.get("$message_type").is_some() => &value,
+        None => return None,
+    };
+
+    let severity = Severity::from_label(message.get("level")?.as_str()?)?;
+    let spans = message.get("spans")?.as_array()?;
+    let Some(span) = spans
+        .iter()
+        .find(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true))
+        .or_else(|| spans.first())
+    else {
+        return Some(None);
+    };
+
+    let number = |key: &str| {
+        span.get(key)
+            .and_then(Value::as_u64)
+            .and_then(|n| u32::try_from(n).ok())
+    };
+    let diagnostic = Diagnostic::new(
+        span.get("file_name")?.as_str()?,
+        severity,
+        message.get("message")?.as_str()?,
+    )
+    .at(number("line_start"), number("column_start"))
+    .code(
+        message
+            .get("code")
+            .and_then(|code| code.get("code"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    );
+    Some(Some(diagnostic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::extract;
+
+    #[test]
+    fn test_human_format() {
+        let output = "\
+   Compiling app v0.1.0 (/work/app)
+warning: unused variable: `y`
+ --> src/lib.rs:3:9
+  |
+3 |     let y = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0308]: mismatched types
+  --> src/main.rs:10:18
+   |
+error: could not compile `app` (bin \"app\") due to 1 previous error
+";
+        let diagnostics = extract("", output);
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].file, "src/lib.rs");
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(9));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "unused variable: `y`");
+        assert_eq!(diagnostics[0].code, None);
+
+        assert_eq!(diagnostics[1].file, "src/main.rs");
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].code.as_deref(), Some("E0308"));
+    }
+
+    #[test]
+    fn test_cargo_json() {
+        let output = concat!(
+            r#"{"reason":"compiler-artifact","package_id":"x"}"#,
+            "\n",
+            r#"{"reason":"compiler-message","message":{"message":"cannot find value `x` in this scope","code":{"code":"E0425","explanation":null},"level":"error","spans":[{"file_name":"src/other.rs","line_start":1,"column_start":1,"is_primary":false},{"file_name":"src/main.rs","line_start":2,"column_start":5,"is_primary":true}],"rendered":"error"}}"#,
+            "\n",
+            r#"{"reason":"build-finished","success":false}"#,
+            "\n",
+        );
+        let diagnostics = extract(output, "");
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::new(
+                "src/main.rs",
+                Severity::Error,
+                "cannot find value `x` in this scope"
+            )
+            .at(Some(2), Some(5))
+            .code(Some("E0425".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_rustc_json() {
+        let output = r#"{"$message_type":"diagnostic","message":"unused import: `std::fs`","code":{"code":"unused_imports","explanation":null},"level":"warning","spans":[{"file_name":"lib.rs","line_start":1,"column_start":5,"is_primary":true}]}"#;
+        let diagnostics = extract("", output);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("unused_imports"));
+    }
+
+    #[test]
+    fn test_test_panic() {
+        let output = "\
+thread 'tests::it_works' panicked at src/lib.rs:12:9:
+assertion `left == right` failed
+  left: 4
+ right: 5
+thread 'tests::old' panicked at 'boom', src/old.rs:3:5
+";
+        let diagnostics = extract(output, "");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "src/lib.rs");
+        assert_eq!(diagnostics[0].line, Some(12));
+        assert_eq!(
+            diagnostics[0].message,
+            "test tests::it_works panicked: assertion `left == right` failed"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! TypeScript compiler diagnostics
+//!
+//! `tsc` prints `file(line,col): error TS1234: message`, or with `--pretty`
+//! (the default on a terminal) `file:line:col - error TS1234: message`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{Diagnostic, Severity};
+
+static PLAIN_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning|message) (TS\d+): (.+)$")
+        .expect("Invalid regex")
+});
+
+static PRETTY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(.+?):(\d+):(\d+) - (error|warning|message) (TS\d+): (.+)$")
+        .expect("Invalid regex")
+});
+
+pub fn parse(lines: &[&str], index: usize) -> Option<(Vec<Diagnostic>, usize)> {
+    let line = lines[index];
+    let caps = PLAIN_RE
+        .captures(line)
+        .or_else(|| PRETTY_RE.captures(line))?;
+
+    let severity = Severity::from_label(&caps[4]).unwrap_or(Severity::Note);
+    let diagnostic = Diagnostic::new(&caps[1], severity, &caps[6])
+        .at(caps[2].parse().ok(), caps[3].parse().ok())
+        .code(Some(caps[5].to_string()));
+    Some((vec![diagnostic], 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::extract;
+
+    #[test]
+    fn test_plain_and_pretty() {
+        let output = "\
+src/app.ts(4,7): error TS2322: Type 'string' is not assignable to type 'number'.
+src/util.ts:12:3 - error TS2304: Cannot find name 'foo'.
+
+12   foo();
+     ~~~
+
+Found 2 errors in 2 files.
+";
+        let diagnostics = extract(output, "");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "src/app.ts");
+        assert_eq!(diagnostics[0].line, Some(4));
+        assert_eq!(diagnostics[0].column, Some(7));
+        assert_eq!(diagnostics[0].code.as_deref(), Some("TS2322"));
+        assert_eq!(diagnostics[1].file, "src/util.ts");
+        assert_eq!(diagnostics[1].message, "Cannot find name 'foo'.");
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+}
@@ -55,10 +55,34 @@ pub enum TaskError {
     #[error("Service not found: {0}")]
     ServiceNotFound(String),
 
+    /// Services depend on each other in a cycle
+    #[error("Dependency cycle detected: {}", cycle.join(" -> "))]
+    DependencyCycle { cycle: Vec<String> },
+
     /// Security violation - path outside allowed directories
     #[error("Security violation: {message}")]
     SecurityViolation { message: String, path: String },
 
+    /// Task refused by the task policy
+    #[error("Task '{task}' is not allowed: {reason}")]
+    TaskDenied { task: String, reason: String },
+
+    /// Task needs the caller to confirm it before it runs
+    #[error("Task '{task}' requires confirmation")]
+    ConfirmationRequired { task: String },
+
+    /// Tool refused because the server is in read-only mode
+    #[error("Read-only mode: {0} is disabled")]
+    ReadOnly(String),
+
+    /// Named or positional argument that is malformed for the runner
+    #[error("Invalid argument '{argument}': {reason}")]
+    InvalidArgument { argument: String, reason: String },
+
+    /// Named argument that would set a variable controlling the build tool
+    #[error("Variable '{name}' cannot be set by {runner} arguments")]
+    ForbiddenVariable { name: String, runner: String },
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -158,6 +182,17 @@ impl From<&TaskError> for ErrorInfo {
                 stderr: None,
                 available: vec![],
             },
+            TaskError::DependencyCycle { cycle } => ErrorInfo {
+                message: format!("Dependency cycle detected: {}", cycle.join(" -> ")),
+                error_type: "dependency_cycle".to_string(),
+                suggestion: Some(
+                    "Remove one of the depends_on entries in the cycle from your configuration"
+                        .to_string(),
+                ),
+                exit_code: None,
+                stderr: None,
+                available: cycle.clone(),
+            },
             TaskError::SecurityViolation { message, path } => ErrorInfo {
                 message: format!("Security violation: {}", message),
                 error_type: "security_violation".to_string(),
@@ -169,6 +204,61 @@ impl From<&TaskError> for ErrorInfo {
                 stderr: None,
                 available: vec![],
             },
+            TaskError::TaskDenied { task, reason } => ErrorInfo {
+                message: format!("Task '{}' is not allowed: {}", task, reason),
+                error_type: "task_denied".to_string(),
+                suggestion: Some(
+                    "Run the task yourself, or change [security.policy] in your configuration"
+                        .to_string(),
+                ),
+                exit_code: None,
+                stderr: None,
+                available: vec![],
+            },
+            TaskError::ConfirmationRequired { task } => ErrorInfo {
+                message: format!("Task '{}' requires confirmation", task),
+                error_type: "confirmation_required".to_string(),
+                suggestion: Some(format!(
+                    "Ask the user to approve running '{}', then call again with confirm: true",
+                    task
+                )),
+                exit_code: None,
+                stderr: None,
+                available: vec![],
+            },
+            TaskError::ReadOnly(tool) => ErrorInfo {
+                message: format!("Read-only mode: {} is disabled", tool),
+                error_type: "read_only".to_string(),
+                suggestion: Some(
+                    "Only list_tasks and detect_runner are available; unset [security].read_only to allow more"
+                        .to_string(),
+                ),
+                exit_code: None,
+                stderr: None,
+                available: vec![],
+            },
+            TaskError::InvalidArgument { argument, reason } => ErrorInfo {
+                message: format!("Invalid argument '{}': {}", argument, reason),
+                error_type: "invalid_argument".to_string(),
+                suggestion: Some(
+                    "Use plain names for named arguments and pass options through the task's own arguments"
+                        .to_string(),
+                ),
+                exit_code: None,
+                stderr: None,
+                available: vec![],
+            },
+            TaskError::ForbiddenVariable { name, runner } => ErrorInfo {
+                message: format!("Variable '{}' cannot be set by {} arguments", name, runner),
+                error_type: "forbidden_variable".to_string(),
+                suggestion: Some(format!(
+                    "Set '{}' in the service's env instead, or add it to [security.args].allow_vars",
+                    name
+                )),
+                exit_code: None,
+                stderr: None,
+                available: vec![],
+            },
             TaskError::Io(e) => ErrorInfo {
                 message: format!("IO error: {}", e),
                 error_type: "io_error".to_string(),
@@ -181,69 +271,13 @@ impl From<&TaskError> for ErrorInfo {
     }
 }
 
-/// Suggest fixes for common error patterns
+/// Suggest a fix for a failed command using the built-in rules
+///
+/// Rules from the configuration are applied with [`Suggestions`].
+///
+/// [`Suggestions`]: crate::suggest::Suggestions
 pub fn suggest_fix(command: &str, stderr: &str) -> Option<String> {
-    // Docker-related errors
-    if stderr.contains("docker") || stderr.contains("Docker") {
-        if stderr.contains("not running") || stderr.contains("Cannot connect") {
-            return Some(
-                "Docker daemon is not running. Start Docker Desktop or the Docker service."
-                    .to_string(),
-            );
-        }
-        if stderr.contains("No such container") {
-            return Some(
-                "Container not found. Try running 'up' first to start the services.".to_string(),
-            );
-        }
-        if stderr.contains("port is already allocated") {
-            return Some(
-                "Port conflict. Stop the conflicting service or use a different port.".to_string(),
-            );
-        }
-    }
-
-    // Permission errors
-    if stderr.contains("Permission denied") {
-        return Some(
-            "Permission denied. Check file permissions or run with appropriate access.".to_string(),
-        );
-    }
-
-    // Command not found
-    if stderr.contains("command not found") || stderr.contains("not found") {
-        if command.contains("make") {
-            return Some("'make' command not found. Install build-essential or make.".to_string());
-        }
-        if command.contains("just") {
-            return Some("'just' command not found. Install just: cargo install just".to_string());
-        }
-        return Some("Required command not found. Check PATH and dependencies.".to_string());
-    }
-
-    // File not found
-    if stderr.contains("No such file") {
-        if command.contains("run.sh") {
-            return Some("run.sh not found. Verify the working directory is correct.".to_string());
-        }
-        return Some("File not found. Check the project path and file existence.".to_string());
-    }
-
-    // Make-specific errors
-    if stderr.contains("No rule to make target") {
-        return Some(
-            "Target not found in Makefile. Run 'list_tasks' to see available targets.".to_string(),
-        );
-    }
-
-    // Just-specific errors
-    if stderr.contains("Justfile does not contain recipe") {
-        return Some(
-            "Recipe not found in justfile. Run 'list_tasks' to see available recipes.".to_string(),
-        );
-    }
-
-    None
+    crate::suggest::Suggestions::builtin().suggest(command, stderr)
 }
 
 #[cfg(test)]
@@ -315,6 +349,30 @@ mod tests {
         assert!(err.to_string().contains("300s"));
     }
 
+    #[test]
+    fn test_dependency_cycle_error() {
+        let err = TaskError::DependencyCycle {
+            cycle: vec!["a".into(), "b".into(), "a".into()],
+        };
+        assert_eq!(err.to_string(), "Dependency cycle detected: a -> b -> a");
+
+        let info = ErrorInfo::from(&err);
+        assert_eq!(info.error_type, "dependency_cycle");
+        assert_eq!(info.available, vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn test_confirmation_required_error() {
+        let err = TaskError::ConfirmationRequired {
+            task: "deploy".into(),
+        };
+        assert_eq!(err.to_string(), "Task 'deploy' requires confirmation");
+
+        let info = ErrorInfo::from(&err);
+        assert_eq!(info.error_type, "confirmation_required");
+        assert!(info.suggestion.unwrap().contains("confirm: true"));
+    }
+
     #[test]
     fn test_suggest_fix_docker_not_running() {
         let suggestion = suggest_fix("docker-compose up", "Cannot connect to Docker daemon");
@@ -3,7 +3,7 @@
 //! Provides a unified interface for running commands with:
 //! - Configurable timeouts
 //! - Output capture (stdout/stderr)
-//! - Output truncation for large outputs
+//! - Head-and-tail truncation of large outputs
 //! - Environment variable injection
 //! - Working directory control
 
@@ -12,19 +12,18 @@ use std::path::Path;
 use std::process::Stdio;
 use std::time::{Duration, Instant};
 
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use tokio::io::AsyncReadExt;
 use tokio::time::timeout;
 
 use crate::error::{suggest_fix, TaskError};
-use crate::runner::RunResult;
+use crate::output::{OutputCapture, OutputLimits};
+#[cfg(unix)]
+use crate::runner::interrupt;
+use crate::runner::{CommandSpec, RunResult};
 
-/// Maximum output size before truncation (in bytes)
+/// Bytes of output kept per stream (in bytes)
 const MAX_OUTPUT_SIZE: usize = 100_000; // 100KB
 
-/// Truncation marker for large outputs
-const TRUNCATION_MARKER: &str = "\n... [output truncated] ...\n";
-
 /// Options for async command execution
 #[derive(Debug, Clone)]
 pub struct ExecOptions {
@@ -36,7 +35,7 @@ pub struct ExecOptions {
     pub timeout: Option<Duration>,
     /// Whether to capture output (vs streaming)
     pub capture_output: bool,
-    /// Maximum output size before truncation
+    /// Bytes of each stream kept: a quarter from the start, the rest from the end
     pub max_output_size: usize,
 }
 
@@ -108,7 +107,7 @@ pub struct ExecResult {
 
 impl ExecResult {
     /// Convert to a RunResult
-    pub fn to_run_result(self, command: impl Into<String>) -> RunResult {
+    pub fn to_run_result(self, command: CommandSpec) -> RunResult {
         if self.success {
             RunResult::success(command, self.stdout, self.duration.as_millis() as u64)
         } else {
@@ -141,23 +140,37 @@ pub async fn exec_command(
     args: &[&str],
     options: &ExecOptions,
 ) -> Result<ExecResult, TaskError> {
+    let mut spec = CommandSpec::new(program)
+        .args(args.iter().copied())
+        .envs(options.env.clone());
+    spec.cwd = options.working_dir.clone();
+
+    exec_spec(&spec, options).await
+}
+
+/// Execute a [`CommandSpec`] asynchronously with timeout support
+///
+/// The spec's working directory and environment are used as-is; only the
+/// timeout and output limits are taken from `options`. On unix the command
+/// runs in a process group of its own (with stdin from `/dev/null`), which is
+/// killed as a whole when the timeout elapses or the future is dropped.
+///
+/// # Errors
+/// * `TaskError::SpawnFailed` - If the command couldn't be spawned
+/// * `TaskError::Timeout` - If the command timed out (when timeout is set)
+pub async fn exec_spec(spec: &CommandSpec, options: &ExecOptions) -> Result<ExecResult, TaskError> {
     let start = Instant::now();
-    let command_str = format!("{} {}", program, args.join(" "));
+    let command_str = spec.to_string();
 
-    let mut cmd = Command::new(program);
-    cmd.args(args);
+    let mut cmd = spec.to_async_command();
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
     cmd.kill_on_drop(true); // Kill process if future is dropped
-
-    // Set working directory
-    if let Some(ref dir) = options.working_dir {
-        cmd.current_dir(dir);
-    }
-
-    // Set environment variables
-    for (key, value) in &options.env {
-        cmd.env(key, value);
+    #[cfg(unix)]
+    {
+        // Out of the terminal's foreground group, so it can't read from it
+        cmd.stdin(Stdio::null());
+        cmd.process_group(0);
     }
 
     tracing::debug!("Executing async: {}", command_str);
@@ -166,6 +179,7 @@ pub async fn exec_command(
         command: command_str.clone(),
         error: e.to_string(),
     })?;
+    let mut group = GroupGuard(child.id());
 
     // Execute with or without timeout
     let result = if let Some(timeout_duration) = options.timeout {
@@ -188,6 +202,7 @@ pub async fn exec_command(
         wait_for_output(child, options.max_output_size).await?
     };
 
+    group.disarm();
     let duration = start.elapsed();
 
     Ok(ExecResult {
@@ -202,6 +217,26 @@ pub async fn exec_command(
     })
 }
 
+/// Kills the process group led by a child unless disarmed once it has
+/// finished, so that a timeout or a dropped future takes down everything
+/// it started
+struct GroupGuard(Option<u32>);
+
+impl GroupGuard {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            interrupt::kill_group(pid);
+        }
+    }
+}
+
 /// Internal result from waiting for process output
 struct WaitResult {
     exit_code: Option<i32>,
@@ -218,11 +253,13 @@ async fn wait_for_output(
 ) -> Result<WaitResult, TaskError> {
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
+    let limits = OutputLimits::total(max_output_size);
+    let stderr_limits = limits.clone();
 
     // Read stdout and stderr concurrently
     let stdout_handle = tokio::spawn(async move {
         if let Some(stdout) = stdout {
-            read_and_truncate(stdout, max_output_size).await
+            read_and_truncate(stdout, &limits, "stdout").await
         } else {
             (String::new(), false)
         }
@@ -230,7 +267,7 @@ async fn wait_for_output(
 
     let stderr_handle = tokio::spawn(async move {
         if let Some(stderr) = stderr {
-            read_and_truncate(stderr, max_output_size).await
+            read_and_truncate(stderr, &stderr_limits, "stderr").await
         } else {
             (String::new(), false)
         }
@@ -257,39 +294,19 @@ async fn wait_for_output(
     })
 }
 
-/// Read from an async reader and truncate if too large
-///
-/// Optimized for memory efficiency:
-/// - Pre-allocates output buffer to avoid repeated reallocations
-/// - Reuses line buffer across iterations instead of allocating new String each time
+/// Read from an async reader, keeping the head and tail within `limits`
 async fn read_and_truncate<R: tokio::io::AsyncRead + Unpin>(
-    reader: R,
-    max_size: usize,
+    mut reader: R,
+    limits: &OutputLimits,
+    stream: &str,
 ) -> (String, bool) {
-    let mut buf_reader = BufReader::new(reader);
-    // Pre-allocate output buffer (cap at 64KB to avoid over-allocation for small max_size)
-    let mut output = String::with_capacity(max_size.min(64 * 1024));
-    // Reuse line buffer across iterations (typical line is ~80 chars, allow some margin)
-    let mut line = String::with_capacity(4096);
-    let mut truncated = false;
+    let mut capture = OutputCapture::new(limits, stream);
+    let mut buf = vec![0; 8 * 1024];
 
     loop {
-        line.clear(); // Reuse buffer instead of allocating new String
-        match buf_reader.read_line(&mut line).await {
+        match reader.read(&mut buf).await {
             Ok(0) => break, // EOF
-            Ok(_) => {
-                if output.len() + line.len() > max_size {
-                    // Truncate
-                    let remaining = max_size.saturating_sub(output.len());
-                    if remaining > 0 {
-                        output.push_str(&line[..remaining.min(line.len())]);
-                    }
-                    output.push_str(TRUNCATION_MARKER);
-                    truncated = true;
-                    break;
-                }
-                output.push_str(&line);
-            }
+            Ok(n) => capture.write(&buf[..n]),
             Err(e) => {
                 tracing::warn!("Error reading output: {}", e);
                 break;
@@ -297,7 +314,8 @@ async fn read_and_truncate<R: tokio::io::AsyncRead + Unpin>(
         }
     }
 
-    (output, truncated)
+    let captured = capture.finish();
+    (captured.text, captured.truncation.is_some())
 }
 
 /// Execute a command synchronously (convenience wrapper for sync contexts)
@@ -540,6 +558,37 @@ mod tests {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_exec_spec_timeout_kills_process_group() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let pid_file = dir.path().join("sleep.pid");
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("sleep 10 & echo $! > \"$PID_FILE\"; wait")
+            .env("PID_FILE", pid_file.display().to_string());
+        let options = ExecOptions::default().with_timeout(Duration::from_millis(300));
+
+        match exec_spec(&spec, &options).await {
+            Err(TaskError::Timeout { .. }) => {}
+            Err(TaskError::SpawnFailed { .. }) => {
+                eprintln!("Skipping test: sh not available");
+                return;
+            }
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+
+        // The background sleep was killed along with the shell
+        let pid: i32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        assert!(status.is_empty() || status.contains(") Z "), "{}", status);
+    }
+
     #[tokio::test]
     async fn test_exec_command_output_truncation() {
         // Generate output larger than max
@@ -599,6 +648,28 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_exec_spec_preserves_argv() {
+        let spec = CommandSpec::new("printf")
+            .arg("[%s]")
+            .arg("two words")
+            .arg("it's")
+            .current_dir("/tmp");
+
+        let result = exec_spec(&spec, &ExecOptions::default()).await;
+
+        match result {
+            Ok(res) => {
+                assert!(res.success);
+                assert_eq!(res.stdout, "[two words][it's]");
+            }
+            Err(TaskError::SpawnFailed { .. }) => {
+                eprintln!("Skipping test: printf not available");
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[tokio::test]
     async fn test_exec_shell_command() {
         let result = exec_shell_command(
@@ -684,7 +755,7 @@ mod tests {
             timed_out: false,
         };
 
-        let run_result = exec_result.to_run_result("test command");
+        let run_result = exec_result.to_run_result(CommandSpec::new("test").arg("command"));
 
         assert!(run_result.success);
         assert_eq!(run_result.exit_code, Some(0));
@@ -705,7 +776,7 @@ mod tests {
             timed_out: false,
         };
 
-        let run_result = exec_result.to_run_result("failing command");
+        let run_result = exec_result.to_run_result(CommandSpec::new("failing"));
 
         assert!(!run_result.success);
         assert_eq!(run_result.exit_code, Some(1));
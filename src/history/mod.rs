@@ -0,0 +1,13 @@
+//! Persistent run history
+//!
+//! Task runs and rebuild steps are recorded with their project, runner,
+//! argv, exit code, duration and the end of their output, so that callers
+//! can look up what failed earlier:
+//! - `record` - run records and query filters
+//! - `store` - the JSONL history file under `$XDG_DATA_HOME/makefilehub`
+
+pub mod record;
+pub mod store;
+
+pub use record::{HistoryFilter, RunKind, RunRecord};
+pub use store::{default_session, RunHistory};
@@ -0,0 +1,262 @@
+//! Run records and filters
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TaskError;
+use crate::output::{ansi, OutputLimits};
+use crate::runner::{CommandSpec, RunResult};
+
+/// What produced a run record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunKind {
+    /// A `run_task` call
+    Task,
+    /// A step of a `rebuild_service` or `rebuild_changed` call
+    Rebuild,
+}
+
+/// One recorded run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Unix timestamp (seconds) when the run was recorded
+    pub timestamp: u64,
+    /// Process that recorded the run, e.g. one MCP server session
+    pub session: String,
+    pub kind: RunKind,
+    /// Canonical project directory
+    pub project: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Runner name, or the program for commands not run through a runner
+    pub runner: String,
+    /// Task name, or the recreated container for rebuild recreate steps
+    pub task: String,
+    /// Full argv, starting with the program
+    pub argv: Vec<String>,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// End of the standard output
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stdout_truncated: bool,
+    /// End of the standard error
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stderr_truncated: bool,
+    /// Why the command couldn't run or finish (spawn failure, timeout, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunRecord {
+    /// Record a completed run
+    pub fn from_result(
+        kind: RunKind,
+        project: &Path,
+        runner: &str,
+        task: &str,
+        result: &RunResult,
+    ) -> Self {
+        Self {
+            success: result.success,
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+            cached: result.cached,
+            // Output printed to a terminal may still be colored
+            stdout: ansi::strip(&result.stdout).into_owned(),
+            stderr: ansi::strip(&result.stderr).into_owned(),
+            ..Self::new(kind, project, runner, task, &result.command)
+        }
+    }
+
+    /// Record a run that failed before producing a result
+    ///
+    /// Secrets are masked with `limits` in both `command`, which is the
+    /// command as it would have run, and the error.
+    pub fn from_error(
+        kind: RunKind,
+        project: &Path,
+        runner: &str,
+        task: &str,
+        command: &CommandSpec,
+        error: &TaskError,
+        limits: &OutputLimits,
+    ) -> Self {
+        let error = limits
+            .for_env(&command.env)
+            .redact(&error.to_string())
+            .into_owned();
+        Self {
+            error: Some(error),
+            ..Self::new(kind, project, runner, task, &command.redacted(limits))
+        }
+    }
+
+    /// Attach the service whose configuration was applied
+    pub fn service(mut self, service: Option<&str>) -> Self {
+        self.service = service.map(str::to_string);
+        self
+    }
+
+    fn new(kind: RunKind, project: &Path, runner: &str, task: &str, command: &CommandSpec) -> Self {
+        let project = project
+            .canonicalize()
+            .unwrap_or_else(|_| project.to_path_buf());
+        Self {
+            timestamp: 0,
+            session: String::new(),
+            kind,
+            project: project.display().to_string(),
+            service: None,
+            runner: runner.to_string(),
+            task: task.to_string(),
+            argv: command.argv(),
+            success: false,
+            exit_code: None,
+            duration_ms: 0,
+            cached: false,
+            stdout: String::new(),
+            stdout_truncated: false,
+            stderr: String::new(),
+            stderr_truncated: false,
+            error: None,
+        }
+    }
+}
+
+/// Criteria for querying the run history
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Project directory or service name
+    pub project: Option<String>,
+    pub service: Option<String>,
+    pub task: Option<String>,
+    /// Only failed runs
+    pub failed_only: bool,
+    /// Only runs recorded by this session
+    pub session: Option<String>,
+    /// Maximum number of records (0 for no limit)
+    pub limit: usize,
+}
+
+impl HistoryFilter {
+    /// Whether a record matches every criterion
+    pub fn matches(&self, record: &RunRecord) -> bool {
+        if let Some(project) = &self.project {
+            let canonical = Path::new(project)
+                .canonicalize()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|_| project.clone());
+            if record.project != canonical && record.service.as_ref() != Some(project) {
+                return false;
+            }
+        }
+        if self.service.is_some() && record.service != self.service {
+            return false;
+        }
+        if self.task.as_ref().is_some_and(|task| &record.task != task) {
+            return false;
+        }
+        if self.failed_only && record.success {
+            return false;
+        }
+        if self
+            .session
+            .as_ref()
+            .is_some_and(|session| &record.session != session)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(project: &str, service: Option<&str>, task: &str, success: bool) -> RunRecord {
+        let result = if success {
+            RunResult::success(CommandSpec::new("make").arg(task), "ok", 5)
+        } else {
+            RunResult::failed(CommandSpec::new("make").arg(task), Some(2), "", "boom", 5)
+        };
+        RunRecord::from_result(RunKind::Task, Path::new(project), "make", task, &result)
+            .service(service)
+    }
+
+    #[test]
+    fn test_from_result() {
+        let record = record("/nonexistent/app", Some("app"), "build", false);
+        assert_eq!(record.project, "/nonexistent/app");
+        assert_eq!(record.service.as_deref(), Some("app"));
+        assert_eq!(record.argv, vec!["make", "build"]);
+        assert_eq!(record.exit_code, Some(2));
+        assert_eq!(record.stderr, "boom");
+        assert!(!record.success);
+    }
+
+    #[test]
+    fn test_from_error() {
+        let error = TaskError::Timeout {
+            command: "make build".to_string(),
+            timeout_secs: 5,
+        };
+        let record = RunRecord::from_error(
+            RunKind::Rebuild,
+            Path::new("/nonexistent/app"),
+            "make",
+            "build",
+            &CommandSpec::new("make").arg("build"),
+            &error,
+            &OutputLimits::default(),
+        );
+        assert!(!record.success);
+        assert_eq!(
+            record.error.as_deref(),
+            Some("Command timed out after 5s: make build")
+        );
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let failed = record("/nonexistent/app", Some("app"), "build", false);
+        let passed = record("/nonexistent/lib", None, "test", true);
+
+        let by_service_name = HistoryFilter {
+            project: Some("app".to_string()),
+            ..Default::default()
+        };
+        assert!(by_service_name.matches(&failed));
+        assert!(!by_service_name.matches(&passed));
+
+        let by_path = HistoryFilter {
+            project: Some("/nonexistent/lib".to_string()),
+            ..Default::default()
+        };
+        assert!(by_path.matches(&passed));
+
+        let failed_only = HistoryFilter {
+            failed_only: true,
+            ..Default::default()
+        };
+        assert!(failed_only.matches(&failed));
+        assert!(!failed_only.matches(&passed));
+
+        let by_task = HistoryFilter {
+            task: Some("test".to_string()),
+            ..Default::default()
+        };
+        assert!(!by_task.matches(&failed));
+        assert!(by_task.matches(&passed));
+    }
+}
@@ -0,0 +1,389 @@
+//! JSONL history file
+//!
+//! One JSON record per line, oldest first. Records are appended with a
+//! single write; once the file holds a quarter more than `max_entries`
+//! lines it is rewritten (via a temporary file and rename) to keep only
+//! the newest `max_entries`. The CLI and the MCP server share the file, so
+//! appends and compaction hold an advisory lock on `<file>.lock` across
+//! processes, besides a mutex serializing concurrent rebuild steps within
+//! one.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use crate::config::Config;
+use crate::error::TaskError;
+
+use super::record::{HistoryFilter, RunRecord};
+
+/// Serializes appends and compaction within the process
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Persistent run history
+#[derive(Debug, Clone)]
+pub struct RunHistory {
+    path: PathBuf,
+    session: String,
+    max_entries: usize,
+    max_output_bytes: usize,
+}
+
+impl RunHistory {
+    /// History stored in `path`, keeping 1000 runs and 8 KiB of output each
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            session: default_session("cli"),
+            max_entries: 1000,
+            max_output_bytes: 8 * 1024,
+        }
+    }
+
+    /// History from the `[history]` config section, or `None` when disabled
+    ///
+    /// Defaults to `$XDG_DATA_HOME/makefilehub/history.jsonl`.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let history = &config.history;
+        if !history.enabled {
+            return None;
+        }
+
+        let path = match &history.path {
+            Some(path) => PathBuf::from(path),
+            None => dirs::data_dir()?.join("makefilehub").join("history.jsonl"),
+        };
+        Some(Self {
+            max_entries: history.max_entries.max(1),
+            max_output_bytes: history.max_output_bytes,
+            ..Self::new(path)
+        })
+    }
+
+    /// Tag recorded runs with `session`
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.session = session.into();
+        self
+    }
+
+    /// Session recorded runs are tagged with
+    pub fn session_id(&self) -> &str {
+        &self.session
+    }
+
+    /// History file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a run, stamping it with the time and session and truncating its output
+    ///
+    /// Failures are logged rather than returned: losing a history record
+    /// must never fail the run itself.
+    pub fn record(&self, mut record: RunRecord) {
+        record.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        record.session = self.session.clone();
+        record.stdout_truncated |= truncate_head(&mut record.stdout, self.max_output_bytes);
+        record.stderr_truncated |= truncate_head(&mut record.stderr, self.max_output_bytes);
+
+        if let Err(e) = self.append(&record) {
+            tracing::warn!("Failed to record run in {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Matching runs, newest first
+    ///
+    /// # Errors
+    /// * `TaskError::Io` - If the history file exists but can't be read
+    pub fn query(&self, filter: &HistoryFilter) -> Result<Vec<RunRecord>, TaskError> {
+        let mut records: Vec<RunRecord> = self
+            .read_all()?
+            .into_iter()
+            .rev()
+            .filter(|record| filter.matches(record))
+            .collect();
+
+        if filter.limit > 0 {
+            records.truncate(filter.limit);
+        }
+        Ok(records)
+    }
+
+    /// Most recent failed run matching `filter`
+    ///
+    /// # Errors
+    /// * `TaskError::Io` - If the history file exists but can't be read
+    pub fn last_failure(&self, filter: &HistoryFilter) -> Result<Option<RunRecord>, TaskError> {
+        let filter = HistoryFilter {
+            failed_only: true,
+            limit: 1,
+            ..filter.clone()
+        };
+        Ok(self.query(&filter)?.into_iter().next())
+    }
+
+    fn append(&self, record: &RunRecord) -> Result<(), TaskError> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| TaskError::Config(format!("Failed to serialize run record: {}", e)))?;
+        line.push('\n');
+
+        // Released when dropped, after any compaction
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.lock_path())?;
+        lock.lock()?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        drop(file);
+
+        if count_lines(&self.path)? > self.max_entries + self.max_entries / 4 {
+            let records = self.read_all()?;
+            let keep = records.len().saturating_sub(self.max_entries);
+            self.rewrite(&records[keep..])?;
+        }
+        Ok(())
+    }
+
+    /// File locked while the history is written
+    fn lock_path(&self) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+
+    /// All readable records, oldest first; corrupt lines are skipped
+    fn read_all(&self) -> Result<Vec<RunRecord>, TaskError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if let Ok(record) = serde_json::from_str(&line) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    fn rewrite(&self, records: &[RunRecord]) -> Result<(), TaskError> {
+        let tmp = self
+            .path
+            .with_extension(format!("jsonl.tmp.{}", std::process::id()));
+        let mut contents = String::new();
+        for record in records {
+            if let Ok(line) = serde_json::to_string(record) {
+                contents.push_str(&line);
+                contents.push('\n');
+            }
+        }
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Number of lines in `path`, counted without parsing them
+fn count_lines(path: &Path) -> Result<usize, TaskError> {
+    let mut file = File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut lines = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(lines);
+        }
+        lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+    }
+}
+
+/// Session id unique to this process, e.g. `mcp-1234-1700000000`
+pub fn default_session(prefix: &str) -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    format!("{}-{}-{}", prefix, std::process::id(), started)
+}
+
+/// Keep only the last `max` bytes of `text` (on a char boundary)
+///
+/// Returns whether anything was removed.
+fn truncate_head(text: &mut String, max: usize) -> bool {
+    if text.len() <= max {
+        return false;
+    }
+    let mut start = text.len() - max;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text.drain(..start);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::RunKind;
+    use crate::runner::{CommandSpec, RunResult};
+    use tempfile::TempDir;
+
+    fn run(task: &str, success: bool, stderr: &str) -> RunRecord {
+        let command = CommandSpec::new("make").arg(task);
+        let result = if success {
+            RunResult::success(command, "ok\n", 10)
+        } else {
+            RunResult::failed(command, Some(2), "", stderr, 10)
+        };
+        RunRecord::from_result(
+            RunKind::Task,
+            Path::new("/nonexistent"),
+            "make",
+            task,
+            &result,
+        )
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let temp = TempDir::new().unwrap();
+        let history = RunHistory::new(temp.path().join("data/history.jsonl")).session("s1");
+
+        history.record(run("build", true, ""));
+        history.record(run("test", false, "assertion failed"));
+        history.record(run("lint", true, ""));
+
+        let all = history.query(&HistoryFilter::default()).unwrap();
+        let tasks: Vec<_> = all.iter().map(|r| r.task.as_str()).collect();
+        assert_eq!(tasks, vec!["lint", "test", "build"]);
+        assert!(all.iter().all(|r| r.session == "s1" && r.timestamp > 0));
+
+        let limited = history
+            .query(&HistoryFilter {
+                limit: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(limited.len(), 1);
+
+        let failure = history
+            .last_failure(&HistoryFilter::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(failure.task, "test");
+        assert_eq!(failure.stderr, "assertion failed");
+
+        let other_session = HistoryFilter {
+            session: Some("s2".to_string()),
+            ..Default::default()
+        };
+        assert!(history.last_failure(&other_session).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let temp = TempDir::new().unwrap();
+        let history = RunHistory::new(temp.path().join("history.jsonl"));
+        assert!(history.query(&HistoryFilter::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_output_truncated_to_tail() {
+        let temp = TempDir::new().unwrap();
+        let history = RunHistory {
+            max_output_bytes: 8,
+            ..RunHistory::new(temp.path().join("history.jsonl"))
+        };
+
+        history.record(run("build", false, "line one\nerror: boom"));
+
+        let record = history.query(&HistoryFilter::default()).unwrap().remove(0);
+        assert_eq!(record.stderr, "or: boom");
+        assert!(record.stderr_truncated);
+        assert!(!record.stdout_truncated);
+    }
+
+    #[test]
+    fn test_compaction_keeps_newest() {
+        let temp = TempDir::new().unwrap();
+        let history = RunHistory {
+            max_entries: 4,
+            ..RunHistory::new(temp.path().join("history.jsonl"))
+        };
+
+        for i in 0..6 {
+            history.record(run(&format!("task{}", i), true, ""));
+        }
+
+        let tasks: Vec<_> = history
+            .query(&HistoryFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|r| r.task)
+            .collect();
+        assert_eq!(tasks, vec!["task5", "task4", "task3", "task2"]);
+    }
+
+    #[test]
+    fn test_append_waits_for_other_writers() {
+        let temp = TempDir::new().unwrap();
+        let history = RunHistory::new(temp.path().join("history.jsonl"));
+
+        // Another process compacting the file holds the lock
+        let held = File::create(history.lock_path()).unwrap();
+        held.lock().unwrap();
+        let writer = {
+            let history = history.clone();
+            std::thread::spawn(move || history.record(run("build", true, "")))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(history.query(&HistoryFilter::default()).unwrap().is_empty());
+
+        held.unlock().unwrap();
+        writer.join().unwrap();
+        assert_eq!(history.query(&HistoryFilter::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config::default();
+        config.history.path = Some("/tmp/makefilehub-history.jsonl".to_string());
+        config.history.max_entries = 10;
+        let history = RunHistory::from_config(&config).unwrap();
+        assert_eq!(history.path(), Path::new("/tmp/makefilehub-history.jsonl"));
+        assert_eq!(history.max_entries, 10);
+
+        config.history.enabled = false;
+        assert!(RunHistory::from_config(&config).is_none());
+    }
+
+    #[test]
+    fn test_truncate_head_char_boundary() {
+        let mut text = "aé".to_string();
+        assert!(truncate_head(&mut text, 1));
+        assert_eq!(text, "");
+        let mut text = "short".to_string();
+        assert!(!truncate_head(&mut text, 10));
+    }
+}
@@ -11,22 +11,52 @@
 //! - XDG-compliant layered configuration
 //! - Environment variable and shell command interpolation
 //! - Service dependency management for complex rebuild orchestration
+//! - Content-hash caching of up-to-date tasks
+//! - Persistent run history
+//! - Structured compiler, linter and test diagnostics from task output
+//! - Test result summaries for common test runners and JUnit XML reports
+//! - Configurable error-suggestion rules
+//! - Head-and-tail output truncation with full logs in spill files
+//! - ANSI escape stripping and color control for captured output
+//! - Live output streaming and pseudo-terminal execution
+//! - Task allow/deny/confirm policy and a read-only mode for MCP callers
+//! - Argument validation against option and variable injection
+//! - Audit log of MCP tool calls
+//! - Secret redaction in task output, history and spill files
+//! - Hash-approved project config files that can't change security settings
+//! - MCP client roots as the allowed project paths of a session
+//! - Path validation that follows symlinks without leaking what exists
+//! - Environment isolation and per-service dotenv files for tasks
+//! - Resource limits and an opt-in Linux namespace sandbox for MCP-run tasks
 //! - MCP tools for Claude Code integration
 //!
 //! ## MCP Tools
 //!
 //! - `run_task` - Run a task/target in a project
 //! - `rebuild_service` - Build service with dependency handling
+//! - `rebuild_changed` - Rebuild services affected by git changes
 //! - `list_tasks` - List available tasks/targets
 //! - `detect_runner` - Detect which build system a project uses
 //! - `get_project_config` - Get resolved configuration
+//! - `watch_task` / `watch_status` / `stop_watch` - Re-run tasks on file changes
+//! - `get_run_history` / `get_last_failure` - Query past runs
+//! - `read_output` - Page through the full output of a truncated run
 
+pub mod cache;
 pub mod cli;
 pub mod config;
+pub mod diagnostics;
 pub mod error;
 pub mod executor;
+pub mod history;
 pub mod mcp;
+pub mod orchestrator;
+pub mod output;
 pub mod runner;
+pub mod security;
+pub mod suggest;
+pub mod testing;
+pub mod watch;
 
 pub use cli::{Cli, Commands};
 pub use config::Config;
@@ -35,7 +65,8 @@ pub use executor::{
     exec_command, exec_command_sync, exec_shell_command, ExecOptions, ExecResult, TaskExecutor,
 };
 pub use mcp::MakefilehubServer;
+pub use orchestrator::{RebuildOptions, RebuildPlan, Rebuilder, ServiceGraph};
 pub use runner::{
-    detect_runner, DetectionResult, FilesFound, JustfileRunner, MakefileRunner, RunnerType,
-    ScriptRunner,
+    detect_runner, CommandSpec, DetectionResult, FilesFound, JustfileRunner, MakefileRunner,
+    RunnerType, ScriptRunner,
 };
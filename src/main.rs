@@ -7,7 +7,11 @@
 //!   makefilehub detect           Detect build system
 //!   makefilehub config <project> Show configuration
 //!   makefilehub rebuild <service> Rebuild service with dependencies
+//!   makefilehub rebuild --changed Rebuild services affected by git changes
+//!   makefilehub watch <task>     Re-run a task on file changes
+//!   makefilehub history          Show recorded runs
 
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::process::ExitCode;
 use std::time::Duration;
@@ -16,16 +20,31 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use colored::Colorize;
 
+use makefilehub::cache::{run_cached, TaskCache};
 use makefilehub::cli::{
-    commands::{ConfigArgs, DetectArgs, ListArgs, OutputFormat, RebuildArgs, RunArgs},
+    commands::{
+        ConfigArgs, DetectArgs, HistoryArgs, ListArgs, OutputFormat, RebuildArgs, RunArgs,
+        TrustArgs, WatchArgs,
+    },
     run_mcp_server, Cli, Commands,
 };
-use makefilehub::config::{load_config, Config};
+use makefilehub::config::loader::{project_config_path, untrusted_project_config};
+use makefilehub::config::trust::strip_security_keys;
+use makefilehub::config::{interpolate_config, load_config, Config, ResolvedService, TrustStore};
+use makefilehub::history::{HistoryFilter, RunHistory, RunKind, RunRecord};
+use makefilehub::mcp::server::RunTaskResponse;
+use makefilehub::orchestrator::{
+    ChangedServices, RebuildOptions, RebuildPlan, Rebuilder, StepAction, StepStatus,
+};
+use makefilehub::output::OutputLimits;
 use makefilehub::runner::{
-    detect_runner,
+    detect_runner, interrupt,
     traits::{RunOptions, Runner},
-    JustfileRunner, MakefileRunner, RunnerType, ScriptRunner,
+    JustfileRunner, MakefileRunner, PtySize, RunnerType, ScriptRunner,
 };
+use makefilehub::suggest::Suggestions;
+use makefilehub::testing;
+use makefilehub::watch::{WatchEvent, WatchSession};
 
 #[tokio::main]
 async fn main() -> ExitCode {
@@ -37,11 +56,44 @@ async fn main() -> ExitCode {
         Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
             eprintln!("{}: {:#}", "error".red().bold(), e);
-            ExitCode::FAILURE
+            match e.downcast_ref::<TaskFailed>() {
+                Some(failed) => ExitCode::from(failed.status()),
+                None => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+/// A task that ran and failed; `makefilehub run` exits with its exit code
+#[derive(Debug)]
+struct TaskFailed {
+    task: String,
+    exit_code: Option<i32>,
+}
+
+impl TaskFailed {
+    /// Exit status for the shell: the task's exit code, 130 if we were
+    /// interrupted, 1 otherwise
+    fn status(&self) -> u8 {
+        match self.exit_code {
+            Some(code) if (1..=255).contains(&code) => code as u8,
+            _ if interrupt::interrupted() => 130,
+            _ => 1,
         }
     }
 }
 
+impl std::fmt::Display for TaskFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code {
+            Some(code) => write!(f, "Task '{}' failed with exit code {}", self.task, code),
+            None => write!(f, "Task '{}' failed", self.task),
+        }
+    }
+}
+
+impl std::error::Error for TaskFailed {}
+
 async fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Mcp => {
@@ -60,7 +112,16 @@ async fn run(cli: Cli) -> Result<()> {
             show_config(args, cli.config.as_deref())?;
         }
         Commands::Rebuild(args) => {
-            rebuild_service(args, cli.config.as_deref(), cli.verbose)?;
+            rebuild_service(args, cli.config.as_deref(), cli.verbose).await?;
+        }
+        Commands::Watch(args) => {
+            watch_task(args, cli.config.as_deref(), cli.verbose).await?;
+        }
+        Commands::History(args) => {
+            show_history(args, cli.config.as_deref())?;
+        }
+        Commands::Trust(args) => {
+            trust_config(args)?;
         }
     }
 
@@ -69,18 +130,16 @@ async fn run(cli: Cli) -> Result<()> {
 
 /// Run a task in a project
 fn run_task(args: RunArgs, config_path: Option<&str>, verbose: bool) -> Result<()> {
-    let config = load_config(config_path)?;
-    let project_path = resolve_project_path(args.project.as_deref(), &config)?;
+    let config = load_resolved_config(config_path)?;
+    let (project_path, service) = resolve_project(args.project.as_deref(), &config)?;
 
     // Get the runner to use
-    let runner_type = if let Some(ref runner_name) = args.runner {
-        parse_runner_type(runner_name)?
-    } else {
-        let detection = detect_runner(&project_path, &config);
-        detection
-            .detected
-            .context("No build system detected in project")?
-    };
+    let runner_type = select_runner_type(
+        &project_path,
+        args.runner.as_deref(),
+        service.as_ref(),
+        &config,
+    )?;
 
     if verbose {
         eprintln!(
@@ -98,30 +157,155 @@ fn run_task(args: RunArgs, config_path: Option<&str>, verbose: bool) -> Result<(
         RunnerType::Script(name) => Box::new(ScriptRunner::new(name)),
     };
 
-    // Build run options
-    let timeout = if args.timeout > 0 {
-        Some(Duration::from_secs(args.timeout))
-    } else {
-        None
+    // Build run options: --timeout wins, then the service timeout, then the default
+    let timeout = match args.timeout {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => service
+            .as_ref()
+            .map(|s| s.timeout_duration())
+            .unwrap_or_else(|| config.defaults.timeout_duration()),
     };
 
+    let task = service
+        .as_ref()
+        .map(|s| s.task_name(&args.task).to_string())
+        .unwrap_or_else(|| args.task.clone());
+
+    let env = service
+        .as_ref()
+        .map(|s| s.environment())
+        .transpose()?
+        .unwrap_or_default();
+    // Tasks run in their own process group, out of reach of Ctrl-C
+    interrupt::install();
     let options = RunOptions {
         working_dir: Some(project_path.clone()),
         args: args.args_as_map(),
         positional_args: args.positional.clone(),
-        env: std::collections::HashMap::new(),
+
         timeout,
         capture_output: !args.stream,
+        output: OutputLimits::from_config(&config)
+            .terminal(
+                &config,
+                !matches!(args.format, OutputFormat::Json) && std::io::stdout().is_terminal(),
+            )
+            .mask(
+                service
+                    .as_ref()
+                    .map(|s| s.secret_values(&env))
+                    .unwrap_or_default(),
+            ),
+        env,
+        pty: match &service {
+            Some(s) => PtySize::select(&s.pty, s.uses_pty(&task), args.pty.then_some(true)),
+            None => PtySize::select(
+                &config.pty,
+                config.pty.applies_to(&task),
+                args.pty.then_some(true),
+            ),
+        },
+        ..Default::default()
+    };
+    let suggestions = Suggestions::new(
+        service
+            .as_ref()
+            .map_or(&config.suggestions, |s| &s.suggestions),
+    )?;
+
+    let cache_config = service
+        .as_ref()
+        .filter(|_| !args.no_cache)
+        .and_then(|s| s.cache_config(&task));
+    let started = std::time::SystemTime::now();
+    let result = run_cached(
+        &TaskCache::from_config(&config),
+        cache_config,
+        runner.as_ref(),
+        &project_path,
+        &task,
+        &options,
+    );
+
+    let service_name = service.as_ref().map(|s| s.name.as_str());
+    let history = RunHistory::from_config(&config);
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            if let Some(history) = &history {
+                let command = runner.build_command(&project_path, &task, &options);
+                history.record(
+                    RunRecord::from_error(
+                        RunKind::Task,
+                        &project_path,
+                        runner.name(),
+                        &task,
+                        &command,
+                        &e,
+                        &options.output,
+                    )
+                    .service(service_name),
+                );
+            }
+            return Err(e.into());
+        }
     };
+    if let Some(history) = &history {
+        history.record(
+            RunRecord::from_result(RunKind::Task, &project_path, runner.name(), &task, &result)
+                .service(service_name),
+        );
+    }
 
-    let result = runner.run_task(&project_path, &args.task, &options)?;
+    if matches!(args.format, OutputFormat::Json) {
+        let success = result.success;
+        let exit_code = result.exit_code;
+        let test_reports = service
+            .as_ref()
+            .map(|s| s.test_reports.clone())
+            .unwrap_or_default();
+        let mut response = RunTaskResponse::from_result(
+            task.clone(),
+            service.map(|s| s.name),
+            runner.name(),
+            result,
+            &suggestions,
+        );
+        response.test_summary = testing::collect(
+            &project_path,
+            &test_reports,
+            started,
+            &response.stdout,
+            &response.stderr,
+        );
+        println!("{}", serde_json::to_string_pretty(&response)?);
+
+        if !success {
+            return Err(TaskFailed { task, exit_code }.into());
+        }
+        return Ok(());
+    }
 
-    // Print output
-    if !result.stdout.is_empty() {
-        print!("{}", result.stdout);
+    if verbose {
+        eprintln!("{}: {}", "command".cyan(), result.command);
     }
-    if !result.stderr.is_empty() {
-        eprint!("{}", result.stderr);
+    if result.cached {
+        eprintln!(
+            "{}: {} is up to date, replaying output",
+            "cached".green(),
+            task
+        );
+    }
+
+    // Print output, unless it was already streamed
+    if !args.stream || result.cached {
+        if !result.stdout.is_empty() {
+            print!("{}", result.stdout);
+        }
+        if !result.stderr.is_empty() {
+            eprint!("{}", result.stderr);
+        }
     }
 
     if result.success {
@@ -129,34 +313,35 @@ fn run_task(args: RunArgs, config_path: Option<&str>, verbose: bool) -> Result<(
             eprintln!(
                 "{}: {} completed in {}ms",
                 "success".green(),
-                args.task,
+                task,
                 result.duration_ms
             );
         }
         Ok(())
     } else {
-        anyhow::bail!(
-            "Task '{}' failed with exit code {:?}",
-            args.task,
-            result.exit_code
-        );
+        if let Some(suggestion) = suggestions.suggest(runner.name(), result.error_output()) {
+            eprintln!("{}: {}", "suggestion".yellow(), suggestion);
+        }
+        Err(TaskFailed {
+            task,
+            exit_code: result.exit_code,
+        }
+        .into())
     }
 }
 
 /// List available tasks in a project
 fn list_tasks(args: ListArgs, config_path: Option<&str>, verbose: bool) -> Result<()> {
-    let config = load_config(config_path)?;
-    let project_path = resolve_project_path(args.project.as_deref(), &config)?;
+    let config = load_resolved_config(config_path)?;
+    let (project_path, service) = resolve_project(args.project.as_deref(), &config)?;
 
     // Get the runner to use
-    let runner_type = if let Some(ref runner_name) = args.runner {
-        parse_runner_type(runner_name)?
-    } else {
-        let detection = detect_runner(&project_path, &config);
-        detection
-            .detected
-            .context("No build system detected in project")?
-    };
+    let runner_type = select_runner_type(
+        &project_path,
+        args.runner.as_deref(),
+        service.as_ref(),
+        &config,
+    )?;
 
     if verbose {
         eprintln!(
@@ -223,7 +408,7 @@ fn list_tasks(args: ListArgs, config_path: Option<&str>, verbose: bool) -> Resul
 
 /// Detect build system in a project
 fn detect_build_system(args: DetectArgs, config_path: Option<&str>) -> Result<()> {
-    let config = load_config(config_path)?;
+    let config = load_resolved_config(config_path)?;
     let project_path = resolve_project_path(args.project.as_deref(), &config)?;
 
     let detection = detect_runner(&project_path, &config);
@@ -297,7 +482,7 @@ fn detect_build_system(args: DetectArgs, config_path: Option<&str>) -> Result<()
 
 /// Show resolved configuration for a project
 fn show_config(args: ConfigArgs, config_path: Option<&str>) -> Result<()> {
-    let config = load_config(config_path)?;
+    let config = load_resolved_config(config_path)?;
 
     // Try as a path first, then as a configured service
     let path = PathBuf::from(&args.project);
@@ -312,10 +497,26 @@ fn show_config(args: ConfigArgs, config_path: Option<&str>) -> Result<()> {
             runner: detection.detected.map(|r| r.to_string()),
             script: None,
             depends_on: vec![],
+            dependents: vec![],
             force_recreate: vec![],
             tasks: std::collections::HashMap::new(),
             env: std::collections::HashMap::new(),
+            secret_env: vec![],
+            env_file: None,
+            env_mode: config.security.env_mode,
+            env_passthrough: config.security.env_passthrough.clone(),
             timeout: config.defaults.timeout,
+            health: None,
+            watch: Default::default(),
+            watch_paths: vec![],
+            cache: Default::default(),
+            test_reports: vec![],
+            suggestions: config.suggestions.clone(),
+            pty: config.pty.clone(),
+            limits: config.security.limits,
+            sandbox: config.security.sandbox.clone(),
+            policy: config.security.policy.clone(),
+            args: config.security.args.clone(),
         }
     } else if config.services.contains_key(&args.project) {
         config.get_service(&args.project)
@@ -354,6 +555,13 @@ fn show_config(args: ConfigArgs, config_path: Option<&str>) -> Result<()> {
                     resolved.depends_on.join(", ")
                 );
             }
+            if !resolved.dependents.is_empty() {
+                println!(
+                    "{}: {}",
+                    "Dependents".cyan(),
+                    resolved.dependents.join(", ")
+                );
+            }
             if !resolved.force_recreate.is_empty() {
                 println!(
                     "{}: {}",
@@ -374,179 +582,519 @@ fn show_config(args: ConfigArgs, config_path: Option<&str>) -> Result<()> {
 }
 
 /// Rebuild a service with dependency handling
-fn rebuild_service(args: RebuildArgs, config_path: Option<&str>, verbose: bool) -> Result<()> {
-    let config = load_config(config_path)?;
+async fn rebuild_service(
+    args: RebuildArgs,
+    config_path: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    let config = load_resolved_config(config_path)?;
+    // Steps run in their own process groups, out of reach of Ctrl-C
+    interrupt::install();
 
-    // Collect all services to rebuild
-    let mut services = vec![args.service.clone()];
+    // Collect all services to rebuild: the named ones, or those affected by git changes
+    let mut services: Vec<String> = args.service.iter().cloned().collect();
+    if args.changed {
+        let affected = ChangedServices::detect(&config, args.since.as_deref(), false)?;
+        for (service, files) in &affected.changed {
+            println!(
+                "{}: {} ({} file{})",
+                "Changed".cyan(),
+                service,
+                files.len(),
+                if files.len() == 1 { "" } else { "s" }
+            );
+            if verbose {
+                for file in files {
+                    println!("  {}", file);
+                }
+            }
+        }
+        if !affected.dependents.is_empty() {
+            println!(
+                "{}: {}",
+                "Dependents".cyan(),
+                affected.dependents.join(", ")
+            );
+        }
+        services.extend(affected.services());
+    }
     services.extend(args.services);
 
-    let mut errors: Vec<String> = Vec::new();
-    let mut rebuilt: Vec<String> = Vec::new();
-    let mut restarted: Vec<String> = Vec::new();
-    let mut recreated: Vec<String> = Vec::new();
+    if services.is_empty() {
+        println!("No services affected by changes");
+        return Ok(());
+    }
 
     for service_name in &services {
         if !config.services.contains_key(service_name) {
-            errors.push(format!("Service '{}' not found in config", service_name));
-            continue;
+            anyhow::bail!("Service '{}' not found in config", service_name);
         }
+    }
 
-        let service = config.get_service(service_name);
+    let options = RebuildOptions {
+        skip_deps: args.skip_deps,
+        skip_recreate: args.skip_recreate,
+        only_requested: args.changed,
+    };
+    let mut plan = RebuildPlan::new(&config, &services, &options)?;
 
-        if verbose {
-            eprintln!("{}: {}", "rebuilding".cyan(), service_name);
-        }
+    if verbose || args.dry_run {
+        println!("{}:", "Plan".cyan());
+        print_plan(&plan);
+    }
+    if args.dry_run {
+        return Ok(());
+    }
 
-        let project_path = PathBuf::from(&service.project_dir);
-        if !project_path.exists() {
-            errors.push(format!(
-                "Project directory '{}' does not exist for service '{}'",
-                service.project_dir, service_name
-            ));
-            continue;
-        }
+    let history = RunHistory::from_config(&config);
+    Rebuilder::new(config)
+        .history(history)
+        .timeout(args.timeout)
+        .max_parallel(args.max_parallel)
+        .fail_fast(args.fail_fast)
+        .no_cache(args.no_cache)
+        .execute(&mut plan)
+        .await;
 
-        // Determine runner
-        let runner_type = if let Some(ref runner_name) = service.runner {
-            match parse_runner_type(runner_name) {
-                Ok(r) => r,
-                Err(e) => {
-                    errors.push(format!("Invalid runner for '{}': {}", service_name, e));
-                    continue;
-                }
+    if verbose {
+        // Steps may have run concurrently; print each one's output as a block
+        for step in &plan.steps {
+            if step.stdout.is_empty() && step.stderr.is_empty() {
+                continue;
             }
+            println!();
+            println!("{} {} {}", "---".cyan(), step.action, step.service.bold());
+            print!("{}", step.stdout);
+            eprint!("{}", step.stderr);
+        }
+        println!();
+        println!("{}:", "Result".cyan());
+        print_plan(&plan);
+        println!();
+    }
+
+    // Report results
+    let rebuilt = plan.succeeded(StepAction::Build);
+    let restarted = plan.succeeded(StepAction::Restart);
+    let recreated = plan.succeeded(StepAction::Recreate);
+    if !rebuilt.is_empty() {
+        println!("{}: {}", "Rebuilt".green(), rebuilt.join(", "));
+    }
+    let cached = plan.cached();
+    if !cached.is_empty() {
+        println!("{}: {}", "Up to date".green(), cached.join(", "));
+    }
+    if !restarted.is_empty() {
+        println!("{}: {}", "Restarted".green(), restarted.join(", "));
+    }
+    if !recreated.is_empty() {
+        println!("{}: {}", "Recreated".green(), recreated.join(", "));
+    }
+    for (service, report) in plan.health() {
+        let status = if report.healthy {
+            "healthy".green()
         } else {
-            let detection = detect_runner(&project_path, &config);
-            match detection.detected {
-                Some(r) => r,
-                None => {
-                    errors.push(format!("No build system detected for '{}'", service_name));
-                    continue;
-                }
-            }
+            "unhealthy".red()
         };
+        println!(
+            "{}: {} {} ({}, {} attempt(s): {})",
+            "Health".cyan(),
+            service,
+            status,
+            report.check,
+            report.attempts,
+            report.message
+        );
+    }
 
-        // Create the appropriate runner
-        let runner: Box<dyn Runner> = match &runner_type {
-            RunnerType::Make => Box::new(MakefileRunner::new()),
-            RunnerType::Just => Box::new(JustfileRunner::new()),
-            RunnerType::Script(name) => Box::new(ScriptRunner::new(name)),
-        };
+    let failures: Vec<_> = plan.failures().collect();
+    if !failures.is_empty() {
+        eprintln!();
+        eprintln!("{}:", "Errors".red());
+        for step in &failures {
+            let detail = step
+                .error
+                .as_ref()
+                .map(|e| format!("{} (exit code {:?})", e.command, e.exit_code))
+                .unwrap_or_default();
+            eprintln!(
+                "  - {} failed for '{}': {}",
+                step.action, step.service, detail
+            );
+        }
+    }
 
-        // Run build task
-        let build_task = service
-            .tasks
-            .get("build")
-            .map(|s| s.as_str())
-            .unwrap_or("build");
-        let timeout = if args.timeout > 0 {
-            Some(Duration::from_secs(args.timeout))
-        } else {
-            None
-        };
-        let options = RunOptions {
-            working_dir: Some(project_path.clone()),
-            args: std::collections::HashMap::new(),
-            positional_args: vec![],
-            env: std::collections::HashMap::new(),
-            timeout,
-            capture_output: true,
-        };
+    if !plan.success() {
+        anyhow::bail!(
+            "Rebuild completed with {} failed and {} skipped step(s)",
+            failures.len(),
+            plan.steps
+                .iter()
+                .filter(|s| s.status == StepStatus::Skipped)
+                .count()
+        );
+    }
 
-        match runner.run_task(&project_path, build_task, &options) {
-            Ok(result) if result.success => {
-                rebuilt.push(service_name.clone());
+    Ok(())
+}
+
+/// Print each plan step with its status
+/// Re-run a task whenever project files change
+async fn watch_task(args: WatchArgs, config_path: Option<&str>, verbose: bool) -> Result<()> {
+    let config = load_resolved_config(config_path)?;
+    let (project_path, service) = resolve_project(args.project.as_deref(), &config)?;
+
+    let runner_type = select_runner_type(
+        &project_path,
+        args.runner.as_deref(),
+        service.as_ref(),
+        &config,
+    )?;
+    let runner: Box<dyn Runner> = match &runner_type {
+        RunnerType::Make => Box::new(MakefileRunner::new()),
+        RunnerType::Just => Box::new(JustfileRunner::new()),
+        RunnerType::Script(name) => Box::new(ScriptRunner::new(name)),
+    };
+
+    let timeout = match args.timeout {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => service
+            .as_ref()
+            .map(|s| s.timeout_duration())
+            .unwrap_or_else(|| config.defaults.timeout_duration()),
+    };
+
+    let task = service
+        .as_ref()
+        .map(|s| s.task_name(&args.task).to_string())
+        .unwrap_or_else(|| args.task.clone());
+
+    let env = service
+        .as_ref()
+        .map(|s| s.environment())
+        .transpose()?
+        .unwrap_or_default();
+    let options = RunOptions {
+        working_dir: Some(project_path.clone()),
+        args: args.args_as_map(),
+        positional_args: args.positional.clone(),
+
+        timeout,
+        capture_output: true,
+        output: OutputLimits::from_config(&config)
+            .terminal(&config, std::io::stdout().is_terminal())
+            .mask(
+                service
+                    .as_ref()
+                    .map(|s| s.secret_values(&env))
+                    .unwrap_or_default(),
+            ),
+        env,
+        ..Default::default()
+    };
+
+    // Command-line globs add to the service's watch settings
+    let mut watch = service.map(|s| s.watch).unwrap_or_default();
+    watch.include.extend(args.include);
+    watch.exclude.extend(args.exclude);
+    if let Some(debounce_ms) = args.debounce_ms {
+        watch.debounce_ms = debounce_ms;
+    }
+
+    let command = runner.build_command(&project_path, &task, &options);
+    let session = WatchSession::new(&project_path, command, options, &watch)?;
+
+    eprintln!(
+        "{}: {} in {} (Ctrl-C to stop)",
+        "watch".cyan(),
+        task,
+        project_path.display()
+    );
+
+    // Runs are in their own process group, out of reach of the terminal's
+    // Ctrl-C; dropping the session kills the one in progress
+    let run = session.run(|event| match event {
+        WatchEvent::Started {
+            command, changed, ..
+        } => {
+            if !changed.is_empty() {
+                eprintln!("{}: changed {}", "watch".cyan(), changed.join(", "));
             }
-            Ok(result) => {
-                errors.push(format!(
-                    "Build failed for '{}': exit code {:?}",
-                    service_name, result.exit_code
-                ));
-                continue;
+            if verbose {
+                eprintln!("{}: {}", "command".cyan(), command);
             }
-            Err(e) => {
-                errors.push(format!("Build failed for '{}': {}", service_name, e));
-                continue;
+        }
+        WatchEvent::Finished {
+            success,
+            exit_code,
+            duration_ms,
+            stdout,
+            stderr,
+            ..
+        } => {
+            print!("{}", stdout);
+            eprint!("{}", stderr);
+            if success {
+                eprintln!(
+                    "{}: {} completed in {}ms",
+                    "success".green(),
+                    task,
+                    duration_ms
+                );
+            } else {
+                eprintln!(
+                    "{}: {} failed with exit code {:?}",
+                    "failed".red(),
+                    task,
+                    exit_code
+                );
             }
         }
+        WatchEvent::Failed { error, .. } => {
+            eprintln!("{}: {}", "failed".red(), error);
+        }
+        WatchEvent::Cancelled { .. } => {
+            eprintln!("{}: files changed, restarting", "cancelled".yellow());
+        }
+    });
 
-        // Handle dependencies
-        if !args.skip_deps {
-            for dep in &service.depends_on {
-                if verbose {
-                    eprintln!(
-                        "{}: {} (dependency of {})",
-                        "restarting".cyan(),
-                        dep,
-                        service_name
-                    );
-                }
-                restarted.push(dep.clone());
+    tokio::select! {
+        result = run => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    Ok(())
+}
+
+/// Show recorded runs, newest first
+fn show_history(args: HistoryArgs, config_path: Option<&str>) -> Result<()> {
+    let config = load_resolved_config(config_path)?;
+    let history = RunHistory::from_config(&config)
+        .context("Run history is disabled (set enabled = true under [history])")?;
+
+    let filter = HistoryFilter {
+        project: args.project,
+        service: args.service,
+        task: args.task,
+        failed_only: args.failed,
+        session: None,
+        limit: args.limit,
+    };
+    let mut runs = history.query(&filter)?;
+    if !args.output {
+        for run in &mut runs {
+            run.stdout.clear();
+            run.stderr.clear();
+        }
+    }
+
+    match args.format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&runs)?);
+        }
+        OutputFormat::Plain => {
+            for run in &runs {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    run.timestamp,
+                    if run.success { "ok" } else { "failed" },
+                    run.project,
+                    run.argv.join(" ")
+                );
             }
         }
+        OutputFormat::Table => {
+            if runs.is_empty() {
+                println!("No recorded runs in {}", history.path().display());
+                return Ok(());
+            }
 
-        // Handle force recreate
-        if !args.skip_recreate {
-            for container in &service.force_recreate {
-                if verbose {
-                    eprintln!("{}: {}", "recreating".cyan(), container);
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            for run in &runs {
+                let status = match (run.success, run.exit_code) {
+                    (true, _) if run.cached => "cached".green(),
+                    (true, _) => "ok".green(),
+                    (false, Some(code)) => format!("exit {}", code).red(),
+                    (false, None) => "error".red(),
+                };
+                println!(
+                    "{:>8}  {:8}  {:>7}  {:20}  {}",
+                    format_age(now.saturating_sub(run.timestamp)),
+                    status,
+                    format!("{}ms", run.duration_ms),
+                    run.service.as_deref().unwrap_or(&run.project),
+                    run.argv.join(" ")
+                );
+                if let Some(error) = &run.error {
+                    println!("          {}", error.red());
+                }
+                for output in [&run.stdout, &run.stderr] {
+                    for line in output.lines() {
+                        println!("          {}", line.dimmed());
+                    }
                 }
-                recreated.push(container.clone());
             }
         }
     }
 
-    // Report results
-    if !rebuilt.is_empty() {
-        println!("{}: {}", "Rebuilt".green(), rebuilt.join(", "));
+    Ok(())
+}
+
+/// Age of a record, e.g. `5m ago`
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
     }
-    if !restarted.is_empty() {
-        println!("{}: {}", "Restarted".green(), restarted.join(", "));
+}
+
+fn print_plan(plan: &RebuildPlan) {
+    for (i, step) in plan.steps.iter().enumerate() {
+        let status = match step.status {
+            StepStatus::Pending => "pending".normal(),
+            StepStatus::Succeeded => "ok".green(),
+            StepStatus::Failed => "failed".red(),
+            StepStatus::Skipped => "skipped".yellow(),
+        };
+        println!(
+            "  {}. {:8} {} ({}) [{}]",
+            i + 1,
+            step.action.to_string(),
+            step.service,
+            step.target,
+            status
+        );
     }
-    if !recreated.is_empty() {
-        println!("{}: {}", "Recreated".green(), recreated.join(", "));
+}
+
+/// Approve, revoke or list trusted project config files
+fn trust_config(args: TrustArgs) -> Result<()> {
+    let mut store = TrustStore::open_default()?;
+
+    if args.list {
+        if store.files().next().is_none() {
+            println!("No trusted project configs in {}", store.path().display());
+        }
+        for (path, hash) in store.files() {
+            println!("{}  {}", &hash[..12], path.display());
+        }
+        return Ok(());
     }
 
-    if !errors.is_empty() {
-        eprintln!();
-        eprintln!("{}:", "Errors".red());
-        for error in &errors {
-            eprintln!("  - {}", error);
+    let path = match args.path.map(PathBuf::from) {
+        Some(path) if path.is_dir() => path.join(project_config_path()),
+        Some(path) => path,
+        None => project_config_path(),
+    };
+
+    if args.revoke {
+        if store.revoke(&path)? {
+            println!("{} {}", "Revoked".green(), path.display());
+        } else {
+            println!("{} was not trusted", path.display());
         }
-        anyhow::bail!("Rebuild completed with {} error(s)", errors.len());
+        return Ok(());
     }
 
+    let (path, hash) = store.trust(&path)?;
+    println!("{} {} ({})", "Trusted".green(), path.display(), &hash[..12]);
+
+    // Tell the user up front what won't apply
+    let mut table: toml::Table = std::fs::read_to_string(&path)?
+        .parse()
+        .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+    let ignored = strip_security_keys(&mut table);
+    if !ignored.is_empty() {
+        println!(
+            "{} security settings in project configs are ignored: {}",
+            "note:".yellow().bold(),
+            ignored.join(", ")
+        );
+    }
     Ok(())
 }
 
+/// Load configuration and expand environment variables in it
+fn load_resolved_config(config_path: Option<&str>) -> Result<Config> {
+    let store = TrustStore::open_default().ok();
+    if let Some(path) = untrusted_project_config(store.as_ref()) {
+        eprintln!(
+            "{} ignoring untrusted {}; run `makefilehub trust` to load it",
+            "warning:".yellow().bold(),
+            path.display()
+        );
+    }
+    let mut config = load_config(config_path)?;
+    interpolate_config(&mut config);
+    Ok(config)
+}
+
 /// Resolve project path from name or path
-fn resolve_project_path(project: Option<&str>, config: &Config) -> Result<PathBuf> {
+///
+/// When the project is a configured service, its resolved configuration is
+/// returned as well so that runner, task, env and timeout overrides apply.
+fn resolve_project(
+    project: Option<&str>,
+    config: &Config,
+) -> Result<(PathBuf, Option<ResolvedService>)> {
     match project {
         Some(p) => {
             // Try as a path first
             let path = PathBuf::from(p);
             if path.exists() {
-                return Ok(path);
+                return Ok((path, None));
             }
 
             // Check if it's a configured service
             if config.services.contains_key(p) {
                 let service = config.get_service(p);
-                return Ok(PathBuf::from(&service.project_dir));
+                return Ok((PathBuf::from(&service.project_dir), Some(service)));
             }
 
             anyhow::bail!("Project '{}' not found", p)
         }
-        None => Ok(std::env::current_dir().context("Failed to get current directory")?),
+        None => Ok((
+            std::env::current_dir().context("Failed to get current directory")?,
+            None,
+        )),
     }
 }
 
+/// Resolve project path from name or path, discarding service configuration
+fn resolve_project_path(project: Option<&str>, config: &Config) -> Result<PathBuf> {
+    resolve_project(project, config).map(|(path, _)| path)
+}
+
+/// Pick the runner: explicit flag, then service config, then detection
+fn select_runner_type(
+    project_path: &std::path::Path,
+    runner: Option<&str>,
+    service: Option<&ResolvedService>,
+    config: &Config,
+) -> Result<RunnerType> {
+    if let Some(runner_name) = runner {
+        return parse_runner_type(runner_name);
+    }
+    if let Some(runner_name) = service.and_then(|s| s.runner_override()) {
+        return parse_runner_type(&runner_name);
+    }
+    detect_runner(project_path, config)
+        .detected
+        .context("No build system detected in project")
+}
+
 /// Parse runner type from string
 fn parse_runner_type(s: &str) -> Result<RunnerType> {
     match s.to_lowercase().as_str() {
         "make" | "makefile" => Ok(RunnerType::Make),
         "just" | "justfile" => Ok(RunnerType::Just),
+        _ if s.starts_with("script:") => Ok(RunnerType::Script(s["script:".len()..].to_string())),
         _ => {
             // Assume it's a script name
             if s.contains('/') || s.ends_with(".sh") {
@@ -562,6 +1110,30 @@ fn parse_runner_type(s: &str) -> Result<RunnerType> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_task_failed_status() {
+        let failed = |exit_code| TaskFailed {
+            task: "test".to_string(),
+            exit_code,
+        };
+        assert_eq!(failed(Some(3)).status(), 3);
+        assert_eq!(failed(Some(130)).status(), 130);
+        assert_eq!(failed(Some(-1)).status(), 1);
+        assert_eq!(failed(None).status(), 1);
+        assert_eq!(
+            failed(Some(2)).to_string(),
+            "Task 'test' failed with exit code 2"
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "5s ago");
+        assert_eq!(format_age(300), "5m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(3 * 86400), "3d ago");
+    }
+
     #[test]
     fn test_parse_runner_type_make() {
         assert!(matches!(
@@ -610,6 +1182,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_runner_type_script_prefix() {
+        if let RunnerType::Script(name) = parse_runner_type("script:./deploy.sh").unwrap() {
+            assert_eq!(name, "./deploy.sh");
+        } else {
+            panic!("Expected Script");
+        }
+    }
+
+    #[test]
+    fn test_select_runner_type_uses_service_runner() {
+        let config = Config::default();
+        let mut service = config.get_service("svc");
+        service.runner = Some("just".to_string());
+
+        let runner =
+            select_runner_type(std::path::Path::new("/tmp"), None, Some(&service), &config)
+                .unwrap();
+        assert!(matches!(runner, RunnerType::Just));
+
+        // Explicit runner wins over the service runner
+        let runner = select_runner_type(
+            std::path::Path::new("/tmp"),
+            Some("make"),
+            Some(&service),
+            &config,
+        )
+        .unwrap();
+        assert!(matches!(runner, RunnerType::Make));
+    }
+
     #[test]
     fn test_resolve_project_path_current_dir() {
         let config = Config::default();
@@ -3,9 +3,13 @@
 //! Provides MCP tools for build system interaction:
 //! - `run_task` - Run a task/target in a project
 //! - `rebuild_service` - Build service with dependency handling
+//! - `rebuild_changed` - Rebuild services affected by git changes
 //! - `list_tasks` - List available tasks/targets
 //! - `detect_runner` - Detect which build system a project uses
 //! - `get_project_config` - Get resolved configuration
+//! - `watch_task` / `watch_status` / `stop_watch` - Re-run tasks on file changes
+//! - `get_run_history` / `get_last_failure` - Query past runs
+//! - `read_output` - Page through the full output of a truncated run
 
 pub mod server;
 
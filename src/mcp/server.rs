@@ -2,28 +2,74 @@
 //!
 //! Implements the MCP tools for makefilehub using rmcp SDK.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
-
-use rmcp::model::{Implementation, ServerCapabilities, ServerInfo, ToolsCapability};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, Implementation, ListToolsResult, PaginatedRequestParam,
+    ServerCapabilities, ServerInfo, ToolsCapability,
+};
+use rmcp::service::{Peer, RequestContext, RoleServer};
 use rmcp::{tool, ServerHandler};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::config::{interpolate_config, load_config, Config};
-use crate::error::{suggest_fix, ErrorInfo, TaskError};
+use crate::cache::{run_cached, TaskCache};
+use crate::config::RootsMode;
+use crate::config::{interpolate_config, load_config, Config, ResolvedService};
+use crate::diagnostics::{self, Diagnostic};
+use crate::error::{ErrorInfo, TaskError};
+use crate::history::{default_session, HistoryFilter, RunHistory, RunKind, RunRecord};
+use crate::orchestrator::{
+    ChangedServices, HealthReport, RebuildOptions, RebuildPlan, Rebuilder, StepAction, StepError,
+};
+use crate::output::{self, OutputLimits, Truncation};
 use crate::runner::{
-    detect_runner, JustfileRunner, MakefileRunner, RunOptions, Runner, RunnerType, ScriptRunner,
-    TaskInfo,
+    detect_runner, override_script, select_runner, CommandSpec, PtySize, RunOptions, RunResult,
+    Runner, TaskInfo,
+};
+use crate::security::audit::{self, ClientInfo};
+use crate::security::roots::resolve_roots;
+use crate::security::{
+    check_args, validate_project, validate_script, AuditEntry, AuditLog, AuditTrail, Policy,
 };
+use crate::suggest::Suggestions;
+use crate::testing::{self, TestSummary};
+use crate::watch::{WatchJobStatus, WatchJobs, WatchSession};
+
+/// How long to wait for the client to list its roots
+const ROOTS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Where the connected client's roots stand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RootsState {
+    /// Not asked for yet, or the request is pending
+    Loading,
+    /// Loaded, or not needed for this client
+    Loaded,
+    /// The client advertises roots but couldn't list them
+    Failed,
+}
 
 /// MCP Server for makefilehub
 #[derive(Clone)]
 pub struct MakefilehubServer {
     /// Loaded configuration
     config: Arc<RwLock<Config>>,
+    /// Background watch jobs
+    watch_jobs: WatchJobs,
+    /// Tags this server's runs in the run history
+    session_id: String,
+    /// The connected client, for requests to it (roots)
+    peer: Option<Peer<RoleServer>>,
+    /// Spill files of this session's runs, with their project directories
+    spill_files: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+    /// Whether the connected client's roots are loaded
+    roots: Arc<tokio::sync::watch::Sender<RootsState>>,
 }
 
 impl MakefilehubServer {
@@ -32,15 +78,18 @@ impl MakefilehubServer {
         let mut config = load_config(None)?;
         interpolate_config(&mut config);
 
-        Ok(Self {
-            config: Arc::new(RwLock::new(config)),
-        })
+        Ok(Self::with_config(config))
     }
 
     /// Create with a specific config
     pub fn with_config(config: Config) -> Self {
         Self {
             config: Arc::new(RwLock::new(config)),
+            watch_jobs: WatchJobs::new(),
+            session_id: default_session("mcp"),
+            peer: None,
+            spill_files: Arc::default(),
+            roots: Arc::new(tokio::sync::watch::Sender::new(RootsState::Loaded)),
         }
     }
 
@@ -52,60 +101,124 @@ impl MakefilehubServer {
         let mut config = load_config(None)?;
         interpolate_config(&mut config);
         let mut cfg = self.config.write().await;
+        // The client's roots belong to the session, not to the files
+        config.security.client_roots = cfg.security.client_roots.take();
         *cfg = config;
         tracing::info!("Configuration reloaded");
         Ok(())
     }
 
+    /// Ask the client for its roots and limit project paths to them
+    ///
+    /// Called once the client is initialized and whenever it reports its
+    /// roots changed. Does nothing for clients without roots support or when
+    /// `security.roots` is `ignore`; a failed request keeps the roots known
+    /// so far. Tool calls wait for the first refresh after the client
+    /// connects, and are refused until it succeeds.
+    pub async fn refresh_roots(&self) {
+        let loaded = self.load_roots().await;
+        self.roots.send_modify(|state| {
+            if loaded {
+                *state = RootsState::Loaded;
+            } else if *state == RootsState::Loading {
+                *state = RootsState::Failed;
+            }
+        });
+    }
+
+    /// Load the client's roots, returning false if it advertises roots but
+    /// didn't list them
+    async fn load_roots(&self) -> bool {
+        if self.config.read().await.security.roots == RootsMode::Ignore {
+            return true;
+        }
+        let Some(peer) = &self.peer else {
+            return true;
+        };
+        if peer.peer_info().capabilities.roots.is_none() {
+            return true;
+        }
+
+        let listed = match tokio::time::timeout(ROOTS_TIMEOUT, peer.list_roots()).await {
+            Ok(Ok(listed)) => listed,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to list client roots: {}", e);
+                return false;
+            }
+            Err(_) => {
+                tracing::warn!("Client did not list its roots in time");
+                return false;
+            }
+        };
+        let roots = resolve_roots(listed.roots.iter().map(|root| root.uri.as_str()));
+        tracing::info!("Client roots: {:?}", roots);
+        self.set_client_roots(roots).await;
+        true
+    }
+
+    /// Wait until the client's roots are loaded, so that no call is checked
+    /// against roots that aren't known yet
+    ///
+    /// # Errors
+    /// If the client advertises roots but hasn't listed them in time
+    async fn wait_for_roots(&self) -> Result<(), rmcp::Error> {
+        let mut state = self.roots.subscribe();
+        let settled = state.wait_for(|state| *state != RootsState::Loading);
+        let loaded = tokio::time::timeout(ROOTS_TIMEOUT, settled)
+            .await
+            .is_ok_and(|state| state.is_ok_and(|state| *state == RootsState::Loaded));
+        if loaded {
+            return Ok(());
+        }
+        Err(rmcp::Error::invalid_request(
+            "The client's roots are not loaded: answer roots/list, or send \
+             notifications/roots/list_changed to have them requested again",
+            None,
+        ))
+    }
+
+    /// Limit project paths to `roots` (see [`RootsMode`])
+    pub async fn set_client_roots(&self, roots: Vec<PathBuf>) {
+        self.config.write().await.security.client_roots = Some(roots);
+    }
+
     /// Get the appropriate runner for a directory
+    ///
+    /// A script named by the override has to stay inside the project (see
+    /// [`validate_script`]).
     fn get_runner(
         &self,
         dir: &std::path::Path,
         runner_override: Option<&str>,
         config: &Config,
     ) -> Result<Box<dyn Runner>, TaskError> {
-        // Use override if provided
-        if let Some(runner_name) = runner_override {
-            return match runner_name {
-                "make" => Ok(Box::new(MakefileRunner::new())),
-                "just" => Ok(Box::new(JustfileRunner::new())),
-                name if name.starts_with("script:") => {
-                    let script = name.strip_prefix("script:").unwrap_or("./run.sh");
-                    Ok(Box::new(ScriptRunner::new(script)))
-                }
-                name => {
-                    // Assume it's a script name
-                    Ok(Box::new(ScriptRunner::new(format!("./{}", name))))
-                }
-            };
-        }
-
-        // Auto-detect
-        let detection = detect_runner(dir, config);
-
-        match detection.detected {
-            Some(RunnerType::Make) => Ok(Box::new(MakefileRunner::new())),
-            Some(RunnerType::Just) => Ok(Box::new(JustfileRunner::new())),
-            Some(RunnerType::Script(script)) => Ok(Box::new(ScriptRunner::new(script))),
-            None => Err(TaskError::NoRunnerDetected {
-                path: dir.display().to_string(),
-                available: detection.available.iter().map(|r| r.to_string()).collect(),
-            }),
+        if let Some(script) = runner_override.and_then(|name| override_script(name, config)) {
+            validate_script(dir, &script, config)?;
         }
+        select_runner(dir, runner_override, config)
     }
 
     /// Resolve a project path from name or path
     ///
+    /// When `project` names a configured service, the fully resolved service
+    /// configuration is returned alongside the path so that its runner, script,
+    /// task overrides, environment and timeout can be applied.
+    ///
     /// # Security
     ///
     /// All paths are validated against the configured allowed_paths to prevent
     /// access to arbitrary directories. This protects against path traversal attacks
-    /// when makefilehub is used as an MCP server.
-    fn resolve_project_path(
+    /// when makefilehub is used as an MCP server. Paths are checked before
+    /// their existence is, and build files that resolve outside are refused.
+    fn resolve_project(
         &self,
         project: Option<&str>,
         config: &Config,
-    ) -> Result<PathBuf, TaskError> {
+    ) -> Result<(PathBuf, Option<ResolvedService>), TaskError> {
+        let mut service: Option<ResolvedService> = None;
+
+        // Nothing outside the allowed directories is looked at, so a refused
+        // path doesn't reveal whether it exists
         let path = match project {
             None => {
                 // Use current directory
@@ -114,61 +227,300 @@ impl MakefilehubServer {
             Some(path_or_name) => {
                 // Check if it's a path
                 let path = PathBuf::from(path_or_name);
-                if path.exists() {
+                let allowed = config.check_path_allowed(&path);
+                if allowed.is_ok() && path.exists() {
                     path
-                } else if let Some(service) = config.services.get(path_or_name) {
+                } else if config.has_service(path_or_name) {
                     // Check if it's a service name
-                    if let Some(ref project_dir) = service.project_dir {
-                        let expanded = PathBuf::from(project_dir);
-                        if expanded.exists() {
-                            expanded
-                        } else {
-                            return Err(TaskError::ProjectNotFound {
-                                path: path_or_name.to_string(),
-                                suggestion: Some(format!(
-                                    "Service '{}' directory '{}' does not exist",
-                                    path_or_name, project_dir
-                                )),
-                            });
-                        }
-                    } else {
+                    let resolved = config.get_service(path_or_name);
+                    let project_dir = PathBuf::from(&resolved.project_dir);
+                    if config.check_path_allowed(&project_dir).is_ok() && !project_dir.exists() {
                         return Err(TaskError::ProjectNotFound {
                             path: path_or_name.to_string(),
                             suggestion: Some(format!(
-                                "Service '{}' has no project_dir configured",
-                                path_or_name
+                                "Service '{}' directory '{}' does not exist",
+                                path_or_name, resolved.project_dir
                             )),
                         });
                     }
+                    service = Some(resolved);
+                    project_dir
                 } else {
                     // Try project patterns
-                    let mut found = None;
-                    for pattern in &config.projects.patterns {
-                        let resolved = pattern.replace("{name}", path_or_name);
-                        let try_path = PathBuf::from(&resolved);
-                        if try_path.exists() {
-                            found = Some(try_path);
-                            break;
+                    let found = config.projects.patterns.iter().find_map(|pattern| {
+                        let try_path = PathBuf::from(pattern.replace("{name}", path_or_name));
+                        (config.check_path_allowed(&try_path).is_ok() && try_path.exists())
+                            .then_some(try_path)
+                    });
+                    match (found, allowed) {
+                        (Some(found), _) => found,
+                        (None, Err(message)) => {
+                            return Err(TaskError::SecurityViolation {
+                                message,
+                                path: path_or_name.to_string(),
+                            })
+                        }
+                        (None, Ok(())) => {
+                            return Err(TaskError::ProjectNotFound {
+                                path: path_or_name.to_string(),
+                                suggestion: Some(format!(
+                                    "Check if '{}' exists or is configured in services",
+                                    path_or_name
+                                )),
+                            })
                         }
                     }
-                    found.ok_or_else(|| TaskError::ProjectNotFound {
-                        path: path_or_name.to_string(),
-                        suggestion: Some(format!(
-                            "Check if '{}' exists or is configured in services",
-                            path_or_name
-                        )),
-                    })?
                 }
             }
         };
 
-        // Validate path is within allowed directories
-        config
-            .validate_path(&path)
-            .map_err(|e| TaskError::SecurityViolation {
-                message: e,
-                path: path.display().to_string(),
+        // Validate path is within allowed directories, along with the build
+        // files in it, and work in the canonical path from here on
+        let script = service.as_ref().and_then(|s| s.script.as_deref());
+        let path = validate_project(&path, config, script)?;
+        audit::note_path(&path);
+
+        Ok((path, service))
+    }
+
+    /// Resolve a project path from name or path, discarding service configuration
+    fn resolve_project_path(
+        &self,
+        project: Option<&str>,
+        config: &Config,
+    ) -> Result<PathBuf, TaskError> {
+        self.resolve_project(project, config).map(|(path, _)| path)
+    }
+
+    /// Build run options for a project, applying the service's env and timeout
+    ///
+    /// Projects that are not configured services get the default timeout.
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If the service's env file can't be loaded
+    fn run_options(
+        project_path: &Path,
+        service: Option<&ResolvedService>,
+        config: &Config,
+    ) -> Result<RunOptions, TaskError> {
+        let env = service
+            .map(|s| s.environment())
+            .transpose()?
+            .unwrap_or_default();
+        let options = RunOptions {
+            working_dir: Some(project_path.to_path_buf()),
+            timeout: service
+                .map(|s| s.timeout_duration())
+                .unwrap_or_else(|| config.defaults.timeout_duration()),
+            output: OutputLimits::from_config(config)
+                .mask(service.map(|s| s.secret_values(&env)).unwrap_or_default()),
+            env,
+            ..Default::default()
+        };
+        // Tasks run on behalf of a client get the configured guardrails
+        Ok(match service {
+            Some(s) => options
+                .confined(&s.limits, &s.sandbox)
+                .isolated(s.env_mode, &s.env_passthrough),
+            None => options
+                .confined(&config.security.limits, &config.security.sandbox)
+                .isolated(config.security.env_mode, &config.security.env_passthrough),
+        })
+    }
+
+    /// Check a task, by requested and mapped name, against the service's
+    /// task policy (or `[security.policy]` for projects that are not services)
+    fn check_policy(
+        service: Option<&ResolvedService>,
+        config: &Config,
+        requested: &str,
+        task: &str,
+        confirmed: bool,
+    ) -> Result<(), TaskError> {
+        let policy = service.map_or(&config.security.policy, |s| &s.policy);
+        Policy::new(policy)?.check(&[requested, task], confirmed)
+    }
+
+    /// Run a tool call with an audit trail in scope, then record `entry`
+    /// completed with its outcome in `log`
+    async fn audited(
+        &self,
+        log: AuditLog,
+        entry: AuditEntry,
+        call: impl std::future::Future<Output = Result<CallToolResult, rmcp::Error>>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let trail = AuditTrail::default();
+        let start = std::time::Instant::now();
+        let result = trail.scope(call).await;
+
+        let response = match &result {
+            Ok(result) => result
+                .content
+                .iter()
+                .filter_map(|content| content.as_text())
+                .map(|text| text.text.as_str())
+                .collect(),
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        };
+        log.session(&self.session_id).record(entry.finish(
+            &response,
+            &trail,
+            start.elapsed().as_millis() as u64,
+        ));
+        result
+    }
+
+    /// Error response for `tool` when the server is in read-only mode
+    async fn refuse_if_read_only(&self, tool: &str) -> Option<String> {
+        if !self.config.read().await.security.read_only {
+            return None;
+        }
+        let error = TaskError::ReadOnly(tool.to_string());
+        Some(ToolError::format(
+            &error,
+            ErrorInfo::from(&error).suggestion,
+        ))
+    }
+}
+
+impl MakefilehubServer {
+    /// Run history for `config`, tagged with this server's session
+    fn history(&self, config: &Config) -> Option<RunHistory> {
+        RunHistory::from_config(config).map(|history| history.session(&self.session_id))
+    }
+
+    /// Recorded runs of `history` matching `filter` in projects this session
+    /// may see, newest first
+    fn visible_runs(
+        history: &RunHistory,
+        filter: &HistoryFilter,
+        config: &Config,
+    ) -> Result<Vec<RunRecord>, TaskError> {
+        // The limit applies to the visible runs
+        let all = HistoryFilter {
+            limit: 0,
+            ..filter.clone()
+        };
+        let runs = history
+            .query(&all)?
+            .into_iter()
+            .filter(|run| config.check_path_allowed(Path::new(&run.project)).is_ok());
+        Ok(match filter.limit {
+            0 => runs.collect(),
+            limit => runs.take(limit).collect(),
+        })
+    }
+
+    /// Remember the spill files of a run in `project_path` for read_output
+    fn note_spill_files(&self, project_path: &Path, result: &RunResult) {
+        let mut spill_files = self.spill_files.lock().unwrap_or_else(|e| e.into_inner());
+        for truncation in [&result.stdout_truncation, &result.stderr_truncation]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(file) = truncation
+                .spill_file
+                .as_ref()
+                .and_then(|f| f.canonicalize().ok())
+            {
+                spill_files.insert(file, project_path.to_path_buf());
+            }
+        }
+    }
+
+    /// Check that this session may read the spill file `file`: one of its
+    /// own runs in an allowed project, or any when it isn't limited to
+    /// client roots
+    fn check_spill_file(&self, file: &Path, config: &Config) -> Result<(), TaskError> {
+        let project = self
+            .spill_files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(file)
+            .cloned();
+        let allowed = match project {
+            Some(project) => config.check_path_allowed(&project),
+            None if config.client_roots().is_none() || config.security.allow_any_path => Ok(()),
+            None => Err(format!(
+                "'{}' is not the output of a run in the client's roots",
+                file.display()
+            )),
+        };
+        allowed.map_err(|message| TaskError::SecurityViolation {
+            message,
+            path: file.display().to_string(),
+        })
+    }
+
+    /// Plan a rebuild of `services` and, unless `dry_run`, execute it with a
+    /// rebuilder adjusted by `configure`
+    async fn rebuild(
+        &self,
+        config: Config,
+        services: &[String],
+        options: &RebuildOptions,
+        dry_run: bool,
+        confirmed: bool,
+        configure: impl FnOnce(Rebuilder) -> Rebuilder,
+    ) -> Result<RebuildServiceResponse, TaskError> {
+        let start = std::time::Instant::now();
+        let mut plan = RebuildPlan::new(&config, services, options)?;
+
+        if !dry_run {
+            // Every task the plan runs must pass its service's policy
+            for step in &plan.steps {
+                let generic = match step.action {
+                    StepAction::Build => "build",
+                    StepAction::Restart => "up",
+                    StepAction::Recreate => continue,
+                };
+                let service = config.get_service(&step.service);
+                Self::check_policy(Some(&service), &config, &step.target, generic, confirmed)?;
+            }
+
+            let history = self.history(&config);
+            configure(
+                Rebuilder::new(config)
+                    .enforce_allowed_paths(true)
+                    .confine(true),
+            )
+            .history(history)
+            .audit(AuditTrail::current())
+            .execute(&mut plan)
+            .await;
+        }
+
+        let errors = plan
+            .failures()
+            .map(|step| {
+                let error = step.error.clone().unwrap_or_else(|| StepError {
+                    command: step.target.clone(),
+                    exit_code: None,
+                    stderr: String::new(),
+                    suggestion: None,
+                });
+                RebuildError {
+                    service: step.service.clone(),
+                    command: error.command,
+                    exit_code: error.exit_code,
+                    stderr: error.stderr,
+                    suggestion: error.suggestion,
+                }
             })
+            .collect();
+
+        Ok(RebuildServiceResponse {
+            success: !dry_run && plan.success(),
+            dry_run,
+            services_rebuilt: plan.succeeded(StepAction::Build),
+            services_restarted: plan.succeeded(StepAction::Restart),
+            containers_recreated: plan.succeeded(StepAction::Recreate),
+            services_cached: plan.cached(),
+            health: plan.health(),
+            errors,
+            plan,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
     }
 }
 
@@ -201,6 +553,21 @@ pub struct RunTaskParams {
     /// Positional arguments
     #[serde(default)]
     pub positional_args: Vec<String>,
+
+    /// Run even if the service's task cache has an up-to-date entry
+    #[serde(default)]
+    pub no_cache: bool,
+
+    /// Run in a pseudo-terminal (true) or with pipes (false), for tools that
+    /// need a TTY; defaults to the service's `pty` setting. Output is merged
+    /// into stdout
+    #[serde(default)]
+    pub pty: Option<bool>,
+
+    /// Confirm a task the task policy marks as needing confirmation; only
+    /// set this once the user has approved running it
+    #[serde(default)]
+    pub confirm: bool,
 }
 
 /// Response from run_task tool
@@ -208,28 +575,95 @@ pub struct RunTaskParams {
 pub struct RunTaskResponse {
     /// Whether the task succeeded
     pub success: bool,
-    /// Task that was run
+    /// Task that was run (after applying service task overrides)
     pub task: String,
+    /// Service whose configuration was applied, if the project is a service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
     /// Runner that was used
     pub runner_used: String,
-    /// Full command that was executed
+    /// Full command that was executed (shell-escaped, safe to copy into a shell)
     pub command_executed: String,
-    /// Standard output (truncated if large)
+    /// Structured command: program, argv, working directory and environment
+    pub command: CommandSpec,
+    /// Standard output (head and tail only if large)
     #[serde(skip_serializing_if = "String::is_empty")]
     pub stdout: String,
-    /// Standard error
+    /// Standard error (head and tail only if large)
     #[serde(skip_serializing_if = "String::is_empty")]
     pub stderr: String,
+    /// What was left out of stdout and where the full output is, if truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout_truncation: Option<Truncation>,
+    /// What was left out of stderr and where the full output is, if truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr_truncation: Option<Truncation>,
     /// Exit code
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exit_code: Option<i32>,
     /// Duration in milliseconds
     pub duration_ms: u64,
+    /// Whether the task was skipped because its cached inputs and outputs are up to date
+    pub cached: bool,
+    /// Compiler, linter and test failure locations found in the output
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Pass/fail/skip counts and failures, if the output is from a known test runner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_summary: Option<TestSummary>,
     /// Error information if failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ErrorInfo>,
 }
 
+impl RunTaskResponse {
+    /// Describe a completed run, extracting diagnostics from its output and
+    /// suggesting a fix from `suggestions` if it failed
+    ///
+    /// `test_summary` is left empty, since JUnit reports depend on the
+    /// service configuration; see [`testing::collect`].
+    pub fn from_result(
+        task: String,
+        service: Option<String>,
+        runner: &str,
+        result: RunResult,
+        suggestions: &Suggestions,
+    ) -> Self {
+        let suggestion = (!result.success)
+            .then(|| suggestions.suggest(runner, result.error_output()))
+            .flatten();
+        Self {
+            diagnostics: diagnostics::extract(&result.stdout, &result.stderr),
+            test_summary: None,
+            success: result.success,
+            task,
+            service,
+            runner_used: runner.to_string(),
+            command_executed: result.command.to_string(),
+            command: result.command.clone(),
+            stdout: result.stdout,
+            stderr: result.stderr.clone(),
+            stdout_truncation: result.stdout_truncation,
+            stderr_truncation: result.stderr_truncation,
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+            cached: result.cached,
+            error: if !result.success {
+                Some(ErrorInfo {
+                    message: format!("Command failed with exit code {:?}", result.exit_code),
+                    error_type: "command_failed".to_string(),
+                    suggestion,
+                    exit_code: result.exit_code,
+                    stderr: Some(result.stderr),
+                    available: vec![],
+                })
+            } else {
+                None
+            },
+        }
+    }
+}
+
 /// Parameters for list_tasks tool
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListTasksParams {
@@ -311,6 +745,7 @@ pub struct ServiceConfigResponse {
     pub project_dir: Option<String>,
     pub runner: Option<String>,
     pub depends_on: Vec<String>,
+    pub dependents: Vec<String>,
     pub force_recreate: Vec<String>,
 }
 
@@ -324,29 +759,61 @@ pub struct RebuildServiceParams {
     #[serde(default)]
     pub services: Vec<String>,
 
-    /// Skip dependency restart
+    /// Only build the listed services: skip dependency builds and dependent restarts
     #[serde(default)]
     pub skip_deps: bool,
 
     /// Skip force-recreate
     #[serde(default)]
     pub skip_recreate: bool,
+
+    /// Only compute and return the rebuild plan without running it
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Maximum number of concurrent build steps (defaults to defaults.max_parallel)
+    #[serde(default)]
+    pub max_parallel: Option<usize>,
+
+    /// Stop starting new steps after the first failure instead of continuing
+    /// with independent services
+    #[serde(default)]
+    pub fail_fast: bool,
+
+    /// Run every build even if its task cache entry is up to date
+    #[serde(default)]
+    pub no_cache: bool,
+
+    /// Confirm a task the task policy marks as needing confirmation; only
+    /// set this once the user has approved running it
+    #[serde(default)]
+    pub confirm: bool,
 }
 
 /// Response from rebuild_service tool
 #[derive(Debug, Serialize)]
 pub struct RebuildServiceResponse {
-    /// Overall success
+    /// Overall success (false for dry runs)
     pub success: bool,
-    /// Services that were rebuilt
+    /// Whether the plan was only computed, not executed
+    pub dry_run: bool,
+    /// Services that were rebuilt, in build order
     pub services_rebuilt: Vec<String>,
-    /// Services that were restarted (dependencies)
+    /// Dependent services that were restarted
     pub services_restarted: Vec<String>,
     /// Containers that were recreated
     pub containers_recreated: Vec<String>,
+    /// Rebuilt services whose build was skipped because it was up to date in the task cache
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub services_cached: Vec<String>,
+    /// Health check outcome per restarted service that has a health check
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub health: BTreeMap<String, HealthReport>,
     /// Errors encountered
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub errors: Vec<RebuildError>,
+    /// Computed plan with the status of each step
+    pub plan: RebuildPlan,
     /// Total duration in milliseconds
     pub duration_ms: u64,
 }
@@ -361,106 +828,411 @@ pub struct RebuildError {
     pub suggestion: Option<String>,
 }
 
-/// Error response for tools
-#[derive(Debug, Serialize)]
-struct ToolError {
-    success: bool,
-    error: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    suggestion: Option<String>,
-}
+/// Parameters for rebuild_changed tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RebuildChangedParams {
+    /// Git ref to compare against (e.g. "main", "HEAD~3"); defaults to
+    /// uncommitted changes in the working tree
+    #[serde(default)]
+    pub since: Option<String>,
 
-impl ToolError {
-    fn format(error: impl std::fmt::Display, suggestion: Option<String>) -> String {
-        serde_json::to_string_pretty(&ToolError {
-            success: false,
-            error: error.to_string(),
-            suggestion,
-        })
-        .unwrap_or_else(|_| format!("{{\"success\":false,\"error\":\"{}\"}}", error))
-    }
-}
+    /// Only build the affected services: skip dependent restarts
+    #[serde(default)]
+    pub skip_deps: bool,
 
-// === MCP Tool Implementations ===
+    /// Skip force-recreate
+    #[serde(default)]
+    pub skip_recreate: bool,
 
-#[tool(tool_box)]
-impl MakefilehubServer {
-    /// Run a task/target in a project
-    ///
-    /// Auto-detects the build system (Makefile, justfile, or script) and runs the specified task.
-    #[tool(
-        description = "Run a task/target in a project. Auto-detects build system (Makefile, justfile, script)."
-    )]
-    pub async fn run_task(&self, #[tool(aggr)] params: RunTaskParams) -> String {
-        let config = self.config.read().await;
+    /// Only compute and return the rebuild plan without running it
+    #[serde(default)]
+    pub dry_run: bool,
 
-        let project_path = match self.resolve_project_path(params.project.as_deref(), &config) {
-            Ok(p) => p,
-            Err(e) => {
-                return ToolError::format(
-                    &e,
-                    Some("Check project path or configure in services".into()),
-                )
-            }
-        };
+    /// Maximum number of concurrent build steps (defaults to defaults.max_parallel)
+    #[serde(default)]
+    pub max_parallel: Option<usize>,
 
-        let runner = match self.get_runner(&project_path, params.runner.as_deref(), &config) {
-            Ok(r) => r,
-            Err(e) => {
-                return ToolError::format(
-                    &e,
-                    Some("Ensure Makefile, justfile, or run.sh exists".into()),
-                )
-            }
-        };
+    /// Stop starting new steps after the first failure instead of continuing
+    /// with independent services
+    #[serde(default)]
+    pub fail_fast: bool,
 
-        let options = RunOptions {
-            working_dir: Some(project_path.clone()),
-            args: params.args,
-            positional_args: params.positional_args,
-            ..Default::default()
-        };
+    /// Run every build even if its task cache entry is up to date
+    #[serde(default)]
+    pub no_cache: bool,
 
-        let result = match runner.run_task(&project_path, &params.task, &options) {
-            Ok(r) => r,
-            Err(e) => return ToolError::format(&e, None),
-        };
+    /// Confirm a task the task policy marks as needing confirmation; only
+    /// set this once the user has approved running it
+    #[serde(default)]
+    pub confirm: bool,
+}
 
-        let response = RunTaskResponse {
-            success: result.success,
-            task: params.task,
-            runner_used: runner.name().to_string(),
-            command_executed: result.command.clone(),
-            stdout: result.stdout,
-            stderr: result.stderr.clone(),
-            exit_code: result.exit_code,
-            duration_ms: result.duration_ms,
-            error: if !result.success {
-                Some(ErrorInfo {
-                    message: format!("Command failed with exit code {:?}", result.exit_code),
-                    error_type: "command_failed".to_string(),
-                    suggestion: suggest_fix(&result.command, &result.stderr),
-                    exit_code: result.exit_code,
-                    stderr: Some(result.stderr),
-                    available: vec![],
-                })
-            } else {
-                None
-            },
-        };
+/// Response from rebuild_changed tool
+#[derive(Debug, Serialize)]
+pub struct RebuildChangedResponse {
+    /// Git ref the changes were computed against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Services with changed files, mapped to those files
+    pub changed: BTreeMap<String, Vec<String>>,
+    /// Services rebuilt because they depend on a changed service
+    pub dependents: Vec<String>,
+    #[serde(flatten)]
+    pub rebuild: RebuildServiceResponse,
+}
 
-        serde_json::to_string_pretty(&response)
-            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
-    }
+/// Parameters for watch_task tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WatchTaskParams {
+    /// Task/target name to re-run on changes
+    pub task: String,
 
-    /// List available tasks/targets in a project
-    #[tool(
-        description = "List available tasks/targets in a project. Returns task names, descriptions, and arguments."
-    )]
-    pub async fn list_tasks(&self, #[tool(aggr)] params: ListTasksParams) -> String {
-        let config = self.config.read().await;
+    /// Project path or service name (defaults to current directory)
+    #[serde(default)]
+    pub project: Option<String>,
 
-        let project_path = match self.resolve_project_path(params.project.as_deref(), &config) {
+    /// Force specific runner ("make", "just", or script name)
+    #[serde(default)]
+    pub runner: Option<String>,
+
+    /// Named arguments as key-value pairs
+    #[serde(default)]
+    pub args: HashMap<String, String>,
+
+    /// Positional arguments
+    #[serde(default)]
+    pub positional_args: Vec<String>,
+
+    /// Extra globs a changed file must match (added to the service's include globs)
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Extra globs for files to ignore (added to the service's exclude globs)
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Quiet period in milliseconds before re-running (defaults to the service setting)
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+
+    /// Confirm a task the task policy marks as needing confirmation; only
+    /// set this once the user has approved running it
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Response from watch_task tool
+#[derive(Debug, Serialize)]
+pub struct WatchTaskResponse {
+    /// Id for watch_status and stop_watch
+    pub job_id: String,
+    /// Task being watched (after applying service task overrides)
+    pub task: String,
+    /// Project directory being watched
+    pub project_path: String,
+    /// Command re-run on each change
+    pub command: String,
+}
+
+/// Parameters for watch_status tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WatchStatusParams {
+    /// Job id returned by watch_task (defaults to all jobs)
+    #[serde(default)]
+    pub job_id: Option<String>,
+}
+
+/// Response from watch_status tool
+#[derive(Debug, Serialize)]
+pub struct WatchStatusResponse {
+    pub jobs: Vec<WatchJobStatus>,
+}
+
+/// Parameters for stop_watch tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StopWatchParams {
+    /// Job id returned by watch_task
+    pub job_id: String,
+}
+
+/// Parameters for get_run_history tool
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct GetRunHistoryParams {
+    /// Project path or service name
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Only runs of this configured service
+    #[serde(default)]
+    pub service: Option<String>,
+
+    /// Only runs of this task
+    #[serde(default)]
+    pub task: Option<String>,
+
+    /// Only failed runs
+    #[serde(default)]
+    pub failed_only: bool,
+
+    /// Only runs made through this MCP server session
+    #[serde(default)]
+    pub current_session: bool,
+
+    /// Maximum number of runs, newest first (default: 20)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Include the recorded stdout/stderr tails
+    #[serde(default)]
+    pub include_output: bool,
+}
+
+/// Response from get_run_history tool
+#[derive(Debug, Serialize)]
+pub struct GetRunHistoryResponse {
+    /// History file the runs were read from
+    pub history_file: String,
+    /// Matching runs, newest first
+    pub runs: Vec<RunRecord>,
+}
+
+/// Parameters for get_last_failure tool
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct GetLastFailureParams {
+    /// Project path or service name
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Only runs of this configured service
+    #[serde(default)]
+    pub service: Option<String>,
+
+    /// Only runs of this task
+    #[serde(default)]
+    pub task: Option<String>,
+
+    /// Only runs made through this MCP server session
+    #[serde(default)]
+    pub current_session: bool,
+}
+
+/// Response from get_last_failure tool
+#[derive(Debug, Serialize)]
+pub struct GetLastFailureResponse {
+    /// Most recent failed run, with its output tail
+    pub failure: Option<RunRecord>,
+}
+
+/// Default number of lines returned by read_output
+const DEFAULT_OUTPUT_PAGE_LINES: usize = 200;
+
+/// Maximum number of lines returned by read_output
+const MAX_OUTPUT_PAGE_LINES: usize = 2000;
+
+/// Parameters for read_output tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadOutputParams {
+    /// Spill file from a response's `stdout_truncation` / `stderr_truncation`
+    pub file: String,
+
+    /// First line to return (0-based)
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Number of lines to return (default 200, at most 2000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Error response for tools
+#[derive(Debug, Serialize)]
+struct ToolError {
+    success: bool,
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion: Option<String>,
+}
+
+impl ToolError {
+    fn format(error: impl std::fmt::Display, suggestion: Option<String>) -> String {
+        serde_json::to_string_pretty(&ToolError {
+            success: false,
+            error: error.to_string(),
+            suggestion,
+        })
+        .unwrap_or_else(|_| format!("{{\"success\":false,\"error\":\"{}\"}}", error))
+    }
+}
+
+// === MCP Tool Implementations ===
+
+#[tool(tool_box)]
+impl MakefilehubServer {
+    /// Run a task/target in a project
+    ///
+    /// Auto-detects the build system (Makefile, justfile, or script) and runs the specified task.
+    #[tool(
+        description = "Run a task/target in a project. Auto-detects build system (Makefile, justfile, script)."
+    )]
+    pub async fn run_task(&self, #[tool(aggr)] params: RunTaskParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("run_task").await {
+            return error;
+        }
+
+        let config = self.config.read().await;
+
+        let (project_path, service) = match self.resolve_project(params.project.as_deref(), &config)
+        {
+            Ok(p) => p,
+            Err(e) => {
+                return ToolError::format(
+                    &e,
+                    Some("Check project path or configure in services".into()),
+                )
+            }
+        };
+
+        // An explicit runner wins over the service's configured runner
+        let runner_override = params
+            .runner
+            .clone()
+            .or_else(|| service.as_ref().and_then(|s| s.runner_override()));
+
+        let runner = match self.get_runner(&project_path, runner_override.as_deref(), &config) {
+            Ok(r) => r,
+            Err(e) => {
+                return ToolError::format(
+                    &e,
+                    Some("Ensure Makefile, justfile, or run.sh exists".into()),
+                )
+            }
+        };
+
+        let task = service
+            .as_ref()
+            .map(|s| s.task_name(&params.task).to_string())
+            .unwrap_or_else(|| params.task.clone());
+
+        if let Err(e) = Self::check_policy(
+            service.as_ref(),
+            &config,
+            &params.task,
+            &task,
+            params.confirm,
+        ) {
+            return ToolError::format(&e, ErrorInfo::from(&e).suggestion);
+        }
+
+        let defaults = match Self::run_options(&project_path, service.as_ref(), &config) {
+            Ok(options) => options,
+            Err(e) => return ToolError::format(&e, ErrorInfo::from(&e).suggestion),
+        };
+        let mut options = RunOptions {
+            args: params.args,
+            positional_args: params.positional_args,
+            pty: match &service {
+                Some(s) => PtySize::select(&s.pty, s.uses_pty(&task), params.pty),
+                None => PtySize::select(&config.pty, config.pty.applies_to(&task), params.pty),
+            },
+            ..defaults
+        };
+        let args = service.as_ref().map_or(&config.security.args, |s| &s.args);
+        if let Err(e) = check_args(args, runner.name(), &task, &mut options) {
+            return ToolError::format(&e, ErrorInfo::from(&e).suggestion);
+        }
+
+        let rules = service
+            .as_ref()
+            .map_or(&config.suggestions, |s| &s.suggestions);
+        let suggestions = match Suggestions::new(rules) {
+            Ok(suggestions) => suggestions,
+            Err(e) => return ToolError::format(&e, None),
+        };
+
+        let cache_config = service
+            .as_ref()
+            .filter(|_| !params.no_cache)
+            .and_then(|s| s.cache_config(&task));
+        let started = SystemTime::now();
+        let result = run_cached(
+            &TaskCache::from_config(&config),
+            cache_config,
+            runner.as_ref(),
+            &project_path,
+            &task,
+            &options,
+        );
+        let history = self.history(&config);
+        let service_name = service.as_ref().map(|s| s.name.as_str());
+        let result = match result {
+            Ok(r) => r,
+            Err(e) => {
+                let command = runner.build_command(&project_path, &task, &options);
+                audit::note_command(
+                    &command.redacted(&options.output),
+                    ErrorInfo::from(&e).exit_code,
+                    0,
+                );
+                if let Some(history) = &history {
+                    history.record(
+                        RunRecord::from_error(
+                            RunKind::Task,
+                            &project_path,
+                            runner.name(),
+                            &task,
+                            &command,
+                            &e,
+                            &options.output,
+                        )
+                        .service(service_name),
+                    );
+                }
+                return ToolError::format(&e, None);
+            }
+        };
+        if !result.cached {
+            audit::note_command(&result.command, result.exit_code, result.duration_ms);
+        }
+        if let Some(history) = &history {
+            history.record(
+                RunRecord::from_result(RunKind::Task, &project_path, runner.name(), &task, &result)
+                    .service(service_name),
+            );
+        }
+        self.note_spill_files(&project_path, &result);
+
+        let test_reports = service
+            .as_ref()
+            .map(|s| s.test_reports.clone())
+            .unwrap_or_default();
+        let mut response = RunTaskResponse::from_result(
+            task,
+            service.map(|s| s.name),
+            runner.name(),
+            result,
+            &suggestions,
+        );
+        response.test_summary = testing::collect(
+            &project_path,
+            &test_reports,
+            started,
+            &response.stdout,
+            &response.stderr,
+        );
+
+        serde_json::to_string_pretty(&response)
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
+    }
+
+    /// List available tasks/targets in a project
+    #[tool(
+        description = "List available tasks/targets in a project. Returns task names, descriptions, and arguments."
+    )]
+    pub async fn list_tasks(&self, #[tool(aggr)] params: ListTasksParams) -> String {
+        let config = self.config.read().await;
+
+        let (project_path, service) = match self.resolve_project(params.project.as_deref(), &config)
+        {
             Ok(p) => p,
             Err(e) => {
                 return ToolError::format(
@@ -470,7 +1242,12 @@ impl MakefilehubServer {
             }
         };
 
-        let runner = match self.get_runner(&project_path, params.runner.as_deref(), &config) {
+        let runner_override = params
+            .runner
+            .clone()
+            .or_else(|| service.as_ref().and_then(|s| s.runner_override()));
+
+        let runner = match self.get_runner(&project_path, runner_override.as_deref(), &config) {
             Ok(r) => r,
             Err(e) => {
                 return ToolError::format(
@@ -551,6 +1328,10 @@ impl MakefilehubServer {
         description = "Get resolved configuration for a project, including service dependencies and tasks."
     )]
     pub async fn get_project_config(&self, #[tool(aggr)] params: GetProjectConfigParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("get_project_config").await {
+            return error;
+        }
+
         let config = self.config.read().await;
 
         let project_path = match self.resolve_project_path(Some(&params.project), &config) {
@@ -572,6 +1353,7 @@ impl MakefilehubServer {
                 project_dir: s.project_dir.clone(),
                 runner: s.runner.clone(),
                 depends_on: s.depends_on.clone(),
+                dependents: s.dependents.clone(),
                 force_recreate: s.force_recreate.clone(),
             });
 
@@ -596,247 +1378,371 @@ impl MakefilehubServer {
             .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
     }
 
-    /// Rebuild a service and handle dependencies
+    /// Start re-running a task whenever project files change
     #[tool(
-        description = "Rebuild a service with dependency handling. Restarts dependent services and force-recreates containers as configured."
+        description = "Watch a project and re-run a task on file changes in the background. Respects .gitignore and include/exclude globs, debounces changes and cancels an in-flight run when files change again. Returns a job_id for watch_status and stop_watch."
     )]
-    pub async fn rebuild_service(&self, #[tool(aggr)] params: RebuildServiceParams) -> String {
-        let start = std::time::Instant::now();
+    pub async fn watch_task(&self, #[tool(aggr)] params: WatchTaskParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("watch_task").await {
+            return error;
+        }
+
         let config = self.config.read().await;
 
-        let mut services_rebuilt = Vec::new();
-        let mut services_restarted = Vec::new();
-        let mut containers_recreated = Vec::new();
-        let mut errors = Vec::new();
+        let (project_path, service) = match self.resolve_project(params.project.as_deref(), &config)
+        {
+            Ok(p) => p,
+            Err(e) => {
+                return ToolError::format(
+                    &e,
+                    Some("Check project path or configure in services".into()),
+                )
+            }
+        };
 
-        // Collect all services to rebuild
-        let mut all_services = vec![params.service.clone()];
-        all_services.extend(params.services);
+        let runner_override = params
+            .runner
+            .clone()
+            .or_else(|| service.as_ref().and_then(|s| s.runner_override()));
 
-        for service_name in &all_services {
-            // Get service config
-            let service_config = config.services.get(service_name);
+        let runner = match self.get_runner(&project_path, runner_override.as_deref(), &config) {
+            Ok(r) => r,
+            Err(e) => {
+                return ToolError::format(
+                    &e,
+                    Some("Ensure Makefile, justfile, or run.sh exists".into()),
+                )
+            }
+        };
 
-            // Resolve project path
-            let project_path = if let Some(sc) = service_config {
-                if let Some(ref dir) = sc.project_dir {
-                    PathBuf::from(dir)
-                } else {
-                    match self.resolve_project_path(Some(service_name), &config) {
-                        Ok(p) => p,
-                        Err(e) => {
-                            errors.push(RebuildError {
-                                service: service_name.clone(),
-                                command: "resolve_path".to_string(),
-                                exit_code: None,
-                                stderr: e.to_string(),
-                                suggestion: Some(
-                                    "Configure project_dir in service config".to_string(),
-                                ),
-                            });
-                            continue;
-                        }
-                    }
-                }
-            } else {
-                match self.resolve_project_path(Some(service_name), &config) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        errors.push(RebuildError {
-                            service: service_name.clone(),
-                            command: "resolve_path".to_string(),
-                            exit_code: None,
-                            stderr: e.to_string(),
-                            suggestion: None,
-                        });
-                        continue;
-                    }
-                }
-            };
+        let task = service
+            .as_ref()
+            .map(|s| s.task_name(&params.task).to_string())
+            .unwrap_or_else(|| params.task.clone());
+
+        if let Err(e) = Self::check_policy(
+            service.as_ref(),
+            &config,
+            &params.task,
+            &task,
+            params.confirm,
+        ) {
+            return ToolError::format(&e, ErrorInfo::from(&e).suggestion);
+        }
 
-            // Get runner
-            let runner_override = service_config.and_then(|sc| sc.runner.as_deref());
-            let runner = match self.get_runner(&project_path, runner_override, &config) {
-                Ok(r) => r,
-                Err(e) => {
-                    errors.push(RebuildError {
-                        service: service_name.clone(),
-                        command: "detect_runner".to_string(),
-                        exit_code: None,
-                        stderr: e.to_string(),
-                        suggestion: None,
-                    });
-                    continue;
-                }
-            };
+        let defaults = match Self::run_options(&project_path, service.as_ref(), &config) {
+            Ok(options) => options,
+            Err(e) => return ToolError::format(&e, ErrorInfo::from(&e).suggestion),
+        };
+        let mut options = RunOptions {
+            args: params.args,
+            positional_args: params.positional_args,
+            ..defaults
+        };
+        let args = service.as_ref().map_or(&config.security.args, |s| &s.args);
+        if let Err(e) = check_args(args, runner.name(), &task, &mut options) {
+            return ToolError::format(&e, ErrorInfo::from(&e).suggestion);
+        }
 
-            // Run build task
-            let build_task = service_config
-                .and_then(|sc| sc.tasks.get("build"))
-                .map(|s| s.as_str())
-                .unwrap_or("build");
+        let mut watch = service.map(|s| s.watch).unwrap_or_default();
+        watch.include.extend(params.include);
+        watch.exclude.extend(params.exclude);
+        if let Some(debounce_ms) = params.debounce_ms {
+            watch.debounce_ms = debounce_ms;
+        }
 
-            let options = RunOptions {
-                working_dir: Some(project_path.clone()),
-                ..Default::default()
-            };
+        let command = runner.build_command(&project_path, &task, &options);
+        audit::note_command(&command.redacted(&options.output), None, 0);
+        let session = match WatchSession::new(&project_path, command, options, &watch) {
+            Ok(session) => session,
+            Err(e) => return ToolError::format(&e, None),
+        };
 
-            match runner.run_task(&project_path, build_task, &options) {
-                Ok(result) => {
-                    if result.success {
-                        services_rebuilt.push(service_name.clone());
-                    } else {
-                        errors.push(RebuildError {
-                            service: service_name.clone(),
-                            command: result.command,
-                            exit_code: result.exit_code,
-                            stderr: result.stderr.clone(),
-                            suggestion: suggest_fix(runner.name(), &result.stderr),
-                        });
-                    }
-                }
-                Err(e) => {
-                    errors.push(RebuildError {
-                        service: service_name.clone(),
-                        command: format!("{} {}", runner.name(), build_task),
-                        exit_code: None,
-                        stderr: e.to_string(),
-                        suggestion: None,
-                    });
-                }
+        let command = session.command().to_string();
+        let job_id = match self.watch_jobs.start(
+            &task,
+            &project_path.display().to_string(),
+            session,
+            config.defaults.max_watches,
+        ) {
+            Ok(job_id) => job_id,
+            Err(message) => {
+                return ToolError::format(
+                    message,
+                    Some("Stop a watch job with stop_watch, or raise defaults.max_watches".into()),
+                )
             }
+        };
+        let response = WatchTaskResponse {
+            command,
+            job_id,
+            task,
+            project_path: project_path.display().to_string(),
+        };
 
-            // Handle dependencies (restart)
-            if !params.skip_deps {
-                if let Some(sc) = service_config {
-                    for dep in &sc.depends_on {
-                        // Try to restart the dependency
-                        match self.resolve_project_path(Some(dep), &config) {
-                            Ok(dep_path) => match self.get_runner(&dep_path, None, &config) {
-                                Ok(dep_runner) => {
-                                    let up_task = config
-                                        .services
-                                        .get(dep)
-                                        .and_then(|s| s.tasks.get("up"))
-                                        .map(|s| s.as_str())
-                                        .unwrap_or("up");
-
-                                    let dep_options = RunOptions {
-                                        working_dir: Some(dep_path.clone()),
-                                        ..Default::default()
-                                    };
-
-                                    match dep_runner.run_task(&dep_path, up_task, &dep_options) {
-                                        Ok(result) if result.success => {
-                                            services_restarted.push(dep.clone());
-                                        }
-                                        Ok(result) => {
-                                            errors.push(RebuildError {
-                                                service: dep.clone(),
-                                                command: format!(
-                                                    "{} {}",
-                                                    dep_runner.name(),
-                                                    up_task
-                                                ),
-                                                exit_code: result.exit_code,
-                                                stderr: result.stderr,
-                                                suggestion: Some(
-                                                    "Check dependency service logs".to_string(),
-                                                ),
-                                            });
-                                        }
-                                        Err(e) => {
-                                            errors.push(RebuildError {
-                                                service: dep.clone(),
-                                                command: format!(
-                                                    "{} {}",
-                                                    dep_runner.name(),
-                                                    up_task
-                                                ),
-                                                exit_code: None,
-                                                stderr: e.to_string(),
-                                                suggestion: None,
-                                            });
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(
-                                        "Failed to get runner for dependency '{}': {}",
-                                        dep,
-                                        e
-                                    );
-                                }
-                            },
-                            Err(e) => {
-                                tracing::warn!(
-                                    "Failed to resolve path for dependency '{}': {}",
-                                    dep,
-                                    e
-                                );
-                            }
-                        }
-                    }
-                }
-            }
+        serde_json::to_string_pretty(&response)
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
+    }
 
-            // Handle force-recreate using async docker compose (modern plugin syntax)
-            if !params.skip_recreate {
-                if let Some(sc) = service_config {
-                    for container in &sc.force_recreate {
-                        let recreate_result = tokio::process::Command::new("docker")
-                            .current_dir(&project_path)
-                            .args(["compose", "up", "-d", "--force-recreate", container])
-                            .output()
-                            .await;
-
-                        match recreate_result {
-                            Ok(output) if output.status.success() => {
-                                containers_recreated.push(container.clone());
-                            }
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                tracing::warn!(
-                                    "Failed to recreate container '{}': {}",
-                                    container,
-                                    stderr
-                                );
-                            }
-                            Err(e) => {
-                                tracing::warn!(
-                                    "Failed to recreate container '{}': {}",
-                                    container,
-                                    e
-                                );
-                            }
-                        }
-                    }
-                }
-            }
+    /// Report the state and recent runs of watch jobs
+    #[tool(
+        description = "Get the state and most recent run events (start, output, exit code, cancellation) of background watch jobs."
+    )]
+    pub async fn watch_status(&self, #[tool(aggr)] params: WatchStatusParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("watch_status").await {
+            return error;
         }
 
-        let response = RebuildServiceResponse {
-            success: errors.is_empty(),
-            services_rebuilt,
-            services_restarted,
-            containers_recreated,
-            errors,
-            duration_ms: start.elapsed().as_millis() as u64,
-        };
+        let jobs = self.watch_jobs.status(params.job_id.as_deref());
 
-        serde_json::to_string_pretty(&response)
+        if let (Some(job_id), true) = (&params.job_id, jobs.is_empty()) {
+            return ToolError::format(
+                format!("Watch job not found: {}", job_id),
+                Some("Call watch_status without job_id to list running jobs".into()),
+            );
+        }
+
+        serde_json::to_string_pretty(&WatchStatusResponse { jobs })
             .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
     }
-}
 
-#[tool(tool_box)]
-impl ServerHandler for MakefilehubServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            protocol_version: Default::default(),
-            capabilities: ServerCapabilities {
-                tools: Some(ToolsCapability {
-                    list_changed: Some(false),
+    /// Stop a watch job
+    #[tool(description = "Stop a background watch job, killing any in-flight run.")]
+    pub async fn stop_watch(&self, #[tool(aggr)] params: StopWatchParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("stop_watch").await {
+            return error;
+        }
+
+        match self.watch_jobs.stop(&params.job_id) {
+            Some(status) => serde_json::to_string_pretty(&status)
+                .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None)),
+            None => ToolError::format(
+                format!("Watch job not found: {}", params.job_id),
+                Some("Call watch_status to list running jobs".into()),
+            ),
+        }
+    }
+
+    /// Query recorded task runs and rebuild steps
+    #[tool(
+        description = "Get recorded task runs and rebuild steps, newest first, with command, exit code and duration. Filter by project, service, task, failures or the current session; set include_output for the output tails."
+    )]
+    pub async fn get_run_history(&self, #[tool(aggr)] params: GetRunHistoryParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("get_run_history").await {
+            return error;
+        }
+
+        let config = self.config.read().await;
+        let Some(history) = self.history(&config) else {
+            return ToolError::format(
+                "Run history is disabled",
+                Some("Set enabled = true in the [history] config section".into()),
+            );
+        };
+
+        let filter = HistoryFilter {
+            project: params.project,
+            service: params.service,
+            task: params.task,
+            failed_only: params.failed_only,
+            session: params.current_session.then(|| self.session_id.clone()),
+            limit: params.limit.unwrap_or(20),
+        };
+        let mut runs = match Self::visible_runs(&history, &filter, &config) {
+            Ok(runs) => runs,
+            Err(e) => return ToolError::format(&e, None),
+        };
+        if !params.include_output {
+            for run in &mut runs {
+                run.stdout.clear();
+                run.stderr.clear();
+            }
+        }
+
+        let response = GetRunHistoryResponse {
+            history_file: history.path().display().to_string(),
+            runs,
+        };
+        serde_json::to_string_pretty(&response)
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
+    }
+
+    /// Get the most recent failed run
+    #[tool(
+        description = "Get the most recent failed task run or rebuild step with its command, exit code, error and output tail. Filter by project, service, task or the current session."
+    )]
+    pub async fn get_last_failure(&self, #[tool(aggr)] params: GetLastFailureParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("get_last_failure").await {
+            return error;
+        }
+
+        let config = self.config.read().await;
+        let Some(history) = self.history(&config) else {
+            return ToolError::format(
+                "Run history is disabled",
+                Some("Set enabled = true in the [history] config section".into()),
+            );
+        };
+
+        let filter = HistoryFilter {
+            project: params.project,
+            service: params.service,
+            task: params.task,
+            failed_only: true,
+            session: params.current_session.then(|| self.session_id.clone()),
+            limit: 1,
+        };
+        match Self::visible_runs(&history, &filter, &config) {
+            Ok(failures) => serde_json::to_string_pretty(&GetLastFailureResponse {
+                failure: failures.into_iter().next(),
+            })
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None)),
+            Err(e) => ToolError::format(&e, None),
+        }
+    }
+
+    /// Page through the full output of a truncated run
+    #[tool(
+        description = "Read the full output of a run whose stdout or stderr was truncated, by line. Pass the spill_file from stdout_truncation / stderr_truncation and page with offset and limit; next_offset is set while more lines remain."
+    )]
+    pub async fn read_output(&self, #[tool(aggr)] params: ReadOutputParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("read_output").await {
+            return error;
+        }
+
+        let config = self.config.read().await;
+        let Some(spill_dir) = config.output.spill_dir() else {
+            return ToolError::format(
+                "Output spill files are disabled",
+                Some("Set spill = true in the [output] config section".into()),
+            );
+        };
+
+        let limit = params
+            .limit
+            .unwrap_or(DEFAULT_OUTPUT_PAGE_LINES)
+            .min(MAX_OUTPUT_PAGE_LINES);
+        let page = output::read_page(&spill_dir, &params.file, params.offset, limit)
+            .and_then(|page| self.check_spill_file(&page.file, &config).map(|()| page));
+        match page {
+            Ok(mut page) => {
+                if config.output.strip_ansi {
+                    for line in &mut page.lines {
+                        *line = output::ansi::strip(line).into_owned();
+                    }
+                }
+                serde_json::to_string_pretty(&page).unwrap_or_else(|e| {
+                    ToolError::format(format!("Serialization error: {}", e), None)
+                })
+            }
+            Err(e) => ToolError::format(&e, None),
+        }
+    }
+
+    /// Rebuild a service and handle dependencies
+    #[tool(
+        description = "Rebuild services in dependency order. Builds transitive depends_on first (independent services in parallel), restarts dependents and waits for their health checks, force-recreates containers, and returns the executed plan with per-step output. Use dry_run to only compute the plan."
+    )]
+    pub async fn rebuild_service(&self, #[tool(aggr)] params: RebuildServiceParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("rebuild_service").await {
+            return error;
+        }
+
+        let config = self.config.read().await.clone();
+
+        // Collect all services to rebuild
+        let mut all_services = vec![params.service.clone()];
+        all_services.extend(params.services);
+
+        let options = RebuildOptions {
+            skip_deps: params.skip_deps,
+            skip_recreate: params.skip_recreate,
+            ..Default::default()
+        };
+        let response = match self
+            .rebuild(
+                config,
+                &all_services,
+                &options,
+                params.dry_run,
+                params.confirm,
+                |rebuilder| {
+                    rebuilder
+                        .max_parallel(params.max_parallel)
+                        .fail_fast(params.fail_fast)
+                        .no_cache(params.no_cache)
+                },
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return ToolError::format(&e, ErrorInfo::from(&e).suggestion),
+        };
+
+        serde_json::to_string_pretty(&response)
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
+    }
+
+    /// Rebuild the services affected by git changes
+    #[tool(
+        description = "Rebuild only the services affected by git changes. Files changed since a ref (or uncommitted changes, including untracked files, when no ref is given) are mapped to services by project_dir and watch_paths; services whose depends_on lists an affected service are rebuilt too, in dependency order. Uses the local git CLI only."
+    )]
+    pub async fn rebuild_changed(&self, #[tool(aggr)] params: RebuildChangedParams) -> String {
+        if let Some(error) = self.refuse_if_read_only("rebuild_changed").await {
+            return error;
+        }
+
+        let config = self.config.read().await.clone();
+
+        let affected = match ChangedServices::detect(&config, params.since.as_deref(), true) {
+            Ok(affected) => affected,
+            Err(e) => return ToolError::format(&e, ErrorInfo::from(&e).suggestion),
+        };
+
+        let options = RebuildOptions {
+            skip_deps: params.skip_deps,
+            skip_recreate: params.skip_recreate,
+            only_requested: true,
+        };
+        let rebuild = match self
+            .rebuild(
+                config,
+                &affected.services(),
+                &options,
+                params.dry_run,
+                params.confirm,
+                |rebuilder| {
+                    rebuilder
+                        .max_parallel(params.max_parallel)
+                        .fail_fast(params.fail_fast)
+                        .no_cache(params.no_cache)
+                },
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return ToolError::format(&e, ErrorInfo::from(&e).suggestion),
+        };
+
+        let response = RebuildChangedResponse {
+            since: params.since,
+            changed: affected.changed,
+            dependents: affected.dependents,
+            rebuild,
+        };
+
+        serde_json::to_string_pretty(&response)
+            .unwrap_or_else(|e| ToolError::format(format!("Serialization error: {}", e), None))
+    }
+}
+
+impl ServerHandler for MakefilehubServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: Default::default(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability {
+                    list_changed: Some(false),
                 }),
                 ..Default::default()
             },
@@ -851,6 +1757,63 @@ impl ServerHandler for MakefilehubServer {
             ),
         }
     }
+
+    async fn on_roots_list_changed(&self) {
+        self.refresh_roots().await;
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.clone()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self.peer = Some(peer);
+        // Until refresh_roots has asked the new client for its roots
+        self.roots.send_replace(RootsState::Loading);
+    }
+
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::Error> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box().list(),
+        })
+    }
+
+    /// Run a tool, recording the call in the audit log when enabled
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        self.wait_for_roots().await?;
+        let Some(log) = AuditLog::from_config(&*self.config.read().await) else {
+            return Self::tool_box()
+                .call(ToolCallContext::new(self, request, context))
+                .await;
+        };
+
+        let client = &context.peer.peer_info().client_info;
+        let entry = AuditEntry {
+            client: Some(ClientInfo {
+                name: client.name.clone(),
+                version: client.version.clone(),
+            }),
+            ..AuditEntry::new(
+                &request.name,
+                request
+                    .arguments
+                    .clone()
+                    .map(Into::into)
+                    .unwrap_or_default(),
+            )
+        };
+        let call = Self::tool_box().call(ToolCallContext::new(self, request, context));
+        self.audited(log, entry, call).await
+    }
 }
 
 #[cfg(test)]
@@ -886,6 +1849,1110 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_run_task_applies_service_config() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "compile:\n\t@echo \"built $$APP_ENV\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            ..Default::default()
+        };
+        service
+            .tasks
+            .insert("build".to_string(), "compile".to_string());
+        service
+            .env
+            .insert("APP_ENV".to_string(), "staging".to_string());
+        config.services.insert("app".to_string(), service);
+        config.history.enabled = false;
+
+        let server = MakefilehubServer::with_config(config);
+        let params = RunTaskParams {
+            task: "build".to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let json: serde_json::Value = serde_json::from_str(&server.run_task(params).await).unwrap();
+
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+        assert_eq!(json["task"], "compile");
+        assert_eq!(json["service"], "app");
+        assert_eq!(json["stdout"], "built staging\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_task_masks_secrets() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "show:\n\t@echo \"$$DB_URL $$API_TOKEN\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            secret_env: vec!["DB_URL".to_string()],
+            ..Default::default()
+        };
+        service
+            .env
+            .insert("DB_URL".to_string(), "postgres://u:pw@db".to_string());
+        service
+            .env
+            .insert("API_TOKEN".to_string(), "tok-abcdef".to_string());
+        config.services.insert("app".to_string(), service);
+        config.history.enabled = false;
+
+        let server = MakefilehubServer::with_config(config);
+        let params = RunTaskParams {
+            task: "show".to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let response = server.run_task(params).await;
+        let json: serde_json::Value = serde_json::from_str(&response).unwrap();
+
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+        assert_eq!(json["stdout"], "[REDACTED] [REDACTED]\n");
+        assert!(!response.contains("tok-abcdef"));
+    }
+
+    #[tokio::test]
+    async fn test_run_task_error_masks_secrets_in_history() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "deploy:\n\t@sleep 5\n").unwrap();
+        let history_path = temp.path().join("history.jsonl");
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.path = Some(history_path.display().to_string());
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            secret_env: vec!["DB_URL".to_string()],
+            timeout: Some(1),
+            ..Default::default()
+        };
+        service
+            .env
+            .insert("DB_URL".to_string(), "postgres://u:pw@db".to_string());
+        config.services.insert("app".to_string(), service);
+
+        let server = MakefilehubServer::with_config(config);
+        let params = RunTaskParams {
+            task: "deploy".to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::from([("url".to_string(), "postgres://u:pw@db".to_string())]),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let response = server.run_task(params).await;
+        if !response.contains("timed out") {
+            eprintln!("Skipping test: make not available ({})", response);
+            return;
+        }
+
+        // The run timed out before producing a result
+        let history = std::fs::read_to_string(&history_path).unwrap();
+        assert!(history.contains("timed out"), "{}", history);
+        assert!(history.contains("url=[REDACTED]"), "{}", history);
+        assert!(!history.contains("pw@db"), "{}", history);
+    }
+
+    #[tokio::test]
+    async fn test_client_roots_limit_projects() {
+        let allowed = tempfile::tempdir().unwrap();
+        let root = allowed.path().join("opened");
+        let other = allowed.path().join("other");
+        for dir in [&root, &other] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(dir.join("Makefile"), "build:\n\t@true\n").unwrap();
+        }
+
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+        let detect = |path: &Path| DetectRunnerParams {
+            project: Some(path.display().to_string()),
+        };
+
+        let result = server.detect_runner(detect(&other)).await;
+        assert!(!result.contains("security_violation"), "{}", result);
+
+        server
+            .set_client_roots(vec![root.canonicalize().unwrap()])
+            .await;
+        let result = server.detect_runner(detect(&root)).await;
+        assert!(!result.contains("security_violation"), "{}", result);
+        let result = server.detect_runner(detect(&other)).await;
+        assert!(result.contains("outside the client's roots"), "{}", result);
+
+        // Reloading the config keeps the session's roots
+        server.reload_config().await.unwrap();
+        let result = server.detect_runner(detect(&other)).await;
+        assert!(result.contains("outside the client's roots"), "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_run_task_rejects_option_task_names() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "build:\n").unwrap();
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+
+        for task in ["--eval=all:;id", "SHELL=/tmp/x"] {
+            let params = RunTaskParams {
+                task: task.to_string(),
+                project: Some(temp.path().display().to_string()),
+                runner: Some("make".to_string()),
+                args: HashMap::new(),
+                positional_args: vec![],
+                no_cache: false,
+                pty: None,
+                confirm: false,
+            };
+            let result = server.run_task(params).await;
+            assert!(result.contains("task names cannot"), "{}", result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_task_rejects_scripts_outside_project() {
+        let allowed = tempfile::tempdir().unwrap();
+        let project = allowed.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let marker = allowed.path().join("ran");
+        let evil = allowed.path().join("evil.sh");
+        std::fs::write(&evil, format!("#!/bin/sh\ntouch {}\n", marker.display())).unwrap();
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+
+        let absolute = format!("script:{}", evil.display());
+        for runner in [absolute.as_str(), "script:../evil.sh", "../evil.sh"] {
+            let params = RunTaskParams {
+                task: "build".to_string(),
+                project: Some(project.display().to_string()),
+                runner: Some(runner.to_string()),
+                args: HashMap::new(),
+                positional_args: vec![],
+                no_cache: false,
+                pty: None,
+                confirm: false,
+            };
+            let result = server.run_task(params).await;
+            assert!(result.contains("without '..'"), "{}", result);
+        }
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_client_roots_limit_history_and_output() {
+        let allowed = tempfile::tempdir().unwrap();
+        let root = allowed.path().join("opened");
+        let other = allowed.path().join("other");
+        for dir in [&root, &other] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(
+                dir.join("Makefile"),
+                "build:\n\t@for i in $$(seq 1 100); do echo \"line $$i\"; done; exit 1\n",
+            )
+            .unwrap();
+        }
+
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.path = Some(allowed.path().join("history.jsonl").display().to_string());
+        config.output.head_bytes = 50;
+        config.output.tail_bytes = 50;
+        let spill_dir = allowed.path().join("spill");
+        config.output.spill_dir = Some(spill_dir.display().to_string());
+        let server = MakefilehubServer::with_config(config);
+
+        // Runs in both projects, then the client opens only one
+        let mut spill_files = Vec::new();
+        for dir in [&root, &other] {
+            let params = RunTaskParams {
+                task: "build".to_string(),
+                project: Some(dir.display().to_string()),
+                runner: Some("make".to_string()),
+                args: HashMap::new(),
+                positional_args: vec![],
+                no_cache: false,
+                pty: None,
+                confirm: false,
+            };
+            let json: serde_json::Value =
+                serde_json::from_str(&server.run_task(params).await).unwrap();
+            if json["runner_used"] != "make" {
+                eprintln!("Skipping test: make not available ({})", json);
+                return;
+            }
+            spill_files.push(
+                json["stdout_truncation"]["spill_file"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        server
+            .set_client_roots(vec![root.canonicalize().unwrap()])
+            .await;
+        let root = root.canonicalize().unwrap().display().to_string();
+
+        let history: serde_json::Value =
+            serde_json::from_str(&server.get_run_history(GetRunHistoryParams::default()).await)
+                .unwrap();
+        let projects: Vec<_> = history["runs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|run| run["project"].as_str().unwrap())
+            .collect();
+        assert_eq!(projects, vec![root.as_str()]);
+
+        let failure: serde_json::Value = serde_json::from_str(
+            &server
+                .get_last_failure(GetLastFailureParams::default())
+                .await,
+        )
+        .unwrap();
+        assert_eq!(failure["failure"]["project"], root.as_str());
+
+        let read = |file: String| {
+            server.read_output(ReadOutputParams {
+                file,
+                offset: 0,
+                limit: None,
+            })
+        };
+        let page: serde_json::Value =
+            serde_json::from_str(&read(spill_files[0].clone()).await).unwrap();
+        assert_eq!(page["total_lines"], 100, "{}", page);
+        let result = read(spill_files[1].clone()).await;
+        assert!(result.contains("outside the client's roots"), "{}", result);
+
+        // Nor can spill files of runs this session didn't make be read
+        std::fs::write(spill_dir.join("1-2-3.stdout.log"), "secret\n").unwrap();
+        let result = read("1-2-3.stdout.log".to_string()).await;
+        assert!(result.contains("not the output of a run"), "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_outside_projects_do_not_leak_existence() {
+        let allowed = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let project = allowed.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.path().display().to_string()];
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+        let detect = |path: &Path| DetectRunnerParams {
+            project: Some(path.display().to_string()),
+        };
+
+        // Existing or not, a path outside gets the same refusal
+        for path in [outside.path().to_path_buf(), outside.path().join("missing")] {
+            let result = server.detect_runner(detect(&path)).await;
+            assert!(result.contains("outside allowed directories"), "{}", result);
+        }
+
+        // A Makefile linking outside the allowed directories is refused
+        #[cfg(unix)]
+        {
+            std::fs::write(outside.path().join("Makefile"), "build:\n\t@true\n").unwrap();
+            std::os::unix::fs::symlink(outside.path().join("Makefile"), project.join("Makefile"))
+                .unwrap();
+            let result = server.detect_runner(detect(&project)).await;
+            assert!(
+                result.contains("Security violation: Build file"),
+                "{}",
+                result
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_task_policy() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "release:\n\t@echo released\nclean-db:\n\t@echo dropped\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.security.policy.deny = vec!["clean-*".to_string()];
+        config.history.enabled = false;
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            policy: crate::config::TaskPolicy {
+                confirm: vec!["release".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for task in ["deploy", "build"] {
+            service
+                .tasks
+                .insert(task.to_string(), "release".to_string());
+        }
+        config.services.insert("app".to_string(), service);
+
+        let server = MakefilehubServer::with_config(config);
+        let run = |task: &str, confirm| RunTaskParams {
+            task: task.to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm,
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run("clean-db", true)).await).unwrap();
+        assert_eq!(json["success"], false);
+        assert!(json["error"].as_str().unwrap().contains("not allowed"));
+
+        // The name a task maps to is checked too
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run("deploy", false)).await).unwrap();
+        assert_eq!(json["error"], "Task 'deploy' requires confirmation");
+        assert!(json["suggestion"]
+            .as_str()
+            .unwrap()
+            .contains("confirm: true"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run("deploy", true)).await).unwrap();
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+        assert_eq!(json["stdout"], "released\n");
+
+        // Rebuilds check every task they would run
+        let params: RebuildServiceParams =
+            serde_json::from_str(r#"{"service": "app", "skip_recreate": true}"#).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&server.rebuild_service(params).await).unwrap();
+        assert_eq!(json["error"], "Task 'release' requires confirmation");
+    }
+
+    #[tokio::test]
+    async fn test_run_task_validates_args() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "show:\n\t@echo '$(MSG)'\n").unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+        let run = |args: &[(&str, &str)], positional: &[&str]| RunTaskParams {
+            task: "show".to_string(),
+            project: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            args: args
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            positional_args: positional.iter().map(|s| s.to_string()).collect(),
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+
+        let json: serde_json::Value = serde_json::from_str(
+            &server
+                .run_task(run(&[("MAKEFLAGS", "--eval=x")], &[]))
+                .await,
+        )
+        .unwrap();
+        assert_eq!(
+            json["error"],
+            "Variable 'MAKEFLAGS' cannot be set by make arguments"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run(&[], &["-f/etc/passwd"])).await).unwrap();
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid argument '-f/etc/passwd'"));
+
+        let json: serde_json::Value = serde_json::from_str(
+            &server
+                .run_task(run(&[("MSG", "$(shell echo injected)")], &[]))
+                .await,
+        )
+        .unwrap();
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+        assert_eq!(json["stdout"], "$(shell echo injected)\n");
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_records_tool_calls() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "build:\n\t@echo built\n").unwrap();
+        let log_path = temp.path().join("audit.jsonl");
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        config.security.audit.enabled = true;
+        config.security.audit.path = Some(log_path.display().to_string());
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            ..Default::default()
+        };
+        service
+            .env
+            .insert("API_TOKEN".to_string(), "s3cret".to_string());
+        config.services.insert("app".to_string(), service);
+
+        let server = MakefilehubServer::with_config(config.clone());
+        let params = serde_json::json!({"task": "build", "project": "app"});
+        let call = async {
+            let response = server
+                .run_task(serde_json::from_value(params.clone()).unwrap())
+                .await;
+            Ok(CallToolResult::success(vec![rmcp::model::Content::text(
+                response,
+            )]))
+        };
+        let log = AuditLog::from_config(&config).unwrap();
+        let entry = AuditEntry {
+            client: Some(ClientInfo {
+                name: "test-client".to_string(),
+                version: "1.0".to_string(),
+            }),
+            ..AuditEntry::new("run_task", params.clone())
+        };
+        server.audited(log, entry, call).await.unwrap();
+
+        let contents = std::fs::read_to_string(&log_path).unwrap();
+        assert!(!contents.contains("s3cret"));
+        let entry: AuditEntry = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(entry.tool, "run_task");
+        assert_eq!(entry.params, params);
+        assert_eq!(entry.client.unwrap().name, "test-client");
+        assert_eq!(entry.session, server.session_id);
+        assert_eq!(
+            entry.paths,
+            vec![temp.path().canonicalize().unwrap().display().to_string()]
+        );
+        let Some(command) = entry.commands.first() else {
+            eprintln!("Skipping test: make not available ({:?})", entry.error);
+            return;
+        };
+        assert_eq!(command.argv, vec!["make", "build"]);
+        assert!(command.env_keys.contains(&"API_TOKEN".to_string()));
+        assert_eq!(command.exit_code, Some(0));
+        assert!(entry.success);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode() {
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.security.read_only = true;
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+
+        let json: serde_json::Value = serde_json::from_str(
+            &server
+                .detect_runner(DetectRunnerParams {
+                    project: Some(".".to_string()),
+                })
+                .await,
+        )
+        .unwrap();
+        assert!(json.get("error").is_none(), "{}", json);
+
+        let params: RunTaskParams = serde_json::from_str(r#"{"task": "build"}"#).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&server.run_task(params).await).unwrap();
+        assert_eq!(json["success"], false);
+        assert_eq!(json["error"], "Read-only mode: run_task is disabled");
+
+        let json: serde_json::Value = serde_json::from_str(
+            &server
+                .get_run_history(serde_json::from_str("{}").unwrap())
+                .await,
+        )
+        .unwrap();
+        assert_eq!(json["error"], "Read-only mode: get_run_history is disabled");
+    }
+
+    #[tokio::test]
+    async fn test_run_task_in_pty() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "up:\n\t@test -t 1 && echo tty || echo pipe\n\t@stty size 2>/dev/null || true\n\t@echo oops >&2; exit 4\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        config.services.insert(
+            "app".to_string(),
+            crate::config::ServiceConfig {
+                project_dir: Some(temp.path().display().to_string()),
+                runner: Some("make".to_string()),
+                pty: Some(crate::config::PtyConfig {
+                    tasks: vec!["up".to_string()],
+                    cols: 100,
+                    rows: 30,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let server = MakefilehubServer::with_config(config);
+        let run = |pty| RunTaskParams {
+            task: "up".to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty,
+            confirm: false,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run(None)).await).unwrap();
+
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+        let stdout = json["stdout"].as_str().unwrap();
+        if !stdout.starts_with("tty\n") {
+            eprintln!("Skipping test: no pseudo-terminals ({})", json);
+            return;
+        }
+        // Merged output, without carriage returns
+        assert!(stdout.contains("30 100\noops\n"), "{}", stdout);
+        assert!(json["stderr"].as_str().unwrap_or_default().is_empty());
+        assert_eq!(json["exit_code"], 2);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&server.run_task(run(Some(false))).await).unwrap();
+        assert!(json["stdout"].as_str().unwrap().starts_with("pipe\n"));
+        assert_eq!(
+            json["stderr"].as_str().unwrap().lines().next(),
+            Some("oops")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_task_uses_cache() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "build:\n\t@echo built\n").unwrap();
+        std::fs::write(temp.path().join("input.txt"), "v1").unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.defaults.cache_dir = Some(temp.path().join(".cache").display().to_string());
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            ..Default::default()
+        };
+        service.cache.insert(
+            "build".to_string(),
+            crate::config::CacheConfig {
+                inputs: vec!["*.txt".to_string()],
+                outputs: vec![],
+            },
+        );
+        config.services.insert("app".to_string(), service);
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+
+        let run = |no_cache: bool| {
+            let server = server.clone();
+            async move {
+                let params = RunTaskParams {
+                    task: "build".to_string(),
+                    project: Some("app".to_string()),
+                    runner: None,
+                    args: HashMap::new(),
+                    positional_args: vec![],
+                    no_cache,
+                    pty: None,
+                    confirm: false,
+                };
+                serde_json::from_str::<serde_json::Value>(&server.run_task(params).await).unwrap()
+            }
+        };
+
+        let first = run(false).await;
+        if first["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", first);
+            return;
+        }
+        assert_eq!(first["cached"], false);
+
+        let second = run(false).await;
+        assert_eq!(second["cached"], true);
+        assert_eq!(second["stdout"], "built\n");
+
+        assert_eq!(run(true).await["cached"], false);
+    }
+
+    #[tokio::test]
+    async fn test_run_task_extracts_diagnostics() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "build:\n\t@echo \"main.c:3:10: error: 'x' undeclared\" >&2; exit 1\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        let server = MakefilehubServer::with_config(config);
+
+        let params = RunTaskParams {
+            task: "build".to_string(),
+            project: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let json: serde_json::Value = serde_json::from_str(&server.run_task(params).await).unwrap();
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+
+        assert_eq!(json["success"], false);
+        assert_eq!(
+            json["diagnostics"],
+            serde_json::json!([{
+                "file": "main.c",
+                "line": 3,
+                "column": 10,
+                "severity": "error",
+                "message": "'x' undeclared",
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_task_test_summary_from_junit_reports() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "test:\n\t@mkdir -p reports && cp junit.xml.in reports/junit.xml; exit 1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp.path().join("junit.xml.in"),
+            r#"<testsuite><testcase name="ok"/><testcase classname="app" name="fails"><failure message="expected 2"/></testcase></testsuite>"#,
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        config.services.insert(
+            "app".to_string(),
+            crate::config::ServiceConfig {
+                project_dir: Some(temp.path().display().to_string()),
+                runner: Some("make".to_string()),
+                test_reports: vec!["reports/*.xml".to_string()],
+                ..Default::default()
+            },
+        );
+        let server = MakefilehubServer::with_config(config);
+
+        let params = RunTaskParams {
+            task: "test".to_string(),
+            project: Some("app".to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let json: serde_json::Value = serde_json::from_str(&server.run_task(params).await).unwrap();
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+
+        let summary = &json["test_summary"];
+        assert_eq!(summary["framework"], "junit");
+        assert_eq!(summary["passed"], 1);
+        assert_eq!(summary["failed"], 1);
+        assert_eq!(summary["failures"][0]["name"], "app.fails");
+        assert_eq!(summary["failures"][0]["message"], "expected 2");
+    }
+
+    #[tokio::test]
+    async fn test_run_task_truncates_and_pages_output() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "build:\n\t@for i in $$(seq 1 500); do echo \"line $$i\"; done\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.enabled = false;
+        config.output.head_bytes = 100;
+        config.output.tail_bytes = 200;
+        config.output.spill_dir = Some(temp.path().join("spill").display().to_string());
+        let server = MakefilehubServer::with_config(config);
+
+        let params = RunTaskParams {
+            task: "build".to_string(),
+            project: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            args: HashMap::new(),
+            positional_args: vec![],
+            no_cache: false,
+            pty: None,
+            confirm: false,
+        };
+        let json: serde_json::Value = serde_json::from_str(&server.run_task(params).await).unwrap();
+        if json["runner_used"] != "make" {
+            eprintln!("Skipping test: make not available ({})", json);
+            return;
+        }
+
+        let stdout = json["stdout"].as_str().unwrap();
+        assert!(stdout.starts_with("line 1\n"));
+        assert!(stdout.ends_with("line 500\n"));
+        assert!(stdout.contains("[output truncated]"));
+        let truncation = &json["stdout_truncation"];
+        assert_eq!(truncation["total_lines"], 500);
+        let spill_file = truncation["spill_file"].as_str().unwrap().to_string();
+
+        let page: serde_json::Value = serde_json::from_str(
+            &server
+                .read_output(ReadOutputParams {
+                    file: spill_file.clone(),
+                    offset: 250,
+                    limit: Some(2),
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(page["lines"], serde_json::json!(["line 251", "line 252"]));
+        assert_eq!(page["total_lines"], 500);
+        assert_eq!(page["next_offset"], 252);
+
+        let outside: serde_json::Value = serde_json::from_str(
+            &server
+                .read_output(ReadOutputParams {
+                    file: temp.path().join("Makefile").display().to_string(),
+                    offset: 0,
+                    limit: None,
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(outside["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_run_history_tools() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "build:\n\t@echo built\ntest:\n\t@echo 'assertion failed' >&2; exit 3\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        config.history.path = Some(temp.path().join("history.jsonl").display().to_string());
+        let server = MakefilehubServer::with_config(config);
+
+        for task in ["test", "build"] {
+            let params = RunTaskParams {
+                task: task.to_string(),
+                project: Some(temp.path().display().to_string()),
+                runner: Some("make".to_string()),
+                args: HashMap::new(),
+                positional_args: vec![],
+                no_cache: false,
+                pty: None,
+                confirm: false,
+            };
+            let json: serde_json::Value =
+                serde_json::from_str(&server.run_task(params).await).unwrap();
+            if json["runner_used"] != "make" {
+                eprintln!("Skipping test: make not available ({})", json);
+                return;
+            }
+        }
+
+        let history: serde_json::Value = serde_json::from_str(
+            &server
+                .get_run_history(GetRunHistoryParams {
+                    current_session: true,
+                    ..Default::default()
+                })
+                .await,
+        )
+        .unwrap();
+        let runs = history["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0]["task"], "build");
+        assert_eq!(runs[0]["success"], true);
+        assert_eq!(runs[0]["stdout"], "");
+        assert_eq!(runs[1]["exit_code"], 2);
+
+        let failure: serde_json::Value = serde_json::from_str(
+            &server
+                .get_last_failure(GetLastFailureParams {
+                    project: Some(temp.path().display().to_string()),
+                    ..Default::default()
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(failure["failure"]["task"], "test");
+        assert_eq!(
+            failure["failure"]["argv"],
+            serde_json::json!(["make", "test"])
+        );
+        assert!(failure["failure"]["stderr"]
+            .as_str()
+            .unwrap()
+            .contains("assertion failed"));
+
+        let none: serde_json::Value = serde_json::from_str(
+            &server
+                .get_last_failure(GetLastFailureParams {
+                    task: Some("build".to_string()),
+                    ..Default::default()
+                })
+                .await,
+        )
+        .unwrap();
+        assert!(none["failure"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_watch_task_lifecycle() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("Makefile"), "build:\n\t@echo built\n").unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        let server = MakefilehubServer::with_config(config);
+
+        let params = WatchTaskParams {
+            task: "build".to_string(),
+            project: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            args: HashMap::new(),
+            positional_args: vec![],
+            include: vec!["src/**".to_string()],
+            exclude: vec![],
+            debounce_ms: Some(50),
+            confirm: false,
+        };
+        let started: serde_json::Value =
+            serde_json::from_str(&server.watch_task(params).await).unwrap();
+        let job_id = started["job_id"].as_str().unwrap().to_string();
+        assert_eq!(started["command"], "make build");
+
+        let status: serde_json::Value = serde_json::from_str(
+            &server
+                .watch_status(WatchStatusParams {
+                    job_id: Some(job_id.clone()),
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(status["jobs"][0]["state"], "running");
+
+        let stopped: serde_json::Value = serde_json::from_str(
+            &server
+                .stop_watch(StopWatchParams {
+                    job_id: job_id.clone(),
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(stopped["state"], "stopped");
+
+        let missing: serde_json::Value = serde_json::from_str(
+            &server
+                .watch_status(WatchStatusParams {
+                    job_id: Some(job_id),
+                })
+                .await,
+        )
+        .unwrap();
+        assert_eq!(missing["success"], false);
+    }
+
+    /// Start watching `project` and wait for the first run to finish
+    async fn first_watch_run(server: &MakefilehubServer, project: &str) -> serde_json::Value {
+        let params = WatchTaskParams {
+            task: "show".to_string(),
+            project: Some(project.to_string()),
+            runner: None,
+            args: HashMap::new(),
+            positional_args: vec![],
+            include: vec![],
+            exclude: vec![],
+            debounce_ms: Some(50),
+            confirm: false,
+        };
+        let started: serde_json::Value =
+            serde_json::from_str(&server.watch_task(params).await).unwrap();
+        let job_id = started["job_id"].as_str().expect("job started").to_string();
+
+        for _ in 0..200 {
+            let status: serde_json::Value = serde_json::from_str(
+                &server
+                    .watch_status(WatchStatusParams {
+                        job_id: Some(job_id.clone()),
+                    })
+                    .await,
+            )
+            .unwrap();
+            let finished = status["jobs"][0]["recent_events"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|e| e["event"] == "finished")
+                .cloned();
+            if let Some(event) = finished {
+                server.stop_watch(StopWatchParams { job_id }).await;
+                return event;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        panic!("Watch run didn't finish");
+    }
+
+    #[tokio::test]
+    async fn test_watch_task_isolates_env() {
+        if which::which("make").is_err() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "show:\n\t@echo \"[$$GREETING] [$$HOME]\"\n",
+        )
+        .unwrap();
+        std::fs::write(temp.path().join(".env"), "GREETING=hello\n").unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        let service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            env_file: Some(".env".to_string()),
+            env_mode: Some(crate::config::EnvMode::Allowlist),
+            env_passthrough: Some(vec!["PATH".to_string()]),
+            ..Default::default()
+        };
+        config.services.insert("app".to_string(), service);
+        config.history.enabled = false;
+
+        let server = MakefilehubServer::with_config(config);
+        let event = first_watch_run(&server, "app").await;
+        assert_eq!(event["stdout"], "[hello] []\n", "{}", event);
+    }
+
+    #[tokio::test]
+    async fn test_watch_task_masks_secrets() {
+        if which::which("make").is_err() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("Makefile"),
+            "show:\n\t@echo \"$$DB_URL\"\n\t@echo \"$$DB_URL\" >&2\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.security.allow_any_path = true;
+        let mut service = crate::config::ServiceConfig {
+            project_dir: Some(temp.path().display().to_string()),
+            runner: Some("make".to_string()),
+            secret_env: vec!["DB_URL".to_string()],
+            ..Default::default()
+        };
+        service
+            .env
+            .insert("DB_URL".to_string(), "postgres://u:pw@db".to_string());
+        config.services.insert("app".to_string(), service);
+        config.history.enabled = false;
+
+        let server = MakefilehubServer::with_config(config);
+        let event = first_watch_run(&server, "app").await;
+        assert_eq!(event["stdout"], "[REDACTED]\n", "{}", event);
+        assert_eq!(event["stderr"], "[REDACTED]\n", "{}", event);
+        assert!(!event.to_string().contains("pw@db"));
+    }
+
     #[test]
     fn test_run_task_params_deserialize() {
         let json = r#"{
@@ -919,20 +2986,28 @@ mod tests {
         let response = RunTaskResponse {
             success: true,
             task: "build".to_string(),
+            service: None,
             runner_used: "make".to_string(),
             command_executed: "make build".to_string(),
+            command: CommandSpec::new("make").arg("build"),
             stdout: "Build successful".to_string(),
             stderr: String::new(),
+            stdout_truncation: None,
+            stderr_truncation: None,
             exit_code: Some(0),
             duration_ms: 1234,
+            cached: false,
+            diagnostics: vec![],
+            test_summary: None,
             error: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"success\":true"));
         assert!(json.contains("\"runner_used\":\"make\""));
-        // stderr should be skipped since empty
+        // stderr and diagnostics should be skipped since empty
         assert!(!json.contains("\"stderr\""));
+        assert!(!json.contains("\"diagnostics\""));
     }
 
     #[test]
@@ -965,6 +3040,128 @@ mod tests {
         assert_eq!(params.services, vec!["frontend"]);
         assert!(params.skip_deps);
         assert!(!params.skip_recreate);
+        assert!(!params.dry_run);
+        assert_eq!(params.max_parallel, None);
+        assert!(!params.fail_fast);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_service_dry_run_returns_plan() {
+        let config: Config = toml::from_str(
+            r#"
+            [services.api]
+            depends_on = ["lib"]
+            dependents = ["web"]
+            "#,
+        )
+        .unwrap();
+        let server = MakefilehubServer::with_config(config);
+
+        let params = RebuildServiceParams {
+            service: "api".to_string(),
+            services: vec![],
+            skip_deps: false,
+            skip_recreate: false,
+            dry_run: true,
+            max_parallel: None,
+            fail_fast: false,
+            no_cache: false,
+            confirm: false,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&server.rebuild_service(params).await).unwrap();
+
+        assert_eq!(json["dry_run"], true);
+        let steps = json["plan"]["steps"].as_array().unwrap();
+        let order: Vec<_> = steps
+            .iter()
+            .map(|s| format!("{} {}", s["action"], s["service"]))
+            .collect();
+        assert_eq!(
+            order,
+            vec![r#""build" "lib""#, r#""build" "api""#, r#""restart" "web""#]
+        );
+        assert!(steps.iter().all(|s| s["status"] == "pending"));
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_changed_dry_run() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        for dir in ["lib", "api", "docs"] {
+            std::fs::create_dir(root.join(dir)).unwrap();
+        }
+        let status = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(root)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::fs::write(root.join("lib/lib.rs"), "").unwrap();
+
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [services.lib]
+            project_dir = "{root}/lib"
+
+            [services.api]
+            project_dir = "{root}/api"
+            depends_on = ["lib"]
+
+            [services.docs]
+            project_dir = "{root}/docs"
+            "#,
+            root = root.display()
+        ))
+        .unwrap();
+        let server = MakefilehubServer::with_config(config);
+
+        let params: RebuildChangedParams = serde_json::from_str(r#"{"dry_run": true}"#).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&server.rebuild_changed(params).await).unwrap();
+
+        assert_eq!(json["changed"]["lib"][0], "lib/lib.rs");
+        assert_eq!(json["dependents"][0], "api");
+        assert_eq!(json["dry_run"], true);
+        let services: Vec<_> = json["plan"]["steps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["service"].as_str().unwrap())
+            .collect();
+        assert_eq!(services, vec!["lib", "api"]);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_service_cycle_error() {
+        let config: Config = toml::from_str(
+            r#"
+            [services.a]
+            depends_on = ["b"]
+
+            [services.b]
+            depends_on = ["a"]
+            "#,
+        )
+        .unwrap();
+        let server = MakefilehubServer::with_config(config);
+
+        let params = RebuildServiceParams {
+            service: "a".to_string(),
+            services: vec![],
+            skip_deps: false,
+            skip_recreate: false,
+            dry_run: false,
+            max_parallel: None,
+            fail_fast: false,
+            no_cache: false,
+            confirm: false,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&server.rebuild_service(params).await).unwrap();
+
+        assert_eq!(json["success"], false);
+        assert_eq!(json["error"], "Dependency cycle detected: a -> b -> a");
     }
 
     #[test]
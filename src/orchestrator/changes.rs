@@ -0,0 +1,380 @@
+//! Affected-service detection from git changes
+//!
+//! Maps files changed since a git ref (or uncommitted changes in the working
+//! tree) to the services that own them. A file belongs to a service when it
+//! is under the service's `project_dir` or one of its `watch_paths`. The
+//! repository of each directory is looked up with `git rev-parse`, so
+//! services can share a monorepo or live in separate repositories. Only the
+//! local `git` CLI is used; nothing is fetched.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::TaskError;
+use crate::runner::CommandSpec;
+
+use super::graph::ServiceGraph;
+
+/// Timeout for each git invocation
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Services affected by a set of git changes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChangedServices {
+    /// Services with changed files, mapped to those files (relative to the repository root)
+    pub changed: BTreeMap<String, Vec<String>>,
+    /// Services affected only because they (transitively) depend on a changed service
+    pub dependents: Vec<String>,
+}
+
+impl ChangedServices {
+    /// Find services affected by changes since `since`, or by uncommitted
+    /// changes (including untracked files) when `since` is `None`
+    ///
+    /// Services whose directories don't exist or aren't in a git repository
+    /// are never affected. With `enforce_allowed_paths`, git is only run in
+    /// allowed directories (see [`Config::check_path_allowed`]): services
+    /// outside them and watch paths leading outside are skipped.
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If `since` looks like a command-line option
+    /// * `TaskError::CommandFailed` - If git fails, e.g. for an unknown ref
+    /// * `TaskError::SpawnFailed` - If git is not installed
+    pub fn detect(
+        config: &Config,
+        since: Option<&str>,
+        enforce_allowed_paths: bool,
+    ) -> Result<Self, TaskError> {
+        if let Some(since) = since {
+            if since.is_empty() || since.starts_with('-') {
+                return Err(TaskError::Config(format!("Invalid git ref: '{}'", since)));
+            }
+        }
+
+        let mut names: Vec<&String> = config.services.keys().collect();
+        names.sort();
+
+        // Changed files per repository root, listed once per repository
+        let mut repos: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut changed = BTreeMap::new();
+
+        let allowed = |path: &Path| {
+            let allowed = !enforce_allowed_paths || config.check_path_allowed(path).is_ok();
+            if !allowed {
+                tracing::debug!(
+                    "Not looking for changes outside allowed directories: {}",
+                    path.display()
+                );
+            }
+            allowed
+        };
+
+        for name in names {
+            let service = config.get_service(name);
+            let project_dir = PathBuf::from(&service.project_dir);
+            // Checked as written first, like project paths from MCP callers
+            if !allowed(&project_dir) {
+                continue;
+            }
+            let roots = std::iter::once(project_dir.clone()).chain(
+                service
+                    .watch_paths
+                    .iter()
+                    .map(|path| project_dir.join(path)),
+            );
+
+            let mut files = BTreeSet::new();
+            for root in roots {
+                if !allowed(&root) {
+                    continue;
+                }
+                let Ok(root) = root.canonicalize() else {
+                    continue;
+                };
+                if !allowed(&root) {
+                    continue;
+                }
+                let dir = if root.is_dir() {
+                    root.as_path()
+                } else {
+                    root.parent().unwrap_or(&root)
+                };
+                let Some(toplevel) = repo_root(dir)? else {
+                    continue;
+                };
+
+                if !repos.contains_key(&toplevel) {
+                    let files = changed_files(&toplevel, since)?;
+                    repos.insert(toplevel.clone(), files);
+                }
+                for file in &repos[&toplevel] {
+                    if toplevel.join(file).starts_with(&root) {
+                        files.insert(file.clone());
+                    }
+                }
+            }
+
+            if !files.is_empty() {
+                changed.insert(name.clone(), files.into_iter().collect());
+            }
+        }
+
+        let changed_names: Vec<String> = changed.keys().cloned().collect();
+        let dependents = ServiceGraph::from_config(config).dependents_of(&changed_names);
+
+        Ok(Self {
+            changed,
+            dependents,
+        })
+    }
+
+    /// All affected services: changed ones first, then their dependents
+    pub fn services(&self) -> Vec<String> {
+        self.changed
+            .keys()
+            .chain(&self.dependents)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether no service is affected
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+}
+
+/// Root of the git repository containing `dir`, or `None` outside a repository
+fn repo_root(dir: &Path) -> Result<Option<PathBuf>, TaskError> {
+    let output = CommandSpec::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(dir)
+        .output(Some(GIT_TIMEOUT))?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(Some(PathBuf::from(toplevel)))
+}
+
+/// Files changed in a repository, relative to its root
+fn changed_files(toplevel: &Path, since: Option<&str>) -> Result<Vec<String>, TaskError> {
+    let mut files = match since {
+        // Committed and uncommitted changes to tracked files since the ref
+        Some(since) => split_nul(&git(
+            toplevel,
+            &["diff", "--name-only", "--no-renames", "-z", since, "--"],
+        )?),
+        // Staged, unstaged and untracked changes; works without any commit
+        None => split_nul(&git(
+            toplevel,
+            &[
+                "status",
+                "--porcelain",
+                "-z",
+                "--no-renames",
+                "--untracked-files=all",
+            ],
+        )?)
+        .into_iter()
+        .filter_map(|entry| entry.get(3..).map(str::to_string))
+        .collect(),
+    };
+
+    if since.is_some() {
+        files.extend(split_nul(&git(
+            toplevel,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )?));
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Run git in `dir` and return its stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String, TaskError> {
+    let spec = CommandSpec::new("git")
+        .args(args.iter().copied())
+        .current_dir(dir);
+    let output = spec.output(Some(GIT_TIMEOUT))?;
+
+    if !output.status.success() {
+        return Err(TaskError::CommandFailed {
+            command: spec.to_string(),
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            suggestion: Some("Check that the git ref exists in the local repository".to_string()),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn split_nul(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let output = CommandSpec::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args.iter().copied())
+            .current_dir(dir)
+            .output(None)
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Monorepo with lib <- api <- web, a separate docs service and a shared proto dir
+    fn monorepo() -> (TempDir, Config) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        for dir in ["lib", "api", "web", "docs", "proto"] {
+            write(root, &format!("{}/README", dir), dir);
+        }
+        run_git(root, &["init", "-q"]);
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-q", "-m", "initial"]);
+
+        let config = toml::from_str(&format!(
+            r#"
+            [services.lib]
+            project_dir = "{root}/lib"
+
+            [services.api]
+            project_dir = "{root}/api"
+            depends_on = ["lib"]
+
+            [services.web]
+            project_dir = "{root}/web"
+            depends_on = ["api"]
+            watch_paths = ["../proto"]
+
+            [services.docs]
+            project_dir = "{root}/docs"
+            "#,
+            root = root.display()
+        ))
+        .unwrap();
+
+        (temp, config)
+    }
+
+    #[test]
+    fn test_working_tree_changes_include_dependents() {
+        let (temp, config) = monorepo();
+        write(temp.path(), "lib/src/new.rs", "// untracked");
+
+        let affected = ChangedServices::detect(&config, None, false).unwrap();
+        assert_eq!(
+            affected.changed,
+            BTreeMap::from([("lib".to_string(), vec!["lib/src/new.rs".to_string()])])
+        );
+        assert_eq!(affected.dependents, vec!["api", "web"]);
+        assert_eq!(affected.services(), vec!["lib", "api", "web"]);
+    }
+
+    #[test]
+    fn test_changes_since_ref_and_watch_paths() {
+        let (temp, config) = monorepo();
+        write(temp.path(), "docs/README", "changed");
+        run_git(temp.path(), &["commit", "-q", "-am", "docs"]);
+        write(temp.path(), "proto/api.proto", "syntax");
+
+        let affected = ChangedServices::detect(&config, Some("HEAD~1"), false).unwrap();
+        assert_eq!(
+            affected.changed.keys().collect::<Vec<_>>(),
+            vec!["docs", "web"]
+        );
+        assert_eq!(affected.changed["web"], vec!["proto/api.proto"]);
+        assert!(affected.dependents.is_empty());
+
+        // Nothing changed since HEAD except the untracked proto file
+        let affected = ChangedServices::detect(&config, Some("HEAD"), false).unwrap();
+        assert_eq!(affected.services(), vec!["web"]);
+    }
+
+    #[test]
+    fn test_enforced_allowed_paths_skip_services() {
+        let (temp, mut config) = monorepo();
+        let root = temp.path().canonicalize().unwrap();
+        config.security.allowed_paths = ["lib", "web"]
+            .iter()
+            .map(|dir| root.join(dir).display().to_string())
+            .collect();
+        write(temp.path(), "lib/src/new.rs", "// untracked");
+        write(temp.path(), "docs/README", "changed");
+        write(temp.path(), "proto/api.proto", "syntax");
+
+        // docs is outside, and web's watch path leads outside
+        let affected = ChangedServices::detect(&config, None, true).unwrap();
+        assert_eq!(affected.changed.keys().collect::<Vec<_>>(), vec!["lib"]);
+
+        let affected = ChangedServices::detect(&config, None, false).unwrap();
+        assert_eq!(
+            affected.changed.keys().collect::<Vec<_>>(),
+            vec!["docs", "lib", "web"]
+        );
+    }
+
+    #[test]
+    fn test_clean_tree_has_no_changes() {
+        let (_temp, config) = monorepo();
+        assert!(ChangedServices::detect(&config, None, false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_refs() {
+        let (_temp, config) = monorepo();
+
+        assert!(matches!(
+            ChangedServices::detect(&config, Some("no-such-ref"), false),
+            Err(TaskError::CommandFailed { .. })
+        ));
+        assert!(matches!(
+            ChangedServices::detect(&config, Some("--output=/tmp/x"), false),
+            Err(TaskError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_service_outside_repository_is_unaffected() {
+        let temp = TempDir::new().unwrap();
+        write(temp.path(), "file", "x");
+        let config: Config = toml::from_str(&format!(
+            "[services.plain]\nproject_dir = \"{}\"\n",
+            temp.path().display()
+        ))
+        .unwrap();
+
+        assert!(ChangedServices::detect(&config, None, false)
+            .unwrap()
+            .is_empty());
+    }
+}
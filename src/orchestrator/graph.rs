@@ -0,0 +1,245 @@
+//! Service dependency graph
+//!
+//! Built from the `[services]` section of the configuration. An edge from
+//! `a` to `b` means service `a` lists `b` in its `depends_on`, so `b` has to
+//! be rebuilt before `a`. Services that are referenced but not configured
+//! are treated as leaves.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::config::Config;
+use crate::error::TaskError;
+
+/// DFS visit state used for cycle detection
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    InProgress,
+    Done,
+}
+
+/// Directed graph of `depends_on` relationships between services
+#[derive(Debug, Clone, Default)]
+pub struct ServiceGraph {
+    depends_on: HashMap<String, Vec<String>>,
+}
+
+impl ServiceGraph {
+    /// Build the graph from configured services
+    pub fn from_config(config: &Config) -> Self {
+        let depends_on = config
+            .services
+            .iter()
+            .map(|(name, service)| (name.clone(), service.depends_on.clone()))
+            .collect();
+
+        Self { depends_on }
+    }
+
+    /// Direct dependencies of a service, in configuration order
+    pub fn dependencies(&self, service: &str) -> &[String] {
+        self.depends_on
+            .get(service)
+            .map(|deps| deps.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Order `roots` and all of their transitive dependencies for rebuilding
+    ///
+    /// Every service appears exactly once and after all of its dependencies.
+    /// Ties are broken by the order of `roots` and of each `depends_on` list.
+    ///
+    /// # Errors
+    /// * `TaskError::DependencyCycle` - If a cycle is reachable from `roots`
+    pub fn build_order(&self, roots: &[String]) -> Result<Vec<String>, TaskError> {
+        let mut marks = HashMap::new();
+        let mut stack = Vec::new();
+        let mut order = Vec::new();
+
+        for root in roots {
+            self.visit(root, &mut marks, &mut stack, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    /// Order only the given services, respecting dependencies between them
+    ///
+    /// Cycles reachable from `services` are still reported even when they
+    /// pass through services outside the set.
+    ///
+    /// # Errors
+    /// * `TaskError::DependencyCycle` - If a cycle is reachable from `services`
+    pub fn order_subset(&self, services: &[String]) -> Result<Vec<String>, TaskError> {
+        let wanted: HashSet<&str> = services.iter().map(|s| s.as_str()).collect();
+
+        Ok(self
+            .build_order(services)?
+            .into_iter()
+            .filter(|s| wanted.contains(s.as_str()))
+            .collect())
+    }
+
+    /// Services that transitively list any of `services` in `depends_on`
+    ///
+    /// `services` themselves are not included. Returned in name order.
+    pub fn dependents_of(&self, services: &[String]) -> Vec<String> {
+        let mut seen: HashSet<&str> = services.iter().map(|s| s.as_str()).collect();
+        let mut queue: Vec<&str> = services.iter().map(|s| s.as_str()).collect();
+        let mut dependents = BTreeSet::new();
+
+        while let Some(service) = queue.pop() {
+            for (name, deps) in &self.depends_on {
+                if deps.iter().any(|dep| dep == service) && seen.insert(name) {
+                    dependents.insert(name.clone());
+                    queue.push(name);
+                }
+            }
+        }
+
+        dependents.into_iter().collect()
+    }
+
+    fn visit(
+        &self,
+        service: &str,
+        marks: &mut HashMap<String, Mark>,
+        stack: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), TaskError> {
+        match marks.get(service) {
+            Some(Mark::Done) => return Ok(()),
+            Some(Mark::InProgress) => {
+                let start = stack.iter().position(|s| s == service).unwrap_or(0);
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(service.to_string());
+                return Err(TaskError::DependencyCycle { cycle });
+            }
+            None => {}
+        }
+
+        marks.insert(service.to_string(), Mark::InProgress);
+        stack.push(service.to_string());
+
+        for dep in self.dependencies(service) {
+            self.visit(dep, marks, stack, order)?;
+        }
+
+        stack.pop();
+        marks.insert(service.to_string(), Mark::Done);
+        order.push(service.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> ServiceGraph {
+        ServiceGraph {
+            depends_on: edges
+                .iter()
+                .map(|(name, deps)| {
+                    (
+                        name.to_string(),
+                        deps.iter().map(|d| d.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_build_order_transitive() {
+        let g = graph(&[("api", &["lib", "db"]), ("lib", &["core"]), ("db", &[])]);
+
+        let order = g.build_order(&names(&["api"])).unwrap();
+        assert_eq!(order, names(&["core", "lib", "db", "api"]));
+    }
+
+    #[test]
+    fn test_build_order_shared_dependency_once() {
+        let g = graph(&[("a", &["shared"]), ("b", &["shared"])]);
+
+        let order = g.build_order(&names(&["a", "b"])).unwrap();
+        assert_eq!(order, names(&["shared", "a", "b"]));
+    }
+
+    #[test]
+    fn test_build_order_unknown_service_is_leaf() {
+        let g = graph(&[("api", &["unconfigured"])]);
+
+        let order = g.build_order(&names(&["api"])).unwrap();
+        assert_eq!(order, names(&["unconfigured", "api"]));
+    }
+
+    #[test]
+    fn test_build_order_detects_cycle() {
+        let g = graph(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]);
+
+        match g.build_order(&names(&["a"])) {
+            Err(TaskError::DependencyCycle { cycle }) => {
+                assert_eq!(cycle, names(&["a", "b", "c", "a"]));
+            }
+            other => panic!("Expected DependencyCycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_order_detects_self_cycle() {
+        let g = graph(&[("a", &["a"])]);
+
+        assert!(matches!(
+            g.build_order(&names(&["a"])),
+            Err(TaskError::DependencyCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn test_order_subset() {
+        let g = graph(&[("api", &["lib"]), ("lib", &["core"])]);
+
+        // Requested out of order, dependencies outside the set are dropped
+        let order = g.order_subset(&names(&["api", "core"])).unwrap();
+        assert_eq!(order, names(&["core", "api"]));
+    }
+
+    #[test]
+    fn test_dependents_of_transitive() {
+        let g = graph(&[
+            ("api", &["lib"]),
+            ("web", &["api"]),
+            ("worker", &["lib"]),
+            ("other", &[]),
+        ]);
+
+        assert_eq!(
+            g.dependents_of(&names(&["lib"])),
+            names(&["api", "web", "worker"])
+        );
+        // Services already in the set aren't repeated
+        assert!(g.dependents_of(&names(&["api", "web"])).is_empty());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [services.api]
+            depends_on = ["lib"]
+
+            [services.lib]
+            "#,
+        )
+        .unwrap();
+
+        let g = ServiceGraph::from_config(&config);
+        assert_eq!(g.dependencies("api"), names(&["lib"]).as_slice());
+        assert!(g.dependencies("lib").is_empty());
+        assert!(g.dependencies("missing").is_empty());
+    }
+}
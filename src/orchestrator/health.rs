@@ -0,0 +1,423 @@
+//! Service health checks
+//!
+//! Run after a service's `up` task succeeds so that a service which exits or
+//! crashes right after starting isn't reported as restarted. Each check is
+//! retried up to `retries` times, `interval` seconds apart, with `timeout`
+//! seconds per attempt. A check that can't be valid, like an http path
+//! that would break the request line, fails without being attempted.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::{HealthCheck, HealthConfig};
+use crate::runner::CommandSpec;
+
+/// Outcome of a service health check
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Whether the service became healthy
+    pub healthy: bool,
+    /// Human-readable description of the check
+    pub check: String,
+    /// Number of attempts made
+    pub attempts: u32,
+    /// Result of the last attempt
+    pub message: String,
+    /// Total time spent checking in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Run a health check until it passes or retries are exhausted
+pub fn check_health(health: &HealthConfig, project_dir: &Path) -> HealthReport {
+    let start = Instant::now();
+    let retries = health.retries.max(1);
+    let invalid = validate(&health.check).err();
+    let mut attempts = 0;
+    let mut result = Err(invalid.clone().unwrap_or_default());
+
+    while invalid.is_none() && attempts < retries {
+        attempts += 1;
+        result = check_once(&health.check, project_dir, health.timeout_duration());
+        if result.is_ok() {
+            break;
+        }
+        if attempts < retries {
+            std::thread::sleep(health.interval_duration());
+        }
+    }
+
+    let healthy = result.is_ok();
+    HealthReport {
+        healthy,
+        check: describe(&health.check),
+        attempts,
+        message: result.unwrap_or_else(|e| e),
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// Short description of a check, e.g. `http 127.0.0.1:8080/health`
+pub fn describe(check: &HealthCheck) -> String {
+    match check {
+        HealthCheck::Http { port, path } => format!("http 127.0.0.1:{}{}", port, path),
+        HealthCheck::Tcp { port } => format!("tcp 127.0.0.1:{}", port),
+        HealthCheck::Command { command } => format!(
+            "command {}",
+            CommandSpec::new(command.first().cloned().unwrap_or_default())
+                .args(command.iter().skip(1))
+        ),
+        HealthCheck::Compose { service } => format!("compose {}", service),
+    }
+}
+
+/// Check that a health check can be sent as configured
+fn validate(check: &HealthCheck) -> Result<(), String> {
+    match check {
+        HealthCheck::Http { path, .. } => {
+            if !path.starts_with('/') {
+                return Err(format!("invalid http path '{}': must start with '/'", path));
+            }
+            if path.chars().any(|c| c.is_control() || c.is_whitespace()) {
+                return Err(format!(
+                    "invalid http path {:?}: must not contain whitespace or control characters",
+                    path
+                ));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Run a single attempt, returning a short status message
+fn check_once(
+    check: &HealthCheck,
+    project_dir: &Path,
+    timeout: Duration,
+) -> Result<String, String> {
+    match check {
+        HealthCheck::Http { port, path } => check_http(*port, path, timeout),
+        HealthCheck::Tcp { port } => {
+            connect(*port, timeout).map(|_| format!("port {} accepting connections", port))
+        }
+        HealthCheck::Command { command } => check_command(command, project_dir, timeout),
+        HealthCheck::Compose { service } => check_compose(service, project_dir, timeout),
+    }
+}
+
+fn connect(port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| format!("connect to {} failed: {}", addr, e))
+}
+
+fn check_http(port: u16, path: &str, timeout: Duration) -> Result<String, String> {
+    let mut stream = connect(port, timeout)?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| e.to_string())?;
+
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\n\r\n",
+        path, port
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("request failed: {}", e))?;
+
+    // Only the status line matters
+    let mut buf = [0u8; 256];
+    let mut len = 0;
+    while len < buf.len() {
+        match stream.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) => return Err(format!("reading response failed: {}", e)),
+        }
+        if buf[..len].contains(&b'\n') {
+            break;
+        }
+    }
+
+    let response = String::from_utf8_lossy(&buf[..len]);
+    let status_line = response.lines().next().unwrap_or_default().trim();
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("invalid HTTP response: {:?}", status_line))?;
+
+    if (200..400).contains(&status) {
+        Ok(format!("HTTP {}", status))
+    } else {
+        Err(format!("HTTP {}", status))
+    }
+}
+
+fn check_command(
+    command: &[String],
+    project_dir: &Path,
+    timeout: Duration,
+) -> Result<String, String> {
+    let Some((program, args)) = command.split_first() else {
+        return Err("empty health check command".to_string());
+    };
+    let spec = CommandSpec::new(program)
+        .args(args)
+        .current_dir(project_dir);
+
+    let output = spec.output(Some(timeout)).map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(format!("{} exited 0", spec))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!(
+            "{} exited with {:?}: {}",
+            spec,
+            output.status.code(),
+            stderr.trim()
+        ))
+    }
+}
+
+fn check_compose(service: &str, project_dir: &Path, timeout: Duration) -> Result<String, String> {
+    let spec = CommandSpec::new("docker")
+        .args(["compose", "ps", "--all", "--format", "json", service])
+        .current_dir(project_dir);
+
+    let output = spec.output(Some(timeout)).map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            spec,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    compose_status(&String::from_utf8_lossy(&output.stdout), service)
+}
+
+/// Evaluate `docker compose ps --format json` output for a service
+///
+/// Newer compose versions print one JSON object per line, older ones a
+/// single array. Containers with a healthcheck must report `healthy`;
+/// containers without one only need to be running.
+fn compose_status(output: &str, service: &str) -> Result<String, String> {
+    let trimmed = output.trim();
+    let containers: Vec<serde_json::Value> = if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).map_err(|e| e.to_string())?
+    } else {
+        trimmed
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    if containers.is_empty() {
+        return Err(format!("no containers for compose service '{}'", service));
+    }
+
+    for container in &containers {
+        let name = container["Name"].as_str().unwrap_or(service);
+        let state = container["State"].as_str().unwrap_or_default();
+        let health = container["Health"].as_str().unwrap_or_default();
+
+        if state != "running" {
+            return Err(format!("{} is {}", name, state));
+        }
+        if !health.is_empty() && health != "healthy" {
+            return Err(format!("{} is {}", name, health));
+        }
+    }
+
+    Ok(format!("{} container(s) running", containers.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn config(check: HealthCheck, retries: u32) -> HealthConfig {
+        HealthConfig {
+            check,
+            retries,
+            interval: 0,
+            timeout: 2,
+        }
+    }
+
+    /// Serve a single fixed HTTP response on a local port
+    fn serve_once(response: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 512];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        port
+    }
+
+    /// A local port with nothing listening on it
+    fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn test_http_healthy() {
+        let port = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        let report = check_health(
+            &config(
+                HealthCheck::Http {
+                    port,
+                    path: "/health".to_string(),
+                },
+                1,
+            ),
+            Path::new("."),
+        );
+
+        assert!(report.healthy, "{:?}", report);
+        assert_eq!(report.attempts, 1);
+        assert_eq!(report.message, "HTTP 200");
+        assert_eq!(report.check, format!("http 127.0.0.1:{}/health", port));
+    }
+
+    #[test]
+    fn test_http_invalid_path() {
+        let port = closed_port();
+        for path in ["health", "/health\r\nX-Injected: 1", "/a b", "/\u{7f}"] {
+            let report = check_health(
+                &config(
+                    HealthCheck::Http {
+                        port,
+                        path: path.to_string(),
+                    },
+                    3,
+                ),
+                Path::new("."),
+            );
+
+            assert!(!report.healthy);
+            assert_eq!(report.attempts, 0);
+            assert!(
+                report.message.starts_with("invalid http path"),
+                "{:?}",
+                report
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_error_status() {
+        let port = serve_once("HTTP/1.1 503 Service Unavailable\r\n\r\n");
+        let report = check_health(
+            &config(
+                HealthCheck::Http {
+                    port,
+                    path: "/".to_string(),
+                },
+                1,
+            ),
+            Path::new("."),
+        );
+
+        assert!(!report.healthy);
+        assert_eq!(report.message, "HTTP 503");
+    }
+
+    #[test]
+    fn test_tcp_retries_until_exhausted() {
+        let port = closed_port();
+        let report = check_health(&config(HealthCheck::Tcp { port }, 3), Path::new("."));
+
+        assert!(!report.healthy);
+        assert_eq!(report.attempts, 3);
+        assert!(report.message.contains("connect"));
+    }
+
+    #[test]
+    fn test_tcp_healthy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let report = check_health(&config(HealthCheck::Tcp { port }, 1), Path::new("."));
+        assert!(report.healthy, "{:?}", report);
+    }
+
+    #[test]
+    fn test_command_check() {
+        let ok = check_health(
+            &config(
+                HealthCheck::Command {
+                    command: vec!["true".to_string()],
+                },
+                1,
+            ),
+            Path::new("."),
+        );
+        let failed = check_health(
+            &config(
+                HealthCheck::Command {
+                    command: vec!["false".to_string()],
+                },
+                2,
+            ),
+            Path::new("."),
+        );
+
+        assert!(ok.healthy, "{:?}", ok);
+        assert!(!failed.healthy);
+        assert_eq!(failed.attempts, 2);
+    }
+
+    #[test]
+    fn test_command_check_empty() {
+        let report = check_health(
+            &config(HealthCheck::Command { command: vec![] }, 1),
+            Path::new("."),
+        );
+        assert!(!report.healthy);
+        assert_eq!(report.message, "empty health check command");
+    }
+
+    #[test]
+    fn test_compose_status_json_lines() {
+        let output = r#"{"Name":"app-web-1","State":"running","Health":"healthy"}
+{"Name":"app-web-2","State":"running","Health":""}"#;
+        assert_eq!(
+            compose_status(output, "web").unwrap(),
+            "2 container(s) running"
+        );
+    }
+
+    #[test]
+    fn test_compose_status_array_unhealthy() {
+        let output = r#"[{"Name":"app-web-1","State":"running","Health":"starting"}]"#;
+        assert_eq!(
+            compose_status(output, "web").unwrap_err(),
+            "app-web-1 is starting"
+        );
+    }
+
+    #[test]
+    fn test_compose_status_exited_or_missing() {
+        let output = r#"{"Name":"app-web-1","State":"exited","Health":""}"#;
+        assert_eq!(
+            compose_status(output, "web").unwrap_err(),
+            "app-web-1 is exited"
+        );
+        assert!(compose_status("", "web").is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! Service rebuild orchestration
+//!
+//! Turns `rebuild` requests into an ordered plan using the service
+//! dependency graph and executes it:
+//! - `depends_on` - services rebuilt first, transitively, in dependency order
+//! - `dependents` - services restarted via their `up` task afterwards, then
+//!   health-checked when they have a `[services.x.health]` block
+//! - `force_recreate` - containers recreated with docker compose at the end
+//!
+//! [`ChangedServices`] selects the services to rebuild from git changes.
+
+pub mod changes;
+pub mod graph;
+pub mod health;
+pub mod plan;
+pub mod rebuild;
+
+pub use changes::ChangedServices;
+pub use graph::ServiceGraph;
+pub use health::{check_health, HealthReport};
+pub use plan::{PlanStep, RebuildOptions, RebuildPlan, StepAction, StepError, StepStatus};
+pub use rebuild::Rebuilder;
@@ -0,0 +1,391 @@
+//! Rebuild plans
+//!
+//! A [`RebuildPlan`] lists every step a rebuild will perform, in execution
+//! order: builds of the requested services and their transitive dependencies
+//! (dependencies first), restarts of their `dependents`, and container
+//! force-recreates. The plan is computed before anything runs and each step
+//! records its outcome and output once executed. Steps whose `after` lists
+//! don't overlap may run concurrently.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::TaskError;
+
+use super::graph::ServiceGraph;
+use super::health::HealthReport;
+
+/// What a plan step does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepAction {
+    /// Run the service's build task
+    Build,
+    /// Run the service's up task, then its health check if configured
+    Restart,
+    /// Force-recreate a docker compose container in the service's project
+    Recreate,
+}
+
+impl std::fmt::Display for StepAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepAction::Build => write!(f, "build"),
+            StepAction::Restart => write!(f, "restart"),
+            StepAction::Recreate => write!(f, "recreate"),
+        }
+    }
+}
+
+/// Outcome of a plan step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Not executed yet
+    Pending,
+    /// Completed successfully
+    Succeeded,
+    /// Ran and failed
+    Failed,
+    /// Not run because a build it waits for did not succeed, or after a
+    /// failure in fail-fast mode
+    Skipped,
+}
+
+/// Details of a failed step
+#[derive(Debug, Clone, Serialize)]
+pub struct StepError {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// A single step of a rebuild plan
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanStep {
+    /// Service the step runs in
+    pub service: String,
+    /// What the step does
+    pub action: StepAction,
+    /// Task to run, or container to recreate
+    pub target: String,
+    /// Services whose build must succeed before this step runs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+    /// Execution status
+    pub status: StepStatus,
+    /// Standard output of the step, kept separate per step
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    /// Standard error of the step
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+    /// Health check outcome, for restarts of services with a health check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthReport>,
+    /// Failure details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<StepError>,
+    /// Step duration in milliseconds, once run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Whether a build was served from the task cache instead of running
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+impl PlanStep {
+    fn new(service: &str, action: StepAction, target: &str, after: Vec<String>) -> Self {
+        Self {
+            service: service.to_string(),
+            action,
+            target: target.to_string(),
+            after,
+            status: StepStatus::Pending,
+            stdout: String::new(),
+            stderr: String::new(),
+            health: None,
+            error: None,
+            duration_ms: None,
+            cached: false,
+        }
+    }
+}
+
+/// Options controlling which steps a plan contains
+#[derive(Debug, Clone, Default)]
+pub struct RebuildOptions {
+    /// Only build the requested services: no dependency builds, no dependent restarts
+    pub skip_deps: bool,
+    /// Don't force-recreate containers
+    pub skip_recreate: bool,
+    /// Build only the requested services (still in dependency order) without
+    /// adding their dependencies; dependents are restarted as usual
+    pub only_requested: bool,
+}
+
+/// Ordered list of rebuild steps
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RebuildPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl RebuildPlan {
+    /// Compute the plan for rebuilding `services`
+    ///
+    /// # Errors
+    /// * `TaskError::DependencyCycle` - If the services' dependencies form a cycle
+    pub fn new(
+        config: &Config,
+        services: &[String],
+        options: &RebuildOptions,
+    ) -> Result<Self, TaskError> {
+        let graph = ServiceGraph::from_config(config);
+        let builds = if options.skip_deps || options.only_requested {
+            graph.order_subset(services)?
+        } else {
+            graph.build_order(services)?
+        };
+        let building: HashSet<&str> = builds.iter().map(|s| s.as_str()).collect();
+
+        let mut steps = Vec::new();
+
+        for name in &builds {
+            let service = config.get_service(name);
+            let after = graph
+                .dependencies(name)
+                .iter()
+                .filter(|dep| building.contains(dep.as_str()))
+                .cloned()
+                .collect();
+            steps.push(PlanStep::new(
+                name,
+                StepAction::Build,
+                service.task_name("build"),
+                after,
+            ));
+        }
+
+        if !options.skip_deps {
+            let mut restarts: Vec<(String, Vec<String>)> = Vec::new();
+            for name in &builds {
+                for dependent in &config.get_service(name).dependents {
+                    match restarts.iter_mut().find(|(d, _)| d == dependent) {
+                        Some((_, after)) => after.push(name.clone()),
+                        None => restarts.push((dependent.clone(), vec![name.clone()])),
+                    }
+                }
+            }
+            for (dependent, after) in restarts {
+                let service = config.get_service(&dependent);
+                steps.push(PlanStep::new(
+                    &dependent,
+                    StepAction::Restart,
+                    service.task_name("up"),
+                    after,
+                ));
+            }
+        }
+
+        if !options.skip_recreate {
+            for name in &builds {
+                let service = config.get_service(name);
+                let mut seen = HashSet::new();
+                for container in &service.force_recreate {
+                    if seen.insert(container) {
+                        steps.push(PlanStep::new(
+                            name,
+                            StepAction::Recreate,
+                            container,
+                            vec![name.clone()],
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// Whether every step succeeded
+    pub fn success(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.status == StepStatus::Succeeded)
+    }
+
+    /// Targets of succeeded steps with the given action
+    ///
+    /// Returns service names for builds and restarts, container names for recreates.
+    pub fn succeeded(&self, action: StepAction) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|step| step.action == action && step.status == StepStatus::Succeeded)
+            .map(|step| match action {
+                StepAction::Recreate => step.target.clone(),
+                _ => step.service.clone(),
+            })
+            .collect()
+    }
+
+    /// Services whose build was served from the task cache
+    pub fn cached(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|step| step.action == StepAction::Build && step.cached)
+            .map(|step| step.service.clone())
+            .collect()
+    }
+
+    /// Health check outcomes by service
+    pub fn health(&self) -> BTreeMap<String, HealthReport> {
+        self.steps
+            .iter()
+            .filter_map(|step| Some((step.service.clone(), step.health.clone()?)))
+            .collect()
+    }
+
+    /// Steps that ran and failed
+    pub fn failures(&self) -> impl Iterator<Item = &PlanStep> {
+        self.steps
+            .iter()
+            .filter(|step| step.status == StepStatus::Failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+            [services.api]
+            depends_on = ["lib"]
+            dependents = ["gateway"]
+            force_recreate = ["api-container"]
+
+            [services.api.tasks]
+            build = "compile"
+
+            [services.lib]
+            dependents = ["gateway", "worker"]
+
+            [services.gateway.tasks]
+            up = "start"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn summary(plan: &RebuildPlan) -> Vec<String> {
+        plan.steps
+            .iter()
+            .map(|s| format!("{} {} {}", s.action, s.service, s.target))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_orders_builds_restarts_recreates() {
+        let plan =
+            RebuildPlan::new(&config(), &["api".to_string()], &RebuildOptions::default()).unwrap();
+
+        assert_eq!(
+            summary(&plan),
+            vec![
+                "build lib build",
+                "build api compile",
+                "restart gateway start",
+                "restart worker up",
+                "recreate api api-container",
+            ]
+        );
+        assert_eq!(plan.steps[1].after, vec!["lib"]);
+        // gateway waits for both services that list it as a dependent
+        assert_eq!(plan.steps[2].after, vec!["lib", "api"]);
+        assert!(plan.steps.iter().all(|s| s.status == StepStatus::Pending));
+    }
+
+    #[test]
+    fn test_plan_skip_deps_and_recreate() {
+        let options = RebuildOptions {
+            skip_deps: true,
+            skip_recreate: true,
+            ..Default::default()
+        };
+        let plan = RebuildPlan::new(&config(), &["api".to_string()], &options).unwrap();
+
+        assert_eq!(summary(&plan), vec!["build api compile"]);
+        assert!(plan.steps[0].after.is_empty());
+    }
+
+    #[test]
+    fn test_plan_only_requested_keeps_restarts() {
+        let options = RebuildOptions {
+            only_requested: true,
+            ..Default::default()
+        };
+        let plan = RebuildPlan::new(&config(), &["api".to_string()], &options).unwrap();
+
+        assert_eq!(
+            summary(&plan),
+            vec![
+                "build api compile",
+                "restart gateway start",
+                "recreate api api-container",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_cycle_error() {
+        let config: Config = toml::from_str(
+            r#"
+            [services.a]
+            depends_on = ["b"]
+
+            [services.b]
+            depends_on = ["a"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            RebuildPlan::new(&config, &["a".to_string()], &RebuildOptions::default()),
+            Err(TaskError::DependencyCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn test_plan_results() {
+        let mut plan =
+            RebuildPlan::new(&config(), &["api".to_string()], &RebuildOptions::default()).unwrap();
+        assert!(!plan.success());
+
+        for step in &mut plan.steps {
+            step.status = StepStatus::Succeeded;
+        }
+        plan.steps[3].status = StepStatus::Failed;
+
+        assert!(!plan.success());
+        assert_eq!(plan.succeeded(StepAction::Build), vec!["lib", "api"]);
+        assert_eq!(plan.succeeded(StepAction::Restart), vec!["gateway"]);
+        assert_eq!(plan.succeeded(StepAction::Recreate), vec!["api-container"]);
+        assert_eq!(plan.failures().count(), 1);
+    }
+
+    #[test]
+    fn test_plan_serialization() {
+        let plan =
+            RebuildPlan::new(&config(), &["lib".to_string()], &RebuildOptions::default()).unwrap();
+        let json = serde_json::to_string(&plan).unwrap();
+
+        assert!(json.contains("\"action\":\"build\""));
+        assert!(json.contains("\"status\":\"pending\""));
+        assert!(!json.contains("\"error\""));
+    }
+}
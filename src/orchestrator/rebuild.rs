@@ -0,0 +1,862 @@
+//! Rebuild plan execution
+//!
+//! Runs the steps of a [`RebuildPlan`] on the tokio runtime. A step starts
+//! once every build listed in its `after` succeeded, so services without a
+//! `depends_on` relationship build concurrently, up to `max_parallel` steps
+//! at a time. A step whose prerequisite failed is marked skipped, so a failed
+//! dependency never leads to building or restarting on top of it.
+//!
+//! In keep-going mode (the default) independent steps still run after a
+//! failure; in fail-fast mode no new steps start once one has failed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinSet;
+
+use crate::cache::{run_cached, TaskCache};
+use crate::config::{Config, ResolvedService};
+use crate::error::{ErrorInfo, TaskError};
+use crate::history::{RunHistory, RunKind, RunRecord};
+use crate::output::OutputLimits;
+use crate::runner::{select_runner, CommandSpec, PtySize, RunOptions, RunResult};
+use crate::security::{validate_project, AuditTrail};
+use crate::suggest::Suggestions;
+
+use super::health::{check_health, HealthReport};
+use super::plan::{PlanStep, RebuildPlan, StepAction, StepError, StepStatus};
+
+impl StepError {
+    /// Describe a step that failed before or while running `command`
+    fn from_task_error(command: impl Into<String>, error: &TaskError) -> Self {
+        let info = ErrorInfo::from(error);
+        Self {
+            command: command.into(),
+            exit_code: info.exit_code,
+            stderr: error.to_string(),
+            suggestion: info.suggestion,
+        }
+    }
+}
+
+/// Output and outcome of running a single step
+#[derive(Default)]
+struct StepRun {
+    stdout: String,
+    stderr: String,
+    health: Option<HealthReport>,
+    error: Option<StepError>,
+    cached: bool,
+}
+
+impl StepRun {
+    fn failed(error: StepError) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Executes rebuild plans against a configuration
+#[derive(Clone)]
+pub struct Rebuilder {
+    config: Arc<Config>,
+    enforce_allowed_paths: bool,
+    confine: bool,
+    timeout: Option<u64>,
+    max_parallel: usize,
+    fail_fast: bool,
+    cache: Option<TaskCache>,
+    history: Option<RunHistory>,
+    audit: Option<AuditTrail>,
+}
+
+impl Rebuilder {
+    /// Create a rebuilder using per-service timeouts, `defaults.max_parallel`,
+    /// keep-going mode, the task cache and no path validation or confinement
+    pub fn new(config: Config) -> Self {
+        let max_parallel = config.defaults.max_parallel;
+        let cache = Some(TaskCache::from_config(&config));
+        Self {
+            config: Arc::new(config),
+            enforce_allowed_paths: false,
+            confine: false,
+            timeout: None,
+            max_parallel,
+            fail_fast: false,
+            cache,
+            history: None,
+            audit: None,
+        }
+    }
+
+    /// Require service directories to be within `[security].allowed_paths`
+    pub fn enforce_allowed_paths(mut self, enforce: bool) -> Self {
+        self.enforce_allowed_paths = enforce;
+        self
+    }
+
+    /// Run build tasks within the services' resource limits and sandbox
+    pub fn confine(mut self, confine: bool) -> Self {
+        self.confine = confine;
+        self
+    }
+
+    /// Override every step's timeout in seconds (0 = no timeout)
+    pub fn timeout(mut self, timeout: Option<u64>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Override the maximum number of concurrently running steps
+    pub fn max_parallel(mut self, max_parallel: Option<usize>) -> Self {
+        if let Some(max_parallel) = max_parallel {
+            self.max_parallel = max_parallel;
+        }
+        self
+    }
+
+    /// Stop starting new steps after the first failure
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Always run builds, ignoring services' cache settings
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        if no_cache {
+            self.cache = None;
+        }
+        self
+    }
+
+    /// Record every executed step in the run history
+    pub fn history(mut self, history: Option<RunHistory>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Report the commands steps run to an audit trail
+    pub fn audit(mut self, audit: Option<AuditTrail>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Run all pending steps of a plan, recording each outcome in place
+    pub async fn execute(&self, plan: &mut RebuildPlan) {
+        let limit = self.max_parallel.max(1);
+        let mut started = vec![false; plan.steps.len()];
+        let mut running: JoinSet<(StepRun, u64)> = JoinSet::new();
+        // Step index and start of each running task, so a panicking one can
+        // still be reported
+        let mut tasks: HashMap<tokio::task::Id, (usize, Instant)> = HashMap::new();
+        let mut aborted = false;
+
+        loop {
+            // Steps are ordered so that every build precedes the steps waiting on it
+            let mut builds: HashMap<String, StepStatus> = HashMap::new();
+            for (index, step) in plan.steps.iter_mut().enumerate() {
+                if step.status == StepStatus::Pending && !started[index] {
+                    let blocked = step.after.iter().any(|service| {
+                        matches!(
+                            builds.get(service),
+                            Some(StepStatus::Failed | StepStatus::Skipped)
+                        )
+                    });
+                    let ready = step
+                        .after
+                        .iter()
+                        .all(|service| builds.get(service) == Some(&StepStatus::Succeeded));
+
+                    if aborted || blocked {
+                        step.status = StepStatus::Skipped;
+                    } else if ready && running.len() < limit {
+                        started[index] = true;
+                        let rebuilder = self.clone();
+                        let step = step.clone();
+                        let task = running.spawn_blocking(move || {
+                            let start = Instant::now();
+                            let run = rebuilder.run_step(&step);
+                            (run, start.elapsed().as_millis() as u64)
+                        });
+                        tasks.insert(task.id(), (index, Instant::now()));
+                    }
+                }
+
+                if step.action == StepAction::Build {
+                    builds.insert(step.service.clone(), step.status);
+                }
+            }
+
+            let Some(joined) = running.join_next_with_id().await else {
+                break;
+            };
+            let id = match &joined {
+                Ok((id, _)) => *id,
+                Err(e) => e.id(),
+            };
+            let Some((index, start)) = tasks.remove(&id) else {
+                continue;
+            };
+            let step = &mut plan.steps[index];
+            let (run, duration_ms) = match joined {
+                Ok((_, result)) => result,
+                Err(e) => {
+                    let run = StepRun::failed(StepError {
+                        command: step.target.clone(),
+                        exit_code: None,
+                        stderr: format!("Step panicked: {}", panic_message(e)),
+                        suggestion: None,
+                    });
+                    (run, start.elapsed().as_millis() as u64)
+                }
+            };
+
+            step.stdout = run.stdout;
+            step.stderr = run.stderr;
+            step.health = run.health;
+            step.cached = run.cached;
+            step.duration_ms = Some(duration_ms);
+            match run.error {
+                None => step.status = StepStatus::Succeeded,
+                Some(error) => {
+                    tracing::warn!(
+                        "{} step for '{}' failed: {}",
+                        step.action,
+                        step.service,
+                        error.stderr
+                    );
+                    step.status = StepStatus::Failed;
+                    step.error = Some(error);
+                    aborted |= self.fail_fast;
+                }
+            }
+        }
+    }
+
+    fn run_step(&self, step: &PlanStep) -> StepRun {
+        let (project_path, service) = match self.resolve(&step.service) {
+            Ok(resolved) => resolved,
+            Err(e) => return StepRun::failed(StepError::from_task_error("resolve_path", &e)),
+        };
+        let suggestions = match Suggestions::new(&service.suggestions) {
+            Ok(suggestions) => suggestions,
+            Err(e) => return StepRun::failed(StepError::from_task_error("suggestions", &e)),
+        };
+        let env = match service.environment() {
+            Ok(env) => env,
+            Err(e) => return StepRun::failed(StepError::from_task_error("env_file", &e)),
+        };
+        let timeout = self.timeout_for(&service);
+
+        if step.action == StepAction::Recreate {
+            // Modern docker compose plugin syntax
+            let command = CommandSpec::new("docker")
+                .args(["compose", "up", "-d", "--force-recreate"])
+                .arg(&step.target)
+                .current_dir(&project_path);
+            let start = Instant::now();
+            let output = match command.output(timeout) {
+                Ok(output) => output,
+                Err(e) => {
+                    self.record(
+                        RunRecord::from_error(
+                            RunKind::Rebuild,
+                            &project_path,
+                            "docker",
+                            &step.target,
+                            &command,
+                            &e,
+                            &OutputLimits::from_config(&self.config),
+                        ),
+                        &command,
+                        step,
+                    );
+                    return StepRun::failed(StepError::from_task_error(command.to_string(), &e));
+                }
+            };
+
+            let limits = OutputLimits::from_config(&self.config).mask(service.secret_values(&env));
+            let stdout = limits
+                .redact(&String::from_utf8_lossy(&output.stdout))
+                .into_owned();
+            let stderr = limits
+                .redact(&String::from_utf8_lossy(&output.stderr))
+                .into_owned();
+            let result = RunResult {
+                success: output.status.success(),
+                exit_code: output.status.code(),
+                stdout: stdout.clone(),
+                stderr: stderr.clone(),
+                command: command.clone(),
+                duration_ms: start.elapsed().as_millis() as u64,
+                cached: false,
+                stdout_truncation: None,
+                stderr_truncation: None,
+            };
+            self.record(
+                RunRecord::from_result(
+                    RunKind::Rebuild,
+                    &project_path,
+                    "docker",
+                    &step.target,
+                    &result,
+                ),
+                &result.command,
+                step,
+            );
+
+            let error = (!output.status.success()).then(|| StepError {
+                suggestion: suggestions.suggest("docker", &stderr),
+                command: command.to_string(),
+                exit_code: output.status.code(),
+                stderr: stderr.clone(),
+            });
+            return StepRun {
+                stdout,
+                stderr,
+                error,
+                ..Default::default()
+            };
+        }
+
+        let runner = match select_runner(
+            &project_path,
+            service.runner_override().as_deref(),
+            &self.config,
+        ) {
+            Ok(runner) => runner,
+            Err(e) => return StepRun::failed(StepError::from_task_error("detect_runner", &e)),
+        };
+
+        let options = RunOptions {
+            working_dir: Some(project_path.clone()),
+            timeout,
+            output: OutputLimits::from_config(&self.config).mask(service.secret_values(&env)),
+            pty: PtySize::select(&service.pty, service.uses_pty(&step.target), None),
+            env,
+            ..Default::default()
+        };
+        let options = if self.confine {
+            options
+                .confined(&service.limits, &service.sandbox)
+                .isolated(service.env_mode, &service.env_passthrough)
+        } else {
+            options
+        };
+
+        // Only builds are cached; restarts always run
+        let cache_config = match (&self.cache, step.action) {
+            (Some(cache), StepAction::Build) => service
+                .cache_config(&step.target)
+                .map(|config| (cache, config)),
+            _ => None,
+        };
+        let result = match cache_config {
+            Some((cache, config)) => run_cached(
+                cache,
+                Some(config),
+                runner.as_ref(),
+                &project_path,
+                &step.target,
+                &options,
+            ),
+            None => runner.run_task(&project_path, &step.target, &options),
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                let command = runner.build_command(&project_path, &step.target, &options);
+                self.record(
+                    RunRecord::from_error(
+                        RunKind::Rebuild,
+                        &project_path,
+                        runner.name(),
+                        &step.target,
+                        &command,
+                        &e,
+                        &options.output,
+                    ),
+                    &command.redacted(&options.output),
+                    step,
+                );
+                return StepRun::failed(StepError::from_task_error(
+                    format!("{} {}", runner.name(), step.target),
+                    &e,
+                ));
+            }
+        };
+
+        self.record(
+            RunRecord::from_result(
+                RunKind::Rebuild,
+                &project_path,
+                runner.name(),
+                &step.target,
+                &result,
+            ),
+            &result.command,
+            step,
+        );
+
+        let mut run = StepRun {
+            error: (!result.success).then(|| StepError {
+                suggestion: suggestions.suggest(runner.name(), result.error_output()),
+                command: result.command.to_string(),
+                exit_code: result.exit_code,
+                stderr: result.stderr.clone(),
+            }),
+            stdout: result.stdout,
+            stderr: result.stderr,
+            health: None,
+            cached: result.cached,
+        };
+
+        // `up` exiting successfully doesn't mean the service stays up
+        if let (StepAction::Restart, None, Some(health)) =
+            (step.action, &run.error, &service.health)
+        {
+            let report = check_health(health, &project_path);
+            if !report.healthy {
+                run.error = Some(StepError {
+                    command: format!("health {}", report.check),
+                    exit_code: None,
+                    stderr: report.message.clone(),
+                    suggestion: Some(format!(
+                        "'{}' started but did not become healthy after {} attempt(s). Check its logs.",
+                        step.service, report.attempts
+                    )),
+                });
+            }
+            run.health = Some(report);
+        }
+
+        run
+    }
+
+    /// Resolve a service's configuration and check its project directory
+    fn resolve(&self, name: &str) -> Result<(PathBuf, ResolvedService), TaskError> {
+        let service = self.config.get_service(name);
+        let project_path = PathBuf::from(&service.project_dir);
+
+        // Allowed paths are checked first, so a refused directory doesn't
+        // reveal whether it exists
+        let allowed =
+            !self.enforce_allowed_paths || self.config.check_path_allowed(&project_path).is_ok();
+        if allowed && !project_path.exists() {
+            return Err(TaskError::ProjectNotFound {
+                path: service.project_dir.clone(),
+                suggestion: Some(format!(
+                    "Configure project_dir for service '{}' in your configuration",
+                    name
+                )),
+            });
+        }
+
+        let project_path = if self.enforce_allowed_paths {
+            validate_project(&project_path, &self.config, service.script.as_deref())?
+        } else {
+            project_path
+        };
+        if let Some(audit) = &self.audit {
+            audit.path(&project_path);
+        }
+
+        Ok((project_path, service))
+    }
+
+    fn record(&self, record: RunRecord, command: &CommandSpec, step: &PlanStep) {
+        if let (Some(audit), false) = (&self.audit, record.cached) {
+            audit.command(command, record.exit_code, record.duration_ms);
+        }
+        if let Some(history) = &self.history {
+            history.record(record.service(Some(&step.service)));
+        }
+    }
+
+    fn timeout_for(&self, service: &ResolvedService) -> Option<Duration> {
+        match self.timeout {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => service.timeout_duration(),
+        }
+    }
+}
+
+/// What a step's task panicked with
+fn panic_message(error: tokio::task::JoinError) -> String {
+    if !error.is_panic() {
+        return error.to_string();
+    }
+    let payload = error.into_panic();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::RebuildOptions;
+
+    fn project(dir: &std::path::Path, name: &str, makefile: &str) -> String {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("Makefile"), makefile).unwrap();
+        path.display().to_string()
+    }
+
+    fn config_for(services: &[(&str, String, &[&str], &[&str])]) -> Config {
+        let mut config = Config::default();
+        for (name, dir, depends_on, dependents) in services {
+            config.services.insert(
+                name.to_string(),
+                crate::config::ServiceConfig {
+                    project_dir: Some(dir.clone()),
+                    runner: Some("make".to_string()),
+                    depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+                    dependents: dependents.iter().map(|s| s.to_string()).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+        config
+    }
+
+    fn make_available() -> bool {
+        std::process::Command::new("make")
+            .arg("--version")
+            .output()
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_execute_uses_build_cache() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join("log");
+        let dir = project(
+            temp.path(),
+            "api",
+            &format!("build:\n\t@echo api >> {}\n", log.display()),
+        );
+        std::fs::write(temp.path().join("api/input.txt"), "v1").unwrap();
+
+        let mut config = config_for(&[("api", dir, &[], &[])]);
+        config.defaults.cache_dir = Some(temp.path().join("cache").display().to_string());
+        config.services.get_mut("api").unwrap().cache.insert(
+            "build".to_string(),
+            crate::config::CacheConfig {
+                inputs: vec!["*.txt".to_string()],
+                outputs: vec![],
+            },
+        );
+
+        let rebuild = |no_cache: bool| {
+            let config = config.clone();
+            async move {
+                let mut plan =
+                    RebuildPlan::new(&config, &["api".to_string()], &RebuildOptions::default())
+                        .unwrap();
+                Rebuilder::new(config)
+                    .no_cache(no_cache)
+                    .execute(&mut plan)
+                    .await;
+                assert!(plan.success());
+                plan
+            }
+        };
+
+        assert!(rebuild(false).await.cached().is_empty());
+        assert_eq!(rebuild(false).await.cached(), vec!["api"]);
+        assert!(rebuild(true).await.cached().is_empty());
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "api\napi\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_runs_steps_in_order() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join("log");
+        let rule =
+            |name: &str, task: &str| format!("{}:\n\t@echo {} >> {}\n", task, name, log.display());
+
+        let config = config_for(&[
+            (
+                "api",
+                project(temp.path(), "api", &rule("api", "build")),
+                &["lib"],
+                &["web"],
+            ),
+            (
+                "lib",
+                project(temp.path(), "lib", &rule("lib", "build")),
+                &[],
+                &[],
+            ),
+            (
+                "web",
+                project(temp.path(), "web", &rule("web", "up")),
+                &[],
+                &[],
+            ),
+        ]);
+
+        let mut plan =
+            RebuildPlan::new(&config, &["api".to_string()], &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config).execute(&mut plan).await;
+
+        assert!(plan.success(), "{:?}", plan);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "lib\napi\nweb\n");
+        assert!(plan.steps.iter().all(|s| s.duration_ms.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_execute_skips_after_failed_dependency() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+
+        let config = config_for(&[
+            (
+                "api",
+                project(temp.path(), "api", "build:\n\t@true\n"),
+                &["lib"],
+                &["web"],
+            ),
+            (
+                "lib",
+                project(temp.path(), "lib", "build:\n\t@exit 3\n"),
+                &[],
+                &[],
+            ),
+            (
+                "web",
+                project(temp.path(), "web", "up:\n\t@true\n"),
+                &[],
+                &[],
+            ),
+        ]);
+
+        let mut plan =
+            RebuildPlan::new(&config, &["api".to_string()], &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config).execute(&mut plan).await;
+
+        let statuses: Vec<_> = plan.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![StepStatus::Failed, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        let error = plan.steps[0].error.as_ref().unwrap();
+        assert_eq!(error.exit_code, Some(2));
+        assert!(!plan.success());
+    }
+
+    #[tokio::test]
+    async fn test_execute_uses_service_suggestions() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        let dir = project(
+            temp.path(),
+            "api",
+            "build:\n\t@echo 'migration 42 is pending' >&2; exit 1\n",
+        );
+        let mut config = config_for(&[("api", dir, &[], &[])]);
+        config.suggestions.push(crate::config::SuggestionRule {
+            pattern: "migration".to_string(),
+            runner: None,
+            suggestion: "global rule".to_string(),
+        });
+        config
+            .services
+            .get_mut("api")
+            .unwrap()
+            .suggestions
+            .push(crate::config::SuggestionRule {
+                pattern: r"migration (\d+) is pending".to_string(),
+                runner: Some("make".to_string()),
+                suggestion: "Run migration $1 first".to_string(),
+            });
+
+        let mut plan =
+            RebuildPlan::new(&config, &["api".to_string()], &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config).execute(&mut plan).await;
+
+        let error = plan.steps[0].error.as_ref().unwrap();
+        assert_eq!(error.suggestion.as_deref(), Some("Run migration 42 first"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_missing_project_dir() {
+        let config = config_for(&[("ghost", "/nonexistent/ghost".to_string(), &[], &[])]);
+
+        let mut plan =
+            RebuildPlan::new(&config, &["ghost".to_string()], &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config).execute(&mut plan).await;
+
+        assert_eq!(plan.steps[0].status, StepStatus::Failed);
+        assert_eq!(
+            plan.steps[0].error.as_ref().unwrap().command,
+            "resolve_path"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_independent_builds_run_concurrently() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+        // Each build marks itself started, then waits for the other one to start
+        let rule = |own: &str, other: &str| {
+            format!(
+                "build:\n\t@touch ../{own}.started; for i in $$(seq 100); do test -f ../{other}.started && echo {own} done && exit 0; sleep 0.05; done; exit 1\n"
+            )
+        };
+
+        let config = config_for(&[
+            ("a", project(temp.path(), "a", &rule("a", "b")), &[], &[]),
+            ("b", project(temp.path(), "b", &rule("b", "a")), &[], &[]),
+        ]);
+
+        let mut plan = RebuildPlan::new(
+            &config,
+            &["a".to_string(), "b".to_string()],
+            &RebuildOptions::default(),
+        )
+        .unwrap();
+        Rebuilder::new(config)
+            .max_parallel(Some(2))
+            .execute(&mut plan)
+            .await;
+
+        assert!(plan.success(), "{:?}", plan);
+        // Output is kept per step
+        assert_eq!(plan.steps[0].stdout, "a done\n");
+        assert_eq!(plan.steps[1].stdout, "b done\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_fail_fast_vs_keep_going() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+
+        let config = config_for(&[
+            (
+                "broken",
+                project(temp.path(), "broken", "build:\n\t@exit 1\n"),
+                &[],
+                &[],
+            ),
+            (
+                "other",
+                project(temp.path(), "other", "build:\n\t@true\n"),
+                &[],
+                &[],
+            ),
+        ]);
+        let services = ["broken".to_string(), "other".to_string()];
+
+        let mut plan = RebuildPlan::new(&config, &services, &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config.clone())
+            .max_parallel(Some(1))
+            .fail_fast(true)
+            .execute(&mut plan)
+            .await;
+        assert_eq!(plan.steps[0].status, StepStatus::Failed);
+        assert_eq!(plan.steps[1].status, StepStatus::Skipped);
+
+        let mut plan = RebuildPlan::new(&config, &services, &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config)
+            .max_parallel(Some(1))
+            .execute(&mut plan)
+            .await;
+        assert_eq!(plan.steps[0].status, StepStatus::Failed);
+        assert_eq!(plan.steps[1].status, StepStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_execute_restart_runs_health_check() {
+        if !make_available() {
+            eprintln!("Skipping test: make not available");
+            return;
+        }
+        let temp = tempfile::tempdir().unwrap();
+
+        let mut config = config_for(&[
+            (
+                "api",
+                project(temp.path(), "api", "build:\n\t@true\n"),
+                &[],
+                &["web", "db"],
+            ),
+            (
+                "web",
+                project(temp.path(), "web", "up:\n\t@true\n"),
+                &[],
+                &[],
+            ),
+            ("db", project(temp.path(), "db", "up:\n\t@true\n"), &[], &[]),
+        ]);
+        let health = |command: &str| {
+            Some(crate::config::HealthConfig {
+                check: crate::config::HealthCheck::Command {
+                    command: vec![command.to_string()],
+                },
+                retries: 2,
+                interval: 0,
+                timeout: 5,
+            })
+        };
+        config.services.get_mut("web").unwrap().health = health("false");
+        config.services.get_mut("db").unwrap().health = health("true");
+
+        let mut plan =
+            RebuildPlan::new(&config, &["api".to_string()], &RebuildOptions::default()).unwrap();
+        Rebuilder::new(config).execute(&mut plan).await;
+
+        let web = &plan.steps[1];
+        assert_eq!(web.status, StepStatus::Failed);
+        let report = web.health.as_ref().unwrap();
+        assert!(!report.healthy);
+        assert_eq!(report.attempts, 2);
+        assert_eq!(web.error.as_ref().unwrap().command, "health command false");
+
+        let db = &plan.steps[2];
+        assert_eq!(db.status, StepStatus::Succeeded);
+        assert!(db.health.as_ref().unwrap().healthy);
+    }
+
+    #[tokio::test]
+    async fn test_panic_message() {
+        let panicked = tokio::task::spawn_blocking(|| panic!("step {} broke", 1))
+            .await
+            .unwrap_err();
+        assert_eq!(panic_message(panicked), "step 1 broke");
+
+        let panicked = tokio::task::spawn_blocking(|| std::panic::panic_any(42))
+            .await
+            .unwrap_err();
+        assert_eq!(panic_message(panicked), "unknown panic");
+    }
+}
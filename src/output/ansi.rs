@@ -0,0 +1,60 @@
+//! ANSI escape sequences
+//!
+//! Removes color and cursor control sequences (CSI), operating system
+//! commands such as hyperlinks and window titles (OSC), device control
+//! strings and the remaining two-byte escapes, leaving the plain text.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static ESCAPE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"\x1b\[[0-?]*[ -/]*[@-~]",             // CSI: colors, cursor movement
+        r"|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)?", // OSC: hyperlinks, titles
+        r"|\x1b[PX^_][^\x1b]*(?:\x1b\\)?",      // DCS, SOS, PM, APC
+        r"|\x1b[()*+][0-9A-Za-z]",              // character set selection
+        r"|\x1b[ -/]*[0-~]",                    // other escapes
+        r"|\x9b[0-?]*[ -/]*[@-~]",              // 8-bit CSI
+    ))
+    .expect("Invalid regex")
+});
+
+/// `text` without ANSI escape sequences
+pub fn strip(text: &str) -> Cow<'_, str> {
+    if !text.contains(['\x1b', '\u{9b}']) {
+        return Cow::Borrowed(text);
+    }
+    ESCAPE_RE.replace_all(text, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_colors() {
+        assert_eq!(
+            strip("\x1b[1m\x1b[31merror\x1b[0m: \x1b[38;5;12mfailed\x1b[m"),
+            "error: failed"
+        );
+    }
+
+    #[test]
+    fn test_strip_cursor_and_osc() {
+        assert_eq!(strip("\x1b[2K\x1b[1Gdone"), "done");
+        assert_eq!(
+            strip("see \x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\"),
+            "see docs"
+        );
+        assert_eq!(strip("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip("\x1b(Bplain\x1b="), "plain");
+    }
+
+    #[test]
+    fn test_plain_text_borrowed() {
+        assert!(matches!(strip("no escapes [1m"), Cow::Borrowed(_)));
+        assert_eq!(strip("unicode ✓ → ok"), "unicode ✓ → ok");
+    }
+}
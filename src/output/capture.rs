@@ -0,0 +1,426 @@
+//! Head-and-tail capture of one output stream
+//!
+//! The first `head_bytes` are kept as they arrive; later bytes go through a
+//! ring buffer of `tail_bytes`, so the end of the stream is always kept no
+//! matter how much came before it. Once bytes are about to be dropped, the
+//! stream so far is written to a spill file and everything after it is
+//! appended there too.
+//!
+//! With a redactor set, secrets are masked in the kept text, and spill
+//! files and the echoed stream are written a line at a time so secrets
+//! split across chunks are masked there too.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ansi, spill, OutputLimits};
+
+/// Longest partial line held back from a spill file or the echo while
+/// masking secrets
+const MAX_PENDING_LINE: usize = 64 * 1024;
+
+/// What was left out of a truncated stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    /// Size of the whole stream
+    pub total_bytes: u64,
+    /// Lines in the whole stream
+    pub total_lines: u64,
+    /// Bytes left out between the head and the tail
+    pub omitted_bytes: u64,
+    /// Lines left out between the head and the tail
+    pub omitted_lines: u64,
+    /// File holding the whole stream, if spilling is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_file: Option<PathBuf>,
+}
+
+/// A captured stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Captured {
+    /// The stream, or its head and tail around a truncation marker
+    pub text: String,
+    /// Set if the middle of the stream was left out
+    pub truncation: Option<Truncation>,
+}
+
+/// Collects a stream chunk by chunk within [`OutputLimits`]
+pub struct OutputCapture {
+    limits: OutputLimits,
+    stream: String,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total_bytes: u64,
+    newlines: u64,
+    dropped_newlines: u64,
+    /// Whether the last dropped byte ended a line
+    dropped_line_end: bool,
+    spill: Option<(PathBuf, BufWriter<File>)>,
+    spill_failed: bool,
+    /// End of the stream not yet written to the spill file, held back
+    /// until its line is complete when masking secrets
+    pending: Vec<u8>,
+    /// End of the stream not yet echoed, held back the same way
+    echo_pending: Vec<u8>,
+}
+
+impl OutputCapture {
+    /// Capture a stream named `stream` (e.g. `stdout`, used in spill file names)
+    pub fn new(limits: &OutputLimits, stream: &str) -> Self {
+        Self {
+            limits: limits.clone(),
+            stream: stream.to_string(),
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total_bytes: 0,
+            newlines: 0,
+            dropped_newlines: 0,
+            dropped_line_end: true,
+            spill: None,
+            spill_failed: false,
+            pending: Vec::new(),
+            echo_pending: Vec::new(),
+        }
+    }
+
+    /// What to echo of the stream as `chunk` arrives, with secrets masked;
+    /// when masking, only complete lines are returned, unless `flush` is set
+    /// or the partial line is too long
+    pub fn echo<'a>(&mut self, chunk: &'a [u8], flush: bool) -> Cow<'a, [u8]> {
+        match &self.limits.redact {
+            Some(redact) => {
+                let lines = complete_lines(&mut self.echo_pending, chunk, flush);
+                Cow::Owned(redact.redact_bytes(&lines).into_owned())
+            }
+            None => Cow::Borrowed(chunk),
+        }
+    }
+
+    /// Add the next chunk of the stream
+    pub fn write(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        let kept = self.head.len() + self.tail.len();
+        if kept + chunk.len() > self.limits.head_bytes + self.limits.tail_bytes {
+            self.start_spill();
+        }
+        if self.spill.is_some() {
+            self.spill_write(chunk, false);
+        }
+
+        self.total_bytes += chunk.len() as u64;
+        self.newlines += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+
+        let room = self.limits.head_bytes.saturating_sub(self.head.len());
+        let (head, rest) = chunk.split_at(room.min(chunk.len()));
+        self.head.extend_from_slice(head);
+        self.tail.extend(rest);
+
+        if self.tail.len() > self.limits.tail_bytes {
+            let excess = self.tail.len() - self.limits.tail_bytes;
+            let mut last = None;
+            for byte in self.tail.drain(..excess) {
+                if byte == b'\n' {
+                    self.dropped_newlines += 1;
+                }
+                last = Some(byte);
+            }
+            self.dropped_line_end = last == Some(b'\n');
+        }
+    }
+
+    /// Finish the stream
+    pub fn finish(mut self) -> Captured {
+        let total_lines = self.newlines
+            + u64::from(
+                self.tail
+                    .back()
+                    .or(self.head.last())
+                    .is_some_and(|&b| b != b'\n'),
+            );
+        if self.spill.is_some() {
+            self.spill_write(&[], true);
+        }
+        let spill_file = self
+            .spill
+            .take()
+            .and_then(|(path, mut file)| match file.flush() {
+                Ok(()) => Some(path),
+                Err(e) => {
+                    tracing::warn!("Failed to write spill file {}: {}", path.display(), e);
+                    let _ = std::fs::remove_file(&path);
+                    None
+                }
+            });
+
+        let mut head = std::mem::take(&mut self.head);
+        let mut tail: Vec<u8> = std::mem::take(&mut self.tail).into();
+        if self.total_bytes == (head.len() + tail.len()) as u64 {
+            head.extend_from_slice(&tail);
+            return Captured {
+                text: self.text(&head),
+                truncation: None,
+            };
+        }
+
+        // Cut the head and tail at line boundaries where there are any
+        let mut omitted_lines = self.dropped_newlines;
+        if !self.dropped_line_end {
+            if let Some(pos) = tail.iter().position(|&b| b == b'\n') {
+                tail.drain(..=pos);
+                omitted_lines += 1;
+            }
+        }
+        if head.last() != Some(&b'\n') {
+            if let Some(pos) = head.iter().rposition(|&b| b == b'\n') {
+                head.truncate(pos + 1);
+            }
+        }
+        let omitted_bytes = self.total_bytes - (head.len() + tail.len()) as u64;
+
+        let mut text = self.text(&head);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "... [output truncated] {} lines ({} bytes) omitted",
+            omitted_lines, omitted_bytes
+        ));
+        if let Some(path) = &spill_file {
+            text.push_str(&format!(", full output in {}", path.display()));
+        }
+        text.push_str(" ...\n");
+        text.push_str(&self.text(&tail));
+
+        Captured {
+            text,
+            truncation: Some(Truncation {
+                total_bytes: self.total_bytes,
+                total_lines,
+                omitted_bytes,
+                omitted_lines,
+                spill_file,
+            }),
+        }
+    }
+
+    /// Kept bytes as text, without escape sequences if configured and with
+    /// secrets masked
+    fn text(&self, bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        let text = if self.limits.strip_ansi {
+            ansi::strip(&text).into_owned()
+        } else {
+            text.into_owned()
+        };
+        self.limits.redact(&text).into_owned()
+    }
+
+    /// Append to the spill file; when masking secrets only complete lines
+    /// are written, unless `flush` is set or the partial line is too long
+    fn spill_write(&mut self, bytes: &[u8], flush: bool) {
+        let Some((path, file)) = &mut self.spill else {
+            return;
+        };
+        let written = match &self.limits.redact {
+            Some(redact) => {
+                let lines = complete_lines(&mut self.pending, bytes, flush);
+                file.write_all(&redact.redact_bytes(&lines))
+            }
+            None => file.write_all(bytes),
+        };
+        if let Err(e) = written {
+            tracing::warn!("Failed to write spill file {}: {}", path.display(), e);
+            self.abandon_spill();
+        }
+    }
+
+    /// Open a spill file holding the stream so far, unless one is open
+    fn start_spill(&mut self) {
+        if self.spill.is_some() || self.spill_failed {
+            return;
+        }
+        let Some(dir) = &self.limits.spill_dir else {
+            return;
+        };
+
+        let opened = spill::create(dir, &self.stream, self.limits.max_spill_files);
+        let (path, file) = match opened {
+            Ok(spill) => spill,
+            Err(e) => {
+                tracing::warn!("Failed to create spill file in {}: {}", dir.display(), e);
+                self.spill_failed = true;
+                return;
+            }
+        };
+        self.spill = Some((path, BufWriter::new(file)));
+        let kept: Vec<u8> = self.head.iter().chain(&self.tail).copied().collect();
+        self.spill_write(&kept, false);
+    }
+
+    /// Stop spilling and remove the incomplete spill file
+    fn abandon_spill(&mut self) {
+        if let Some((path, _)) = self.spill.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        self.spill_failed = true;
+    }
+}
+
+/// Append `bytes` to `pending` and take its complete lines, or all of it
+/// if `flush` is set or the partial line is too long
+fn complete_lines(pending: &mut Vec<u8>, bytes: &[u8], flush: bool) -> Vec<u8> {
+    pending.extend_from_slice(bytes);
+    let end = if flush || pending.len() > MAX_PENDING_LINE {
+        pending.len()
+    } else {
+        pending
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |pos| pos + 1)
+    };
+    pending.drain(..end).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RedactConfig;
+    use crate::security::Redactor;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn lines(from: usize, to: usize) -> String {
+        (from..=to).map(|i| format!("line {:03}\n", i)).collect()
+    }
+
+    fn capture(limits: &OutputLimits, text: &str, chunk: usize) -> Captured {
+        let mut capture = OutputCapture::new(limits, "stdout");
+        for part in text.as_bytes().chunks(chunk) {
+            capture.write(part);
+        }
+        capture.finish()
+    }
+
+    #[test]
+    fn test_small_output_unchanged() {
+        let limits = OutputLimits::total(1000);
+        let captured = capture(&limits, "hello\nworld", 3);
+        assert_eq!(captured.text, "hello\nworld");
+        assert!(captured.truncation.is_none());
+    }
+
+    #[test]
+    fn test_keeps_head_and_tail() {
+        // Every line is 9 bytes: "line 001\n"
+        let limits = OutputLimits {
+            head_bytes: 20,
+            tail_bytes: 30,
+            ..Default::default()
+        };
+        let captured = capture(&limits, &lines(1, 100), 7);
+
+        let truncation = captured.truncation.unwrap();
+        assert_eq!(truncation.total_bytes, 900);
+        assert_eq!(truncation.total_lines, 100);
+        // Two whole lines of head, three of tail
+        assert_eq!(truncation.omitted_lines, 95);
+        assert_eq!(truncation.omitted_bytes, 900 - 18 - 27);
+        assert!(truncation.spill_file.is_none());
+        assert_eq!(
+            captured.text,
+            format!(
+                "{}... [output truncated] 95 lines (855 bytes) omitted ...\n{}",
+                lines(1, 2),
+                lines(98, 100)
+            )
+        );
+    }
+
+    #[test]
+    fn test_tail_already_on_line_boundary() {
+        let limits = OutputLimits {
+            head_bytes: 9,
+            tail_bytes: 18,
+            ..Default::default()
+        };
+        let truncation = capture(&limits, &lines(1, 10), 9).truncation.unwrap();
+        assert_eq!(truncation.omitted_lines, 7);
+        assert_eq!(truncation.omitted_bytes, 63);
+    }
+
+    #[test]
+    fn test_spills_full_output() {
+        let dir = TempDir::new().unwrap();
+        let limits = OutputLimits::total(40).spill_to(dir.path(), 10);
+        let text = lines(1, 50);
+        let captured = capture(&limits, &text, 64);
+
+        let spill = captured.truncation.unwrap().spill_file.unwrap();
+        assert!(spill.starts_with(dir.path()));
+        assert_eq!(std::fs::read_to_string(&spill).unwrap(), text);
+        assert!(captured.text.contains(&spill.display().to_string()));
+    }
+
+    #[test]
+    fn test_masks_secrets_in_text_and_spill() {
+        let dir = TempDir::new().unwrap();
+        let mut redactor = Redactor::new(&RedactConfig::default()).unwrap();
+        redactor.mask(["hunter22"]);
+        let limits = OutputLimits {
+            redact: Some(Arc::new(redactor)),
+            ..OutputLimits::total(60).spill_to(dir.path(), 10)
+        };
+        let text = format!("{}password hunter22\n{}", lines(1, 20), lines(21, 40));
+
+        // Chunks of 5 bytes split the secret across writes
+        let captured = capture(&limits, &text, 5);
+        let spill = captured.truncation.unwrap().spill_file.unwrap();
+        let spilled = std::fs::read_to_string(&spill).unwrap();
+        assert_eq!(spilled, text.replace("hunter22", "[REDACTED]"));
+
+        let small = capture(&limits, "token hunter22", 3);
+        assert_eq!(small.text, "token [REDACTED]");
+    }
+
+    #[test]
+    fn test_no_spill_without_truncation() {
+        let dir = TempDir::new().unwrap();
+        let limits = OutputLimits::total(1000).spill_to(dir.path(), 10);
+        capture(&limits, &lines(1, 5), 4);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let colored = "\x1b[32mok\x1b[0m\n";
+        let plain = OutputLimits::total(1000);
+        assert_eq!(capture(&plain, colored, 3).text, colored);
+
+        let stripped = OutputLimits {
+            strip_ansi: true,
+            ..plain
+        };
+        assert_eq!(capture(&stripped, colored, 3).text, "ok\n");
+    }
+
+    #[test]
+    fn test_unterminated_last_line_counts() {
+        let limits = OutputLimits {
+            head_bytes: 4,
+            tail_bytes: 4,
+            ..Default::default()
+        };
+        let truncation = capture(&limits, "a\nb\nc\nd\ne\nlast", 2)
+            .truncation
+            .unwrap();
+        assert_eq!(truncation.total_lines, 6);
+    }
+}
@@ -0,0 +1,240 @@
+//! Task output capture
+//!
+//! Keeps the beginning and the end of a stream in memory, where the command
+//! line and the errors usually are, and drops the middle of large outputs:
+//! - `capture` - head buffer plus tail ring buffer, counting omitted lines
+//! - `spill` - full logs of truncated streams written to spill files, and
+//!   paging through them
+//! - `ansi` - stripping ANSI escape sequences
+//!
+//! Nothing is dropped until a stream outgrows `head_bytes + tail_bytes`, so
+//! short outputs are returned unchanged and never spilled. Secrets are
+//! masked by the limits' [`Redactor`] in everything kept, spilled or echoed.
+
+pub mod ansi;
+pub mod capture;
+pub mod spill;
+
+pub use capture::{Captured, OutputCapture, Truncation};
+pub use spill::{read_page, OutputPage};
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::{ColorMode, Config};
+use crate::security::Redactor;
+
+/// Variables set on tasks that are asked for colors
+const COLOR_ENV: &[(&str, &str)] = &[
+    ("CLICOLOR_FORCE", "1"),
+    ("FORCE_COLOR", "1"),
+    ("CARGO_TERM_COLOR", "always"),
+];
+
+/// Variables set on tasks that are asked not to use colors
+const NO_COLOR_ENV: &[(&str, &str)] = &[("NO_COLOR", "1"), ("CARGO_TERM_COLOR", "never")];
+
+/// Default bytes kept from the start of a stream
+const DEFAULT_HEAD_BYTES: usize = 16 * 1024;
+
+/// Default bytes kept from the end of a stream
+const DEFAULT_TAIL_BYTES: usize = 64 * 1024;
+
+/// How much of a stream to keep and where to spill the rest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLimits {
+    /// Bytes kept from the start of the stream
+    pub head_bytes: usize,
+    /// Bytes kept from the end of the stream
+    pub tail_bytes: usize,
+    /// Directory for spill files (`None` = don't spill)
+    pub spill_dir: Option<PathBuf>,
+    /// Number of most recent spill files kept in `spill_dir`
+    pub max_spill_files: usize,
+    /// Remove ANSI escape sequences from the captured text
+    pub strip_ansi: bool,
+    /// Ask the task for colors (`Some(true)`), for none (`Some(false)`), or
+    /// leave its environment alone (`None`)
+    pub color: Option<bool>,
+    /// Secrets to mask in the output (`None` = don't mask)
+    pub redact: Option<Arc<Redactor>>,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            head_bytes: DEFAULT_HEAD_BYTES,
+            tail_bytes: DEFAULT_TAIL_BYTES,
+            spill_dir: None,
+            max_spill_files: 0,
+            strip_ansi: false,
+            color: None,
+            redact: None,
+        }
+    }
+}
+
+impl OutputLimits {
+    /// Limits from the `[output]` configuration, for output that is
+    /// returned rather than shown on a terminal
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            head_bytes: config.output.head_bytes,
+            tail_bytes: config.output.tail_bytes,
+            spill_dir: config.output.spill_dir(),
+            max_spill_files: config.output.max_spill_files,
+            strip_ansi: config.output.strip_ansi,
+            color: Some(config.output.color == ColorMode::Always),
+            redact: redactor(config).map(Arc::new),
+        }
+    }
+
+    /// Also mask `values`, e.g. a service's secret environment values
+    pub fn mask<I, S>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if let Some(redact) = &mut self.redact {
+            Arc::make_mut(redact).mask(values);
+        }
+        self
+    }
+
+    /// `text` with secrets masked, for output captured some other way
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match &self.redact {
+            Some(redact) => redact.redact(text),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    /// Limits for a task run with the extra variables `env`, masking the
+    /// values of those with secret names
+    pub fn for_env<K, V>(&self, env: impl IntoIterator<Item = (K, V)>) -> Cow<'_, Self>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let Some(redact) = &self.redact else {
+            return Cow::Borrowed(self);
+        };
+        let mut extended = Redactor::clone(redact);
+        extended.env(env);
+        if extended == **redact {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(Self {
+            redact: Some(Arc::new(extended)),
+            ..self.clone()
+        })
+    }
+
+    /// Adjust for output that will be printed to a terminal: unless colors
+    /// are disabled, ask for them and keep escape sequences
+    pub fn terminal(mut self, config: &Config, is_terminal: bool) -> Self {
+        if is_terminal && config.output.color != ColorMode::Never {
+            self.color = Some(true);
+            self.strip_ansi = false;
+        }
+        self
+    }
+
+    /// Environment variables to set on the task, and ones to remove
+    pub fn color_env(
+        &self,
+    ) -> (
+        &'static [(&'static str, &'static str)],
+        &'static [&'static str],
+    ) {
+        match self.color {
+            Some(true) => (COLOR_ENV, &["NO_COLOR"]),
+            Some(false) => (NO_COLOR_ENV, &["CLICOLOR_FORCE", "FORCE_COLOR"]),
+            None => (&[], &[]),
+        }
+    }
+
+    /// Keep at most `max_bytes` in total: a quarter from the start, the rest
+    /// from the end
+    pub fn total(max_bytes: usize) -> Self {
+        let head_bytes = max_bytes / 4;
+        Self {
+            head_bytes,
+            tail_bytes: max_bytes - head_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Write the full output of truncated streams to `dir`, keeping the
+    /// `max_files` most recent spill files
+    pub fn spill_to(mut self, dir: impl Into<PathBuf>, max_files: usize) -> Self {
+        self.spill_dir = Some(dir.into());
+        self.max_spill_files = max_files;
+        self
+    }
+}
+
+/// The configured redactor with the values of our own secret variables,
+/// falling back to the default settings if the configured ones are invalid
+fn redactor(config: &Config) -> Option<Redactor> {
+    let settings = &config.security.redact;
+    if !settings.enabled {
+        return None;
+    }
+    let mut redactor = Redactor::new(settings).unwrap_or_else(|e| {
+        tracing::warn!("{}; masking with the default settings", e);
+        Redactor::new(&Default::default()).expect("Invalid default redaction settings")
+    });
+    redactor.env(std::env::vars());
+    Some(redactor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_splits_head_and_tail() {
+        let limits = OutputLimits::total(100);
+        assert_eq!((limits.head_bytes, limits.tail_bytes), (25, 75));
+        assert!(limits.spill_dir.is_none());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config::default();
+        config.output.head_bytes = 10;
+        config.output.spill_dir = Some("/tmp/spill".to_string());
+        let limits = OutputLimits::from_config(&config);
+        assert_eq!(limits.head_bytes, 10);
+        assert_eq!(limits.spill_dir, Some(PathBuf::from("/tmp/spill")));
+
+        config.output.spill = false;
+        assert!(OutputLimits::from_config(&config).spill_dir.is_none());
+    }
+
+    #[test]
+    fn test_color() {
+        let mut config = Config::default();
+        let limits = OutputLimits::from_config(&config);
+        assert!(limits.strip_ansi);
+        assert_eq!(limits.color, Some(false));
+        assert!(limits.color_env().0.contains(&("NO_COLOR", "1")));
+
+        let terminal = limits.clone().terminal(&config, true);
+        assert!(!terminal.strip_ansi);
+        assert_eq!(terminal.color, Some(true));
+        assert!(terminal.color_env().1.contains(&"NO_COLOR"));
+        assert_eq!(limits.clone().terminal(&config, false), limits);
+
+        config.output.color = ColorMode::Never;
+        let never = OutputLimits::from_config(&config).terminal(&config, true);
+        assert_eq!(never.color, Some(false));
+        assert!(never.strip_ansi);
+
+        config.output.color = ColorMode::Always;
+        assert_eq!(OutputLimits::from_config(&config).color, Some(true));
+        assert_eq!(OutputLimits::default().color_env(), (&[][..], &[][..]));
+    }
+}
@@ -0,0 +1,293 @@
+//! Spill files
+//!
+//! Full logs of truncated streams, named `<secs>-<pid>-<n>.<stream>.log`
+//! in the spill directory. Only the most recent `max_spill_files` are kept;
+//! other files in the directory are left alone. The directory is created
+//! private (0700) and must belong to the current user. [`read_page`] pages
+//! through spill files by line, refusing anything else.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::error::TaskError;
+
+/// Distinguishes spill files created by this process in the same second
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Names of spill files
+static SPILL_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d+-\d+-\d+\.[a-z]+\.log$").expect("Invalid regex"));
+
+/// A page of lines from a spill file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutputPage {
+    pub file: PathBuf,
+    /// Index of the first line returned (0-based)
+    pub offset: usize,
+    pub lines: Vec<String>,
+    /// Lines in the whole file
+    pub total_lines: usize,
+    /// Offset of the next page, if there are more lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+}
+
+/// Create a new spill file in `dir`, first removing the oldest ones so at
+/// most `max_files` remain including it
+pub(crate) fn create(
+    dir: &Path,
+    stream: &str,
+    max_files: usize,
+) -> std::io::Result<(PathBuf, File)> {
+    ensure_dir(dir)?;
+    prune(dir, max_files.saturating_sub(1));
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!(
+        "{}-{}-{}.{}.log",
+        secs,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        stream
+    ));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // Task output may contain secrets
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(&path)?;
+    Ok((path, file))
+}
+
+/// Create `dir` if needed, readable by the user only, and check that it is
+/// a directory of theirs rather than one (or a symlink) someone else put there
+fn ensure_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)?;
+
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() {
+        return Err(std::io::Error::other(format!(
+            "Spill directory {} is not a directory",
+            dir.display()
+        )));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // SAFETY: geteuid has no preconditions
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            return Err(std::io::Error::other(format!(
+                "Spill directory {} belongs to another user",
+                dir.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is named like a spill file
+fn is_spill_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| SPILL_NAME.is_match(name))
+}
+
+/// Remove all but the `keep` most recently modified spill files in `dir`
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| is_spill_file(&entry.path()))
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= keep {
+        return;
+    }
+
+    files.sort();
+    for (_, path) in &files[..files.len() - keep] {
+        if let Err(e) = std::fs::remove_file(path) {
+            tracing::debug!("Failed to remove spill file {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Read up to `limit` lines of the spill file `file`, starting at line `offset`
+///
+/// `file` is a path or a file name within `spill_dir`.
+///
+/// # Errors
+/// * `TaskError::SecurityViolation` - If `file` is outside `spill_dir`
+/// * `TaskError::Io` - If the file can't be read
+pub fn read_page(
+    spill_dir: &Path,
+    file: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<OutputPage, TaskError> {
+    let path = spill_dir.join(file);
+    let outside = || TaskError::SecurityViolation {
+        message: format!(
+            "'{}' is not an output file in {}",
+            file,
+            spill_dir.display()
+        ),
+        path: path.display().to_string(),
+    };
+    let dir = spill_dir.canonicalize().map_err(|_| outside())?;
+    let canonical = path.canonicalize().map_err(|_| outside())?;
+    if canonical.parent() != Some(dir.as_path())
+        || !is_spill_file(&canonical)
+        || !canonical.is_file()
+    {
+        return Err(outside());
+    }
+
+    let mut reader = BufReader::new(File::open(&canonical)?);
+    let mut lines = Vec::new();
+    let mut total_lines = 0;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        if total_lines >= offset && lines.len() < limit {
+            let line = String::from_utf8_lossy(&buf);
+            lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+        }
+        total_lines += 1;
+    }
+
+    let end = offset + lines.len();
+    Ok(OutputPage {
+        file: canonical,
+        offset,
+        lines,
+        total_lines,
+        next_offset: (end < total_lines).then_some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_prunes_oldest() {
+        let dir = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for _ in 0..4 {
+            let (path, _) = create(dir.path(), "stdout", 3).unwrap();
+            paths.push(path);
+            // Distinct modification times
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+        assert!(!paths[0].exists());
+        assert!(paths[3].exists());
+        assert!(paths[3].to_string_lossy().ends_with(".stdout.log"));
+    }
+
+    #[test]
+    fn test_prune_keeps_other_files() {
+        let dir = TempDir::new().unwrap();
+        let other = dir.path().join("server.log");
+        std::fs::write(&other, "keep me").unwrap();
+        for _ in 0..3 {
+            create(dir.path(), "stdout", 1).unwrap();
+        }
+
+        assert!(other.exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        assert!(read_page(dir.path(), "server.log", 0, 10).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spill_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("spill");
+        create(&dir, "stdout", 10).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // A symlink planted in place of the directory is refused
+        let link = temp.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(create(&link, "stdout", 10).is_err());
+    }
+
+    #[test]
+    fn test_read_page() {
+        let dir = TempDir::new().unwrap();
+        let (path, mut file) = create(dir.path(), "stderr", 10).unwrap();
+        for i in 0..25 {
+            writeln!(file, "line {}", i).unwrap();
+        }
+        drop(file);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+        let page = read_page(dir.path(), &name, 0, 10).unwrap();
+        assert_eq!(page.lines.len(), 10);
+        assert_eq!(page.lines[0], "line 0");
+        assert_eq!(page.total_lines, 25);
+        assert_eq!(page.next_offset, Some(10));
+
+        let last = read_page(dir.path(), &path.display().to_string(), 20, 10).unwrap();
+        assert_eq!(
+            last.lines,
+            vec!["line 20", "line 21", "line 22", "line 23", "line 24"]
+        );
+        assert_eq!(last.next_offset, None);
+    }
+
+    #[test]
+    fn test_read_page_outside_spill_dir() {
+        let dir = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+        let secret = other.path().join("secret.log");
+        std::fs::write(&secret, "token").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        for file in [
+            secret.display().to_string(),
+            "../secret.log".to_string(),
+            "sub".to_string(),
+            "missing.log".to_string(),
+        ] {
+            let result = read_page(dir.path(), &file, 0, 10);
+            assert!(
+                matches!(result, Err(TaskError::SecurityViolation { .. })),
+                "{}: {:?}",
+                file,
+                result
+            );
+        }
+    }
+}
@@ -0,0 +1,1020 @@
+//! Structured command specification
+//!
+//! A [`CommandSpec`] describes exactly what a runner executes: the program,
+//! its argv, the working directory and any extra environment variables.
+//! The same value is used to spawn the process and to report it, so logs
+//! and MCP responses always match what actually ran.
+//!
+//! # Display
+//!
+//! `Display` renders the program and arguments shell-escaped, so the output
+//! can be pasted into a POSIX shell and re-executed verbatim:
+//!
+//! ```
+//! use makefilehub::runner::CommandSpec;
+//!
+//! let spec = CommandSpec::new("make").arg("build").arg("MSG=hello world");
+//! assert_eq!(spec.to_string(), "make build 'MSG=hello world'");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::env;
+use super::interrupt;
+use super::pty::{self, PtySize};
+#[cfg(unix)]
+use super::sandbox;
+use super::traits::RunOptions;
+use crate::error::TaskError;
+use crate::output::{Captured, OutputCapture, OutputLimits};
+
+/// How often to poll a child process while waiting for it with a timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Size of the chunks read from a child's pipes while capturing
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Exit status and output of a command captured within [`OutputLimits`]
+#[derive(Debug)]
+pub struct CapturedOutput {
+    pub status: ExitStatus,
+    pub stdout: Captured,
+    pub stderr: Captured,
+}
+
+/// A program invocation with argv, working directory and environment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CommandSpec {
+    /// Program to execute (resolved via PATH if not absolute)
+    pub program: String,
+    /// Arguments passed to the program, one element per argv entry
+    pub args: Vec<String>,
+    /// Working directory for the process
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Extra environment variables set on top of the inherited environment
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl CommandSpec {
+    /// Create a command spec for a program with no arguments
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Append a single argument
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Append multiple arguments
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the working directory
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(dir.into());
+        self
+    }
+
+    /// A copy with secrets masked in the arguments and environment values,
+    /// for reporting the command rather than running it
+    pub fn redacted(&self, limits: &OutputLimits) -> Self {
+        let limits = limits.for_env(&self.env);
+        Self {
+            program: self.program.clone(),
+            args: self
+                .args
+                .iter()
+                .map(|arg| limits.redact(arg).into_owned())
+                .collect(),
+            cwd: self.cwd.clone(),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), limits.redact(value).into_owned()))
+                .collect(),
+        }
+    }
+
+    /// Set an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set multiple environment variables
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Full argv including the program as the first element
+    pub fn argv(&self) -> Vec<String> {
+        std::iter::once(self.program.clone())
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+
+    /// Build a `std::process::Command` from this spec
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        if let Some(ref dir) = self.cwd {
+            cmd.current_dir(dir);
+        }
+        cmd.envs(&self.env);
+        cmd
+    }
+
+    /// Build a `tokio::process::Command` from this spec
+    pub fn to_async_command(&self) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(&self.program);
+        cmd.args(&self.args);
+        if let Some(ref dir) = self.cwd {
+            cmd.current_dir(dir);
+        }
+        cmd.envs(&self.env);
+        cmd
+    }
+
+    /// Run the command to completion, capturing stdout and stderr
+    ///
+    /// When `timeout` is set the process is killed once it elapses.
+    ///
+    /// # Errors
+    /// * `TaskError::SpawnFailed` - If the process could not be started
+    /// * `TaskError::Timeout` - If the process ran longer than `timeout`
+    pub fn output(&self, timeout: Option<Duration>) -> Result<Output, TaskError> {
+        tracing::debug!("Executing: {}", self);
+
+        let mut cmd = self.to_command();
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // A timeout kills everything the command started
+        #[cfg(unix)]
+        if timeout.is_some() {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        let mut child = cmd.spawn().map_err(|e| TaskError::SpawnFailed {
+            command: self.to_string(),
+            error: e.to_string(),
+        })?;
+
+        let Some(limit) = timeout else {
+            return child.wait_with_output().map_err(TaskError::Io);
+        };
+
+        // Drain pipes on background threads so a chatty child can't block on a full pipe
+        let stdout = child.stdout.take().map(read_to_end_thread);
+        let stderr = child.stderr.take().map(read_to_end_thread);
+        let foreground = interrupt::Foreground::new(child.id(), false);
+        let status = self.wait(&mut child, Some(limit), None);
+        drop(foreground);
+        let status = status?;
+
+        Ok(Output {
+            status,
+            stdout: stdout.map(join_reader).unwrap_or_default(),
+            stderr: stderr.map(join_reader).unwrap_or_default(),
+        })
+    }
+
+    /// Run the command to completion, keeping the head and tail of stdout and
+    /// stderr within `limits`
+    ///
+    /// When `timeout` is set the process is killed once it elapses.
+    ///
+    /// # Errors
+    /// * `TaskError::SpawnFailed` - If the process could not be started
+    /// * `TaskError::Timeout` - If the process ran longer than `timeout`
+    pub fn capture(
+        &self,
+        timeout: Option<Duration>,
+        limits: &OutputLimits,
+    ) -> Result<CapturedOutput, TaskError> {
+        self.run(
+            &RunOptions {
+                timeout,
+                output: limits.clone(),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Run the command to completion in the foreground, showing its output
+    /// live while keeping it within `limits` as [`capture`](Self::capture)
+    /// does
+    ///
+    /// stdin is inherited, and stdout and stderr are copied to ours as they
+    /// arrive. On unix the command runs in its own process group, which gets
+    /// the terminal and any forwarded interrupts (see
+    /// [`interrupt`](super::interrupt)) and is killed as a whole on timeout.
+    ///
+    /// # Errors
+    /// * `TaskError::SpawnFailed` - If the process could not be started
+    /// * `TaskError::Timeout` - If the process ran longer than `timeout`
+    pub fn stream(
+        &self,
+        timeout: Option<Duration>,
+        limits: &OutputLimits,
+    ) -> Result<CapturedOutput, TaskError> {
+        self.run(
+            &RunOptions {
+                timeout,
+                output: limits.clone(),
+                capture_output: false,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Run the command in a pseudo-terminal of `size`, keeping its merged
+    /// output within `limits` (returned as stdout)
+    ///
+    /// With `stream` set the output is also copied to our stdout as it
+    /// arrives. The command runs in a session of its own, killed as a whole
+    /// on timeout.
+    ///
+    /// # Errors
+    /// * `TaskError::SpawnFailed` - If the pseudo-terminal could not be opened
+    ///   or the process could not be started
+    /// * `TaskError::Timeout` - If the process ran longer than `timeout`
+    pub fn in_pty(
+        &self,
+        timeout: Option<Duration>,
+        limits: &OutputLimits,
+        size: PtySize,
+        stream: bool,
+    ) -> Result<CapturedOutput, TaskError> {
+        self.run(
+            &RunOptions {
+                timeout,
+                output: limits.clone(),
+                capture_output: !stream,
+                pty: Some(size),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// Run the command with [`stream`](Self::stream),
+    /// [`capture`](Self::capture) or [`in_pty`](Self::in_pty) as `options`
+    /// ask, within its resource limits and sandbox and starting from the
+    /// environment its `env_mode` gives
+    ///
+    /// # Errors
+    /// * `TaskError::SpawnFailed` - If the process could not be started, or
+    ///   the limits or sandbox could not be applied
+    /// * `TaskError::Config` - If an `env_passthrough` glob is invalid
+    /// * `TaskError::Timeout` - If the process ran longer than the timeout
+    pub fn execute(&self, options: &RunOptions) -> Result<CapturedOutput, TaskError> {
+        self.run(options, None)
+    }
+
+    /// [`execute`](Self::execute) on a blocking thread, for async callers
+    ///
+    /// Dropping the returned future cancels the run: the command runs in a
+    /// process group of its own on unix, and the whole group is killed.
+    ///
+    /// # Errors
+    /// As [`execute`](Self::execute).
+    pub async fn execute_async(&self, options: RunOptions) -> Result<CapturedOutput, TaskError> {
+        /// Cancels the run when the future holding it is dropped
+        struct CancelOnDrop(Arc<AtomicBool>);
+
+        impl Drop for CancelOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(cancel.clone());
+        let spec = self.clone();
+        tokio::task::spawn_blocking(move || spec.run(&options, Some(&cancel)))
+            .await
+            .map_err(|e| TaskError::Io(std::io::Error::other(e)))?
+    }
+
+    fn run(
+        &self,
+        options: &RunOptions,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<CapturedOutput, TaskError> {
+        tracing::debug!("Executing: {}", self);
+        let timeout = options.timeout;
+        let limits = options.output.for_env(&self.env);
+        let limits = &*limits;
+        let stream = !options.capture_output;
+        let spawn_failed = |error: String| TaskError::SpawnFailed {
+            command: self.to_string(),
+            error,
+        };
+
+        let mut cmd = self.to_command();
+        self.isolate_env(&mut cmd, options)?;
+        // Variables set explicitly on the spec take precedence
+        let (set, remove) = limits.color_env();
+        for (key, value) in set.iter().filter(|(key, _)| !self.env.contains_key(*key)) {
+            cmd.env(key, value);
+        }
+        for key in remove.iter().filter(|key| !self.env.contains_key(**key)) {
+            cmd.env_remove(key);
+        }
+
+        self.confine(&mut cmd, options)?;
+        if let Some(size) = options.pty {
+            return self.run_pty(cmd, timeout, limits, stream, size, cancel);
+        }
+
+        let terminal = stream && std::io::stdin().is_terminal();
+        if stream {
+            cmd.stdin(Stdio::inherit());
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt;
+                cmd.process_group(0);
+                if terminal {
+                    // SAFETY: take_terminal only makes async-signal-safe calls
+                    unsafe { cmd.pre_exec(interrupt::take_terminal) };
+                }
+            }
+        } else {
+            cmd.stdin(Stdio::null());
+            // A timed out or cancelled run takes everything it started down
+            // with it
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt;
+                cmd.process_group(0);
+            }
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_failed(e.to_string()))?;
+        let foreground = interrupt::Foreground::new(child.id(), terminal);
+
+        let stdout = child.stdout.take().map(|pipe| {
+            let echo = stream.then(|| Box::new(std::io::stdout()) as Box<dyn Write + Send>);
+            capture_thread(pipe, OutputCapture::new(limits, "stdout"), echo)
+        });
+        let stderr = child.stderr.take().map(|pipe| {
+            let echo = stream.then(|| Box::new(std::io::stderr()) as Box<dyn Write + Send>);
+            capture_thread(pipe, OutputCapture::new(limits, "stderr"), echo)
+        });
+        let status = self.wait(&mut child, timeout, cancel);
+        drop(foreground);
+
+        Ok(CapturedOutput {
+            status: status?,
+            stdout: stdout.map(join_capture).unwrap_or_default(),
+            stderr: stderr.map(join_capture).unwrap_or_default(),
+        })
+    }
+
+    /// Replace the environment `cmd` inherits as `options.env_mode` asks,
+    /// keeping the variables set on the spec
+    fn isolate_env(&self, cmd: &mut Command, options: &RunOptions) -> Result<(), TaskError> {
+        let base = env::base_env(
+            options.env_mode,
+            &options.env_passthrough,
+            std::env::vars_os(),
+        )?;
+        if let Some(base) = base {
+            cmd.env_clear().envs(base).envs(&self.env);
+            // Marked as removed, so a pseudo-terminal still gets a default
+            if !cmd.get_envs().any(|(key, _)| key == "TERM") {
+                cmd.env_remove("TERM");
+            }
+        }
+        Ok(())
+    }
+
+    /// Have `cmd` apply the resource limits and sandbox of `options` before
+    /// it starts
+    fn confine(&self, cmd: &mut Command, options: &RunOptions) -> Result<(), TaskError> {
+        let failed = |e: std::io::Error| TaskError::SpawnFailed {
+            command: self.to_string(),
+            error: format!("sandbox: {}", e),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            let confinement = sandbox::Confinement::prepare(
+                &options.resources,
+                options.sandbox.as_ref(),
+                self.cwd.as_deref(),
+            )
+            .map_err(failed)?;
+            if let Some(confinement) = confinement {
+                // SAFETY: apply only makes async-signal-safe calls
+                unsafe { cmd.pre_exec(move || confinement.apply()) };
+            }
+            Ok(())
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = cmd;
+            if options.resources.is_empty() && options.sandbox.is_none() {
+                return Ok(());
+            }
+            Err(failed(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "resource limits require unix",
+            )))
+        }
+    }
+
+    /// Spawn `cmd` on a new pseudo-terminal and capture its output
+    #[cfg(unix)]
+    fn run_pty(
+        &self,
+        mut cmd: Command,
+        timeout: Option<Duration>,
+        limits: &OutputLimits,
+        stream: bool,
+        size: PtySize,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<CapturedOutput, TaskError> {
+        use std::os::unix::process::CommandExt;
+
+        let spawn_failed = |error: String| TaskError::SpawnFailed {
+            command: self.to_string(),
+            error,
+        };
+        let pty::Pty { master, slave } =
+            pty::Pty::open(size).map_err(|e| spawn_failed(format!("pseudo-terminal: {}", e)))?;
+        let slave_stdio = || {
+            slave
+                .try_clone()
+                .map(Stdio::from)
+                .map_err(|e| spawn_failed(format!("pseudo-terminal: {}", e)))
+        };
+
+        // Unless TERM is set on the command or inherited
+        let term = cmd
+            .get_envs()
+            .find(|(key, _)| *key == "TERM")
+            .map(|(_, value)| value.is_some());
+        if !term.unwrap_or_else(|| std::env::var_os("TERM").is_some()) {
+            cmd.env("TERM", "xterm-256color");
+        }
+        cmd.stdin(slave_stdio()?)
+            .stdout(slave_stdio()?)
+            .stderr(slave_stdio()?);
+        // SAFETY: controlling_terminal only makes async-signal-safe calls
+        unsafe { cmd.pre_exec(pty::controlling_terminal) };
+
+        let spawned = cmd.spawn();
+        // The master only sees the end of the output once no slave
+        // descriptor is left open on our side
+        drop(cmd);
+        drop(slave);
+        let mut child = spawned.map_err(|e| spawn_failed(e.to_string()))?;
+        let foreground = interrupt::Foreground::new(child.id(), false);
+
+        let echo = stream.then(|| Box::new(std::io::stdout()) as Box<dyn Write + Send>);
+        let output = capture_thread(
+            pty::MasterReader(master),
+            OutputCapture::new(limits, "output"),
+            echo,
+        );
+        let status = self.wait(&mut child, timeout, cancel);
+        drop(foreground);
+
+        Ok(CapturedOutput {
+            status: status?,
+            stdout: join_capture(output),
+            stderr: Captured::default(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn run_pty(
+        &self,
+        _cmd: Command,
+        _timeout: Option<Duration>,
+        _limits: &OutputLimits,
+        _stream: bool,
+        _size: PtySize,
+        _cancel: Option<&AtomicBool>,
+    ) -> Result<CapturedOutput, TaskError> {
+        Err(TaskError::SpawnFailed {
+            command: self.to_string(),
+            error: "pseudo-terminals are only supported on unix".to_string(),
+        })
+    }
+
+    /// Wait for a spawned child that leads its own process group, killing
+    /// the group once `timeout` elapses or `cancel` is set
+    fn wait(
+        &self,
+        child: &mut Child,
+        timeout: Option<Duration>,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<ExitStatus, TaskError> {
+        if timeout.is_none() && cancel.is_none() {
+            return child.wait().map_err(TaskError::Io);
+        }
+
+        let deadline = timeout.map(|limit| Instant::now() + limit);
+        loop {
+            if let Some(status) = child.try_wait().map_err(TaskError::Io)? {
+                return Ok(status);
+            }
+            let now = Instant::now();
+            let cancelled = cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst));
+            let timed_out = deadline.is_some_and(|deadline| now >= deadline);
+            if cancelled || timed_out {
+                #[cfg(unix)]
+                interrupt::kill_group(child.id());
+                let _ = child.kill();
+                let _ = child.wait();
+                if cancelled {
+                    return Err(TaskError::Io(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        format!("{} was cancelled", self),
+                    )));
+                }
+                // Reader threads are detached: grandchildren may still hold the pipes open
+                return Err(TaskError::Timeout {
+                    command: self.to_string(),
+                    // Rounded up, so a sub-second limit isn't reported as 0
+                    timeout_secs: timeout.unwrap_or_default().as_secs_f64().ceil() as u64,
+                });
+            }
+            let sleep = deadline.map_or(POLL_INTERVAL, |deadline| {
+                (deadline - now).min(POLL_INTERVAL)
+            });
+            std::thread::sleep(sleep);
+        }
+    }
+}
+
+impl std::fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", shell_quote(&self.program))?;
+        for arg in &self.args {
+            write!(f, " {}", shell_quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a pipe to completion on a background thread
+fn read_to_end_thread<R: Read + Send + 'static>(mut reader: R) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Collect the bytes read by a reader thread
+fn join_reader(handle: JoinHandle<Vec<u8>>) -> Vec<u8> {
+    handle.join().unwrap_or_default()
+}
+
+/// Feed a pipe into `capture` on a background thread, copying it to `echo`
+/// as it arrives
+fn capture_thread<R: Read + Send + 'static>(
+    mut reader: R,
+    mut capture: OutputCapture,
+    mut echo: Option<Box<dyn Write + Send>>,
+) -> JoinHandle<Captured> {
+    std::thread::spawn(move || {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut copy = |capture: &mut OutputCapture, chunk: &[u8], flush: bool| {
+            if let Some(out) = &mut echo {
+                // Keep capturing even if our own stdout went away
+                let chunk = capture.echo(chunk, flush);
+                if out.write_all(&chunk).and_then(|_| out.flush()).is_err() {
+                    echo = None;
+                }
+            }
+        };
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    copy(&mut capture, &buf[..n], false);
+                    capture.write(&buf[..n]);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    tracing::warn!("Error reading output: {}", e);
+                    break;
+                }
+            }
+        }
+        // The last line may not end in a newline
+        copy(&mut capture, &[], true);
+        capture.finish()
+    })
+}
+
+/// Collect the output captured by a capture thread
+fn join_capture(handle: JoinHandle<Captured>) -> Captured {
+    handle.join().unwrap_or_default()
+}
+
+/// Quote a string for safe use as a single POSIX shell word
+///
+/// Strings made only of shell-safe characters are returned unchanged;
+/// everything else is wrapped in single quotes with embedded quotes escaped.
+pub fn shell_quote(s: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_safe) {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(format!("'{}'", s.replace('\'', r"'\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvMode;
+
+    #[test]
+    fn test_command_spec_builder() {
+        let spec = CommandSpec::new("make")
+            .arg("build")
+            .args(["A=1", "B=2"])
+            .current_dir("/projects/app")
+            .env("RUST_LOG", "debug");
+
+        assert_eq!(spec.program, "make");
+        assert_eq!(spec.args, vec!["build", "A=1", "B=2"]);
+        assert_eq!(spec.cwd, Some(PathBuf::from("/projects/app")));
+        assert_eq!(spec.env.get("RUST_LOG"), Some(&"debug".to_string()));
+        assert_eq!(spec.argv(), vec!["make", "build", "A=1", "B=2"]);
+    }
+
+    #[test]
+    fn test_display_plain_args() {
+        let spec = CommandSpec::new("just").arg("test").arg("--release");
+        assert_eq!(spec.to_string(), "just test --release");
+    }
+
+    #[test]
+    fn test_display_quotes_spaces_and_quotes() {
+        let spec = CommandSpec::new("./run.sh")
+            .arg("deploy")
+            .arg("--msg=it's done")
+            .arg("two words")
+            .arg("");
+
+        assert_eq!(
+            spec.to_string(),
+            r#"./run.sh deploy '--msg=it'\''s done' 'two words' ''"#
+        );
+    }
+
+    #[test]
+    fn test_shell_quote_metacharacters() {
+        assert_eq!(shell_quote("safe-value_1.0"), "safe-value_1.0");
+        assert_eq!(shell_quote("$(rm -rf /)"), "'$(rm -rf /)'");
+        assert_eq!(shell_quote("a;b"), "'a;b'");
+        assert_eq!(shell_quote("*"), "'*'");
+    }
+
+    #[test]
+    fn test_display_is_replayable() {
+        let spec = CommandSpec::new("printf")
+            .arg("%s|")
+            .arg("it's")
+            .arg("a b")
+            .arg("$HOME");
+
+        let direct = spec.output(None);
+        let replayed = Command::new("sh").arg("-c").arg(spec.to_string()).output();
+
+        match (direct, replayed) {
+            (Ok(direct), Ok(replayed)) => {
+                assert_eq!(direct.stdout, replayed.stdout);
+                assert_eq!(String::from_utf8_lossy(&direct.stdout), "it's|a b|$HOME|");
+            }
+            _ => eprintln!("Skipping test: printf or sh not available"),
+        }
+    }
+
+    #[test]
+    fn test_output_spawn_failed() {
+        let spec = CommandSpec::new("nonexistent_command_12345").arg("x y");
+
+        match spec.output(None) {
+            Err(TaskError::SpawnFailed { command, .. }) => {
+                assert_eq!(command, "nonexistent_command_12345 'x y'");
+            }
+            other => panic!("Expected SpawnFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_output_uses_cwd_and_env() {
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("echo \"$MY_VAR $(pwd)\"")
+            .current_dir("/tmp")
+            .env("MY_VAR", "value");
+
+        match spec.output(Some(Duration::from_secs(10))) {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                assert!(stdout.contains("value"));
+                assert!(stdout.contains("/tmp"));
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: sh not available"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_output_timeout() {
+        let spec = CommandSpec::new("sleep").arg("10");
+        let start = Instant::now();
+
+        match spec.output(Some(Duration::from_millis(100))) {
+            Err(TaskError::Timeout { command, .. }) => {
+                assert_eq!(command, "sleep 10");
+                assert!(start.elapsed() < Duration::from_secs(5));
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: sleep not available"),
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_output_within_timeout() {
+        let spec = CommandSpec::new("echo").arg("done");
+
+        match spec.output(Some(Duration::from_secs(10))) {
+            Ok(output) => {
+                assert!(output.status.success());
+                assert_eq!(String::from_utf8_lossy(&output.stdout), "done\n");
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: echo not available"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_capture_masks_secret_env() {
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("echo \"$DEPLOY_TOKEN\"; echo \"$DEPLOY_TOKEN\" >&2")
+            .env("DEPLOY_TOKEN", "abc123xyz");
+        let limits = OutputLimits {
+            redact: Some(std::sync::Arc::new(
+                crate::security::Redactor::new(&Default::default()).unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        match spec.capture(None, &limits) {
+            Ok(output) => {
+                assert_eq!(output.stdout.text, "[REDACTED]\n");
+                assert_eq!(output.stderr.text, "[REDACTED]\n");
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: sh not available"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_echo_masks_secret_across_chunks() {
+        /// Echo target the test can read back
+        #[derive(Clone, Default)]
+        struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut redactor = crate::security::Redactor::new(&Default::default()).unwrap();
+        redactor.mask(["hunter22"]);
+        let limits = OutputLimits {
+            redact: Some(std::sync::Arc::new(redactor)),
+            ..Default::default()
+        };
+        // The secret straddles the first chunk boundary
+        let mut text = "x".repeat(CHUNK_SIZE - 4);
+        text.push_str("hunter22\nlast");
+
+        let echo = Shared::default();
+        let captured = join_capture(capture_thread(
+            std::io::Cursor::new(text.clone().into_bytes()),
+            OutputCapture::new(&limits, "stdout"),
+            Some(Box::new(echo.clone())),
+        ));
+        let echoed = String::from_utf8(echo.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(echoed, text.replace("hunter22", "[REDACTED]"));
+        assert!(!captured.text.contains("hunter22"));
+    }
+
+    #[test]
+    fn test_execute_isolates_env() {
+        std::env::set_var("COMMAND_TEST_SERVER_SECRET", "leaked");
+        std::env::set_var("COMMAND_TEST_PASSED", "passed");
+        let spec = CommandSpec::new("/bin/sh")
+            .arg("-c")
+            .arg("echo \"$COMMAND_TEST_SERVER_SECRET|$COMMAND_TEST_PASSED|$OWN|${HOME:+home}\"")
+            .env("OWN", "own");
+        let run = |mode| {
+            let options = RunOptions::default().isolated(mode, &["COMMAND_TEST_P*".to_string()]);
+            spec.execute(&options).map(|output| output.stdout.text)
+        };
+
+        match run(EnvMode::Inherit) {
+            Ok(stdout) => {
+                let home = if std::env::var_os("HOME").is_some() {
+                    "home"
+                } else {
+                    ""
+                };
+                assert_eq!(stdout, format!("leaked|passed|own|{}\n", home));
+                assert_eq!(
+                    run(EnvMode::Clean).unwrap(),
+                    format!("|passed|own|{}\n", home)
+                );
+                assert_eq!(run(EnvMode::Allowlist).unwrap(), "|passed|own|\n");
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: sh not available"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+        std::env::remove_var("COMMAND_TEST_SERVER_SECRET");
+        std::env::remove_var("COMMAND_TEST_PASSED");
+    }
+
+    #[test]
+    fn test_capture_sets_color_env() {
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("printf '%s|%s|%s' \"$NO_COLOR\" \"$CLICOLOR_FORCE\" \"$CARGO_TERM_COLOR\"");
+        let limits = |color| OutputLimits {
+            color,
+            ..Default::default()
+        };
+
+        match spec.capture(None, &limits(Some(false))) {
+            Ok(output) => assert_eq!(output.stdout.text, "1||never"),
+            Err(TaskError::SpawnFailed { .. }) => {
+                eprintln!("Skipping test: sh not available");
+                return;
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+        let output = spec.capture(None, &limits(Some(true))).unwrap();
+        assert_eq!(output.stdout.text, "|1|always");
+
+        // Explicit variables win
+        let explicit = spec.clone().env("CARGO_TERM_COLOR", "auto");
+        let output = explicit.capture(None, &limits(Some(false))).unwrap();
+        assert_eq!(output.stdout.text, "1||auto");
+    }
+
+    #[test]
+    fn test_stream_captures_and_keeps_exit_code() {
+        let _lock = interrupt::TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("echo streamed; echo oops >&2; exit 3");
+
+        match spec.stream(None, &OutputLimits::default()) {
+            Ok(output) => {
+                assert_eq!(output.status.code(), Some(3));
+                assert_eq!(output.stdout.text, "streamed\n");
+                assert_eq!(output.stderr.text, "oops\n");
+            }
+            Err(TaskError::SpawnFailed { .. }) => eprintln!("Skipping test: sh not available"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stream_timeout_kills_process_group() {
+        let _lock = interrupt::TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::TempDir::new().unwrap();
+        let pid_file = dir.path().join("sleep.pid");
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("sleep 10 & echo $! > \"$PID_FILE\"; wait")
+            .env("PID_FILE", pid_file.display().to_string());
+
+        match spec.stream(Some(Duration::from_millis(300)), &OutputLimits::default()) {
+            Err(TaskError::Timeout { .. }) => {}
+            Err(TaskError::SpawnFailed { .. }) => {
+                eprintln!("Skipping test: sh not available");
+                return;
+            }
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+
+        // The background sleep was killed along with the shell
+        let pid: i32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let status = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        assert!(status.is_empty() || status.contains(") Z "), "{}", status);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_timeout_kills_process_group() {
+        let _lock = interrupt::TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::TempDir::new().unwrap();
+        let pid_file = dir.path().join("sleep.pid");
+        let spec = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("sleep 10 & echo $! > \"$PID_FILE\"; wait")
+            .env("PID_FILE", pid_file.display().to_string());
+
+        match spec.capture(Some(Duration::from_millis(300)), &OutputLimits::default()) {
+            // Sub-second limits are reported rounded up
+            Err(TaskError::Timeout { timeout_secs, .. }) => assert_eq!(timeout_secs, 1),
+            Err(TaskError::SpawnFailed { .. }) => {
+                eprintln!("Skipping test: sh not available");
+                return;
+            }
+            other => panic!("Expected Timeout, got {:?}", other),
+        }
+
+        // The background sleep was killed along with the shell
+        let pid: i32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let status = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        assert!(status.is_empty() || status.contains(") Z "), "{}", status);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let spec = CommandSpec::new("make").arg("a b");
+        let json = serde_json::to_string(&spec).unwrap();
+
+        assert!(json.contains("\"program\":\"make\""));
+        assert!(json.contains("\"args\":[\"a b\"]"));
+        // Empty cwd/env are skipped
+        assert!(!json.contains("cwd"));
+        assert!(!json.contains("env"));
+    }
+}
@@ -5,11 +5,19 @@
 //! - justfile or Justfile (just)
 //! - Custom scripts like run.sh, build.sh (configurable)
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
 use crate::config::Config;
+use crate::error::TaskError;
+use crate::runner::{JustfileRunner, MakefileRunner, Runner, ScriptRunner};
+
+/// Makefile names, in the order they are detected
+pub const MAKEFILE_NAMES: &[&str] = &["Makefile", "makefile", "GNUmakefile"];
+
+/// Justfile names
+pub const JUSTFILE_NAMES: &[&str] = &["justfile", "Justfile", ".justfile"];
 
 /// Type of build system runner
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -120,7 +128,7 @@ pub fn detect_runner(dir: &Path, config: &Config) -> DetectionResult {
 /// Check for Makefile in the directory
 fn check_makefile(dir: &Path, result: &mut DetectionResult) {
     // Check both "Makefile" and "makefile"
-    for name in &["Makefile", "makefile", "GNUmakefile"] {
+    for name in MAKEFILE_NAMES {
         let path = dir.join(name);
         if path.exists() && path.is_file() {
             result.files_found.makefile = true;
@@ -138,7 +146,7 @@ fn check_makefile(dir: &Path, result: &mut DetectionResult) {
 /// Check for justfile in the directory
 fn check_justfile(dir: &Path, result: &mut DetectionResult) {
     // Check both "justfile" and "Justfile"
-    for name in &["justfile", "Justfile", ".justfile"] {
+    for name in JUSTFILE_NAMES {
         let path = dir.join(name);
         if path.exists() && path.is_file() {
             result.files_found.justfile = true;
@@ -191,19 +199,30 @@ fn check_scripts(dir: &Path, config: &Config, result: &mut DetectionResult) {
     }
 }
 
+/// Files in `dir` a runner may read or execute: makefiles, justfiles and
+/// the configured scripts
+pub fn build_files(dir: &Path, config: &Config) -> Vec<PathBuf> {
+    let scripts = config
+        .runners
+        .script
+        .scripts
+        .iter()
+        .chain([&config.defaults.default_script])
+        .map(|script| script.strip_prefix("./").unwrap_or(script));
+    MAKEFILE_NAMES
+        .iter()
+        .chain(JUSTFILE_NAMES)
+        .copied()
+        .chain(scripts)
+        .map(|name| dir.join(name))
+        .collect()
+}
+
 /// Check if a specific runner type is available in a directory
 pub fn is_runner_available(dir: &Path, runner: &RunnerType) -> bool {
     match runner {
-        RunnerType::Make => {
-            dir.join("Makefile").exists()
-                || dir.join("makefile").exists()
-                || dir.join("GNUmakefile").exists()
-        }
-        RunnerType::Just => {
-            dir.join("justfile").exists()
-                || dir.join("Justfile").exists()
-                || dir.join(".justfile").exists()
-        }
+        RunnerType::Make => MAKEFILE_NAMES.iter().any(|name| dir.join(name).exists()),
+        RunnerType::Just => JUSTFILE_NAMES.iter().any(|name| dir.join(name).exists()),
         RunnerType::Script(name) => {
             let name_clean = name.strip_prefix("./").unwrap_or(name);
             let path = dir.join(name_clean);
@@ -225,6 +244,57 @@ pub fn is_runner_available(dir: &Path, runner: &RunnerType) -> bool {
     }
 }
 
+/// The script a runner override names, or `None` for `"make"` and `"just"`
+///
+/// See [`select_runner`] for the accepted overrides.
+pub fn override_script(runner_name: &str, config: &Config) -> Option<String> {
+    match runner_name {
+        "make" | "just" => None,
+        "script" => Some(config.defaults.default_script.clone()),
+        name => match name.strip_prefix("script:") {
+            Some(script) => Some(script.to_string()),
+            // Assume it's a script name
+            None => Some(format!("./{}", name)),
+        },
+    }
+}
+
+/// Get the runner for a directory, honoring an explicit runner override
+///
+/// The override accepts `"make"`, `"just"`, `"script"` (the default script),
+/// `"script:<path>"` or a bare script name. Without an override the build
+/// system is auto-detected.
+///
+/// # Errors
+/// * `TaskError::NoRunnerDetected` - If no override is given and nothing is detected
+pub fn select_runner(
+    dir: &Path,
+    runner_override: Option<&str>,
+    config: &Config,
+) -> Result<Box<dyn Runner>, TaskError> {
+    // Use override if provided
+    if let Some(runner_name) = runner_override {
+        return match (runner_name, override_script(runner_name, config)) {
+            (_, Some(script)) => Ok(Box::new(ScriptRunner::new(script))),
+            ("make", None) => Ok(Box::new(MakefileRunner::new())),
+            _ => Ok(Box::new(JustfileRunner::new())),
+        };
+    }
+
+    // Auto-detect
+    let detection = detect_runner(dir, config);
+
+    match detection.detected {
+        Some(RunnerType::Make) => Ok(Box::new(MakefileRunner::new())),
+        Some(RunnerType::Just) => Ok(Box::new(JustfileRunner::new())),
+        Some(RunnerType::Script(script)) => Ok(Box::new(ScriptRunner::new(script))),
+        None => Err(TaskError::NoRunnerDetected {
+            path: dir.display().to_string(),
+            available: detection.available.iter().map(|r| r.to_string()).collect(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,103 @@
+//! Environment isolation for tasks
+//!
+//! By default a task inherits the whole environment of the process that
+//! runs it, which for the MCP server is whatever the client started it
+//! with. In `clean` mode a task starts from a few basic variables instead,
+//! and in `allowlist` mode from nothing; either way the variables matching
+//! `env_passthrough` are passed through, and the task's own variables
+//! (its service's `env_file` and `env`) are set on top.
+
+use std::ffi::OsString;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::config::EnvMode;
+use crate::error::TaskError;
+
+/// Variables kept in `clean` mode
+pub const CLEAN_ENV: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "TMPDIR", "TERM", "LANG", "LC_ALL", "LC_CTYPE",
+    "TZ",
+];
+
+/// The variables of `vars` a task starts from in `mode`, or `None` to
+/// inherit them all
+///
+/// # Errors
+/// * `TaskError::Config` - If a passthrough glob is invalid
+pub fn base_env<I>(
+    mode: EnvMode,
+    passthrough: &[String],
+    vars: I,
+) -> Result<Option<Vec<(OsString, OsString)>>, TaskError>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let basic: &[&str] = match mode {
+        EnvMode::Inherit => return Ok(None),
+        EnvMode::Clean => CLEAN_ENV,
+        EnvMode::Allowlist => &[],
+    };
+    let passthrough = globset(passthrough)?;
+    Ok(Some(
+        vars.into_iter()
+            .filter(|(key, _)| {
+                key.to_str()
+                    .is_some_and(|key| basic.contains(&key) || passthrough.is_match(key))
+            })
+            .collect(),
+    ))
+}
+
+/// Compile passthrough globs
+fn globset(patterns: &[String]) -> Result<GlobSet, TaskError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            TaskError::Config(format!("Invalid env_passthrough glob '{}': {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| TaskError::Config(format!("Invalid env_passthrough globs: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vec<(OsString, OsString)> {
+        ["PATH", "HOME", "AWS_SECRET_ACCESS_KEY", "AWS_REGION", "CI"]
+            .iter()
+            .map(|key| (key.into(), "x".into()))
+            .collect()
+    }
+
+    fn keys(env: Option<Vec<(OsString, OsString)>>) -> Vec<String> {
+        env.unwrap()
+            .into_iter()
+            .map(|(key, _)| key.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_base_env() {
+        let passthrough = vec!["AWS_REGION".to_string(), "C?".to_string()];
+        assert!(base_env(EnvMode::Inherit, &passthrough, vars())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            keys(base_env(EnvMode::Clean, &passthrough, vars()).unwrap()),
+            vec!["PATH", "HOME", "AWS_REGION", "CI"]
+        );
+        assert_eq!(
+            keys(base_env(EnvMode::Allowlist, &passthrough, vars()).unwrap()),
+            vec!["AWS_REGION", "CI"]
+        );
+        assert!(keys(base_env(EnvMode::Allowlist, &[], vars()).unwrap()).is_empty());
+
+        let err = base_env(EnvMode::Clean, &["[".to_string()], vars()).unwrap_err();
+        assert!(err.to_string().contains("env_passthrough"), "{}", err);
+    }
+}
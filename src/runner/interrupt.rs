@@ -0,0 +1,163 @@
+//! Interrupt forwarding for tasks
+//!
+//! A task runs in its own process group so that everything it spawns can
+//! be signalled, and killed on timeout, together. When stdin is a terminal the group is
+//! also made the terminal's foreground group, so Ctrl-C reaches the task
+//! directly, just like in a shell; the terminal is handed back once the
+//! task exits.
+//!
+//! SIGINT and SIGTERM delivered to makefilehub itself (e.g. by Ctrl-C for a
+//! captured task, or by a supervisor) are forwarded to the groups of the
+//! running tasks once [`install`] has been called. Without a running task
+//! they terminate makefilehub as usual.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Process groups of the tasks currently running (0 = free slot); rebuild
+/// steps run in parallel
+static FOREGROUND: [AtomicI32; 16] = [const { AtomicI32::new(0) }; 16];
+
+/// Serializes tests that register a foreground group
+#[cfg(test)]
+pub(crate) static TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Set once SIGINT or SIGTERM was received
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Forward SIGINT and SIGTERM to the foreground task from now on
+pub fn install() {
+    #[cfg(unix)]
+    {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // SAFETY: the handler only touches atomics and calls
+            // async-signal-safe functions
+            unsafe {
+                libc::signal(libc::SIGINT, handler);
+                libc::signal(libc::SIGTERM, handler);
+            }
+        });
+    }
+}
+
+/// Whether makefilehub received SIGINT or SIGTERM
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+extern "C" fn handle(signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    if !forward(signal) {
+        // SAFETY: async-signal-safe; restores the default action and
+        // terminates as if no handler had been installed
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+/// Send `signal` to the running tasks' process groups, returning whether
+/// there were any
+#[cfg(unix)]
+pub(crate) fn forward(signal: libc::c_int) -> bool {
+    let mut forwarded = false;
+    for slot in &FOREGROUND {
+        let group = slot.load(Ordering::SeqCst);
+        // SAFETY: kill has no memory safety requirements
+        forwarded |= group > 0 && unsafe { libc::kill(-group, signal) } == 0;
+    }
+    forwarded
+}
+
+/// Marks a process group as a running task until dropped
+pub(crate) struct Foreground {
+    /// Registered group, if a slot was free
+    group: Option<&'static AtomicI32>,
+    /// Whether the terminal was handed to the group and must be taken back
+    terminal: bool,
+}
+
+impl Foreground {
+    /// Register the group led by `pid`; `terminal` is set when the group was
+    /// made the terminal's foreground group
+    pub(crate) fn new(pid: u32, terminal: bool) -> Self {
+        let group = FOREGROUND.iter().find(|slot| {
+            slot.compare_exchange(0, pid as i32, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        });
+        if group.is_none() {
+            tracing::debug!("Too many running tasks to forward signals to {}", pid);
+        }
+        Self { group, terminal }
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        if let Some(group) = self.group {
+            group.store(0, Ordering::SeqCst);
+        }
+        #[cfg(unix)]
+        if self.terminal {
+            // SAFETY: plain syscalls on stdin; SIGTTOU is ignored while a
+            // background process takes the terminal back
+            unsafe {
+                let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                libc::signal(libc::SIGTTOU, previous);
+            }
+        }
+    }
+}
+
+/// Make the calling process's group the terminal's foreground group
+///
+/// Runs in the child between fork and exec, so it only uses
+/// async-signal-safe calls.
+#[cfg(unix)]
+pub(crate) fn take_terminal() -> std::io::Result<()> {
+    // SAFETY: async-signal-safe syscalls on stdin
+    unsafe {
+        let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+        libc::signal(libc::SIGTTOU, previous);
+    }
+    Ok(())
+}
+
+/// Kill the whole process group led by `pid`
+#[cfg(unix)]
+pub(crate) fn kill_group(pid: u32) {
+    // SAFETY: kill has no memory safety requirements
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::Command;
+
+    #[test]
+    fn test_forward_to_foreground_group() {
+        let Ok(mut child) = Command::new("sleep").arg("10").process_group(0).spawn() else {
+            eprintln!("Skipping test: sleep not available");
+            return;
+        };
+
+        let _lock = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        {
+            let _foreground = Foreground::new(child.id(), false);
+            assert!(forward(libc::SIGINT));
+        }
+        assert!(!forward(libc::SIGINT));
+
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGINT));
+    }
+}
@@ -24,6 +24,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Deserialize;
 
+use super::command::CommandSpec;
 use super::traits::{RunOptions, RunResult, Runner, RunnerResult, TaskArg, TaskInfo};
 use crate::error::{suggest_fix, TaskError};
 
@@ -321,47 +322,19 @@ impl JustfileRunner {
     ) -> RunnerResult<RunResult> {
         let start = Instant::now();
 
-        let mut cmd = Command::new(&self.just_command);
-        cmd.current_dir(dir);
-        cmd.arg(task);
-
-        // Add named arguments (just uses positional or --arg=value syntax)
-        // For simplicity, we'll pass them as positional: key=value
-        for (key, value) in &options.args {
-            cmd.arg(format!("{}={}", key, value));
-        }
-
-        // Add positional arguments
-        for arg in &options.positional_args {
-            cmd.arg(arg);
-        }
-
-        // Set environment variables
-        for (key, value) in &options.env {
-            cmd.env(key, value);
-        }
-
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let command_str = self.build_command(task, options);
-
-        tracing::debug!("Executing: {}", command_str);
-
-        let output = cmd.output().map_err(|e| TaskError::SpawnFailed {
-            command: command_str.clone(),
-            error: e.to_string(),
-        })?;
+        let command = self.build_command(dir, task, options);
+        let output = command.execute(options)?;
 
         let duration_ms = start.elapsed().as_millis() as u64;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let stderr = &output.stderr.text;
 
         if output.status.success() {
-            Ok(RunResult::success(command_str, stdout, duration_ms))
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
+                duration_ms,
+            ))
         } else {
-            let exit_code = output.status.code();
-
             // Check if recipe exists
             if stderr.contains("Justfile does not contain recipe")
                 || stderr.contains("Just was unable to find")
@@ -374,15 +347,13 @@ impl JustfileRunner {
                 return Err(TaskError::TaskNotFound {
                     task: task.to_string(),
                     available: available_names,
-                    suggestion: suggest_fix(&command_str, &stderr),
+                    suggestion: suggest_fix(&command.to_string(), stderr),
                 });
             }
 
-            Ok(RunResult::failed(
-                command_str,
-                exit_code,
-                stdout,
-                stderr,
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
                 duration_ms,
             ))
         }
@@ -432,20 +403,22 @@ impl Runner for JustfileRunner {
         self.execute_just(dir, task, options)
     }
 
-    fn build_command(&self, task: &str, options: &RunOptions) -> String {
-        let mut parts = vec![self.just_command.clone(), task.to_string()];
+    fn build_command(&self, dir: &Path, task: &str, options: &RunOptions) -> CommandSpec {
+        let mut command = CommandSpec::new(&self.just_command)
+            .arg(task)
+            .current_dir(dir)
+            .envs(options.env.clone());
 
-        // Add named arguments as key=value
-        for (key, value) in &options.args {
-            parts.push(format!("{}={}", key, value));
+        // Add named arguments (just uses positional or --arg=value syntax)
+        // For simplicity, we'll pass them as positional: key=value
+        let mut named: Vec<_> = options.args.iter().collect();
+        named.sort();
+        for (key, value) in named {
+            command = command.arg(format!("{}={}", key, value));
         }
 
         // Add positional arguments
-        for arg in &options.positional_args {
-            parts.push(arg.clone());
-        }
-
-        parts.join(" ")
+        command.args(options.positional_args.clone())
     }
 }
 
@@ -686,8 +659,9 @@ _helper:
         let runner = JustfileRunner::new();
         let options = RunOptions::default();
 
-        let cmd = runner.build_command("build", &options);
-        assert_eq!(cmd, "just build");
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.to_string(), "just build");
+        assert_eq!(cmd.cwd, Some(std::path::PathBuf::from("/project")));
     }
 
     #[test]
@@ -697,10 +671,8 @@ _helper:
             .with_arg("target", "debug")
             .with_positional("extra");
 
-        let cmd = runner.build_command("build", &options);
-        assert!(cmd.contains("just build"));
-        assert!(cmd.contains("target=debug"));
-        assert!(cmd.contains("extra"));
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.args, vec!["build", "target=debug", "extra"]);
     }
 
     #[test]
@@ -708,8 +680,8 @@ _helper:
         let runner = JustfileRunner::with_command("/usr/local/bin/just");
         let options = RunOptions::default();
 
-        let cmd = runner.build_command("build", &options);
-        assert!(cmd.starts_with("/usr/local/bin/just"));
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.program, "/usr/local/bin/just");
     }
 
     #[test]
@@ -20,6 +20,7 @@ use std::time::Instant;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use super::command::CommandSpec;
 use super::traits::{RunOptions, RunResult, Runner, RunnerResult, TaskArg, TaskInfo};
 use crate::error::{suggest_fix, TaskError};
 
@@ -259,49 +260,19 @@ impl MakefileRunner {
     ) -> RunnerResult<RunResult> {
         let start = Instant::now();
 
-        let mut cmd = Command::new(&self.make_command);
-        cmd.current_dir(dir);
-        cmd.arg(task);
-
-        // Add named arguments as VAR=value
-        for (key, value) in &options.args {
-            cmd.arg(format!("{}={}", key, value));
-        }
-
-        // Add positional arguments after --
-        if !options.positional_args.is_empty() {
-            cmd.arg("--");
-            for arg in &options.positional_args {
-                cmd.arg(arg);
-            }
-        }
-
-        // Set environment variables
-        for (key, value) in &options.env {
-            cmd.env(key, value);
-        }
-
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let command_str = self.build_command(task, options);
-
-        tracing::debug!("Executing: {}", command_str);
-
-        let output = cmd.output().map_err(|e| TaskError::SpawnFailed {
-            command: command_str.clone(),
-            error: e.to_string(),
-        })?;
+        let command = self.build_command(dir, task, options);
+        let output = command.execute(options)?;
 
         let duration_ms = start.elapsed().as_millis() as u64;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let stderr = &output.stderr.text;
 
         if output.status.success() {
-            Ok(RunResult::success(command_str, stdout, duration_ms))
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
+                duration_ms,
+            ))
         } else {
-            let exit_code = output.status.code();
-
             // Check if task exists to provide better error
             if stderr.contains("No rule to make target") {
                 let available = self.list_tasks(dir).unwrap_or_default();
@@ -311,15 +282,13 @@ impl MakefileRunner {
                 return Err(TaskError::TaskNotFound {
                     task: task.to_string(),
                     available: available_names,
-                    suggestion: suggest_fix(&command_str, &stderr),
+                    suggestion: suggest_fix(&command.to_string(), stderr),
                 });
             }
 
-            Ok(RunResult::failed(
-                command_str.clone(),
-                exit_code,
-                stdout,
-                stderr.clone(),
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
                 duration_ms,
             ))
         }
@@ -366,23 +335,25 @@ impl Runner for MakefileRunner {
         self.execute_make(dir, task, options)
     }
 
-    fn build_command(&self, task: &str, options: &RunOptions) -> String {
-        let mut parts = vec![self.make_command.clone(), task.to_string()];
+    fn build_command(&self, dir: &Path, task: &str, options: &RunOptions) -> CommandSpec {
+        let mut command = CommandSpec::new(&self.make_command)
+            .arg(task)
+            .current_dir(dir)
+            .envs(options.env.clone());
 
-        // Add named arguments
-        for (key, value) in &options.args {
-            parts.push(format!("{}={}", key, value));
+        // Add named arguments as VAR=value (sorted for a deterministic argv)
+        let mut named: Vec<_> = options.args.iter().collect();
+        named.sort();
+        for (key, value) in named {
+            command = command.arg(format!("{}={}", key, value));
         }
 
-        // Add positional arguments
+        // Add positional arguments after --
         if !options.positional_args.is_empty() {
-            parts.push("--".to_string());
-            for arg in &options.positional_args {
-                parts.push(arg.clone());
-            }
+            command = command.arg("--").args(options.positional_args.clone());
         }
 
-        parts.join(" ")
+        command
     }
 }
 
@@ -604,8 +575,11 @@ build:
         let runner = MakefileRunner::new();
         let options = RunOptions::default();
 
-        let cmd = runner.build_command("build", &options);
-        assert_eq!(cmd, "make build");
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.program, "make");
+        assert_eq!(cmd.args, vec!["build"]);
+        assert_eq!(cmd.cwd, Some(std::path::PathBuf::from("/project")));
+        assert_eq!(cmd.to_string(), "make build");
     }
 
     #[test]
@@ -617,10 +591,9 @@ build:
             .insert("TARGET".to_string(), "release".to_string());
         options.args.insert("VERBOSE".to_string(), "1".to_string());
 
-        let cmd = runner.build_command("build", &options);
-        assert!(cmd.contains("make build"));
-        assert!(cmd.contains("TARGET=release"));
-        assert!(cmd.contains("VERBOSE=1"));
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        // Named arguments are sorted by key
+        assert_eq!(cmd.args, vec!["build", "TARGET=release", "VERBOSE=1"]);
     }
 
     #[test]
@@ -630,8 +603,8 @@ build:
             .with_positional("arg1")
             .with_positional("arg2");
 
-        let cmd = runner.build_command("test", &options);
-        assert!(cmd.contains("make test -- arg1 arg2"));
+        let cmd = runner.build_command(Path::new("/project"), "test", &options);
+        assert_eq!(cmd.to_string(), "make test -- arg1 arg2");
     }
 
     #[test]
@@ -639,8 +612,22 @@ build:
         let runner = MakefileRunner::with_command("gmake");
         let options = RunOptions::default();
 
-        let cmd = runner.build_command("build", &options);
-        assert!(cmd.starts_with("gmake"));
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.program, "gmake");
+    }
+
+    #[test]
+    fn test_build_command_quotes_values_with_spaces() {
+        let runner = MakefileRunner::new();
+        let options = RunOptions::default()
+            .with_arg("MSG", "hello world")
+            .with_env("DEBUG", "1");
+
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        // The value stays a single argv entry and is quoted for display
+        assert_eq!(cmd.args, vec!["build", "MSG=hello world"]);
+        assert_eq!(cmd.to_string(), "make build 'MSG=hello world'");
+        assert_eq!(cmd.env.get("DEBUG"), Some(&"1".to_string()));
     }
 
     #[test]
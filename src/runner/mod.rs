@@ -4,15 +4,29 @@
 //! - Makefile (make)
 //! - justfile (just)
 //! - Custom scripts (run.sh, build.sh, etc.)
+//!
+//! Tasks normally run with their output captured; `interrupt` handles the
+//! process groups and signals of tasks streamed to the terminal, `pty` runs
+//! tasks that need a terminal in a pseudo-terminal, `sandbox` applies
+//! resource limits and the namespace sandbox, and `env` decides which of
+//! our environment variables tasks start with.
 
+pub mod command;
 pub mod detect;
+pub mod env;
+pub mod interrupt;
 pub mod justfile;
 pub mod makefile;
+pub mod pty;
+#[cfg(unix)]
+pub(crate) mod sandbox;
 pub mod script;
 pub mod traits;
 
+pub use command::{shell_quote, CapturedOutput, CommandSpec};
 pub use detect::*;
 pub use justfile::JustfileRunner;
 pub use makefile::MakefileRunner;
+pub use pty::PtySize;
 pub use script::ScriptRunner;
 pub use traits::*;
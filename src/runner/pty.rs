@@ -0,0 +1,218 @@
+//! Pseudo-terminal execution
+//!
+//! Some tools (interactive scripts, `docker compose` with a TTY, progress
+//! bars) change their behavior or hang when their output is a pipe. In PTY
+//! mode the task gets the slave side of a pseudo-terminal of a fixed size as
+//! stdin, stdout, stderr and controlling terminal, in a session of its own.
+//! Everything it writes arrives merged on the master side, which is what
+//! gets captured.
+//!
+//! Output post-processing (`ONLCR`) is turned off, so lines end in `\n`
+//! rather than `\r\n`. Nothing is written to the task's input.
+
+use crate::config::PtyConfig;
+
+/// Terminal size of a pseudo-terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtySize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl PtySize {
+    /// Size to run a task with: `requested` overrides `configured` (whether
+    /// the `[pty]` settings apply to the task); `None` means pipes
+    pub fn select(config: &PtyConfig, configured: bool, requested: Option<bool>) -> Option<Self> {
+        requested.unwrap_or(configured).then_some(Self {
+            cols: config.cols,
+            rows: config.rows,
+        })
+    }
+}
+
+#[cfg(unix)]
+pub(crate) use unix::{controlling_terminal, MasterReader, Pty};
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    use super::PtySize;
+
+    /// An open pseudo-terminal pair
+    pub(crate) struct Pty {
+        pub(crate) master: File,
+        pub(crate) slave: File,
+    }
+
+    impl Pty {
+        /// Open a pseudo-terminal of `size`
+        pub(crate) fn open(size: PtySize) -> io::Result<Self> {
+            // SAFETY: posix_openpt returns a new descriptor that File takes
+            // ownership of
+            let master = unsafe {
+                let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                File::from_raw_fd(fd)
+            };
+            let fd = master.as_raw_fd();
+            // SAFETY: plain calls on a descriptor we own
+            unsafe {
+                if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) != 0
+                    || libc::grantpt(fd) != 0
+                    || libc::unlockpt(fd) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            let slave = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_NOCTTY)
+                .open(slave_name(&master)?)?;
+
+            // SAFETY: termios and winsize are plain structs filled in or read
+            // by the calls
+            unsafe {
+                let mut termios: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(slave.as_raw_fd(), &mut termios) == 0 {
+                    termios.c_oflag &= !libc::ONLCR;
+                    libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios);
+                }
+                let winsize = libc::winsize {
+                    ws_row: size.rows,
+                    ws_col: size.cols,
+                    ws_xpixel: 0,
+                    ws_ypixel: 0,
+                };
+                if libc::ioctl(fd, libc::TIOCSWINSZ, &winsize) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            Ok(Self { master, slave })
+        }
+    }
+
+    /// Path of the slave side of `master`
+    #[cfg(target_os = "linux")]
+    fn slave_name(master: &File) -> io::Result<String> {
+        let mut buf = [0 as libc::c_char; 128];
+        // SAFETY: ptsname_r writes a NUL-terminated name into buf
+        unsafe {
+            let rc = libc::ptsname_r(master.as_raw_fd(), buf.as_mut_ptr(), buf.len());
+            if rc != 0 {
+                return Err(io::Error::from_raw_os_error(rc));
+            }
+            Ok(std::ffi::CStr::from_ptr(buf.as_ptr())
+                .to_string_lossy()
+                .into_owned())
+        }
+    }
+
+    /// Path of the slave side of `master`
+    #[cfg(not(target_os = "linux"))]
+    fn slave_name(master: &File) -> io::Result<String> {
+        // ptsname uses a static buffer
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // SAFETY: the returned string is copied while holding the lock
+        unsafe {
+            let name = libc::ptsname(master.as_raw_fd());
+            if name.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned())
+        }
+    }
+
+    /// Start a new session with stdin (the slave) as its controlling terminal
+    ///
+    /// Runs in the child between fork and exec, so it only uses
+    /// async-signal-safe calls.
+    pub(crate) fn controlling_terminal() -> io::Result<()> {
+        // SAFETY: async-signal-safe syscalls
+        unsafe {
+            if libc::setsid() < 0 || libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the master side, treating the hangup once every slave
+    /// descriptor is closed as the end of the output
+    pub(crate) struct MasterReader(pub(crate) File);
+
+    impl Read for MasterReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf) {
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+                other => other,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_select() {
+        let config = PtyConfig::default();
+        assert_eq!(PtySize::select(&config, false, None), None);
+        assert_eq!(
+            PtySize::select(&config, true, None),
+            Some(PtySize {
+                cols: 120,
+                rows: 40
+            })
+        );
+        assert_eq!(PtySize::select(&config, true, Some(false)), None);
+        assert!(PtySize::select(&config, false, Some(true)).is_some());
+    }
+
+    #[test]
+    fn test_child_sees_terminal_of_size() {
+        let pty = match Pty::open(PtySize { cols: 91, rows: 17 }) {
+            Ok(pty) => pty,
+            Err(e) => {
+                eprintln!("Skipping test: no pseudo-terminals: {}", e);
+                return;
+            }
+        };
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("test -t 0 && test -t 1 && stty size")
+            .stdin(Stdio::from(pty.slave.try_clone().unwrap()))
+            .stdout(Stdio::from(pty.slave.try_clone().unwrap()))
+            .stderr(Stdio::from(pty.slave));
+        // SAFETY: controlling_terminal only makes async-signal-safe calls
+        unsafe { cmd.pre_exec(controlling_terminal) };
+        let Ok(mut child) = cmd.spawn() else {
+            eprintln!("Skipping test: sh not available");
+            return;
+        };
+        drop(cmd);
+
+        let mut output = String::new();
+        MasterReader(pty.master)
+            .read_to_string(&mut output)
+            .unwrap();
+        assert!(child.wait().unwrap().success(), "{}", output);
+        assert_eq!(output, "17 91\n");
+    }
+}
@@ -0,0 +1,409 @@
+//! Resource limits and namespace sandbox for task processes
+//!
+//! Both are applied in the child between fork and exec. Everything that
+//! needs allocating (paths, uid maps) is prepared in the parent by
+//! [`Confinement::prepare`]; [`Confinement::apply`] then only makes
+//! async-signal-safe calls.
+//!
+//! The sandbox (Linux only) enters new user and mount namespaces, and a new
+//! network namespace unless network access is kept:
+//! 1. the user's uid and gid are mapped to themselves
+//! 2. the project directory and the writable paths are cloned as detached
+//!    mount trees (`open_tree`)
+//! 3. every mount is made read-only (`mount_setattr`)
+//! 4. the clones are moved back over their paths (`move_mount`), which are
+//!    therefore the only writable places
+//! 5. loopback is brought up in the new network namespace
+
+use std::ffi::CString;
+use std::io;
+use std::path::Path;
+
+use crate::config::{ResourceLimits, SandboxConfig};
+
+/// Most paths that can be kept writable in the sandbox
+#[cfg(target_os = "linux")]
+const MAX_WRITABLE: usize = 32;
+
+/// Limits and sandbox prepared for one process
+#[derive(Debug)]
+pub(crate) struct Confinement {
+    limits: ResourceLimits,
+    #[cfg(target_os = "linux")]
+    sandbox: Option<linux::Sandbox>,
+}
+
+impl Confinement {
+    /// Prepare `limits` and, if given, the sandbox for a process working in
+    /// `project`; `None` if there is nothing to apply
+    ///
+    /// # Errors
+    /// * If a sandbox is requested on a platform without namespaces, or
+    ///   with more than 32 writable paths
+    pub(crate) fn prepare(
+        limits: &ResourceLimits,
+        sandbox: Option<&SandboxConfig>,
+        project: Option<&Path>,
+    ) -> io::Result<Option<Self>> {
+        if limits.is_empty() && sandbox.is_none() {
+            return Ok(None);
+        }
+
+        #[cfg(target_os = "linux")]
+        return Ok(Some(Self {
+            limits: *limits,
+            sandbox: sandbox
+                .map(|sandbox| linux::Sandbox::prepare(sandbox, project))
+                .transpose()?,
+        }));
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = project;
+            if sandbox.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the sandbox requires Linux namespaces",
+                ));
+            }
+            Ok(Some(Self { limits: *limits }))
+        }
+    }
+
+    /// Enter the sandbox and apply the limits to the calling process
+    ///
+    /// Runs in the child between fork and exec.
+    pub(crate) fn apply(&self) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.enter()?;
+        }
+
+        let limits = &self.limits;
+        set_limit(libc::RLIMIT_CPU as _, limits.cpu_secs)?;
+        set_limit(
+            libc::RLIMIT_AS as _,
+            limits
+                .address_space_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+        )?;
+        set_limit(libc::RLIMIT_NOFILE as _, limits.open_files)?;
+        set_limit(libc::RLIMIT_NPROC as _, limits.max_processes)?;
+
+        if let Some(nice) = limits.nice {
+            // SAFETY: setpriority has no memory safety requirements
+            check(unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) })?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(class) = limits.ionice {
+            linux::set_io_priority(class, limits.ionice_level.unwrap_or(4))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Lower both the soft and hard limit of `resource` to `value`, capped to
+/// the current hard limit
+fn set_limit(resource: i32, value: Option<u64>) -> io::Result<()> {
+    let Some(value) = value else {
+        return Ok(());
+    };
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit and setrlimit only access the rlimit passed in
+    unsafe {
+        check(libc::getrlimit(resource as _, &mut limit))?;
+        let value = (value as libc::rlim_t).min(limit.rlim_max);
+        limit.rlim_cur = value;
+        limit.rlim_max = value;
+        check(libc::setrlimit(resource as _, &limit))
+    }
+}
+
+/// Turn a -1 return value into the `errno` error
+fn check<T: PartialEq + From<i8>>(ret: T) -> io::Result<()> {
+    if ret == T::from(-1) {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use crate::config::IoClass;
+
+    /// IOPRIO_WHO_PROCESS
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    /// Shift of the class in an I/O priority
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    /// Set the I/O scheduling class and level of the calling process
+    pub(super) fn set_io_priority(class: IoClass, level: u8) -> io::Result<()> {
+        let (class, level) = match class {
+            IoClass::BestEffort => (2, level.min(7) as libc::c_int),
+            IoClass::Idle => (3, 0),
+        };
+        // SAFETY: ioprio_set takes plain integers
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                (class << IOPRIO_CLASS_SHIFT) | level,
+            )
+        };
+        check(ret)
+    }
+
+    /// A sandbox prepared in the parent
+    #[derive(Debug)]
+    pub(super) struct Sandbox {
+        flags: libc::c_int,
+        uid_map: CString,
+        gid_map: CString,
+        /// Project directory followed by the other writable paths, all
+        /// existing and canonical
+        writable: Vec<CString>,
+        cwd: Option<CString>,
+    }
+
+    impl Sandbox {
+        pub(super) fn prepare(config: &SandboxConfig, project: Option<&Path>) -> io::Result<Self> {
+            let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+            if !config.network {
+                flags |= libc::CLONE_NEWNET;
+            }
+            // SAFETY: getuid and getgid cannot fail
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+            let mut writable: Vec<CString> = Vec::new();
+            let paths = project
+                .into_iter()
+                .chain(config.writable.iter().map(Path::new));
+            for path in paths {
+                // Missing paths are skipped; symlinks are resolved so the
+                // mount lands on the directory itself
+                let Ok(path) = path.canonicalize() else {
+                    continue;
+                };
+                let path = c_path(&path)?;
+                if !writable.contains(&path) {
+                    writable.push(path);
+                }
+            }
+            if writable.len() > MAX_WRITABLE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("at most {} writable sandbox paths", MAX_WRITABLE),
+                ));
+            }
+
+            Ok(Self {
+                flags,
+                uid_map: CString::new(format!("{} {} 1\n", uid, uid))?,
+                gid_map: CString::new(format!("{} {} 1\n", gid, gid))?,
+                writable,
+                cwd: project.map(c_path).transpose()?,
+            })
+        }
+
+        /// Enter the namespaces and set up the mounts; runs in the child
+        pub(super) fn enter(&self) -> io::Result<()> {
+            // SAFETY: only async-signal-safe syscalls on NUL-terminated
+            // strings prepared by the parent
+            unsafe {
+                check(libc::unshare(self.flags))?;
+                write_file(c"/proc/self/setgroups", c"deny")?;
+                write_file(c"/proc/self/uid_map", &self.uid_map)?;
+                write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+                // Keep our mount changes from propagating to the host
+                check(libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                ))?;
+
+                let mut trees = [-1 as libc::c_int; MAX_WRITABLE];
+                for (tree, path) in trees.iter_mut().zip(&self.writable) {
+                    let fd = libc::syscall(
+                        libc::SYS_open_tree,
+                        libc::AT_FDCWD,
+                        path.as_ptr(),
+                        libc::OPEN_TREE_CLONE | libc::AT_RECURSIVE as libc::c_uint,
+                    );
+                    check(fd)?;
+                    *tree = fd as libc::c_int;
+                }
+
+                let attr = libc::mount_attr {
+                    attr_set: libc::MOUNT_ATTR_RDONLY,
+                    attr_clr: 0,
+                    propagation: 0,
+                    userns_fd: 0,
+                };
+                check(libc::syscall(
+                    libc::SYS_mount_setattr,
+                    libc::AT_FDCWD,
+                    c"/".as_ptr(),
+                    libc::AT_RECURSIVE,
+                    &attr as *const libc::mount_attr,
+                    std::mem::size_of::<libc::mount_attr>(),
+                ))?;
+
+                for (&tree, path) in trees.iter().zip(&self.writable) {
+                    let moved = libc::syscall(
+                        libc::SYS_move_mount,
+                        tree,
+                        c"".as_ptr(),
+                        libc::AT_FDCWD,
+                        path.as_ptr(),
+                        libc::MOVE_MOUNT_F_EMPTY_PATH,
+                    );
+                    libc::close(tree);
+                    check(moved)?;
+                }
+
+                // The working directory still points into the read-only mount
+                if let Some(cwd) = &self.cwd {
+                    check(libc::chdir(cwd.as_ptr()))?;
+                }
+
+                if self.flags & libc::CLONE_NEWNET != 0 {
+                    loopback_up()?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        use std::os::unix::ffi::OsStrExt;
+        Ok(CString::new(path.as_os_str().as_bytes())?)
+    }
+
+    /// Write `contents` to the existing file `path`
+    unsafe fn write_file(path: &std::ffi::CStr, contents: &std::ffi::CStr) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        check(fd)?;
+        let bytes = contents.to_bytes();
+        let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+        libc::close(fd);
+        if written != bytes.len() as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Bring up the loopback interface of the current network namespace
+    unsafe fn loopback_up() -> io::Result<()> {
+        let sock = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+        check(sock)?;
+        let mut request: libc::ifreq = std::mem::zeroed();
+        for (dst, &src) in request.ifr_name.iter_mut().zip(b"lo") {
+            *dst = src as libc::c_char;
+        }
+        let mut result = check(libc::ioctl(sock, libc::SIOCGIFFLAGS as _, &mut request));
+        if result.is_ok() {
+            request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+            result = check(libc::ioctl(sock, libc::SIOCSIFFLAGS as _, &request));
+        }
+        libc::close(sock);
+        result
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    /// Output of `sh -c script` run under `confinement`, or `None` if the
+    /// platform doesn't allow it
+    fn run(confinement: Confinement, dir: &Path, script: &str) -> Option<String> {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script).current_dir(dir);
+        // SAFETY: apply only makes async-signal-safe calls
+        unsafe { cmd.pre_exec(move || confinement.apply()) };
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
+                Some(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+            Ok(output) => panic!("{}", String::from_utf8_lossy(&output.stderr)),
+            Err(e) => {
+                eprintln!("Skipping test: {}", e);
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_nothing_to_apply() {
+        let prepared = Confinement::prepare(&ResourceLimits::default(), None, None).unwrap();
+        assert!(prepared.is_none());
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ResourceLimits {
+            open_files: Some(64),
+            cpu_secs: Some(30),
+            nice: Some(5),
+            ..Default::default()
+        };
+        let confinement = Confinement::prepare(&limits, None, None).unwrap().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        if let Some(output) = run(confinement, dir.path(), "ulimit -n; ulimit -t; nice") {
+            assert_eq!(output, "64\n30\n5\n");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sandbox_read_only_outside_project() {
+        let project = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new_in(project.path().parent().unwrap()).unwrap();
+        let sandbox = SandboxConfig {
+            enabled: true,
+            network: false,
+            writable: vec![],
+        };
+        let confinement = Confinement::prepare(
+            &ResourceLimits::default(),
+            Some(&sandbox),
+            Some(project.path()),
+        )
+        .unwrap()
+        .unwrap();
+
+        // Namespaces may be unavailable (e.g. in containers)
+        let mut cmd = Command::new("unshare");
+        if !cmd
+            .args(["-Ur", "true"])
+            .status()
+            .is_ok_and(|s| s.success())
+        {
+            eprintln!("Skipping test: user namespaces not available");
+            return;
+        }
+
+        let script = format!(
+            "touch inside && ! touch '{}/outside' 2>/dev/null && echo done",
+            outside.path().display()
+        );
+        if let Some(output) = run(confinement, project.path(), &script) {
+            assert_eq!(output, "done\n");
+            assert!(project.path().join("inside").exists());
+            assert!(!outside.path().join("outside").exists());
+        }
+    }
+}
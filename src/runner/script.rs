@@ -20,6 +20,7 @@ use std::time::Instant;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use super::command::CommandSpec;
 use super::traits::{RunOptions, RunResult, Runner, RunnerResult, TaskInfo};
 use crate::error::{suggest_fix, TaskError};
 
@@ -305,60 +306,28 @@ impl ScriptRunner {
         task: &str,
         options: &RunOptions,
     ) -> RunnerResult<RunResult> {
-        let script_path = self
-            .find_script(dir)
-            .ok_or_else(|| TaskError::NoRunnerDetected {
+        if self.find_script(dir).is_none() {
+            return Err(TaskError::NoRunnerDetected {
                 path: dir.display().to_string(),
                 available: vec![],
-            })?;
-
-        let start = Instant::now();
-
-        let mut cmd = Command::new(&self.shell);
-        cmd.current_dir(dir);
-        cmd.arg(&script_path);
-        cmd.arg(task);
-
-        // Add positional arguments first
-        for arg in &options.positional_args {
-            cmd.arg(arg);
-        }
-
-        // Add named arguments as --key value or --key=value
-        for (key, value) in &options.args {
-            if value.is_empty() {
-                cmd.arg(format!("--{}", key));
-            } else {
-                cmd.arg(format!("--{}={}", key, value));
-            }
-        }
-
-        // Set environment variables
-        for (key, value) in &options.env {
-            cmd.env(key, value);
+            });
         }
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let command_str = self.build_command(task, options);
-
-        tracing::debug!("Executing: {}", command_str);
+        let start = Instant::now();
 
-        let output = cmd.output().map_err(|e| TaskError::SpawnFailed {
-            command: command_str.clone(),
-            error: e.to_string(),
-        })?;
+        let command = self.build_command(dir, task, options);
+        let output = command.execute(options)?;
 
         let duration_ms = start.elapsed().as_millis() as u64;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let stderr = &output.stderr.text;
 
         if output.status.success() {
-            Ok(RunResult::success(command_str, stdout, duration_ms))
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
+                duration_ms,
+            ))
         } else {
-            let exit_code = output.status.code();
-
             // Check for common error patterns
             if stderr.contains("Unknown command")
                 || stderr.contains("not a valid command")
@@ -372,15 +341,13 @@ impl ScriptRunner {
                 return Err(TaskError::TaskNotFound {
                     task: task.to_string(),
                     available: available_names,
-                    suggestion: suggest_fix(&command_str, &stderr),
+                    suggestion: suggest_fix(&command.to_string(), stderr),
                 });
             }
 
-            Ok(RunResult::failed(
-                command_str,
-                exit_code,
-                stdout,
-                stderr,
+            Ok(RunResult::captured(
+                command.redacted(&options.output),
+                output,
                 duration_ms,
             ))
         }
@@ -428,24 +395,32 @@ impl Runner for ScriptRunner {
         self.execute_script(dir, task, options)
     }
 
-    fn build_command(&self, task: &str, options: &RunOptions) -> String {
-        let mut parts = vec![self.script_name.clone(), task.to_string()];
+    fn build_command(&self, dir: &Path, task: &str, options: &RunOptions) -> CommandSpec {
+        let script_name = self
+            .script_name
+            .strip_prefix("./")
+            .unwrap_or(&self.script_name);
 
-        // Add positional arguments
-        for arg in &options.positional_args {
-            parts.push(arg.clone());
-        }
+        // Positional arguments come first, right after the task
+        let mut command = CommandSpec::new(&self.shell)
+            .arg(dir.join(script_name).to_string_lossy())
+            .arg(task)
+            .args(options.positional_args.clone())
+            .current_dir(dir)
+            .envs(options.env.clone());
 
-        // Add named arguments
-        for (key, value) in &options.args {
+        // Add named arguments as --key or --key=value (sorted for a deterministic argv)
+        let mut named: Vec<_> = options.args.iter().collect();
+        named.sort();
+        for (key, value) in named {
             if value.is_empty() {
-                parts.push(format!("--{}", key));
+                command = command.arg(format!("--{}", key));
             } else {
-                parts.push(format!("--{}={}", key, value));
+                command = command.arg(format!("--{}={}", key, value));
             }
         }
 
-        parts.join(" ")
+        command
     }
 }
 
@@ -676,8 +651,10 @@ _setup() {
         let runner = ScriptRunner::new("./run.sh");
         let options = RunOptions::default();
 
-        let cmd = runner.build_command("build", &options);
-        assert_eq!(cmd, "./run.sh build");
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(cmd.program, "bash");
+        assert_eq!(cmd.args, vec!["/project/run.sh", "build"]);
+        assert_eq!(cmd.to_string(), "bash /project/run.sh build");
     }
 
     #[test]
@@ -687,10 +664,11 @@ _setup() {
             .with_positional("arg1")
             .with_arg("verbose", "true");
 
-        let cmd = runner.build_command("build", &options);
-        assert!(cmd.contains("./run.sh build"));
-        assert!(cmd.contains("arg1"));
-        assert!(cmd.contains("--verbose=true"));
+        let cmd = runner.build_command(Path::new("/project"), "build", &options);
+        assert_eq!(
+            cmd.args,
+            vec!["/project/run.sh", "build", "arg1", "--verbose=true"]
+        );
     }
 
     #[test]
@@ -698,9 +676,9 @@ _setup() {
         let runner = ScriptRunner::new("./run.sh");
         let options = RunOptions::default().with_arg("verbose", "");
 
-        let cmd = runner.build_command("test", &options);
-        assert!(cmd.contains("--verbose"));
-        assert!(!cmd.contains("="));
+        let cmd = runner.build_command(Path::new("/project"), "test", &options);
+        assert_eq!(cmd.args.last(), Some(&"--verbose".to_string()));
+        assert!(!cmd.to_string().contains('='));
     }
 
     #[test]
@@ -7,7 +7,11 @@ use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
 
+use super::command::{CapturedOutput, CommandSpec};
+use super::pty::PtySize;
+use crate::config::{EnvMode, ResourceLimits, SandboxConfig};
 use crate::error::TaskError;
+use crate::output::{OutputLimits, Truncation};
 
 /// Result type for runner operations
 pub type RunnerResult<T> = Result<T, TaskError>;
@@ -64,7 +68,7 @@ impl TaskInfo {
 }
 
 /// Options for running a task
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct RunOptions {
     /// Working directory (defaults to current directory)
     pub working_dir: Option<std::path::PathBuf>,
@@ -76,8 +80,39 @@ pub struct RunOptions {
     pub env: HashMap<String, String>,
     /// Timeout for the command
     pub timeout: Option<Duration>,
-    /// Capture output instead of streaming
+    /// Capture output instead of streaming it to the terminal
     pub capture_output: bool,
+    /// How much captured output to keep
+    pub output: OutputLimits,
+    /// Run in a pseudo-terminal of this size, with stdout and stderr merged
+    pub pty: Option<PtySize>,
+    /// Resource limits applied to the task's process
+    pub resources: ResourceLimits,
+    /// Run in this namespace sandbox
+    pub sandbox: Option<SandboxConfig>,
+    /// Environment the task starts from, before `env` is applied
+    pub env_mode: EnvMode,
+    /// Globs on variable names passed through in `clean` and `allowlist` mode
+    pub env_passthrough: Vec<String>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            working_dir: None,
+            args: HashMap::new(),
+            positional_args: Vec::new(),
+            env: HashMap::new(),
+            timeout: None,
+            capture_output: true,
+            output: OutputLimits::default(),
+            pty: None,
+            resources: ResourceLimits::default(),
+            sandbox: None,
+            env_mode: EnvMode::Inherit,
+            env_passthrough: Vec::new(),
+        }
+    }
 }
 
 impl RunOptions {
@@ -112,6 +147,21 @@ impl RunOptions {
         self.env.insert(key.into(), value.into());
         self
     }
+
+    /// Apply resource limits, and the sandbox if it is enabled
+    pub fn confined(mut self, resources: &ResourceLimits, sandbox: &SandboxConfig) -> Self {
+        self.resources = *resources;
+        self.sandbox = sandbox.enabled.then(|| sandbox.clone());
+        self
+    }
+
+    /// Start the task from the environment `mode` gives, passing through the
+    /// variables matching `passthrough`
+    pub fn isolated(mut self, mode: EnvMode, passthrough: &[String]) -> Self {
+        self.env_mode = mode;
+        self.env_passthrough = passthrough.to_vec();
+        self
+    }
 }
 
 /// Result of running a task
@@ -127,31 +177,38 @@ pub struct RunResult {
     /// Standard error
     pub stderr: String,
     /// Command that was executed
-    pub command: String,
+    pub command: CommandSpec,
     /// Duration in milliseconds
     pub duration_ms: u64,
+    /// Whether the result was served from the task cache instead of running
+    pub cached: bool,
+    /// What was left out of stdout, if it was truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout_truncation: Option<Truncation>,
+    /// What was left out of stderr, if it was truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr_truncation: Option<Truncation>,
 }
 
 impl RunResult {
     /// Create a successful result
-    pub fn success(
-        command: impl Into<String>,
-        stdout: impl Into<String>,
-        duration_ms: u64,
-    ) -> Self {
+    pub fn success(command: CommandSpec, stdout: impl Into<String>, duration_ms: u64) -> Self {
         Self {
             success: true,
             exit_code: Some(0),
             stdout: stdout.into(),
             stderr: String::new(),
-            command: command.into(),
+            command,
             duration_ms,
+            cached: false,
+            stdout_truncation: None,
+            stderr_truncation: None,
         }
     }
 
     /// Create a failed result
     pub fn failed(
-        command: impl Into<String>,
+        command: CommandSpec,
         exit_code: Option<i32>,
         stdout: impl Into<String>,
         stderr: impl Into<String>,
@@ -162,10 +219,61 @@ impl RunResult {
             exit_code,
             stdout: stdout.into(),
             stderr: stderr.into(),
-            command: command.into(),
+            command,
             duration_ms,
+            cached: false,
+            stdout_truncation: None,
+            stderr_truncation: None,
         }
     }
+
+    /// Create a result from captured output, successful if the command exited with 0
+    ///
+    /// A command killed by a signal gets exit code 128 + the signal number,
+    /// as shells report it.
+    pub fn captured(command: CommandSpec, output: CapturedOutput, duration_ms: u64) -> Self {
+        if output.status.success() {
+            Self {
+                stdout_truncation: output.stdout.truncation,
+                ..Self::success(command, output.stdout.text, duration_ms)
+            }
+        } else {
+            Self {
+                stdout_truncation: output.stdout.truncation,
+                stderr_truncation: output.stderr.truncation,
+                ..Self::failed(
+                    command,
+                    exit_code(&output.status),
+                    output.stdout.text,
+                    output.stderr.text,
+                    duration_ms,
+                )
+            }
+        }
+    }
+
+    /// Where the task's errors are: stderr, or stdout when stderr is empty
+    /// (as for tasks run in a pseudo-terminal)
+    pub fn error_output(&self) -> &str {
+        if self.stderr.is_empty() {
+            &self.stdout
+        } else {
+            &self.stderr
+        }
+    }
+}
+
+/// Exit code of a finished process, 128 + the signal number if it was killed
+fn exit_code(status: &std::process::ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status
+            .code()
+            .or_else(|| status.signal().map(|signal| 128 + signal))
+    }
+    #[cfg(not(unix))]
+    status.code()
 }
 
 /// Trait for build system runners
@@ -205,15 +313,19 @@ pub trait Runner: Send + Sync {
     /// * `TaskError::Timeout` - If the command times out
     fn run_task(&self, dir: &Path, task: &str, options: &RunOptions) -> RunnerResult<RunResult>;
 
-    /// Build the command line for a task (for display/logging)
+    /// Build the command for a task
+    ///
+    /// The returned spec is what `run_task` executes, so it can also be used
+    /// for display, logging and replaying the exact invocation.
     ///
     /// # Arguments
+    /// * `dir` - Directory containing the build file (becomes the working directory)
     /// * `task` - Task name
     /// * `options` - Run options
     ///
     /// # Returns
-    /// * Full command string that would be executed
-    fn build_command(&self, task: &str, options: &RunOptions) -> String;
+    /// * `CommandSpec` with program, argv, working directory and environment
+    fn build_command(&self, dir: &Path, task: &str, options: &RunOptions) -> CommandSpec;
 
     /// Check if a task exists
     ///
@@ -285,20 +397,21 @@ mod tests {
 
     #[test]
     fn test_run_result_success() {
-        let result = RunResult::success("make build", "Build successful", 1234);
+        let command = CommandSpec::new("make").arg("build");
+        let result = RunResult::success(command.clone(), "Build successful", 1234);
 
         assert!(result.success);
         assert_eq!(result.exit_code, Some(0));
         assert_eq!(result.stdout, "Build successful");
         assert!(result.stderr.is_empty());
-        assert_eq!(result.command, "make build");
+        assert_eq!(result.command, command);
         assert_eq!(result.duration_ms, 1234);
     }
 
     #[test]
     fn test_run_result_failed() {
         let result = RunResult::failed(
-            "make test",
+            CommandSpec::new("make").arg("test"),
             Some(1),
             "Running tests...",
             "Test failed: assertion error",
@@ -324,12 +437,13 @@ mod tests {
 
     #[test]
     fn test_run_result_serialization() {
-        let result = RunResult::success("make build", "ok", 100);
+        let result = RunResult::success(CommandSpec::new("make").arg("build"), "ok", 100);
 
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
         assert!(json.contains("\"exit_code\":0"));
-        assert!(json.contains("\"command\":\"make build\""));
+        assert!(json.contains("\"program\":\"make\""));
+        assert!(json.contains("\"args\":[\"build\"]"));
     }
 
     #[test]
@@ -341,6 +455,6 @@ mod tests {
         assert!(options.positional_args.is_empty());
         assert!(options.env.is_empty());
         assert!(options.timeout.is_none());
-        assert!(!options.capture_output);
+        assert!(options.capture_output);
     }
 }
@@ -0,0 +1,314 @@
+//! Validation of task arguments
+//!
+//! Named arguments become `KEY=value` for make and just and `--key=value`
+//! for scripts, and positional arguments are passed on as they are, so a
+//! caller could otherwise:
+//! - set variables that control the tool itself (`MAKEFLAGS=--eval=...`,
+//!   `SHELL=/tmp/x`)
+//! - smuggle options in through keys (`x --file=other`) or positional
+//!   arguments starting with `-`
+//! - have make run commands through `$(shell ...)` in a value
+//!
+//! The task name goes into the command line too, right after the program,
+//! so it may not start with `-` or contain `=` either.
+//!
+//! Make and just take `VAR=value` words anywhere on the command line, even
+//! after `--`, so positional arguments containing `=` are checked as
+//! assignments too.
+//!
+//! Arguments are checked per runner; make values have `$` escaped instead
+//! of being refused.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::ArgsConfig;
+use crate::error::TaskError;
+use crate::runner::RunOptions;
+
+/// Make and just variables that change how the tool runs or what it runs with
+const FORBIDDEN_VARS: &[&str] = &[
+    "MAKE",
+    "MAKEFLAGS",
+    "MFLAGS",
+    "GNUMAKEFLAGS",
+    "MAKEFILES",
+    "MAKELEVEL",
+    "MAKEOVERRIDES",
+    "MAKECMDGOALS",
+    "MAKESHELL",
+    "SHELL",
+    "VPATH",
+    "PATH",
+    "IFS",
+    "ENV",
+    "BASH_ENV",
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+];
+
+/// Make variable names (no `.`, `:`, `=`, `#` or whitespace)
+static MAKE_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("Invalid regex"));
+
+/// just identifiers
+static JUST_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_-]*$").expect("Invalid regex"));
+
+/// Long option names for scripts
+static SCRIPT_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_.-]*$").expect("Invalid regex"));
+
+/// Check `task` and the named and positional arguments in `options` for
+/// `runner` ("make", "just" or a script name), escaping make values in place
+pub fn check_args(
+    config: &ArgsConfig,
+    runner: &str,
+    task: &str,
+    options: &mut RunOptions,
+) -> Result<(), TaskError> {
+    check_task(task)?;
+    let (key_syntax, sets_variables) = match runner {
+        "make" => (&*MAKE_KEY, true),
+        "just" => (&*JUST_KEY, true),
+        _ => (&*SCRIPT_KEY, false),
+    };
+
+    let check_named = |key: &str, value: &mut String| -> Result<(), TaskError> {
+        if !key_syntax.is_match(key) {
+            return Err(TaskError::InvalidArgument {
+                argument: key.to_string(),
+                reason: format!("not a valid {} argument name", runner),
+            });
+        }
+        if sets_variables && is_forbidden(config, key) {
+            return Err(TaskError::ForbiddenVariable {
+                name: key.to_string(),
+                runner: runner.to_string(),
+            });
+        }
+        check_value(key, value)?;
+        if runner == "make" && config.escape_make_values {
+            *value = value.replace('$', "$$");
+        }
+        Ok(())
+    };
+
+    for (key, value) in options.args.iter_mut() {
+        check_named(key, value)?;
+    }
+
+    for arg in options.positional_args.iter_mut() {
+        check_value(arg, arg)?;
+        if arg.starts_with('-') && !config.allow_flags {
+            return Err(TaskError::InvalidArgument {
+                argument: arg.clone(),
+                reason: "positional arguments cannot start with '-'".to_string(),
+            });
+        }
+        // An assignment to make or just, wherever it appears
+        if let Some((key, value)) = arg.split_once('=').filter(|_| sets_variables) {
+            let mut value = value.to_string();
+            check_named(key, &mut value)?;
+            *arg = format!("{}={}", key, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Task names can't be options or assignments
+fn check_task(task: &str) -> Result<(), TaskError> {
+    let reason = if task.is_empty() {
+        "task name is empty"
+    } else if task.starts_with('-') {
+        "task names cannot start with '-'"
+    } else if task.contains('=') {
+        "task names cannot contain '='"
+    } else {
+        return check_value(task, task);
+    };
+    Err(TaskError::InvalidArgument {
+        argument: task.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+fn is_forbidden(config: &ArgsConfig, name: &str) -> bool {
+    let matches = |list: &[String]| list.iter().any(|n| n.eq_ignore_ascii_case(name));
+    if matches(&config.allow_vars) {
+        return false;
+    }
+    FORBIDDEN_VARS.iter().any(|n| n.eq_ignore_ascii_case(name)) || matches(&config.deny_vars)
+}
+
+fn check_value(argument: &str, value: &str) -> Result<(), TaskError> {
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(TaskError::InvalidArgument {
+            argument: argument.to_string(),
+            reason: "contains control characters".to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[(&str, &str)], positional: &[&str]) -> RunOptions {
+        RunOptions {
+            args: args
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            positional_args: positional.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn check(runner: &str, args: &[(&str, &str)], positional: &[&str]) -> Result<(), TaskError> {
+        check_args(
+            &ArgsConfig::default(),
+            runner,
+            "build",
+            &mut options(args, positional),
+        )
+    }
+
+    #[test]
+    fn test_plain_arguments_pass() {
+        assert!(check("make", &[("TARGET", "release")], &["app"]).is_ok());
+        assert!(check("just", &[("log-level", "debug")], &[]).is_ok());
+        assert!(check("run.sh", &[("dry-run", "")], &["a b"]).is_ok());
+    }
+
+    #[test]
+    fn test_forbidden_variables() {
+        for name in ["MAKEFLAGS", "SHELL", "shell", "PATH", "LD_PRELOAD"] {
+            assert!(
+                matches!(
+                    check("make", &[(name, "x")], &[]),
+                    Err(TaskError::ForbiddenVariable { .. })
+                ),
+                "{}",
+                name
+            );
+        }
+        assert!(matches!(
+            check("just", &[("MAKEFLAGS", "x")], &[]),
+            Err(TaskError::ForbiddenVariable { .. })
+        ));
+        // Script keys become options, not variables
+        assert!(check("run.sh", &[("shell", "zsh")], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_key_syntax_per_runner() {
+        assert!(check("make", &[(".SHELLFLAGS", "-c")], &[]).is_err());
+        assert!(check("make", &[("A B", "x")], &[]).is_err());
+        assert!(check("make", &[("log-level", "x")], &[]).is_err());
+        assert!(check("just", &[("log-level", "x")], &[]).is_ok());
+        assert!(check("run.sh", &[("-file", "x")], &[]).is_err());
+        assert!(check("run.sh", &[("x=y --force", "")], &[]).is_err());
+    }
+
+    #[test]
+    fn test_values_and_positional_args() {
+        assert!(matches!(
+            check("make", &[], &["--eval=all:;rm -rf /"]),
+            Err(TaskError::InvalidArgument { .. })
+        ));
+        assert!(check("run.sh", &[("msg", "a\nb")], &[]).is_err());
+        assert!(check("run.sh", &[], &["tab\tseparated"]).is_ok());
+
+        let config = ArgsConfig {
+            allow_flags: true,
+            ..Default::default()
+        };
+        assert!(check_args(
+            &config,
+            "run.sh",
+            "build",
+            &mut options(&[], &["--verbose"])
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_positional_assignments() {
+        for runner in ["make", "just"] {
+            for arg in ["SHELL=/tmp/x", "MAKEFILES=/tmp/evil.mk", "makeflags=-j1"] {
+                assert!(
+                    matches!(
+                        check(runner, &[], &[arg]),
+                        Err(TaskError::ForbiddenVariable { .. })
+                    ),
+                    "{} {}",
+                    runner,
+                    arg
+                );
+            }
+        }
+        // Other assignment forms have keys make doesn't take
+        assert!(check("make", &[], &["X:=$(shell id)"]).is_err());
+        assert!(check("make", &[], &["X+=y"]).is_err());
+
+        let mut opts = options(&[], &["VERSION=$(shell id)", "app"]);
+        check_args(&ArgsConfig::default(), "make", "build", &mut opts).unwrap();
+        assert_eq!(opts.positional_args, vec!["VERSION=$$(shell id)", "app"]);
+
+        // Scripts get the word as it is
+        assert!(check("run.sh", &[], &["SHELL=/tmp/x"]).is_ok());
+    }
+
+    #[test]
+    fn test_task_names() {
+        for runner in ["make", "just", "run.sh"] {
+            let check = |task: &str| {
+                check_args(&ArgsConfig::default(), runner, task, &mut options(&[], &[]))
+            };
+            assert!(check("build").is_ok());
+            assert!(check("db:migrate").is_ok());
+            for task in ["--eval=all:;id", "-f/tmp/x.mk", "SHELL=/tmp/x", "", "a\nb"] {
+                assert!(
+                    matches!(check(task), Err(TaskError::InvalidArgument { .. })),
+                    "{} {:?}",
+                    runner,
+                    task
+                );
+            }
+        }
+
+        // Even when positional flags are allowed
+        let config = ArgsConfig {
+            allow_flags: true,
+            ..Default::default()
+        };
+        assert!(check_args(&config, "make", "-n", &mut options(&[], &[])).is_err());
+    }
+
+    #[test]
+    fn test_make_values_are_escaped() {
+        let mut opts = options(&[("CFLAGS", "$(shell id) -O2")], &[]);
+        check_args(&ArgsConfig::default(), "make", "build", &mut opts).unwrap();
+        assert_eq!(opts.args["CFLAGS"], "$$(shell id) -O2");
+
+        let mut opts = options(&[("VERSION", "$x")], &[]);
+        check_args(&ArgsConfig::default(), "just", "build", &mut opts).unwrap();
+        assert_eq!(opts.args["VERSION"], "$x");
+    }
+
+    #[test]
+    fn test_configured_variables() {
+        let config = ArgsConfig {
+            deny_vars: vec!["DEPLOY_ENV".to_string()],
+            allow_vars: vec!["PATH".to_string()],
+            ..Default::default()
+        };
+        let mut deploy = options(&[("DEPLOY_ENV", "prod")], &[]);
+        assert!(check_args(&config, "make", "build", &mut deploy).is_err());
+        let mut path = options(&[("PATH", "/opt/bin")], &[]);
+        assert!(check_args(&config, "make", "build", &mut path).is_ok());
+    }
+}
@@ -0,0 +1,410 @@
+//! Audit log of MCP tool calls
+//!
+//! Every tool call is appended as one JSON line: the tool, its parameters,
+//! the client that made the call (from MCP initialization), the project
+//! paths it resolved, the commands it ran (argv, working directory, the
+//! names of the environment variables set, exit code) and how long it took.
+//! Environment values and task output are never logged.
+//!
+//! Commands are collected in an [`AuditTrail`] that is in scope for the
+//! duration of a call; code that runs commands reports them with
+//! [`note_path`] and [`note_command`], which do nothing outside a call.
+
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::TaskError;
+use crate::runner::CommandSpec;
+
+/// Serializes appends and rotation within the process
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+tokio::task_local! {
+    static TRAIL: AuditTrail;
+}
+
+/// MCP client that made a call, as reported at initialization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub name: String,
+    pub version: String,
+}
+
+/// A command run during a tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditCommand {
+    /// Full argv, starting with the program
+    pub argv: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Names of the environment variables set for the command
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_keys: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+impl AuditCommand {
+    fn new(command: &CommandSpec, exit_code: Option<i32>, duration_ms: u64) -> Self {
+        Self {
+            argv: std::iter::once(command.program.clone())
+                .chain(command.args.iter().cloned())
+                .collect(),
+            cwd: command.cwd.as_ref().map(|cwd| cwd.display().to_string()),
+            env_keys: command.env.keys().cloned().collect(),
+            exit_code,
+            duration_ms,
+        }
+    }
+}
+
+/// One tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp (seconds) when the call finished
+    pub timestamp: u64,
+    /// Server session that handled the call
+    pub session: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<ClientInfo>,
+    pub tool: String,
+    /// Call parameters as sent by the client
+    pub params: serde_json::Value,
+    /// Project directories the call resolved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<AuditCommand>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl AuditEntry {
+    /// Entry for a call to `tool`, to be completed with [`AuditEntry::finish`]
+    pub fn new(tool: &str, params: serde_json::Value) -> Self {
+        Self {
+            timestamp: 0,
+            session: String::new(),
+            client: None,
+            tool: tool.to_string(),
+            params,
+            paths: Vec::new(),
+            commands: Vec::new(),
+            success: false,
+            error: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// Fill in the outcome from the tool's JSON response and what the
+    /// call's trail collected
+    pub fn finish(mut self, response: &str, trail: &AuditTrail, duration_ms: u64) -> Self {
+        let response: serde_json::Value = serde_json::from_str(response).unwrap_or_default();
+        self.error = response["error"].as_str().map(str::to_string);
+        self.success = self.error.is_none() && response["success"].as_bool().unwrap_or(true);
+
+        let collected = trail.0.lock().unwrap_or_else(|e| e.into_inner());
+        self.paths = collected.paths.clone();
+        self.commands = collected.commands.clone();
+        self.duration_ms = duration_ms;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Collected {
+    paths: Vec<String>,
+    commands: Vec<AuditCommand>,
+}
+
+/// Paths and commands collected during one tool call
+///
+/// Clones share the same collection, so a trail can be handed to tasks the
+/// call spawns.
+#[derive(Debug, Clone, Default)]
+pub struct AuditTrail(Arc<Mutex<Collected>>);
+
+impl AuditTrail {
+    /// Run `future` with this trail in scope
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        TRAIL.scope(self.clone(), future).await
+    }
+
+    /// The trail of the call in progress, if any
+    pub fn current() -> Option<Self> {
+        TRAIL.try_with(Clone::clone).ok()
+    }
+
+    /// Record a project directory the call resolved
+    pub fn path(&self, path: &Path) {
+        let path = path.display().to_string();
+        let mut collected = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if !collected.paths.contains(&path) {
+            collected.paths.push(path);
+        }
+    }
+
+    /// Record a command the call ran
+    pub fn command(&self, command: &CommandSpec, exit_code: Option<i32>, duration_ms: u64) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .commands
+            .push(AuditCommand::new(command, exit_code, duration_ms));
+    }
+}
+
+/// Record a project directory on the current call's trail, if any
+pub fn note_path(path: &Path) {
+    if let Some(trail) = AuditTrail::current() {
+        trail.path(path);
+    }
+}
+
+/// Record a command on the current call's trail, if any
+pub fn note_command(command: &CommandSpec, exit_code: Option<i32>, duration_ms: u64) {
+    if let Some(trail) = AuditTrail::current() {
+        trail.command(command, exit_code, duration_ms);
+    }
+}
+
+/// Append-only, size-rotated audit log
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    session: String,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl AuditLog {
+    /// Log in `path`, rotating at 10 MiB and keeping 5 rotated files
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            session: String::new(),
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+
+    /// Log from `[security.audit]`, or `None` when disabled
+    ///
+    /// Defaults to `$XDG_STATE_HOME/makefilehub/audit.jsonl`.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let audit = &config.security.audit;
+        if !audit.enabled {
+            return None;
+        }
+
+        let path = match &audit.path {
+            Some(path) => PathBuf::from(path),
+            None => dirs::state_dir()
+                .or_else(dirs::data_local_dir)?
+                .join("makefilehub")
+                .join("audit.jsonl"),
+        };
+        Some(Self {
+            max_bytes: audit.max_bytes,
+            max_files: audit.max_files,
+            ..Self::new(path)
+        })
+    }
+
+    /// Tag entries with `session`
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.session = session.into();
+        self
+    }
+
+    /// Log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry, stamping it with the time and session
+    ///
+    /// Failures are logged rather than returned: a call that already ran
+    /// can't be undone.
+    pub fn record(&self, mut entry: AuditEntry) {
+        entry.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        entry.session = self.session.clone();
+
+        if let Err(e) = self.append(&entry) {
+            tracing::warn!("Failed to write audit log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> Result<(), TaskError> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| TaskError::Config(format!("Failed to serialize audit entry: {}", e)))?;
+        line.push('\n');
+
+        let size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&self.path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Shift `path.N` to `path.N+1` (dropping the oldest) and `path` to `path.1`
+    fn rotate(&self) -> Result<(), TaskError> {
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn read(path: &Path) -> Vec<AuditEntry> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_trail_collects_within_scope() {
+        note_command(&CommandSpec::new("ignored"), Some(0), 1);
+
+        let trail = AuditTrail::default();
+        trail
+            .scope(async {
+                note_path(Path::new("/srv/app"));
+                note_path(Path::new("/srv/app"));
+                let command = CommandSpec::new("make")
+                    .arg("build")
+                    .current_dir("/srv/app")
+                    .env("API_TOKEN", "s3cret");
+                note_command(&command, Some(2), 15);
+            })
+            .await;
+
+        let entry = AuditEntry::new("run_task", serde_json::json!({"task": "build"})).finish(
+            r#"{"success": false, "exit_code": 2}"#,
+            &trail,
+            20,
+        );
+        assert!(!entry.success);
+        assert_eq!(entry.paths, vec!["/srv/app"]);
+        assert_eq!(entry.commands.len(), 1);
+        let command = &entry.commands[0];
+        assert_eq!(command.argv, vec!["make", "build"]);
+        assert_eq!(command.env_keys, vec!["API_TOKEN"]);
+        assert_eq!(command.exit_code, Some(2));
+        assert!(!serde_json::to_string(&entry).unwrap().contains("s3cret"));
+    }
+
+    #[test]
+    fn test_finish_reads_errors() {
+        let trail = AuditTrail::default();
+        let entry = AuditEntry::new("run_task", serde_json::Value::Null).finish(
+            r#"{"success": false, "error": "Task 'deploy' requires confirmation"}"#,
+            &trail,
+            1,
+        );
+        assert_eq!(
+            entry.error.as_deref(),
+            Some("Task 'deploy' requires confirmation")
+        );
+
+        let entry = AuditEntry::new("list_tasks", serde_json::Value::Null).finish(
+            r#"{"runner": "make", "tasks": []}"#,
+            &trail,
+            1,
+        );
+        assert!(entry.success);
+    }
+
+    #[test]
+    fn test_record_appends_and_rotates() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state/audit.jsonl");
+        let log = AuditLog {
+            max_bytes: 400,
+            max_files: 2,
+            ..AuditLog::new(&path)
+        }
+        .session("mcp-1");
+
+        for i in 0..12 {
+            log.record(AuditEntry::new(
+                "detect_runner",
+                serde_json::json!({"n": i}),
+            ));
+        }
+
+        let current = read(&path);
+        assert!(!current.is_empty());
+        assert!(current
+            .iter()
+            .all(|e| e.session == "mcp-1" && e.timestamp > 0));
+        assert_eq!(current.last().unwrap().params["n"], 11);
+        assert!(std::fs::metadata(&path).unwrap().len() <= 400);
+
+        assert!(log.rotated(1).exists());
+        assert!(log.rotated(2).exists());
+        assert!(!log.rotated(3).exists());
+        let previous = read(&log.rotated(1));
+        assert_eq!(
+            previous.last().unwrap().params["n"].as_u64().unwrap() + 1,
+            current[0].params["n"].as_u64().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config::default();
+        assert!(AuditLog::from_config(&config).is_none());
+
+        config.security.audit.enabled = true;
+        config.security.audit.path = Some("/var/log/makefilehub/audit.jsonl".to_string());
+        let log = AuditLog::from_config(&config).unwrap();
+        assert_eq!(log.path(), Path::new("/var/log/makefilehub/audit.jsonl"));
+    }
+}
@@ -0,0 +1,24 @@
+//! Guardrails for MCP callers
+//!
+//! Path restrictions live in [`Config::validate_path`](crate::config::Config::validate_path);
+//! this module holds the rest:
+//! - `paths` - project directories, refusing build files and scripts that
+//!   lead outside
+//! - `policy` - which tasks may run, and which need confirmation
+//! - `args` - validation of the arguments passed to tasks
+//! - `audit` - append-only log of MCP tool calls
+//! - `redact` - masking secrets in task output, for every caller
+//! - `roots` - the MCP client's workspace roots, which narrow the allowed paths
+
+pub mod args;
+pub mod audit;
+pub mod paths;
+pub mod policy;
+pub mod redact;
+pub mod roots;
+
+pub use args::check_args;
+pub use audit::{AuditEntry, AuditLog, AuditTrail};
+pub use paths::{validate_project, validate_script};
+pub use policy::Policy;
+pub use redact::Redactor;
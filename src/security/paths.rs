@@ -0,0 +1,233 @@
+//! Project directories for MCP callers
+//!
+//! [`Config::validate_path`] decides on the path as written before looking
+//! at the filesystem, then returns the canonical path, which is what every
+//! later step works in. A validated directory can still hold a Makefile,
+//! justfile or script that is a symlink to somewhere else, and make or the
+//! shell would follow it; such build files are refused here instead, as
+//! are scripts a caller names that live outside the project.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::TaskError;
+use crate::runner::build_files;
+
+/// Validate a project directory and the build files in it, returning the
+/// canonical directory
+///
+/// `script` is a service's own script, checked along with the configured
+/// ones.
+///
+/// # Errors
+/// * `TaskError::SecurityViolation` - If the directory is outside the
+///   allowed directories, doesn't exist, or holds a build file that resolves
+///   outside them
+pub fn validate_project(
+    path: &Path,
+    config: &Config,
+    script: Option<&str>,
+) -> Result<PathBuf, TaskError> {
+    let dir = config
+        .validate_path(path)
+        .map_err(|message| TaskError::SecurityViolation {
+            message,
+            path: path.display().to_string(),
+        })?;
+    if config.security.allow_any_path {
+        return Ok(dir);
+    }
+
+    let mut files = build_files(&dir, config);
+    if let Some(script) = script {
+        files.push(dir.join(script.strip_prefix("./").unwrap_or(script)));
+    }
+    for file in files {
+        // Only files that are there; a dangling symlink counts
+        if file.symlink_metadata().is_err() {
+            continue;
+        }
+        let violation = |message: String| TaskError::SecurityViolation {
+            message,
+            path: file.display().to_string(),
+        };
+        let target = file.canonicalize().map_err(|e| {
+            violation(format!(
+                "Build file '{}' can't be resolved: {}",
+                file.display(),
+                e
+            ))
+        })?;
+        config.check_path_allowed(&target).map_err(|e| {
+            violation(format!(
+                "Build file '{}' resolves outside allowed directories: {}",
+                file.display(),
+                e
+            ))
+        })?;
+    }
+    Ok(dir)
+}
+
+/// Validate a script a caller picked as the runner for the canonical
+/// project directory `dir`
+///
+/// The script must be a relative path inside the project; once resolved it
+/// has to stay inside both the project and the allowed directories.
+///
+/// # Errors
+/// * `TaskError::SecurityViolation` - If the script is absolute, contains
+///   `..`, or resolves outside the project or the allowed directories
+pub fn validate_script(dir: &Path, script: &str, config: &Config) -> Result<(), TaskError> {
+    let violation = |message: String| TaskError::SecurityViolation {
+        message,
+        path: script.to_string(),
+    };
+    let relative = Path::new(script);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(violation(format!(
+            "Script '{}' must be a relative path inside the project, without '..'",
+            script
+        )));
+    }
+
+    let file = dir.join(relative);
+    // A missing script fails when it is run; a dangling symlink counts
+    if file.symlink_metadata().is_err() {
+        return Ok(());
+    }
+    let target = file
+        .canonicalize()
+        .map_err(|e| violation(format!("Script '{}' can't be resolved: {}", script, e)))?;
+    if !target.starts_with(dir) {
+        return Err(violation(format!(
+            "Script '{}' resolves outside the project '{}'",
+            script,
+            dir.display()
+        )));
+    }
+    config.check_path_allowed(&target).map_err(|e| {
+        violation(format!(
+            "Script '{}' resolves outside allowed directories: {}",
+            script, e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn config(allowed: &Path) -> Config {
+        let mut config = Config::default();
+        config.security.allowed_paths = vec![allowed.display().to_string()];
+        config
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_build_files() {
+        use std::os::unix::fs::symlink;
+
+        let allowed = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let project = allowed.path().join("project");
+        fs::create_dir(&project).unwrap();
+        fs::write(allowed.path().join("common.mk"), "build:\n").unwrap();
+        fs::write(outside.path().join("evil.mk"), "build:\n").unwrap();
+        let config = config(allowed.path());
+
+        // A symlink within the allowed directories is fine
+        symlink(allowed.path().join("common.mk"), project.join("Makefile")).unwrap();
+        assert_eq!(
+            validate_project(&project, &config, None).unwrap(),
+            project.canonicalize().unwrap()
+        );
+
+        // One leading outside is refused
+        symlink(outside.path().join("evil.mk"), project.join("justfile")).unwrap();
+        let err = validate_project(&project, &config, None).unwrap_err();
+        assert!(
+            err.to_string().contains("outside allowed directories"),
+            "{}",
+            err
+        );
+        fs::remove_file(project.join("justfile")).unwrap();
+
+        // So is a service script, and a dangling symlink
+        symlink(outside.path().join("evil.sh"), project.join("deploy.sh")).unwrap();
+        assert!(validate_project(&project, &config, None).is_ok());
+        let err = validate_project(&project, &config, Some("./deploy.sh")).unwrap_err();
+        assert!(err.to_string().contains("can't be resolved"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_project_dir() {
+        let allowed = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let link = allowed.path().join("link");
+        std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+
+        let err = validate_project(&link, &config(allowed.path()), None).unwrap_err();
+        assert!(err.to_string().contains("through a symlink"), "{}", err);
+    }
+
+    #[test]
+    fn test_outside_paths_are_refused_before_existence() {
+        let allowed = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let config = config(allowed.path());
+
+        // Same error whether or not the path exists
+        let existing = validate_project(outside.path(), &config, None).unwrap_err();
+        let missing = validate_project(&outside.path().join("nope"), &config, None).unwrap_err();
+        assert!(existing.to_string().contains("outside allowed directories"));
+        assert!(missing.to_string().contains("outside allowed directories"));
+
+        // `..` can't climb out
+        let escape = allowed.path().join("..").join("..");
+        assert!(config.check_path_allowed(&escape).is_err());
+
+        let err = validate_project(&allowed.path().join("nope"), &config, None).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_script() {
+        let allowed = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let project = allowed.path().join("project");
+        fs::create_dir(&project).unwrap();
+        let project = project.canonicalize().unwrap();
+        fs::write(project.join("run.sh"), "#!/bin/sh\n").unwrap();
+        fs::write(allowed.path().join("shared.sh"), "#!/bin/sh\n").unwrap();
+        let config = config(allowed.path());
+
+        assert!(validate_script(&project, "./run.sh", &config).is_ok());
+        assert!(validate_script(&project, "scripts/missing.sh", &config).is_ok());
+
+        // Absolute paths and `..` are refused as written
+        for script in [
+            "/tmp/evil.sh",
+            "../shared.sh",
+            "./../../x.sh",
+            "a/../run.sh",
+        ] {
+            let err = validate_script(&project, script, &config).unwrap_err();
+            assert!(err.to_string().contains("without '..'"), "{}", err);
+        }
+
+        // So is a symlink leading out of the project
+        std::os::unix::fs::symlink(outside.path(), project.join("out")).unwrap();
+        fs::write(outside.path().join("evil.sh"), "#!/bin/sh\n").unwrap();
+        let err = validate_script(&project, "out/evil.sh", &config).unwrap_err();
+        assert!(err.to_string().contains("outside the project"), "{}", err);
+    }
+}
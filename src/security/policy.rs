@@ -0,0 +1,140 @@
+//! Task allow/deny/confirm policy
+//!
+//! A task is checked under every name it goes by (the requested name and
+//! the name a service maps it to), so a rule for `deploy` also covers a
+//! service whose `deploy` task is really `release-prod`:
+//! - it is refused if any name matches `deny`
+//! - it is refused if `allow` is set and no name matches it
+//! - it needs confirmation if any name matches `confirm`
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::config::TaskPolicy;
+use crate::error::TaskError;
+
+/// A compiled task policy
+#[derive(Debug, Clone)]
+pub struct Policy {
+    allow: Option<GlobSet>,
+    deny: GlobSet,
+    confirm: GlobSet,
+}
+
+impl Policy {
+    /// Compile a policy, failing on invalid globs
+    pub fn new(config: &TaskPolicy) -> Result<Self, TaskError> {
+        Ok(Self {
+            allow: if config.allow.is_empty() {
+                None
+            } else {
+                Some(build_globset(&config.allow)?)
+            },
+            deny: build_globset(&config.deny)?,
+            confirm: build_globset(&config.confirm)?,
+        })
+    }
+
+    /// Check that the task known by `names` (requested name first) may run;
+    /// `confirmed` is whether the caller confirmed it
+    pub fn check(&self, names: &[&str], confirmed: bool) -> Result<(), TaskError> {
+        let task = names.first().copied().unwrap_or_default().to_string();
+
+        if names.iter().any(|name| self.deny.is_match(name)) {
+            return Err(TaskError::TaskDenied {
+                task,
+                reason: "matches a denied pattern".to_string(),
+            });
+        }
+        if let Some(allow) = &self.allow {
+            if !names.iter().any(|name| allow.is_match(name)) {
+                return Err(TaskError::TaskDenied {
+                    task,
+                    reason: "not in the allowed tasks".to_string(),
+                });
+            }
+        }
+        if !confirmed && names.iter().any(|name| self.confirm.is_match(name)) {
+            return Err(TaskError::ConfirmationRequired { task });
+        }
+        Ok(())
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, TaskError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| TaskError::Config(format!("Invalid task glob '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| TaskError::Config(format!("Invalid task globs: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &[&str], deny: &[&str], confirm: &[&str]) -> Policy {
+        let strings = |patterns: &[&str]| patterns.iter().map(|s| s.to_string()).collect();
+        Policy::new(&TaskPolicy {
+            allow: strings(allow),
+            deny: strings(deny),
+            confirm: strings(confirm),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_empty_policy_allows_everything() {
+        assert!(policy(&[], &[], &[]).check(&["deploy"], false).is_ok());
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow_and_confirm() {
+        let policy = policy(&["*"], &["clean-db", "drop-*"], &["drop-*"]);
+        assert!(policy.check(&["build"], false).is_ok());
+        assert!(matches!(
+            policy.check(&["drop-tables"], true),
+            Err(TaskError::TaskDenied { .. })
+        ));
+        // The mapped name is checked too
+        assert!(matches!(
+            policy.check(&["reset", "clean-db"], false),
+            Err(TaskError::TaskDenied { task, .. }) if task == "reset"
+        ));
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let policy = policy(&["build", "test*"], &[], &[]);
+        assert!(policy.check(&["test-unit"], false).is_ok());
+        assert!(policy.check(&["build", "compile"], false).is_ok());
+        assert!(matches!(
+            policy.check(&["deploy"], false),
+            Err(TaskError::TaskDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_confirmation() {
+        let policy = policy(&[], &[], &["deploy*"]);
+        assert!(matches!(
+            policy.check(&["deploy-prod"], false),
+            Err(TaskError::ConfirmationRequired { .. })
+        ));
+        assert!(policy.check(&["deploy-prod"], true).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let result = Policy::new(&TaskPolicy {
+            deny: vec!["[".to_string()],
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(TaskError::Config(_))));
+    }
+}
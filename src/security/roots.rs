@@ -0,0 +1,110 @@
+//! MCP client roots
+//!
+//! Clients that support roots tell the server which workspaces they have
+//! open as `file://` URIs. The server asks for them once the client is
+//! initialized and again whenever the client reports they changed, and
+//! keeps them canonicalized in `security.client_roots`, where
+//! [`Config::validate_path`](crate::config::Config::validate_path) checks
+//! project paths against them.
+
+use std::path::PathBuf;
+
+/// Local path of a `file://` URI, percent-decoded
+///
+/// Only URIs without a host or with `localhost` are local; anything else
+/// (other schemes, remote hosts) gives `None`.
+pub fn root_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(slash) if rest[..slash].eq_ignore_ascii_case("localhost") => &rest[slash..],
+        _ => return None,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    percent_decode(path).map(PathBuf::from)
+}
+
+/// Canonical directories of the given root URIs, skipping ones that aren't
+/// local or don't exist
+pub fn resolve_roots<'a>(uris: impl IntoIterator<Item = &'a str>) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    for uri in uris {
+        let Some(path) = root_path(uri) else {
+            tracing::warn!("Ignoring client root that is not a local file URI: {}", uri);
+            continue;
+        };
+        match path.canonicalize() {
+            Ok(canonical) if canonical.is_dir() => roots.push(canonical),
+            Ok(_) => tracing::warn!("Ignoring client root that is not a directory: {}", uri),
+            Err(e) => tracing::warn!("Ignoring client root {}: {}", uri, e),
+        }
+    }
+    roots.sort();
+    roots.dedup();
+    roots
+}
+
+/// Decode `%XX` escapes, failing on malformed escapes, invalid UTF-8 or
+/// encoded NUL bytes
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3)?;
+            let byte = u8::from_str_radix(hex, 16).ok()?;
+            if byte == 0 {
+                return None;
+            }
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_root_path() {
+        assert_eq!(
+            root_path("file:///home/me/my%20project"),
+            Some(PathBuf::from("/home/me/my project"))
+        );
+        assert_eq!(
+            root_path("file://localhost/srv/app"),
+            Some(PathBuf::from("/srv/app"))
+        );
+        assert_eq!(root_path("file://server/share"), None);
+        assert_eq!(root_path("https://example.com/repo"), None);
+        assert_eq!(root_path("/home/me/project"), None);
+        assert_eq!(root_path("file:///bad%2"), None);
+        assert_eq!(root_path("file:///nul%00byte"), None);
+    }
+
+    #[test]
+    fn test_resolve_roots() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        let canonical = dir.path().canonicalize().unwrap();
+
+        let dir_uri = format!("file://{}", dir.path().display());
+        let file_uri = format!("file://{}", file.display());
+        let uris = [
+            dir_uri.as_str(),
+            dir_uri.as_str(),
+            file_uri.as_str(),
+            "file:///does/not/exist",
+            "https://example.com",
+        ];
+        assert_eq!(resolve_roots(uris), vec![canonical]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Built-in suggestion rules
+//!
+//! Tried after any configured rules, in this order.
+
+use crate::config::SuggestionRule;
+
+/// `(match, runner, suggestion)`
+const RULES: &[(&str, Option<&str>, &str)] = &[
+    // Docker
+    (
+        r"(?s)[Dd]ocker.*(?:not running|Cannot connect)|(?:not running|Cannot connect).*[Dd]ocker",
+        None,
+        "Docker daemon is not running. Start Docker Desktop or the Docker service.",
+    ),
+    (
+        "No such container",
+        None,
+        "Container not found. Try running 'up' first to start the services.",
+    ),
+    (
+        "port is already allocated",
+        None,
+        "Port conflict. Stop the conflicting service or use a different port.",
+    ),
+    // Permissions
+    (
+        "Permission denied",
+        None,
+        "Permission denied. Check file permissions or run with appropriate access.",
+    ),
+    // Missing commands
+    (
+        r"\bmake: (?:command )?not found",
+        None,
+        "'make' command not found. Install build-essential or make.",
+    ),
+    (
+        r"\bjust: (?:command )?not found",
+        None,
+        "'just' command not found. Install just: cargo install just",
+    ),
+    (
+        r"([\w.+-]+): (?:command )?not found",
+        None,
+        "'$1' command not found. Check PATH and dependencies.",
+    ),
+    (
+        "not found",
+        None,
+        "Required command not found. Check PATH and dependencies.",
+    ),
+    // Missing files
+    (
+        "No such file",
+        Some("run.sh"),
+        "run.sh not found. Verify the working directory is correct.",
+    ),
+    (
+        "No such file",
+        None,
+        "File not found. Check the project path and file existence.",
+    ),
+    // Make and just
+    (
+        r"No rule to make target [`'‘]([^'’]+)['’]",
+        None,
+        "Target '$1' not found in Makefile. Run 'list_tasks' to see available targets.",
+    ),
+    (
+        "No rule to make target",
+        None,
+        "Target not found in Makefile. Run 'list_tasks' to see available targets.",
+    ),
+    (
+        "[Jj]ustfile does not contain recipe",
+        None,
+        "Recipe not found in justfile. Run 'list_tasks' to see available recipes.",
+    ),
+];
+
+/// The built-in rules as configuration entries
+pub fn rules() -> Vec<SuggestionRule> {
+    RULES
+        .iter()
+        .map(|(pattern, runner, suggestion)| SuggestionRule {
+            pattern: pattern.to_string(),
+            runner: runner.map(str::to_string),
+            suggestion: suggestion.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+//! Error-suggestion rules
+//!
+//! A failed command's stderr is matched against an ordered list of rules;
+//! the first matching rule whose runner filter applies provides the
+//! suggestion:
+//! - service rules (`[[services.<name>.suggestions]]`)
+//! - global rules (`[[suggestions]]`)
+//! - `builtin` - docker, permission, missing command/file and make/just
+//!   recipe errors
+//!
+//! Suggestion text may use the match's capture groups (`$1`, `${name}`).
+
+pub mod builtin;
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::SuggestionRule;
+use crate::error::TaskError;
+
+/// A compiled rule
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Regex,
+    runner: Option<String>,
+    suggestion: String,
+}
+
+impl Rule {
+    fn compile(rule: &SuggestionRule) -> Result<Self, TaskError> {
+        let pattern = Regex::new(&rule.pattern).map_err(|e| {
+            TaskError::Config(format!(
+                "Invalid suggestion pattern '{}': {}",
+                rule.pattern, e
+            ))
+        })?;
+        Ok(Self {
+            pattern,
+            runner: rule.runner.as_deref().map(program_name),
+            suggestion: rule.suggestion.clone(),
+        })
+    }
+
+    fn applies_to(&self, runner: &str) -> bool {
+        self.runner.as_deref().is_none_or(|r| r == runner)
+    }
+}
+
+static BUILTIN: Lazy<Suggestions> = Lazy::new(|| Suggestions {
+    rules: builtin::rules()
+        .iter()
+        .map(|rule| Rule::compile(rule).expect("Invalid built-in suggestion rule"))
+        .collect(),
+});
+
+/// Ordered suggestion rules
+#[derive(Debug, Clone)]
+pub struct Suggestions {
+    rules: Vec<Rule>,
+}
+
+impl Suggestions {
+    /// The built-in rules alone
+    pub fn builtin() -> &'static Self {
+        &BUILTIN
+    }
+
+    /// `rules`, in order, followed by the built-in rules
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If a rule's pattern is not a valid regex
+    pub fn new(rules: &[SuggestionRule]) -> Result<Self, TaskError> {
+        let mut compiled = rules
+            .iter()
+            .map(Rule::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        compiled.extend(BUILTIN.rules.iter().cloned());
+        Ok(Self { rules: compiled })
+    }
+
+    /// Suggest a fix for `stderr` from a command run by `runner`
+    ///
+    /// `runner` is a runner name (`make`, `./run.sh`) or program; only its
+    /// file name is compared with a rule's `runner`.
+    pub fn suggest(&self, runner: &str, stderr: &str) -> Option<String> {
+        let runner = program_name(runner);
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(&runner))
+            .find_map(|rule| {
+                let caps = rule.pattern.captures(stderr)?;
+                let mut suggestion = String::new();
+                caps.expand(&rule.suggestion, &mut suggestion);
+                Some(suggestion)
+            })
+    }
+}
+
+/// File name of a command's program, e.g. `run.sh` for `./run.sh build`
+pub fn program_name(command: &str) -> String {
+    let program = command.split_whitespace().next().unwrap_or_default();
+    Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| program.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, runner: Option<&str>, suggestion: &str) -> SuggestionRule {
+        SuggestionRule {
+            pattern: pattern.to_string(),
+            runner: runner.map(str::to_string),
+            suggestion: suggestion.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_user_rules_before_builtin() {
+        let suggestions = Suggestions::new(&[rule(
+            "Permission denied",
+            None,
+            "Run `chmod +x` on the script",
+        )])
+        .unwrap();
+        assert_eq!(
+            suggestions.suggest("./run.sh", "bash: ./run.sh: Permission denied"),
+            Some("Run `chmod +x` on the script".to_string())
+        );
+        // Built-in rules still apply after the user's
+        assert!(suggestions
+            .suggest("make", "make: *** No rule to make target 'x'.  Stop.")
+            .unwrap()
+            .contains("Makefile"));
+    }
+
+    #[test]
+    fn test_capture_groups() {
+        let suggestions = Suggestions::new(&[rule(
+            r"Cannot find module '(?P<module>[^']+)'",
+            None,
+            "Install it: npm install ${module} ($1)",
+        )])
+        .unwrap();
+        assert_eq!(
+            suggestions.suggest("make", "Error: Cannot find module 'left-pad'"),
+            Some("Install it: npm install left-pad (left-pad)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_runner_filter() {
+        let suggestions = Suggestions::new(&[
+            rule(
+                "database is locked",
+                Some("just"),
+                "just: stop the dev server",
+            ),
+            rule(
+                "database is locked",
+                Some("./run.sh"),
+                "run.sh: stop the dev server",
+            ),
+        ])
+        .unwrap();
+        assert_eq!(suggestions.suggest("make", "database is locked"), None);
+        assert_eq!(
+            suggestions.suggest("just", "database is locked"),
+            Some("just: stop the dev server".to_string())
+        );
+        assert_eq!(
+            suggestions.suggest("./run.sh", "database is locked"),
+            Some("run.sh: stop the dev server".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = Suggestions::new(&[rule("(", None, "never")]);
+        assert!(matches!(result, Err(TaskError::Config(_))));
+    }
+
+    #[test]
+    fn test_program_name() {
+        assert_eq!(program_name("./run.sh build"), "run.sh");
+        assert_eq!(program_name("/usr/bin/make -C dir build"), "make");
+        assert_eq!(program_name("docker"), "docker");
+        assert_eq!(program_name(""), "");
+    }
+}
@@ -0,0 +1,153 @@
+//! `cargo test` (libtest) output
+//!
+//! Counts come from every `test result:` line (one per test binary).
+//! Failures are the `test <name> ... FAILED` lines; their message and
+//! location come from the `---- <name> stdout ----` section.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::TestSummary;
+
+static RESULT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^test result: (?:ok|FAILED)\. (\d+) passed; (\d+) failed; (\d+) ignored;")
+        .expect("Invalid regex")
+});
+
+static FAILED_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^test (.+?) \.\.\. FAILED$").expect("Invalid regex"));
+
+static SECTION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^---- (.+?) stdout ----$").expect("Invalid regex"));
+
+static PANIC_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"panicked at (?:'(.*)', )?([^\s']+:\d+:\d+):?$").expect("Invalid regex")
+});
+
+pub fn recognize(output: &str) -> Option<TestSummary> {
+    let mut summary = TestSummary::new("cargo");
+    let mut recognized = false;
+    let mut failed = Vec::new();
+    let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut section: Option<&str> = None;
+
+    for line in output.lines() {
+        if let Some(caps) = RESULT_RE.captures(line) {
+            recognized = true;
+            summary.passed += caps[1].parse::<u32>().unwrap_or(0);
+            summary.failed += caps[2].parse::<u32>().unwrap_or(0);
+            summary.skipped += caps[3].parse::<u32>().unwrap_or(0);
+        } else if let Some(caps) = FAILED_RE.captures(line) {
+            failed.push(caps.get(1).map_or("", |name| name.as_str()));
+        } else if let Some(caps) = SECTION_RE.captures(line) {
+            section = caps.get(1).map(|name| name.as_str());
+        } else if line == "failures:" || line.starts_with("test result:") {
+            section = None;
+        } else if let Some(name) = section {
+            sections.entry(name).or_default().push(line);
+        }
+    }
+    if !recognized {
+        return None;
+    }
+
+    for name in failed {
+        let lines = sections.get(name).map(Vec::as_slice).unwrap_or_default();
+        let (message, location) = describe(lines);
+        summary.push_failure(name, &message, location);
+    }
+    Some(summary)
+}
+
+/// Message and location of a failure from its captured stdout
+fn describe(lines: &[&str]) -> (String, Option<String>) {
+    let mut location = None;
+    let mut message = Vec::new();
+
+    for line in lines {
+        if let Some(caps) = PANIC_RE.captures(line) {
+            location = caps.get(2).map(|l| l.as_str().to_string());
+            if let Some(inline) = caps.get(1) {
+                message.push(inline.as_str());
+            }
+        } else if !line.starts_with("note: run with `RUST_BACKTRACE") {
+            message.push(line);
+        }
+    }
+
+    (message.join("\n").trim().to_string(), location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+running 3 tests
+test tests::adds ... ok
+test tests::ignored ... ignored
+test tests::subtracts ... FAILED
+
+failures:
+
+---- tests::subtracts stdout ----
+
+thread 'tests::subtracts' panicked at src/lib.rs:14:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::subtracts
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+running 2 tests
+test it_works ... ok
+test it_also_works ... ok
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+
+    #[test]
+    fn test_recognize() {
+        let summary = recognize(OUTPUT).unwrap();
+        assert_eq!(summary.framework, "cargo");
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (3, 1, 1));
+        assert_eq!(summary.failures.len(), 1);
+
+        let failure = &summary.failures[0];
+        assert_eq!(failure.name, "tests::subtracts");
+        assert_eq!(failure.location.as_deref(), Some("src/lib.rs:14:9"));
+        assert_eq!(
+            failure.message,
+            "assertion `left == right` failed\n  left: 1\n right: 2"
+        );
+    }
+
+    #[test]
+    fn test_old_panic_format() {
+        let output = "\
+test tests::boom ... FAILED
+---- tests::boom stdout ----
+thread 'tests::boom' panicked at 'explicit panic', src/lib.rs:3:5
+failures:
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+        let summary = recognize(output).unwrap();
+        assert_eq!(summary.failures[0].message, "explicit panic");
+        assert_eq!(
+            summary.failures[0].location.as_deref(),
+            Some("src/lib.rs:3:5")
+        );
+    }
+
+    #[test]
+    fn test_not_cargo() {
+        assert!(recognize("test passed\n").is_none());
+    }
+}
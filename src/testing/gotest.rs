@@ -0,0 +1,167 @@
+//! `go test` output
+//!
+//! With `-json`, every line is a test2json event; per-test `pass`, `fail`
+//! and `skip` actions are counted and a failure's message is its `output`
+//! events. Plain `go test -v` output is recognized from its
+//! `--- PASS/FAIL/SKIP: Name` lines.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use super::TestSummary;
+
+static RESULT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*--- (PASS|FAIL|SKIP): (\S+)(?: \([\d.]+s\))?$").expect("Invalid regex")
+});
+
+/// `    file_test.go:12: message`, as printed by `t.Errorf` and friends
+static LOCATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s+([\w./-]+\.go:\d+): ").expect("Invalid regex"));
+
+pub fn recognize_json(output: &str) -> Option<TestSummary> {
+    let mut summary = TestSummary::new("go");
+    let mut recognized = false;
+    let mut outputs: HashMap<(String, String), Vec<String>> = HashMap::new();
+
+    for line in output.lines() {
+        if !line.starts_with('{') {
+            continue;
+        }
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let Some(action) = event.get("Action").and_then(Value::as_str) else {
+            continue;
+        };
+        recognized = true;
+
+        // Package-level events have no Test
+        let Some(test) = event.get("Test").and_then(Value::as_str) else {
+            continue;
+        };
+        let package = event.get("Package").and_then(Value::as_str).unwrap_or("");
+        let key = (package.to_string(), test.to_string());
+
+        match action {
+            "output" => {
+                if let Some(text) = event.get("Output").and_then(Value::as_str) {
+                    outputs.entry(key).or_default().push(text.to_string());
+                }
+            }
+            "pass" => summary.passed += 1,
+            "skip" => summary.skipped += 1,
+            "fail" => {
+                summary.failed += 1;
+                let lines = outputs.remove(&key).unwrap_or_default();
+                let lines: Vec<&str> = lines.iter().map(|l| l.trim_end()).collect();
+                let (message, location) = describe(&lines);
+                summary.push_failure(test, &message, location);
+            }
+            _ => {}
+        }
+    }
+
+    recognized.then_some(summary)
+}
+
+pub fn recognize(output: &str) -> Option<TestSummary> {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut summary = TestSummary::new("go");
+    let mut recognized = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(caps) = RESULT_RE.captures(line) else {
+            continue;
+        };
+        recognized = true;
+        match &caps[1] {
+            "PASS" => summary.passed += 1,
+            "SKIP" => summary.skipped += 1,
+            _ => {
+                summary.failed += 1;
+                // Messages follow the result line, indented deeper
+                let indent = line.len() - line.trim_start().len();
+                let body: Vec<&str> = lines[index + 1..]
+                    .iter()
+                    .take_while(|l| l.len() - l.trim_start().len() > indent)
+                    .filter(|l| !RESULT_RE.is_match(l))
+                    .copied()
+                    .collect();
+                let (message, location) = describe(&body);
+                summary.push_failure(&caps[2], &message, location);
+            }
+        }
+    }
+
+    recognized.then_some(summary)
+}
+
+/// Message and location of a failure from its output lines
+fn describe(lines: &[&str]) -> (String, Option<String>) {
+    let location = lines
+        .iter()
+        .find_map(|line| LOCATION_RE.captures(line))
+        .map(|caps| caps[1].to_string());
+    let message: Vec<&str> = lines
+        .iter()
+        .filter(|line| !line.starts_with("=== ") && !RESULT_RE.is_match(line))
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    (message.join("\n"), location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognize_json() {
+        let output = r#"{"Action":"start","Package":"example.com/app"}
+{"Action":"run","Package":"example.com/app","Test":"TestAdd"}
+{"Action":"output","Package":"example.com/app","Test":"TestAdd","Output":"=== RUN   TestAdd\n"}
+{"Action":"output","Package":"example.com/app","Test":"TestAdd","Output":"    math_test.go:12: got 3, want 4\n"}
+{"Action":"output","Package":"example.com/app","Test":"TestAdd","Output":"--- FAIL: TestAdd (0.00s)\n"}
+{"Action":"fail","Package":"example.com/app","Test":"TestAdd","Elapsed":0}
+{"Action":"pass","Package":"example.com/app","Test":"TestSub","Elapsed":0}
+{"Action":"skip","Package":"example.com/app","Test":"TestSlow","Elapsed":0}
+{"Action":"fail","Package":"example.com/app","Elapsed":0.01}
+"#;
+        let summary = recognize_json(output).unwrap();
+        assert_eq!(summary.framework, "go");
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (1, 1, 1));
+        let failure = &summary.failures[0];
+        assert_eq!(failure.name, "TestAdd");
+        assert_eq!(failure.message, "math_test.go:12: got 3, want 4");
+        assert_eq!(failure.location.as_deref(), Some("math_test.go:12"));
+    }
+
+    #[test]
+    fn test_recognize_plain() {
+        let output = "\
+=== RUN   TestAdd
+    math_test.go:12: got 3, want 4
+--- FAIL: TestAdd (0.00s)
+    math_test.go:12: got 3, want 4
+=== RUN   TestSub
+--- PASS: TestSub (0.00s)
+FAIL
+FAIL\texample.com/app\t0.005s
+";
+        let summary = recognize(output).unwrap();
+        assert_eq!((summary.passed, summary.failed), (1, 1));
+        assert_eq!(
+            summary.failures[0].location.as_deref(),
+            Some("math_test.go:12")
+        );
+    }
+
+    #[test]
+    fn test_not_go() {
+        assert!(recognize_json(r#"{"reason":"compiler-artifact"}"#).is_none());
+        assert!(recognize("ok\n").is_none());
+    }
+}
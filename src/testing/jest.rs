@@ -0,0 +1,157 @@
+//! Jest and Vitest output
+//!
+//! Jest prints `Tests: 1 failed, 2 passed, 3 total` and a `● Suite › test`
+//! block per failure; Vitest prints `Tests  1 failed | 2 passed (3)` and a
+//! `FAIL  file > suite > test` block per failure. Locations come from the
+//! first stack frame (`at ... (file:line:col)` / `❯ file:line:col`) outside
+//! `node_modules`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{counts, TestSummary};
+
+static JEST_TOTALS_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^Tests:\s+(.+ total)$").expect("Invalid regex"));
+
+static VITEST_TOTALS_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*Tests\s+(.+?)\s*\(\d+\)$").expect("Invalid regex"));
+
+static JEST_FAILURE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*● (.+)$").expect("Invalid regex"));
+
+static VITEST_FAILURE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*FAIL\s+(\S+ > .+)$").expect("Invalid regex"));
+
+static FRAME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:\(|❯ |at )([^\s()]+:\d+:\d+)\)?\s*$").expect("Invalid regex"));
+
+pub fn recognize(output: &str) -> Option<TestSummary> {
+    let lines: Vec<&str> = output.lines().collect();
+
+    let (framework, totals, failure_re) =
+        if let Some(caps) = lines.iter().find_map(|line| JEST_TOTALS_RE.captures(line)) {
+            ("jest", caps[1].to_string(), &*JEST_FAILURE_RE)
+        } else {
+            let caps = lines
+                .iter()
+                .find_map(|line| VITEST_TOTALS_RE.captures(line))?;
+            ("vitest", caps[1].to_string(), &*VITEST_FAILURE_RE)
+        };
+
+    let mut summary = TestSummary::new(framework);
+    let mut recognized = false;
+    for (count, word) in counts(&totals) {
+        match word {
+            "passed" => summary.passed += count,
+            "failed" => summary.failed += count,
+            "skipped" | "todo" => summary.skipped += count,
+            _ => continue,
+        }
+        recognized = true;
+    }
+    if !recognized {
+        return None;
+    }
+
+    let starts: Vec<usize> = (0..lines.len())
+        .filter(|&i| failure_re.is_match(lines[i]))
+        .collect();
+    for (n, &start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+        let block = &lines[start + 1..end];
+
+        let name = failure_re
+            .captures(lines[start])
+            .map(|caps| caps[1].trim().to_string())
+            .unwrap_or_default();
+        // The message runs from the first non-blank line to the next blank one
+        let message: Vec<&str> = block
+            .iter()
+            .map(|line| line.trim())
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| !line.is_empty())
+            .collect();
+        let location = block
+            .iter()
+            .filter_map(|line| FRAME_RE.captures(line))
+            .map(|caps| caps[1].to_string())
+            .find(|frame| !frame.contains("node_modules"));
+
+        summary.push_failure(&name, &message.join("\n"), location);
+    }
+
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognize_jest() {
+        let output = "\
+FAIL src/sum.test.js
+  math
+    ✓ adds (2 ms)
+    ✕ subtracts (3 ms)
+
+  ● math › subtracts
+
+    expect(received).toBe(expected) // Object.is equality
+
+    Expected: 1
+    Received: 2
+
+      4 |   test('subtracts', () => {
+    > 5 |     expect(sub(3, 1)).toBe(1);
+        |                       ^
+
+      at Object.<anonymous> (src/sum.test.js:5:23)
+
+Test Suites: 1 failed, 1 total
+Tests:       1 failed, 1 skipped, 1 passed, 3 total
+";
+        let summary = recognize(output).unwrap();
+        assert_eq!(summary.framework, "jest");
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (1, 1, 1));
+        let failure = &summary.failures[0];
+        assert_eq!(failure.name, "math › subtracts");
+        assert_eq!(
+            failure.message,
+            "expect(received).toBe(expected) // Object.is equality"
+        );
+        assert_eq!(failure.location.as_deref(), Some("src/sum.test.js:5:23"));
+    }
+
+    #[test]
+    fn test_recognize_vitest() {
+        let output = "\
+ ❯ src/sum.test.ts (2 tests | 1 failed) 5ms
+   × math > subtracts
+
+⎯⎯⎯⎯⎯⎯⎯ Failed Tests 1 ⎯⎯⎯⎯⎯⎯⎯
+
+ FAIL  src/sum.test.ts > math > subtracts
+AssertionError: expected 2 to be 1 // Object.is equality
+ ❯ src/sum.test.ts:5:23
+
+ Test Files  1 failed (1)
+      Tests  1 failed | 1 passed (2)
+";
+        let summary = recognize(output).unwrap();
+        assert_eq!(summary.framework, "vitest");
+        assert_eq!((summary.passed, summary.failed), (1, 1));
+        let failure = &summary.failures[0];
+        assert_eq!(failure.name, "src/sum.test.ts > math > subtracts");
+        assert!(failure
+            .message
+            .starts_with("AssertionError: expected 2 to be 1"));
+        assert_eq!(failure.location.as_deref(), Some("src/sum.test.ts:5:23"));
+    }
+
+    #[test]
+    fn test_not_jest() {
+        assert!(recognize("Tests run: 5\n").is_none());
+    }
+}
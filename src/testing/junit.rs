@@ -0,0 +1,259 @@
+//! JUnit XML reports
+//!
+//! Reads the `<testcase>` elements of every report matching the configured
+//! globs that was modified during the run, so stale reports from earlier
+//! runs are ignored. Only the subset of XML that JUnit writers produce is
+//! understood: elements, double- or single-quoted attributes and the
+//! predefined entities.
+
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use globset::{GlobBuilder, GlobSetBuilder};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use walkdir::WalkDir;
+
+use crate::error::TaskError;
+
+use super::TestSummary;
+
+static TESTCASE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<testcase\b([^>]*?)(?:/>|>(.*?)</testcase>)").expect("Invalid regex")
+});
+
+static OUTCOME_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<(failure|error|skipped)\b([^>]*?)(?:/>|>(.*?)</(?:failure|error|skipped)>)")
+        .expect("Invalid regex")
+});
+
+static ATTR_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("Invalid regex"));
+
+/// Summarize reports matching `patterns` under `project_dir` written at or after `since`
+///
+/// Returns `None` when no such report exists.
+///
+/// # Errors
+/// * `TaskError::Config` - If a pattern is not a valid glob
+pub fn read_reports(
+    project_dir: &Path,
+    patterns: &[String],
+    since: SystemTime,
+) -> Result<Option<TestSummary>, TaskError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                TaskError::Config(format!("Invalid test report glob '{}': {}", pattern, e))
+            })?;
+        builder.add(glob);
+    }
+    let globs = builder
+        .build()
+        .map_err(|e| TaskError::Config(format!("Invalid test report globs: {}", e)))?;
+
+    let mut reports: Vec<PathBuf> = Vec::new();
+    for pattern in patterns {
+        // Only walk below the pattern's literal prefix, e.g. `target/junit`
+        let root = project_dir.join(literal_prefix(pattern));
+        for entry in WalkDir::new(&root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            let Ok(relative) = entry.path().strip_prefix(project_dir) else {
+                continue;
+            };
+            let fresh = entry
+                .metadata()
+                .ok()
+                .and_then(|meta| meta.modified().ok())
+                .is_some_and(|modified| modified >= since);
+            if entry.file_type().is_file()
+                && fresh
+                && globs.is_match(relative)
+                && !reports.iter().any(|p| p == entry.path())
+            {
+                reports.push(entry.path().to_path_buf());
+            }
+        }
+    }
+    if reports.is_empty() {
+        return Ok(None);
+    }
+
+    let mut summary = TestSummary::new("junit");
+    for report in reports {
+        match std::fs::read_to_string(&report) {
+            Ok(xml) => parse(&xml, &mut summary),
+            Err(e) => tracing::warn!("Failed to read {}: {}", report.display(), e),
+        }
+    }
+    Ok(Some(summary))
+}
+
+/// Add the test cases of one report to `summary`
+pub fn parse(xml: &str, summary: &mut TestSummary) {
+    for case in TESTCASE_RE.captures_iter(xml) {
+        let attrs = case.get(1).map_or("", |m| m.as_str());
+        let body = case.get(2).map_or("", |m| m.as_str());
+
+        let Some(outcome) = OUTCOME_RE.captures(body) else {
+            summary.passed += 1;
+            continue;
+        };
+        if &outcome[1] == "skipped" {
+            summary.skipped += 1;
+            continue;
+        }
+        summary.failed += 1;
+
+        let name = match (attr(attrs, "classname"), attr(attrs, "name")) {
+            (Some(class), Some(name)) if !class.is_empty() => format!("{}.{}", class, name),
+            (_, Some(name)) => name,
+            (class, None) => class.unwrap_or_default(),
+        };
+        let outcome_attrs = outcome.get(2).map_or("", |m| m.as_str());
+        let text = outcome
+            .get(3)
+            .map_or(String::new(), |m| unescape(m.as_str()));
+        let message = attr(outcome_attrs, "message")
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| text.lines().next().unwrap_or("failed").to_string());
+        let location = attr(attrs, "file").map(|file| match attr(attrs, "line") {
+            Some(line) => format!("{}:{}", file, line),
+            None => file,
+        });
+
+        summary.push_failure(&name, &message, location);
+    }
+}
+
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    ATTR_RE
+        .captures_iter(attrs)
+        .find(|caps| &caps[1] == name)
+        .and_then(|caps| caps.get(2).or_else(|| caps.get(3)))
+        .map(|value| unescape(value.as_str()))
+}
+
+fn unescape(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
+
+/// Leading path components of `pattern` without glob metacharacters
+///
+/// For a pattern without any glob this is the file's directory.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    let components: Vec<Component> = Path::new(pattern).components().collect();
+    let literal = components
+        .iter()
+        .take_while(|c| {
+            matches!(c, Component::Normal(s)
+                if !s.to_string_lossy().contains(['*', '?', '[', '{']))
+        })
+        .count();
+
+    let prefix: PathBuf = components[..literal].iter().collect();
+    if literal == components.len() {
+        prefix.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="math" tests="4" failures="1" errors="1" skipped="1">
+    <testcase classname="math.Add" name="adds" time="0.001"/>
+    <testcase classname="math.Sub" name="subtracts" file="tests/sub.py" line="12">
+      <failure message="expected 1 &lt; 2" type="AssertionError">Traceback...</failure>
+    </testcase>
+    <testcase name="crashes">
+      <error type="RuntimeError"><![CDATA[boom & more]]></error>
+    </testcase>
+    <testcase classname="math" name="slow"><skipped message="too slow"/></testcase>
+  </testsuite>
+</testsuites>
+"#;
+
+    #[test]
+    fn test_parse() {
+        let mut summary = TestSummary::new("junit");
+        parse(REPORT, &mut summary);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (1, 2, 1));
+
+        assert_eq!(summary.failures[0].name, "math.Sub.subtracts");
+        assert_eq!(summary.failures[0].message, "expected 1 < 2");
+        assert_eq!(
+            summary.failures[0].location.as_deref(),
+            Some("tests/sub.py:12")
+        );
+
+        assert_eq!(summary.failures[1].name, "crashes");
+        assert_eq!(summary.failures[1].message, "boom & more");
+        assert_eq!(summary.failures[1].location, None);
+    }
+
+    #[test]
+    fn test_read_reports_skips_stale() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("target/junit");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("report.xml"), REPORT).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a report").unwrap();
+        let patterns = vec!["target/junit/*.xml".to_string()];
+
+        let before = SystemTime::now() - Duration::from_secs(60);
+        let summary = read_reports(temp.path(), &patterns, before)
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.framework, "junit");
+        assert_eq!(summary.failed, 2);
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        assert!(read_reports(temp.path(), &patterns, later)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(
+            literal_prefix("target/junit/*.xml"),
+            PathBuf::from("target/junit")
+        );
+        assert_eq!(literal_prefix("**/junit.xml"), PathBuf::new());
+        assert_eq!(literal_prefix("report.xml"), PathBuf::new());
+        assert_eq!(
+            literal_prefix("build/test-results/TEST-a.xml"),
+            PathBuf::from("build/test-results")
+        );
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let temp = TempDir::new().unwrap();
+        let result = read_reports(temp.path(), &["[".to_string()], SystemTime::now());
+        assert!(matches!(result, Err(TaskError::Config(_))));
+    }
+}
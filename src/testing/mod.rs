@@ -0,0 +1,155 @@
+//! Test result summaries
+//!
+//! Turns test runner output into pass/fail/skip counts plus the name,
+//! message and location of each failure:
+//! - `gotest` - `go test -json` and plain `go test -v`
+//! - `cargo` - `cargo test` (libtest)
+//! - `jest` - Jest and Vitest
+//! - `pytest` - pytest
+//! - `junit` - JUnit XML reports written to configured paths
+//!
+//! JUnit reports written during the run take precedence over recognizing
+//! the output, since they are what the runner was configured to produce.
+
+pub mod cargo;
+pub mod gotest;
+pub mod jest;
+pub mod junit;
+pub mod pytest;
+
+use std::path::Path;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of failures listed in a summary
+pub const MAX_FAILURES: usize = 50;
+
+/// Maximum length of a failure message in bytes
+const MAX_MESSAGE_BYTES: usize = 2000;
+
+/// Outcome of a test run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestSummary {
+    /// Runner the summary was recognized from, e.g. `cargo`, `pytest`, `junit`
+    pub framework: String,
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    /// First [`MAX_FAILURES`] failures, in report order
+    pub failures: Vec<TestFailure>,
+}
+
+/// A failed test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestFailure {
+    pub name: String,
+    pub message: String,
+    /// `file:line[:col]` of the failing assertion, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl TestSummary {
+    pub fn new(framework: &str) -> Self {
+        Self {
+            framework: framework.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a failure, up to [`MAX_FAILURES`]
+    pub fn push_failure(&mut self, name: &str, message: &str, location: Option<String>) {
+        if self.failures.len() >= MAX_FAILURES {
+            return;
+        }
+        let mut message = message.trim().to_string();
+        if message.len() > MAX_MESSAGE_BYTES {
+            let mut end = MAX_MESSAGE_BYTES;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+            message.push_str("...");
+        }
+        self.failures.push(TestFailure {
+            name: name.to_string(),
+            message,
+            location,
+        });
+    }
+}
+
+/// Recognizes a test runner's output, returning `None` if it isn't in its format
+type Recognizer = fn(&str) -> Option<TestSummary>;
+
+const RECOGNIZERS: &[Recognizer] = &[
+    gotest::recognize_json,
+    cargo::recognize,
+    jest::recognize,
+    pytest::recognize,
+    gotest::recognize,
+];
+
+/// Summarize a test run from its output
+pub fn summarize(stdout: &str, stderr: &str) -> Option<TestSummary> {
+    let output = format!("{}\n{}", stdout, stderr);
+    RECOGNIZERS.iter().find_map(|recognize| recognize(&output))
+}
+
+/// Summarize a test run, preferring JUnit reports matching `reports` (globs
+/// relative to `project_dir`) that were written at or after `since`
+pub fn collect(
+    project_dir: &Path,
+    reports: &[String],
+    since: SystemTime,
+    stdout: &str,
+    stderr: &str,
+) -> Option<TestSummary> {
+    if !reports.is_empty() {
+        match junit::read_reports(project_dir, reports, since) {
+            Ok(Some(summary)) => return Some(summary),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read JUnit reports: {}", e),
+        }
+    }
+    summarize(stdout, stderr)
+}
+
+static COUNT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) ([a-z]+)").expect("Invalid regex"));
+
+/// `(count, word)` pairs in a summary line such as `2 failed, 3 passed`
+fn counts(line: &str) -> impl Iterator<Item = (u32, &str)> {
+    COUNT_RE.captures_iter(line).filter_map(|caps| {
+        let count = caps.get(1)?.as_str().parse().ok()?;
+        Some((count, caps.get(2)?.as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_unrecognized() {
+        assert!(summarize("Build complete\n", "").is_none());
+    }
+
+    #[test]
+    fn test_push_failure_limits() {
+        let mut summary = TestSummary::new("cargo");
+        for i in 0..MAX_FAILURES + 5 {
+            summary.push_failure(&format!("t{}", i), &"x".repeat(3000), None);
+        }
+        assert_eq!(summary.failures.len(), MAX_FAILURES);
+        assert_eq!(summary.failures[0].message.len(), MAX_MESSAGE_BYTES + 3);
+    }
+
+    #[test]
+    fn test_counts() {
+        let pairs: Vec<_> = counts("1 failed, 2 passed, 3 skipped in 0.1s").collect();
+        assert_eq!(pairs, vec![(1, "failed"), (2, "passed"), (3, "skipped")]);
+    }
+}
@@ -0,0 +1,111 @@
+//! pytest output
+//!
+//! Counts come from the final `N failed, M passed in 0.12s` line (with or
+//! without the `===` rule); failures from the short test summary
+//! (`FAILED path::test - message`, shown by default since pytest 7).
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{counts, TestSummary};
+
+static FINAL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^=*\s*((?:\d+ [a-z]+(?:, )?)+) in [\d.]+s\b.*$").expect("Invalid regex")
+});
+
+static FAILED_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:FAILED|ERROR) ((\S+?\.py)(?:::\S+)?)(?: - (.+))?$").expect("Invalid regex")
+});
+
+pub fn recognize(output: &str) -> Option<TestSummary> {
+    let lines: Vec<&str> = output.lines().collect();
+    let totals = lines
+        .iter()
+        .rev()
+        .find_map(|line| FINAL_RE.captures(line))?;
+
+    let mut summary = TestSummary::new("pytest");
+    let mut recognized = false;
+    for (count, word) in counts(&totals[1]) {
+        match word {
+            "passed" | "xpassed" => summary.passed += count,
+            "failed" | "error" | "errors" => summary.failed += count,
+            "skipped" | "xfailed" => summary.skipped += count,
+            _ => continue,
+        }
+        recognized = true;
+    }
+    if !recognized {
+        return None;
+    }
+
+    for line in &lines {
+        let Some(caps) = FAILED_RE.captures(line) else {
+            continue;
+        };
+        let path = &caps[2];
+        // The traceback ends with `path:line: ExceptionType`
+        let prefix = format!("{}:", path);
+        let location = lines
+            .iter()
+            .filter_map(|l| l.strip_prefix(&prefix))
+            .find_map(|rest| rest.split_once(": ").map(|(line, _)| line))
+            .filter(|line| line.chars().all(|c| c.is_ascii_digit()))
+            .map(|line| format!("{}:{}", path, line))
+            .or_else(|| Some(path.to_string()));
+        summary.push_failure(
+            &caps[1],
+            caps.get(3).map_or("failed", |m| m.as_str()),
+            location,
+        );
+    }
+
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognize() {
+        let output = "\
+============================= test session starts ==============================
+collected 4 items
+
+tests/test_math.py .F.s                                                   [100%]
+
+=================================== FAILURES ===================================
+___________________________________ test_add ___________________________________
+
+    def test_add():
+>       assert 1 + 1 == 3
+E       assert 2 == 3
+
+tests/test_math.py:5: AssertionError
+=========================== short test summary info ============================
+FAILED tests/test_math.py::test_add - assert 2 == 3
+==================== 1 failed, 2 passed, 1 skipped in 0.05s ====================
+";
+        let summary = recognize(output).unwrap();
+        assert_eq!(summary.framework, "pytest");
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 1, 1));
+        let failure = &summary.failures[0];
+        assert_eq!(failure.name, "tests/test_math.py::test_add");
+        assert_eq!(failure.message, "assert 2 == 3");
+        assert_eq!(failure.location.as_deref(), Some("tests/test_math.py:5"));
+    }
+
+    #[test]
+    fn test_quiet_mode() {
+        let summary = recognize("..s\n2 passed, 1 skipped in 0.01s\n").unwrap();
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 0, 1));
+        assert!(summary.failures.is_empty());
+    }
+
+    #[test]
+    fn test_not_pytest() {
+        assert!(recognize("Built in 2s\n").is_none());
+        assert!(recognize("Compiled 3 files in 0.2s\n").is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Path filtering for watch mode
+//!
+//! Decides whether a changed path should trigger a re-run: paths inside
+//! `.git`, paths ignored by the project's `.gitignore` (or
+//! `.git/info/exclude`) and paths matching an exclude glob never do; when
+//! include globs are configured, a path must match one of them.
+
+use std::path::{Component, Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::config::WatchConfig;
+use crate::error::TaskError;
+
+/// Filter for changed paths under a project root
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    root: PathBuf,
+    gitignore: Gitignore,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    /// Build a filter for `root` from a service's watch settings
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If an include or exclude glob is invalid
+    pub fn new(root: &Path, config: &WatchConfig) -> Result<Self, TaskError> {
+        let mut builder = GitignoreBuilder::new(root);
+        // Missing ignore files are fine
+        let _ = builder.add(root.join(".gitignore"));
+        let _ = builder.add(root.join(".git").join("info").join("exclude"));
+        let gitignore = builder
+            .build()
+            .map_err(|e| TaskError::Config(format!("Invalid .gitignore: {}", e)))?;
+
+        let include = if config.include.is_empty() {
+            None
+        } else {
+            Some(build_globset(&config.include)?)
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            gitignore,
+            include,
+            exclude: build_globset(&config.exclude)?,
+        })
+    }
+
+    /// Project root the filter applies to
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether a change to `path` should trigger a run
+    pub fn matches(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        if relative
+            .components()
+            .any(|c| c == Component::Normal(".git".as_ref()))
+        {
+            return false;
+        }
+        if self
+            .gitignore
+            .matched_path_or_any_parents(relative, path.is_dir())
+            .is_ignore()
+        {
+            return false;
+        }
+        if self.exclude.is_match(relative) {
+            return false;
+        }
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative))
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, TaskError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| TaskError::Config(format!("Invalid watch glob '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| TaskError::Config(format!("Invalid watch globs: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn watch_config(include: &[&str], exclude: &[&str]) -> WatchConfig {
+        WatchConfig {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gitignore_respected() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::create_dir_all(temp.path().join("target/debug")).unwrap();
+
+        let filter = PathFilter::new(temp.path(), &WatchConfig::default()).unwrap();
+
+        assert!(filter.matches(&temp.path().join("src/main.rs")));
+        assert!(!filter.matches(&temp.path().join("target/debug/app")));
+        assert!(!filter.matches(&temp.path().join("build.log")));
+        assert!(!filter.matches(&temp.path().join(".git/index")));
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let temp = TempDir::new().unwrap();
+        let filter = PathFilter::new(
+            temp.path(),
+            &watch_config(&["src/**/*.rs", "Cargo.toml"], &["src/generated/**"]),
+        )
+        .unwrap();
+
+        assert!(filter.matches(&temp.path().join("src/main.rs")));
+        assert!(filter.matches(&temp.path().join("src/a/b.rs")));
+        assert!(filter.matches(&temp.path().join("Cargo.toml")));
+        assert!(!filter.matches(&temp.path().join("README.md")));
+        assert!(!filter.matches(&temp.path().join("src/generated/api.rs")));
+    }
+
+    #[test]
+    fn test_paths_outside_root() {
+        let temp = TempDir::new().unwrap();
+        let filter = PathFilter::new(temp.path(), &WatchConfig::default()).unwrap();
+
+        assert!(!filter.matches(Path::new("/elsewhere/file.rs")));
+        assert!(!filter.matches(temp.path()));
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        let temp = TempDir::new().unwrap();
+        let result = PathFilter::new(temp.path(), &watch_config(&["src/[.rs"], &[]));
+
+        assert!(matches!(result, Err(TaskError::Config(_))));
+    }
+}
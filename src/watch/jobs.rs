@@ -0,0 +1,314 @@
+//! Background watch jobs
+//!
+//! The MCP server can't block a tool call on a watch session, so sessions run
+//! as tokio tasks registered here under a job id. Each job keeps its most
+//! recent events so that callers can poll for results.
+//!
+//! Only so many jobs may run at once. A job whose watcher stopped on its own
+//! is dropped once its final status has been reported, and only the most
+//! recent of those not yet reported are kept.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use super::session::{WatchEvent, WatchSession};
+
+/// Number of events kept per job
+const MAX_RECENT_EVENTS: usize = 20;
+
+/// Number of finished jobs kept until their status is reported
+const MAX_FINISHED_JOBS: usize = 16;
+
+/// Lifecycle state of a watch job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchJobState {
+    /// Watching for changes
+    Running,
+    /// Stopped by request
+    Stopped,
+    /// The watcher could not be started or stopped unexpectedly
+    Failed,
+}
+
+/// Snapshot of a watch job
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchJobStatus {
+    pub job_id: String,
+    pub task: String,
+    pub project_path: String,
+    pub command: String,
+    pub state: WatchJobState,
+    /// Number of runs started so far
+    pub runs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Most recent events, oldest first
+    pub recent_events: VecDeque<WatchEvent>,
+}
+
+struct WatchJob {
+    /// Order the job was started in
+    seq: u64,
+    status: Arc<Mutex<WatchJobStatus>>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct JobTable {
+    next_id: u64,
+    jobs: BTreeMap<String, WatchJob>,
+}
+
+/// Registry of background watch jobs
+#[derive(Clone, Default)]
+pub struct WatchJobs {
+    table: Arc<Mutex<JobTable>>,
+}
+
+impl WatchJobs {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a session in the background and return its job id
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Errors
+    /// If `max_running` jobs are already running (at least one may always run)
+    pub fn start(
+        &self,
+        task: &str,
+        project_path: &str,
+        session: WatchSession,
+        max_running: usize,
+    ) -> Result<String, String> {
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        table.prune();
+        let running = table
+            .jobs
+            .values()
+            .filter(|job| !job.handle.is_finished())
+            .count();
+        if running >= max_running.max(1) {
+            return Err(format!(
+                "Too many watch jobs: {} running (max_watches = {})",
+                running,
+                max_running.max(1)
+            ));
+        }
+        table.next_id += 1;
+        let job_id = format!("watch-{}", table.next_id);
+
+        let status = Arc::new(Mutex::new(WatchJobStatus {
+            job_id: job_id.clone(),
+            task: task.to_string(),
+            project_path: project_path.to_string(),
+            command: session.command().to_string(),
+            state: WatchJobState::Running,
+            runs: 0,
+            error: None,
+            recent_events: VecDeque::new(),
+        }));
+
+        let sink = status.clone();
+        let handle = tokio::spawn(async move {
+            let events = sink.clone();
+            let result = session
+                .run(move |event| {
+                    let mut status = events.lock().unwrap_or_else(|e| e.into_inner());
+                    if let WatchEvent::Started { run, .. } = event {
+                        status.runs = run;
+                    }
+                    if status.recent_events.len() == MAX_RECENT_EVENTS {
+                        status.recent_events.pop_front();
+                    }
+                    status.recent_events.push_back(event);
+                })
+                .await;
+
+            let mut status = sink.lock().unwrap_or_else(|e| e.into_inner());
+            status.state = WatchJobState::Failed;
+            status.error = Some(match result {
+                Ok(()) => "File watcher stopped".to_string(),
+                Err(e) => e.to_string(),
+            });
+        });
+
+        let seq = table.next_id;
+        table.jobs.insert(
+            job_id.clone(),
+            WatchJob {
+                seq,
+                status,
+                handle,
+            },
+        );
+        Ok(job_id)
+    }
+
+    /// Status of one job, or of all jobs when `job_id` is `None`
+    ///
+    /// Finished jobs are dropped once reported here.
+    pub fn status(&self, job_id: Option<&str>) -> Vec<WatchJobStatus> {
+        let mut table = self.table.lock().unwrap_or_else(|e| e.into_inner());
+        let mut reported = Vec::new();
+        let statuses = table
+            .jobs
+            .iter()
+            .filter(|(id, _)| job_id.is_none_or(|wanted| wanted == id.as_str()))
+            .map(|(id, job)| {
+                // A finished job's status is final
+                if job.handle.is_finished() {
+                    reported.push(id.clone());
+                }
+                snapshot(job)
+            })
+            .collect();
+        for id in reported {
+            table.jobs.remove(&id);
+        }
+        statuses
+    }
+
+    /// Stop a job, killing any in-flight run, and return its final status
+    pub fn stop(&self, job_id: &str) -> Option<WatchJobStatus> {
+        let job = self
+            .table
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .jobs
+            .remove(job_id)?;
+
+        job.handle.abort();
+        let mut status = snapshot(&job);
+        if status.state == WatchJobState::Running {
+            status.state = WatchJobState::Stopped;
+        }
+        Some(status)
+    }
+}
+
+impl JobTable {
+    /// Drop the oldest finished jobs beyond [`MAX_FINISHED_JOBS`]
+    fn prune(&mut self) {
+        let mut finished: Vec<(u64, String)> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.handle.is_finished())
+            .map(|(id, job)| (job.seq, id.clone()))
+            .collect();
+        finished.sort();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_JOBS);
+        for (_, id) in finished.into_iter().take(excess) {
+            self.jobs.remove(&id);
+        }
+    }
+}
+
+impl Drop for JobTable {
+    fn drop(&mut self) {
+        for job in self.jobs.values() {
+            job.handle.abort();
+        }
+    }
+}
+
+fn snapshot(job: &WatchJob) -> WatchJobStatus {
+    job.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WatchConfig;
+    use crate::runner::{CommandSpec, RunOptions};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_start_status_stop() {
+        let temp = TempDir::new().unwrap();
+        let session = WatchSession::new(
+            temp.path(),
+            CommandSpec::new("echo").arg("built"),
+            RunOptions::in_dir(temp.path()),
+            &WatchConfig::default(),
+        )
+        .unwrap();
+
+        let jobs = WatchJobs::new();
+        let id = jobs
+            .start("build", &temp.path().display().to_string(), session, 4)
+            .unwrap();
+        assert_eq!(id, "watch-1");
+
+        // Wait for the initial run to finish
+        for _ in 0..200 {
+            let status = &jobs.status(Some(&id))[0];
+            if status
+                .recent_events
+                .iter()
+                .any(|e| matches!(e, WatchEvent::Finished { .. }))
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+
+        let status = &jobs.status(None)[0];
+        assert_eq!(status.state, WatchJobState::Running);
+        assert_eq!(status.runs, 1);
+        assert_eq!(status.command, "echo built");
+
+        let stopped = jobs.stop(&id).unwrap();
+        assert_eq!(stopped.state, WatchJobState::Stopped);
+        assert!(jobs.status(None).is_empty());
+        assert!(jobs.stop(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_limit_and_finished_jobs() {
+        let temp = TempDir::new().unwrap();
+        let session = |dir: &std::path::Path| {
+            WatchSession::new(
+                dir,
+                CommandSpec::new("true"),
+                RunOptions::in_dir(dir),
+                &WatchConfig::default(),
+            )
+            .unwrap()
+        };
+        let path = temp.path().display().to_string();
+
+        let jobs = WatchJobs::new();
+        let running = jobs.start("build", &path, session(temp.path()), 1).unwrap();
+        let err = jobs
+            .start("build", &path, session(temp.path()), 1)
+            .unwrap_err();
+        assert!(err.contains("Too many watch jobs"), "{}", err);
+
+        // A job whose watcher can't start finishes, and is dropped once reported
+        let gone = temp.path().join("gone");
+        std::fs::create_dir(&gone).unwrap();
+        let failing = session(&gone);
+        std::fs::remove_dir(&gone).unwrap();
+        let failed = jobs.start("build", &path, failing, 2).unwrap();
+        let mut reported = false;
+        for _ in 0..200 {
+            match jobs.status(Some(&failed)).first() {
+                Some(status) => reported |= status.state == WatchJobState::Failed,
+                None => break,
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        assert!(reported);
+        let remaining: Vec<_> = jobs.status(None).into_iter().map(|s| s.job_id).collect();
+        assert_eq!(remaining, vec![running]);
+    }
+}
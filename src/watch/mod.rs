@@ -0,0 +1,14 @@
+//! Watch mode
+//!
+//! Re-runs a task whenever files in its project change:
+//! - `filter` - `.gitignore`-aware include/exclude filtering of changed paths
+//! - `session` - debounced re-runs that cancel an in-flight run on new changes
+//! - `jobs` - background sessions for the MCP server, addressed by job id
+
+pub mod filter;
+pub mod jobs;
+pub mod session;
+
+pub use filter::PathFilter;
+pub use jobs::{WatchJobState, WatchJobStatus, WatchJobs};
+pub use session::{WatchEvent, WatchSession};
@@ -0,0 +1,372 @@
+//! Watch sessions
+//!
+//! A [`WatchSession`] runs a command once, then again every time relevant
+//! files under the project change. Changes are debounced: a run starts only
+//! after no new change arrived for the debounce period. A change that lands
+//! while the command is still running cancels it (its process group is
+//! killed) before the next run is scheduled.
+//!
+//! Each run goes through [`CommandSpec::execute`] with the session's
+//! [`RunOptions`], so watch runs get the same resource limits, sandbox,
+//! environment and output handling as any other run.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::config::WatchConfig;
+use crate::error::TaskError;
+use crate::runner::{CommandSpec, RunOptions, RunResult};
+
+use super::filter::PathFilter;
+
+/// Something that happened during a watch session
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// A run started; `changed` lists the files that triggered it
+    Started {
+        run: u64,
+        command: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        changed: Vec<String>,
+    },
+    /// A run completed
+    Finished {
+        run: u64,
+        success: bool,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        stdout: String,
+        stderr: String,
+    },
+    /// A run could not be started or timed out
+    Failed { run: u64, error: String },
+    /// A run was killed because files changed while it was running
+    Cancelled { run: u64 },
+}
+
+/// Re-runs a command whenever files in a project change
+pub struct WatchSession {
+    /// What is run on each change
+    spec: CommandSpec,
+    /// `spec` with its secrets masked, as reported
+    command: CommandSpec,
+    options: RunOptions,
+    filter: PathFilter,
+    debounce: Duration,
+}
+
+impl WatchSession {
+    /// Create a session watching `project_dir` and running `command` with
+    /// `options` (timeout, limits, sandbox, environment and output limits)
+    ///
+    /// # Errors
+    /// * `TaskError::Io` - If the project directory can't be resolved
+    /// * `TaskError::Config` - If a watch glob is invalid
+    pub fn new(
+        project_dir: &Path,
+        command: CommandSpec,
+        options: RunOptions,
+        config: &WatchConfig,
+    ) -> Result<Self, TaskError> {
+        // Watcher events carry canonical paths
+        let root = project_dir.canonicalize()?;
+
+        Ok(Self {
+            command: command.redacted(&options.output),
+            spec: command,
+            options: RunOptions {
+                capture_output: true,
+                ..options
+            },
+            filter: PathFilter::new(&root, config)?,
+            debounce: Duration::from_millis(config.debounce_ms),
+        })
+    }
+
+    /// Command run on each change, with secrets masked
+    pub fn command(&self) -> &CommandSpec {
+        &self.command
+    }
+
+    /// Watch until the file watcher stops, reporting progress to `on_event`
+    ///
+    /// Runs forever under normal operation; drop the future (or abort its
+    /// task) to stop watching, which also kills an in-flight run.
+    ///
+    /// # Errors
+    /// * `TaskError::Config` - If the file watcher can't be started
+    pub async fn run(self, mut on_event: impl FnMut(WatchEvent)) -> Result<(), TaskError> {
+        let root = self.filter.root().to_path_buf();
+        let (tx, mut rx) = mpsc::unbounded_channel::<PathBuf>();
+
+        let filter = self.filter.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                for path in event.paths {
+                    if filter.matches(&path) {
+                        let _ = tx.send(path);
+                    }
+                }
+            })
+            .map_err(|e| TaskError::Config(format!("Failed to start file watcher: {}", e)))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| TaskError::Config(format!("Failed to watch {}: {}", root.display(), e)))?;
+
+        let mut changed: Vec<PathBuf> = Vec::new();
+        let mut run = 0;
+
+        loop {
+            run += 1;
+            on_event(WatchEvent::Started {
+                run,
+                command: self.command.to_string(),
+                changed: changed.drain(..).map(|p| relative(&root, &p)).collect(),
+            });
+
+            // Scope the run so that dropping it kills the process before debouncing
+            let next_change = {
+                let start = Instant::now();
+                let exec = self.spec.execute_async(self.options.clone());
+                tokio::pin!(exec);
+
+                tokio::select! {
+                    result = &mut exec => {
+                        on_event(match result {
+                            Ok(mut output) => {
+                                let duration_ms = start.elapsed().as_millis() as u64;
+                                // Kept for successful runs too, unlike in a RunResult
+                                let stderr = std::mem::take(&mut output.stderr.text);
+                                let result =
+                                    RunResult::captured(self.spec.clone(), output, duration_ms);
+                                WatchEvent::Finished {
+                                    run,
+                                    success: result.success,
+                                    exit_code: result.exit_code,
+                                    duration_ms,
+                                    stdout: result.stdout,
+                                    stderr,
+                                }
+                            }
+                            Err(e) => WatchEvent::Failed {
+                                run,
+                                error: self
+                                    .options
+                                    .output
+                                    .for_env(&self.spec.env)
+                                    .redact(&e.to_string())
+                                    .into_owned(),
+                            },
+                        });
+                        rx.recv().await
+                    }
+                    path = rx.recv() => {
+                        if path.is_some() {
+                            on_event(WatchEvent::Cancelled { run });
+                        }
+                        path
+                    }
+                }
+            };
+
+            let Some(path) = next_change else {
+                // The watcher went away; nothing will trigger another run
+                return Ok(());
+            };
+            changed.push(path);
+
+            // Debounce: wait until changes stop arriving
+            while let Ok(Some(path)) = tokio::time::timeout(self.debounce, rx.recv()).await {
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ResourceLimits, SandboxConfig};
+    use crate::output::OutputLimits;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    async fn wait_for(events: &Arc<Mutex<Vec<WatchEvent>>>, check: impl Fn(&[WatchEvent]) -> bool) {
+        for _ in 0..200 {
+            if check(&events.lock().unwrap()) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("Timed out waiting for events: {:?}", events.lock().unwrap());
+    }
+
+    fn finished(events: &[WatchEvent]) -> usize {
+        events
+            .iter()
+            .filter(|e| matches!(e, WatchEvent::Finished { .. }))
+            .count()
+    }
+
+    fn start(
+        temp: &TempDir,
+        command: CommandSpec,
+        config: WatchConfig,
+    ) -> (Arc<Mutex<Vec<WatchEvent>>>, tokio::task::JoinHandle<()>) {
+        start_with(temp, command, RunOptions::in_dir(temp.path()), config)
+    }
+
+    fn start_with(
+        temp: &TempDir,
+        command: CommandSpec,
+        options: RunOptions,
+        config: WatchConfig,
+    ) -> (Arc<Mutex<Vec<WatchEvent>>>, tokio::task::JoinHandle<()>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let session = WatchSession::new(temp.path(), command, options, &config).unwrap();
+        let sink = events.clone();
+        let handle = tokio::spawn(async move {
+            let _ = session
+                .run(move |event| sink.lock().unwrap().push(event))
+                .await;
+        });
+        (events, handle)
+    }
+
+    #[tokio::test]
+    async fn test_runs_initially_and_on_change() {
+        let temp = TempDir::new().unwrap();
+        fs_write(&temp, ".gitignore", "ignored.txt\n");
+        let config = WatchConfig {
+            debounce_ms: 50,
+            ..Default::default()
+        };
+        let (events, handle) = start(&temp, CommandSpec::new("echo").arg("hi"), config);
+
+        wait_for(&events, |e| finished(e) == 1).await;
+
+        // Ignored files don't trigger a run
+        fs_write(&temp, "ignored.txt", "x");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(finished(&events.lock().unwrap()), 1);
+
+        fs_write(&temp, "main.rs", "fn main() {}");
+        wait_for(&events, |e| finished(e) == 2).await;
+        handle.abort();
+
+        let events = events.lock().unwrap();
+        let changed = events.iter().find_map(|e| match e {
+            WatchEvent::Started {
+                run: 2, changed, ..
+            } => Some(changed.clone()),
+            _ => None,
+        });
+        assert_eq!(changed, Some(vec!["main.rs".to_string()]));
+        assert!(matches!(
+            &events[1],
+            WatchEvent::Finished { success: true, stdout, .. } if stdout == "hi\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_change_cancels_in_flight_run() {
+        let temp = TempDir::new().unwrap();
+        let config = WatchConfig {
+            debounce_ms: 50,
+            ..Default::default()
+        };
+        let (events, handle) = start(&temp, CommandSpec::new("sleep").arg("30"), config);
+
+        wait_for(&events, |e| {
+            e.iter()
+                .any(|e| matches!(e, WatchEvent::Started { run: 1, .. }))
+        })
+        .await;
+        fs_write(&temp, "trigger.txt", "x");
+
+        wait_for(&events, |e| {
+            e.iter()
+                .any(|e| matches!(e, WatchEvent::Cancelled { run: 1 }))
+                && e.iter()
+                    .any(|e| matches!(e, WatchEvent::Started { run: 2, .. }))
+        })
+        .await;
+        handle.abort();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runs_within_resource_limits() {
+        let temp = TempDir::new().unwrap();
+        let limits = ResourceLimits {
+            open_files: Some(5),
+            ..Default::default()
+        };
+        let options = RunOptions::in_dir(temp.path()).confined(&limits, &SandboxConfig::default());
+        let command = CommandSpec::new("sh")
+            .args(["-c", "ulimit -n; exec 3>/dev/null 4>/dev/null 5>/dev/null"]);
+        let (events, handle) = start_with(&temp, command, options, WatchConfig::default());
+
+        wait_for(&events, |e| finished(e) == 1).await;
+        handle.abort();
+
+        // The shell runs out of descriptors
+        let events = events.lock().unwrap();
+        assert!(
+            matches!(
+                &events[1],
+                WatchEvent::Finished { success: false, stdout, .. } if stdout == "5\n"
+            ),
+            "{:?}",
+            events
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_is_ansi_stripped() {
+        let temp = TempDir::new().unwrap();
+        let options = RunOptions {
+            output: OutputLimits {
+                strip_ansi: true,
+                ..Default::default()
+            },
+            ..RunOptions::in_dir(temp.path())
+        };
+        let command = CommandSpec::new("printf").arg("\\033[31mred\\033[0m\\n");
+        let (events, handle) = start_with(&temp, command, options, WatchConfig::default());
+
+        wait_for(&events, |e| finished(e) == 1).await;
+        handle.abort();
+
+        let events = events.lock().unwrap();
+        assert!(
+            matches!(&events[1], WatchEvent::Finished { stdout, .. } if stdout == "red\n"),
+            "{:?}",
+            events
+        );
+    }
+
+    fn fs_write(temp: &TempDir, name: &str, contents: &str) {
+        std::fs::write(temp.path().join(name), contents).unwrap();
+    }
+}